@@ -146,6 +146,7 @@ fn rag_dataset_size_benchmarks(c: &mut Criterion) {
                         auto_cleanup: false,
                         cleanup_interval_secs: 3600,
                         enable_persistence: false,
+                        ..Default::default()
                     };
                     let store = Arc::new(ContextStore::new(config).unwrap());
                     let rag = RagProcessor::with_defaults(store.clone());
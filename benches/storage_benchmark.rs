@@ -1,6 +1,14 @@
-use context_mcp::{context::ContextDomain, Context, ContextStore, StorageConfig};
+use chrono::{Duration as AgeDuration, Utc};
+use context_mcp::{
+    context::{ContextDomain, ContextQuery},
+    Context, ContextStore, StorageConfig,
+};
+#[cfg(feature = "persistence")]
+use context_mcp::{FlushPolicy, RecordFormat};
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use std::hint::black_box;
+#[cfg(feature = "persistence")]
+use std::{sync::Arc, time::Duration};
 use tokio::runtime::Runtime;
 
 fn storage_benchmarks(c: &mut Criterion) {
@@ -15,6 +23,7 @@ fn storage_benchmarks(c: &mut Criterion) {
                 auto_cleanup: false,
                 cleanup_interval_secs: 3600,
                 enable_persistence: false,
+                ..Default::default()
             };
             let store = ContextStore::new(config).unwrap();
             let ctx = Context::new("Test content", ContextDomain::Code);
@@ -32,11 +41,12 @@ fn storage_benchmarks(c: &mut Criterion) {
                 auto_cleanup: false,
                 cleanup_interval_secs: 3600,
                 enable_persistence: false,
+                ..Default::default()
             };
             let store = ContextStore::new(config).unwrap();
             let ctx = Context::new("Test content", ContextDomain::Code);
 
-            let id = store.store(ctx).await.unwrap();
+            let id = store.store(ctx).await.unwrap().id;
             let _retrieved = store.get(&id).await.unwrap();
         });
     });
@@ -55,6 +65,7 @@ fn storage_benchmarks(c: &mut Criterion) {
                         auto_cleanup: false,
                         cleanup_interval_secs: 3600,
                         enable_persistence: false,
+                        ..Default::default()
                     };
                     let store = ContextStore::new(config).unwrap();
 
@@ -81,6 +92,7 @@ fn storage_benchmarks(c: &mut Criterion) {
                 auto_cleanup: false,
                 cleanup_interval_secs: 3600,
                 enable_persistence: false,
+                ..Default::default()
             };
             let store = ContextStore::new(config).unwrap();
 
@@ -90,6 +102,129 @@ fn storage_benchmarks(c: &mut Criterion) {
             }
         });
     });
+
+    // Benchmark: Last-hour query over 50k contexts, served by the creation-time index
+    temporal_query(c, &rt);
+
+    // Benchmark: Persisted ingest under each flush policy, and as one batch
+    #[cfg(feature = "persistence")]
+    persisted_ingest(c, &rt);
+
+    // Benchmark: Store and get latency, and disk size, per record format
+    #[cfg(feature = "persistence")]
+    record_formats(c, &rt);
+}
+
+#[cfg(feature = "persistence")]
+fn persisted_ingest(c: &mut Criterion, rt: &Runtime) {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let mut group = c.benchmark_group("persisted_ingest_100");
+    let cases = [
+        ("store_every_write", FlushPolicy::EveryWrite, false),
+        (
+            "store_interval",
+            FlushPolicy::Interval(Duration::from_millis(100)),
+            false,
+        ),
+        ("store_batch", FlushPolicy::EveryWrite, true),
+    ];
+    for (name, flush_policy, batched) in cases {
+        let config = StorageConfig::builder()
+            .memory_cache_size(1000)
+            .persist_path(temp_dir.path().join(name))
+            .flush_policy(flush_policy)
+            .build();
+        let store = Arc::new(ContextStore::new(config).unwrap());
+        let _flusher = rt.block_on(async { ContextStore::spawn_flush_task(&store) });
+        group.bench_function(name, |b| {
+            b.to_async(rt).iter(|| async {
+                let contexts: Vec<Context> = (0..100)
+                    .map(|i| Context::new(format!("Test content {}", i), ContextDomain::Code))
+                    .collect();
+                if batched {
+                    store.store_batch(black_box(contexts)).await.unwrap();
+                } else {
+                    for ctx in contexts {
+                        store.store(black_box(ctx)).await.unwrap();
+                    }
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+#[cfg(feature = "persistence")]
+fn record_formats(c: &mut Criterion, rt: &Runtime) {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let embedded = |i: usize| {
+        Context::new(format!("Embedded content {}", i), ContextDomain::Code)
+            .with_embedding((0..384).map(|d| ((i * 384 + d) as f32).sin()).collect())
+    };
+
+    let mut group = c.benchmark_group("record_format_1000x384");
+    for format in [RecordFormat::Json, RecordFormat::Bincode] {
+        // A cache smaller than the data set, so gets decode from disk
+        let config = StorageConfig::builder()
+            .memory_cache_size(10)
+            .persist_path(temp_dir.path().join(format.to_string()))
+            .flush_policy(FlushPolicy::Never)
+            .record_format(format)
+            .build();
+        let store = ContextStore::new(config).unwrap();
+        let ids: Vec<_> = rt.block_on(async {
+            let contexts: Vec<Context> = (0..1000).map(embedded).collect();
+            store.store_batch(contexts).await.unwrap()
+        });
+        let disk_bytes: usize = store.get_disk_usage_by_domain().unwrap().values().sum();
+        eprintln!("{}: {} bytes on disk for 1000 contexts", format, disk_bytes);
+
+        group.bench_function(BenchmarkId::new("store", format), |b| {
+            let mut i = 1000;
+            b.to_async(rt).iter(|| {
+                i += 1;
+                let ctx = embedded(i);
+                let store = &store;
+                async move { store.store(black_box(ctx)).await.unwrap() }
+            });
+        });
+        group.bench_function(BenchmarkId::new("get", format), |b| {
+            let mut next = ids.iter().cycle();
+            b.to_async(rt).iter(|| {
+                let id = next.next().unwrap();
+                let store = &store;
+                async move { store.get(black_box(id)).await.unwrap() }
+            });
+        });
+    }
+    group.finish();
+}
+
+fn temporal_query(c: &mut Criterion, rt: &Runtime) {
+    let store = ContextStore::new(StorageConfig::memory_only(50_000)).unwrap();
+    rt.block_on(async {
+        let now = Utc::now();
+        let contexts: Vec<Context> = (0..50_000)
+            .map(|i| {
+                let mut ctx = Context::new(format!("Test content {}", i), ContextDomain::Code);
+                // The last 100 within the hour, the rest spread over the past weeks
+                if i < 49_900 {
+                    ctx.created_at = now - AgeDuration::minutes(61 + i as i64);
+                }
+                ctx
+            })
+            .collect();
+        store.store_batch(contexts).await.unwrap();
+    });
+    let query = ContextQuery::new().with_max_age_hours(1).with_limit(100);
+    let matches = rt.block_on(store.count(&query)).unwrap();
+    eprintln!("last hour: {} of 50000 contexts match", matches);
+
+    c.bench_function("query_last_hour_of_50k", |b| {
+        b.to_async(rt).iter(|| async {
+            store.query(black_box(&query)).await.unwrap();
+        });
+    });
 }
 
 criterion_group!(benches, storage_benchmarks);
@@ -1,17 +1,40 @@
-use context_mcp::context::ContextDomain;
+use context_mcp::context::{ContextDomain, ContextQuery};
+use context_mcp::storage::blocking::ContextStoreBlocking;
 use context_mcp::{Context, ContextStore, StorageConfig};
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Create storage configuration
-    let config = StorageConfig::default();
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    blocking_flavor()?;
+
+    // The async API needs a runtime; with #[tokio::main] this would be the whole of main
+    tokio::runtime::Runtime::new()?.block_on(async_flavor())
+}
+
+/// Synchronous API, for code without an async runtime of its own
+fn blocking_flavor() -> Result<(), Box<dyn std::error::Error>> {
+    let store = ContextStoreBlocking::new(StorageConfig::memory_only(100))?;
+
+    let ctx = Context::new("Stored without async", ContextDomain::Documentation);
+    let id = store.store(ctx)?.id;
+    let retrieved = store.get(&id)?.expect("Context should exist");
+    println!("[blocking] Retrieved: {}", retrieved.content);
+
+    Ok(())
+}
+
+/// Async API
+async fn async_flavor() -> Result<(), Box<dyn std::error::Error>> {
+    // Create storage configuration; unset fields keep their defaults
+    let config = StorageConfig::builder()
+        .memory_cache_size(1_000)
+        .persist_path("./data/basic_usage")
+        .build();
 
     // Create context store
     let store = ContextStore::new(config)?;
 
     // Store some context
     let ctx = Context::new("This is some important information", ContextDomain::Code);
-    let id = store.store(ctx).await?;
+    let id = store.store(ctx).await?.id;
     println!("Stored context with ID: {}", id);
 
     // Retrieve it
@@ -19,11 +42,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Retrieved: {}", retrieved.content);
 
     // Query contexts
-    let query = context_mcp::context::ContextQuery::new()
+    let query = ContextQuery::new()
         .with_domain(ContextDomain::Code)
         .with_limit(10);
     let results = store.query(&query).await?;
     println!("Found {} matching contexts", results.len());
 
+    // Or search by text
+    let matches = store.query_text("important", 10, None).await?;
+    println!("Found {} contexts mentioning 'important'", matches.len());
+
     Ok(())
 }
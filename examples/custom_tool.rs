@@ -0,0 +1,83 @@
+//! Example of registering a custom tool
+//!
+//! Defines a `word_count` tool, registers it on a server's tool registry and
+//! calls it through the JSON-RPC `tools/call` method, the same path a client
+//! would take.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use context_mcp::protocol::{CallToolResult, InputSchema, JsonRpcRequest, PropertySchema, Tool};
+use context_mcp::server::{McpServer, ServerConfig};
+use context_mcp::storage::StorageConfig;
+use context_mcp::tools::{ContextTool, ToolContext};
+
+/// Counts the words in a stored context
+struct WordCountTool;
+
+#[async_trait]
+impl ContextTool for WordCountTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "word_count".to_string(),
+            description: Some("Count the words in a stored context".to_string()),
+            input_schema: InputSchema::object()
+                .with_required("id", PropertySchema::string("Context ID")),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let id = context_mcp::ContextId::from_string(
+            args["id"].as_str().unwrap_or_default().to_string(),
+        );
+
+        match cx.store.get(&id).await {
+            Ok(Some(ctx)) => CallToolResult::json(json!({
+                "id": id.to_string(),
+                "words": ctx.content.split_whitespace().count()
+            })),
+            Ok(None) => CallToolResult::error(format!("Context not found: {}", id)),
+            Err(e) => CallToolResult::error(format!("Error: {}", e)),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = ServerConfig {
+        storage: StorageConfig::memory_only(100),
+        ..Default::default()
+    };
+    let server = McpServer::new(config)?;
+    server.tools().register(Box::new(WordCountTool))?;
+
+    // Store a context through the built-in tool
+    let stored = server
+        .handle_request(JsonRpcRequest::new(
+            "tools/call",
+            Some(json!({
+                "name": "store_context",
+                "arguments": { "content": "the quick brown fox jumps" }
+            })),
+        ))
+        .await;
+    let text = stored.result.as_ref().unwrap()["content"][0]["text"]
+        .as_str()
+        .unwrap();
+    let id = serde_json::from_str::<Value>(text)?["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Call the custom tool
+    let response = server
+        .handle_request(JsonRpcRequest::new(
+            "tools/call",
+            Some(json!({ "name": "word_count", "arguments": { "id": id } })),
+        ))
+        .await;
+    println!("{}", serde_json::to_string_pretty(&response.result)?);
+
+    Ok(())
+}
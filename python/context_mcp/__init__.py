@@ -0,0 +1,173 @@
+"""Python bindings for the context-mcp storage and retrieval core.
+
+Stores, queries and retrieval run in Rust with the same scoring as the MCP
+server; results come back as plain dataclasses.
+
+    >>> store = ContextStore()
+    >>> context_id = store.store("rust borrow checker", domain="code", tags=["rust"])
+    >>> store.get(context_id).content
+    'rust borrow checker'
+    >>> result = RagProcessor(store).retrieve(RetrievalQuery(text="borrow checker"))
+    >>> result.contexts[0].context.id == context_id
+    True
+"""
+
+from __future__ import annotations
+
+import json
+from dataclasses import dataclass, field
+from os import PathLike
+from typing import Any, Optional, Union
+
+from . import _native
+
+__all__ = [
+    "Context",
+    "ContextStore",
+    "RagProcessor",
+    "RetrievalQuery",
+    "RetrievalResult",
+    "ScoredContext",
+]
+
+
+@dataclass
+class Context:
+    """A stored context; ``raw`` holds every field the store returned."""
+
+    id: str
+    content: str
+    domain: Any
+    tags: list[str]
+    importance: float
+    source: str
+    created_at: str
+    accessed_at: str
+    version: int
+    raw: dict[str, Any] = field(repr=False)
+
+    @classmethod
+    def from_dict(cls, data: dict[str, Any]) -> "Context":
+        metadata = data["metadata"]
+        return cls(
+            id=data["id"],
+            content=data["content"],
+            domain=data["domain"],
+            tags=list(metadata.get("tags", [])),
+            importance=metadata.get("importance", 0.0),
+            source=metadata.get("source", ""),
+            created_at=data["created_at"],
+            accessed_at=data["accessed_at"],
+            version=data.get("version", 0),
+            raw=data,
+        )
+
+
+@dataclass
+class RetrievalQuery:
+    """Filters for :meth:`RagProcessor.retrieve`."""
+
+    text: Optional[str] = None
+    domain: Optional[str] = None
+    tags: list[str] = field(default_factory=list)
+    min_importance: Optional[float] = None
+    max_results: Optional[int] = None
+
+
+@dataclass
+class ScoredContext:
+    """A retrieved context with its score and score breakdown."""
+
+    context: Context
+    score: float
+    score_breakdown: dict[str, Any]
+
+
+@dataclass
+class RetrievalResult:
+    """Contexts retrieved for a query, best first."""
+
+    contexts: list[ScoredContext]
+    candidates_considered: int
+    processing_time_ms: int
+    query_summary: str
+
+    @classmethod
+    def from_dict(cls, data: dict[str, Any]) -> "RetrievalResult":
+        return cls(
+            contexts=[
+                ScoredContext(
+                    context=Context.from_dict(scored["context"]),
+                    score=scored["score"],
+                    score_breakdown=scored["score_breakdown"],
+                )
+                for scored in data["contexts"]
+            ],
+            candidates_considered=data["candidates_considered"],
+            processing_time_ms=data["processing_time_ms"],
+            query_summary=data["query_summary"],
+        )
+
+
+class ContextStore:
+    """Context store, in memory or persisted under ``path``.
+
+    Missing contexts raise ``KeyError`` where an ID must exist, invalid
+    arguments ``ValueError`` and storage failures ``RuntimeError``.
+    """
+
+    def __init__(
+        self, path: Optional[Union[str, PathLike]] = None, cache_size: int = 1000
+    ) -> None:
+        self._native = _native.ContextStore(
+            None if path is None else str(path), cache_size
+        )
+
+    def store(
+        self,
+        content: str,
+        domain: Optional[str] = None,
+        tags: Optional[list[str]] = None,
+        importance: Optional[float] = None,
+        source: Optional[str] = None,
+    ) -> str:
+        """Store a context and return its ID."""
+        return self._native.store(content, domain, tags, importance, source)
+
+    def get(self, context_id: str) -> Optional[Context]:
+        """The context with ``context_id``, or ``None`` if it does not exist."""
+        data = self._native.get(context_id)
+        return None if data is None else Context.from_dict(json.loads(data))
+
+    def query(
+        self,
+        domain: Optional[str] = None,
+        tags: Optional[list[str]] = None,
+        min_importance: Optional[float] = None,
+        limit: int = 100,
+    ) -> list[Context]:
+        """Contexts matching every given filter."""
+        data = self._native.query(domain, tags, min_importance, limit)
+        return [Context.from_dict(item) for item in json.loads(data)]
+
+    def stats(self) -> dict[str, Any]:
+        """Storage statistics as a dict."""
+        return json.loads(self._native.stats())
+
+
+class RagProcessor:
+    """Retrieval over a store, scored as the ``retrieve_contexts`` tool scores it."""
+
+    def __init__(self, store: ContextStore) -> None:
+        self._native = _native.RagProcessor(store._native)
+
+    def retrieve(self, query: RetrievalQuery) -> RetrievalResult:
+        """Retrieve contexts for ``query``, best first."""
+        data = self._native.retrieve(
+            query.text,
+            query.domain,
+            query.tags or None,
+            query.min_importance,
+            query.max_results,
+        )
+        return RetrievalResult.from_dict(json.loads(data))
@@ -0,0 +1,194 @@
+//! Python bindings for the context-mcp storage and retrieval core
+//!
+//! Builds the `context_mcp._native` extension module. The `context_mcp`
+//! Python package wraps it and turns the JSON it returns into dicts and
+//! dataclasses. Every call blocks on a shared tokio runtime with the GIL
+//! released, so Python threads keep running while the store works.
+
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+use context_mcp::context::{ContextDomain, ContextId, ContextQuery};
+use context_mcp::rag::{RagProcessor as CoreRagProcessor, RetrievalQuery};
+use context_mcp::{Context, ContextError, ContextStore as CoreContextStore, StorageConfig};
+use pyo3::exceptions::{PyKeyError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use serde::Serialize;
+use tokio::runtime::Runtime;
+
+/// Runtime shared by every store and processor in the process
+fn runtime() -> PyResult<&'static Runtime> {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| PyRuntimeError::new_err(format!("cannot start runtime: {}", e)))?;
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Run `future` to completion without holding the GIL
+fn block_on<F, T>(py: Python<'_>, future: F) -> PyResult<T>
+where
+    F: std::future::Future<Output = context_mcp::Result<T>> + Send,
+    T: Send,
+{
+    py.allow_threads(|| runtime()?.block_on(future).map_err(to_py_err))
+}
+
+/// Missing contexts raise `KeyError`, bad arguments `ValueError`, anything else `RuntimeError`
+fn to_py_err(err: ContextError) -> PyErr {
+    match err.root() {
+        ContextError::NotFound(_) => PyKeyError::new_err(err.to_string()),
+        ContextError::InvalidQuery(_) | ContextError::Config(_) => {
+            PyValueError::new_err(err.to_string())
+        }
+        _ => PyRuntimeError::new_err(err.to_string()),
+    }
+}
+
+fn to_json(value: &impl Serialize) -> PyResult<String> {
+    serde_json::to_string(value).map_err(|e| PyRuntimeError::new_err(e.to_string()))
+}
+
+fn parse_id(id: &str) -> PyResult<ContextId> {
+    ContextId::parse(id).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Context store; in memory, or persisted under `path`
+#[pyclass(module = "context_mcp._native", frozen)]
+struct ContextStore {
+    inner: Arc<CoreContextStore>,
+}
+
+#[pymethods]
+impl ContextStore {
+    #[new]
+    #[pyo3(signature = (path=None, cache_size=1000))]
+    fn new(path: Option<PathBuf>, cache_size: usize) -> PyResult<Self> {
+        let config = match path {
+            Some(path) => StorageConfig {
+                auto_cleanup: false,
+                ..StorageConfig::with_persistence(cache_size, path)
+            },
+            None => StorageConfig {
+                auto_cleanup: false,
+                ..StorageConfig::memory_only(cache_size)
+            },
+        };
+        let inner = CoreContextStore::new(config).map_err(to_py_err)?;
+        Ok(Self {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Store a context and return its ID
+    #[pyo3(signature = (content, domain=None, tags=None, importance=None, source=None))]
+    fn store(
+        &self,
+        py: Python<'_>,
+        content: String,
+        domain: Option<String>,
+        tags: Option<Vec<String>>,
+        importance: Option<f32>,
+        source: Option<String>,
+    ) -> PyResult<String> {
+        let domain = domain.map_or_else(ContextDomain::default, |d| ContextDomain::parse(&d));
+        let mut context = Context::new(content, domain);
+        if let Some(tags) = tags {
+            context = context.with_tags(tags);
+        }
+        if let Some(importance) = importance {
+            context = context.with_importance(importance);
+        }
+        if let Some(source) = source {
+            context = context.with_source(source);
+        }
+        let outcome = block_on(py, self.inner.store(context))?;
+        Ok(outcome.id.to_string())
+    }
+
+    /// The context as JSON, or `None` if it does not exist
+    fn get(&self, py: Python<'_>, id: String) -> PyResult<Option<String>> {
+        let id = parse_id(&id)?;
+        block_on(py, self.inner.get(&id))?
+            .map(|context| to_json(&context))
+            .transpose()
+    }
+
+    /// Matching contexts as a JSON array
+    #[pyo3(signature = (domain=None, tags=None, min_importance=None, limit=100))]
+    fn query(
+        &self,
+        py: Python<'_>,
+        domain: Option<String>,
+        tags: Option<Vec<String>>,
+        min_importance: Option<f32>,
+        limit: usize,
+    ) -> PyResult<String> {
+        let mut query = ContextQuery::new().with_limit(limit);
+        if let Some(domain) = domain {
+            query = query.with_domain(ContextDomain::parse(&domain));
+        }
+        if let Some(tags) = tags {
+            query = query.with_tags(tags);
+        }
+        if let Some(min_importance) = min_importance {
+            query = query.with_min_importance(min_importance);
+        }
+        to_json(&block_on(py, self.inner.query(&query))?)
+    }
+
+    /// Storage statistics as JSON
+    fn stats(&self, py: Python<'_>) -> PyResult<String> {
+        let stats = block_on(py, async { Ok(self.inner.stats().await) })?;
+        to_json(&stats)
+    }
+}
+
+/// Retrieval over a store, scored as the `retrieve_contexts` tool scores it
+#[pyclass(module = "context_mcp._native", frozen)]
+struct RagProcessor {
+    inner: Arc<CoreRagProcessor>,
+}
+
+#[pymethods]
+impl RagProcessor {
+    #[new]
+    fn new(store: PyRef<'_, ContextStore>) -> Self {
+        Self {
+            inner: Arc::new(CoreRagProcessor::with_defaults(store.inner.clone())),
+        }
+    }
+
+    /// Retrieval result as JSON, best contexts first
+    #[pyo3(signature = (text=None, domain=None, tags=None, min_importance=None, max_results=None))]
+    fn retrieve(
+        &self,
+        py: Python<'_>,
+        text: Option<String>,
+        domain: Option<String>,
+        tags: Option<Vec<String>>,
+        min_importance: Option<f32>,
+        max_results: Option<usize>,
+    ) -> PyResult<String> {
+        let query = RetrievalQuery {
+            text,
+            domain: domain.as_deref().map(ContextDomain::parse),
+            tags: tags.unwrap_or_default(),
+            min_importance,
+            max_results,
+            ..Default::default()
+        };
+        to_json(&block_on(py, self.inner.retrieve(&query))?)
+    }
+}
+
+#[pymodule]
+fn _native(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<ContextStore>()?;
+    m.add_class::<RagProcessor>()?;
+    Ok(())
+}
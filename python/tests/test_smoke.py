@@ -0,0 +1,47 @@
+"""Store and retrieve round trips through the native module."""
+
+import pytest
+
+from context_mcp import ContextStore, RagProcessor, RetrievalQuery
+
+
+@pytest.fixture
+def store(tmp_path):
+    return ContextStore(tmp_path / "store")
+
+
+def test_store_get_round_trip(store):
+    context_id = store.store(
+        "rust borrow checker", domain="code", tags=["rust"], importance=0.8
+    )
+
+    context = store.get(context_id)
+    assert context.content == "rust borrow checker"
+    assert context.tags == ["rust"]
+    assert context.importance == pytest.approx(0.8)
+    assert store.get("ctx-does-not-exist") is None
+
+
+def test_query_filters_by_domain(store):
+    store.store("notes", domain="general")
+    code_id = store.store("fn main() {}", domain="code")
+
+    assert [c.id for c in store.query(domain="code")] == [code_id]
+
+
+def test_store_then_retrieve(store):
+    important = store.store("vector index", domain="code", importance=0.9)
+    store.store("grocery list", domain="general", importance=0.2)
+
+    result = RagProcessor(store).retrieve(RetrievalQuery(text="index"))
+
+    assert result.contexts[0].context.id == important
+    assert result.candidates_considered == 2
+    assert store.stats()["memory_count"] == 2
+
+
+def test_persists_across_reopen(tmp_path):
+    path = tmp_path / "store"
+    context_id = ContextStore(path).store("kept on disk")
+
+    assert ContextStore(path).get(context_id).content == "kept on disk"
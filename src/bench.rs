@@ -0,0 +1,366 @@
+//! Load generator for a running server
+//!
+//! Pre-populates a synthetic corpus, then runs writer and reader workers
+//! against the server for a fixed duration and reports per-operation
+//! latency percentiles and error counts.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context as _};
+use clap::Args;
+use context_mcp::client::result_text;
+use futures::stream::{self, StreamExt};
+use serde_json::{json, Value};
+
+use crate::cli_client::{ConnectionArgs, McpClient};
+
+/// Words synthetic contexts are built from
+const VOCABULARY: &[&str] = &[
+    "async",
+    "borrow",
+    "cache",
+    "checker",
+    "cluster",
+    "context",
+    "decay",
+    "domain",
+    "embedding",
+    "index",
+    "latency",
+    "lifetime",
+    "memory",
+    "query",
+    "request",
+    "retrieval",
+    "server",
+    "storage",
+    "stream",
+    "temporal",
+    "thread",
+    "token",
+    "trait",
+    "vector",
+];
+
+/// Words per synthetic context
+const CONTENT_WORDS: usize = 24;
+
+/// Options for the `bench` subcommand
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+
+    /// Concurrent workers storing new contexts
+    #[arg(long, default_value = "4")]
+    pub writers: usize,
+
+    /// Concurrent workers reading contexts
+    #[arg(long, default_value = "16")]
+    pub readers: usize,
+
+    /// How long to run the workload, e.g. 60s or 5m
+    #[arg(long, default_value = "60s", value_parser = humantime::parse_duration)]
+    pub duration: Duration,
+
+    /// Contexts stored before the workload starts
+    #[arg(long, default_value = "10000")]
+    pub corpus_size: usize,
+
+    /// Target operations per second across all workers (0 = unthrottled)
+    #[arg(long, default_value = "0")]
+    pub rate: u32,
+
+    /// Relative weights of reader operations, e.g. retrieve=2,query=1,get=1
+    #[arg(long, default_value = "retrieve=2,query=1,get=1", value_parser = parse_read_mix)]
+    pub read_mix: ReadMix,
+}
+
+/// Operation issued by a worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Operation {
+    Store,
+    Retrieve,
+    Query,
+    Get,
+}
+
+impl Operation {
+    fn name(self) -> &'static str {
+        match self {
+            Operation::Store => "store",
+            Operation::Retrieve => "retrieve",
+            Operation::Query => "query",
+            Operation::Get => "get",
+        }
+    }
+}
+
+/// Weighted choice of reader operations
+#[derive(Debug, Clone)]
+pub struct ReadMix(Vec<(Operation, u32)>);
+
+impl ReadMix {
+    fn pick(&self) -> Operation {
+        let total: u32 = self.0.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rand::random_range(0..total);
+        for &(op, weight) in &self.0 {
+            if roll < weight {
+                return op;
+            }
+            roll -= weight;
+        }
+        self.0[0].0
+    }
+}
+
+/// Parse `op=weight` pairs; ops are retrieve, query and get
+fn parse_read_mix(s: &str) -> Result<ReadMix, String> {
+    let mut mix = Vec::new();
+    for pair in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (name, weight) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("'{}' is not op=weight", pair))?;
+        let op = match name.trim() {
+            "retrieve" => Operation::Retrieve,
+            "query" => Operation::Query,
+            "get" => Operation::Get,
+            other => return Err(format!("unknown read operation '{}'", other)),
+        };
+        let weight: u32 = weight
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{}' is not a whole-number weight", weight))?;
+        if weight > 0 {
+            mix.push((op, weight));
+        }
+    }
+    if mix.is_empty() {
+        return Err("at least one operation needs a positive weight".to_string());
+    }
+    Ok(ReadMix(mix))
+}
+
+/// Latencies and failures recorded by one worker
+#[derive(Default)]
+struct Samples {
+    latencies: BTreeMap<Operation, Vec<Duration>>,
+    errors: BTreeMap<Operation, u64>,
+}
+
+impl Samples {
+    fn record(&mut self, op: Operation, elapsed: Duration, ok: bool) {
+        if ok {
+            self.latencies.entry(op).or_default().push(elapsed);
+        } else {
+            *self.errors.entry(op).or_default() += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Samples) {
+        for (op, latencies) in other.latencies {
+            self.latencies.entry(op).or_default().extend(latencies);
+        }
+        for (op, errors) in other.errors {
+            *self.errors.entry(op).or_default() += errors;
+        }
+    }
+}
+
+/// Synthetic content, distinct for every `seed`
+fn synthetic_content(seed: u64) -> String {
+    let mut words: Vec<&str> = (0..CONTENT_WORDS)
+        .map(|_| VOCABULARY[rand::random_range(0..VOCABULARY.len())])
+        .collect();
+    words.push("seed");
+    let mut content = words.join(" ");
+    content.push_str(&format!(" {}", seed));
+    content
+}
+
+/// Tool name and arguments for one operation
+fn request_for(op: Operation, seed: u64, ids: &[String]) -> (&'static str, Value) {
+    let word = || VOCABULARY[rand::random_range(0..VOCABULARY.len())];
+    match op {
+        Operation::Store => (
+            "store_context",
+            json!({"content": synthetic_content(seed), "domain": "Code", "tags": [word()]}),
+        ),
+        Operation::Retrieve => (
+            "retrieve_contexts",
+            json!({"text": format!("{} {}", word(), word()), "max_results": 10}),
+        ),
+        Operation::Query => ("query_contexts", json!({"tags": [word()], "limit": 10})),
+        Operation::Get => (
+            "get_context",
+            json!({"id": ids[rand::random_range(0..ids.len())]}),
+        ),
+    }
+}
+
+/// Issue one operation, returning the tool's text output on success
+async fn issue(client: &McpClient, name: &str, args: Value) -> anyhow::Result<String> {
+    let result = client.call_tool(name, args).await?;
+    let text = result_text(&result);
+    if result.is_error {
+        bail!("{}", text);
+    }
+    Ok(text)
+}
+
+/// Store the initial corpus and return the stored IDs
+async fn populate(
+    client: &McpClient,
+    size: usize,
+    concurrency: usize,
+) -> anyhow::Result<Vec<String>> {
+    let results: Vec<anyhow::Result<String>> = stream::iter(0..size as u64)
+        .map(|seed| async move {
+            let (name, args) = request_for(Operation::Store, seed, &[]);
+            let text = issue(client, name, args).await?;
+            let body: Value = serde_json::from_str(&text).context("store returned non-JSON")?;
+            body["id"]
+                .as_str()
+                .map(str::to_string)
+                .context("store returned no id")
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    results.into_iter().collect()
+}
+
+/// Run operations until `deadline`, pacing each call by `interval`
+async fn worker(
+    client: Arc<McpClient>,
+    ids: Arc<Vec<String>>,
+    next_op: impl Fn() -> Operation,
+    first_seed: u64,
+    interval: Option<Duration>,
+    deadline: Instant,
+) -> Samples {
+    let mut samples = Samples::default();
+    let mut ticker = interval.map(tokio::time::interval);
+    let mut seed = first_seed;
+
+    while Instant::now() < deadline {
+        if let Some(ref mut ticker) = ticker {
+            ticker.tick().await;
+        }
+        let op = next_op();
+        let (name, args) = request_for(op, seed, &ids);
+        seed += 1;
+
+        let started = Instant::now();
+        let ok = issue(&client, name, args).await.is_ok();
+        samples.record(op, started.elapsed(), ok);
+    }
+    samples
+}
+
+/// Latency at percentile `p` (0-100) of sorted samples
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0) * (sorted.len() - 1) as f64).round() as usize;
+    sorted[rank.min(sorted.len() - 1)]
+}
+
+/// Run the load test and print a report
+pub async fn run(args: BenchArgs) -> anyhow::Result<()> {
+    if args.writers + args.readers == 0 {
+        bail!("--writers and --readers cannot both be zero");
+    }
+    if args.readers > 0 && args.corpus_size == 0 {
+        bail!("readers need a corpus; set --corpus-size above zero");
+    }
+
+    let client = Arc::new(McpClient::connect(&args.connection).await?);
+
+    eprintln!("Populating {} contexts...", args.corpus_size);
+    let started = Instant::now();
+    let ids = Arc::new(populate(&client, args.corpus_size, args.writers + args.readers).await?);
+    eprintln!("Populated in {:.1}s", started.elapsed().as_secs_f64());
+
+    let workers = (args.writers + args.readers) as u32;
+    let interval =
+        (args.rate > 0).then(|| Duration::from_secs_f64(f64::from(workers) / f64::from(args.rate)));
+    let deadline = Instant::now() + args.duration;
+
+    eprintln!(
+        "Running {} writers and {} readers for {}...",
+        args.writers,
+        args.readers,
+        humantime::format_duration(args.duration)
+    );
+    let mut handles = Vec::new();
+    for w in 0..args.writers {
+        // Seeds past the corpus keep writer content distinct from it and from each other
+        let first_seed = args.corpus_size as u64 + (w as u64) * (u32::MAX as u64);
+        handles.push(tokio::spawn(worker(
+            client.clone(),
+            ids.clone(),
+            || Operation::Store,
+            first_seed,
+            interval,
+            deadline,
+        )));
+    }
+    for _ in 0..args.readers {
+        let mix = args.read_mix.clone();
+        handles.push(tokio::spawn(worker(
+            client.clone(),
+            ids.clone(),
+            move || mix.pick(),
+            0,
+            interval,
+            deadline,
+        )));
+    }
+
+    let mut samples = Samples::default();
+    for handle in handles {
+        samples.merge(handle.await?);
+    }
+    let elapsed = args.duration.as_secs_f64();
+
+    println!(
+        "{:<10} {:>9} {:>8} {:>9} {:>10} {:>10} {:>10} {:>10}",
+        "operation", "ok", "errors", "ops/s", "p50 ms", "p90 ms", "p99 ms", "max ms"
+    );
+    let mut ops: Vec<Operation> = samples
+        .latencies
+        .keys()
+        .chain(samples.errors.keys())
+        .copied()
+        .collect();
+    ops.sort();
+    ops.dedup();
+    let mut total_errors = 0;
+    for op in ops {
+        let mut latencies = samples.latencies.remove(&op).unwrap_or_default();
+        latencies.sort();
+        let errors = samples.errors.get(&op).copied().unwrap_or(0);
+        total_errors += errors;
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        println!(
+            "{:<10} {:>9} {:>8} {:>9.1} {:>10.2} {:>10.2} {:>10.2} {:>10.2}",
+            op.name(),
+            latencies.len(),
+            errors,
+            (latencies.len() as u64 + errors) as f64 / elapsed,
+            ms(percentile(&latencies, 50.0)),
+            ms(percentile(&latencies, 90.0)),
+            ms(percentile(&latencies, 99.0)),
+            ms(latencies.last().copied().unwrap_or_default()),
+        );
+    }
+
+    if total_errors > 0 {
+        eprintln!("{} operations failed", total_errors);
+    }
+    Ok(())
+}
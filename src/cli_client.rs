@@ -0,0 +1,301 @@
+//! Shell client for calling server tools
+//!
+//! Builds JSON-RPC `tools/call` requests and sends them to a server over HTTP
+//! or to a child process speaking the stdio transport.
+
+use std::process::Stdio;
+
+use anyhow::{bail, Context as _};
+use clap::{Args, Subcommand};
+use serde_json::{json, Map, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::Mutex;
+
+use context_mcp::client::{result_text, ContextMcpClient};
+use context_mcp::protocol::{CallToolResult, JsonRpcRequest, JsonRpcResponse};
+
+/// Options for the `client` subcommand
+#[derive(Args, Debug)]
+pub struct ClientArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+
+    #[command(subcommand)]
+    pub call: ToolCall,
+}
+
+/// Where to send requests
+#[derive(Args, Debug, Clone)]
+pub struct ConnectionArgs {
+    /// Server URL; requests go to its /mcp endpoint
+    #[arg(
+        long,
+        default_value = "http://127.0.0.1:3000",
+        conflicts_with = "stdio_cmd"
+    )]
+    pub url: String,
+
+    /// Spawn this command and talk to it over the stdio transport
+    #[arg(long)]
+    pub stdio_cmd: Option<String>,
+}
+
+/// Tool to call
+#[derive(Subcommand, Debug)]
+pub enum ToolCall {
+    /// Store a context
+    Store {
+        /// Context content
+        #[arg(long)]
+        content: String,
+        /// Context domain
+        #[arg(long)]
+        domain: Option<String>,
+        /// Comma-separated tags
+        #[arg(long, value_delimiter = ',')]
+        tags: Vec<String>,
+        /// Source of the context
+        #[arg(long)]
+        source: Option<String>,
+        /// Importance 0.0-1.0
+        #[arg(long)]
+        importance: Option<f64>,
+        /// Time to live in hours
+        #[arg(long)]
+        ttl_hours: Option<f64>,
+    },
+    /// Get a context by ID
+    Get {
+        /// Context ID
+        id: String,
+    },
+    /// Query contexts with filters
+    Query {
+        /// Filter by domain
+        #[arg(long)]
+        domain: Option<String>,
+        /// Comma-separated tag filter
+        #[arg(long, value_delimiter = ',')]
+        tags: Vec<String>,
+        /// Minimum importance threshold
+        #[arg(long)]
+        min_importance: Option<f64>,
+        /// Maximum results
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Retrieve contexts with RAG scoring
+    Retrieve {
+        /// Text query
+        text: String,
+        /// Domain filter
+        #[arg(long)]
+        domain: Option<String>,
+        /// Comma-separated tag filter
+        #[arg(long, value_delimiter = ',')]
+        tags: Vec<String>,
+        /// Maximum results
+        #[arg(long)]
+        max_results: Option<usize>,
+    },
+    /// Delete a context by ID
+    Delete {
+        /// Context ID
+        id: String,
+    },
+    /// Print storage statistics
+    Stats,
+}
+
+impl ToolCall {
+    /// Tool name and arguments for this call
+    pub fn into_tool_call(self) -> (&'static str, Value) {
+        let mut args = Map::new();
+        let name = match self {
+            ToolCall::Store {
+                content,
+                domain,
+                tags,
+                source,
+                importance,
+                ttl_hours,
+            } => {
+                args.insert("content".into(), json!(content));
+                insert_opt(&mut args, "domain", domain);
+                insert_tags(&mut args, tags);
+                insert_opt(&mut args, "source", source);
+                insert_opt(&mut args, "importance", importance);
+                insert_opt(&mut args, "ttl_hours", ttl_hours);
+                "store_context"
+            }
+            ToolCall::Get { id } => {
+                args.insert("id".into(), json!(id));
+                "get_context"
+            }
+            ToolCall::Query {
+                domain,
+                tags,
+                min_importance,
+                limit,
+            } => {
+                insert_opt(&mut args, "domain", domain);
+                insert_tags(&mut args, tags);
+                insert_opt(&mut args, "min_importance", min_importance);
+                insert_opt(&mut args, "limit", limit);
+                "query_contexts"
+            }
+            ToolCall::Retrieve {
+                text,
+                domain,
+                tags,
+                max_results,
+            } => {
+                args.insert("text".into(), json!(text));
+                insert_opt(&mut args, "domain", domain);
+                insert_tags(&mut args, tags);
+                insert_opt(&mut args, "max_results", max_results);
+                "retrieve_contexts"
+            }
+            ToolCall::Delete { id } => {
+                args.insert("id".into(), json!(id));
+                "delete_context"
+            }
+            ToolCall::Stats => "get_storage_stats",
+        };
+        (name, Value::Object(args))
+    }
+}
+
+fn insert_opt<T: Into<Value>>(args: &mut Map<String, Value>, key: &str, value: Option<T>) {
+    if let Some(value) = value {
+        args.insert(key.to_string(), value.into());
+    }
+}
+
+fn insert_tags(args: &mut Map<String, Value>, tags: Vec<String>) {
+    if !tags.is_empty() {
+        args.insert("tags".to_string(), json!(tags));
+    }
+}
+
+/// Connection to a running server
+pub enum McpClient {
+    Http(ContextMcpClient),
+    Stdio {
+        // Held so the server is killed when the client is dropped
+        _child: Child,
+        // Boxed to keep the enum small next to the HTTP variant
+        io: Box<Mutex<(ChildStdin, Lines<BufReader<ChildStdout>>)>>,
+    },
+}
+
+impl McpClient {
+    /// Connect as described by the command-line options
+    pub async fn connect(args: &ConnectionArgs) -> anyhow::Result<Self> {
+        match &args.stdio_cmd {
+            Some(cmd) => Self::spawn(cmd).await,
+            None => Ok(Self::http(&args.url)),
+        }
+    }
+
+    /// Send requests to a server's HTTP endpoint
+    pub fn http(url: &str) -> Self {
+        McpClient::Http(ContextMcpClient::new(url))
+    }
+
+    /// Spawn a stdio server and complete the initialize handshake
+    pub async fn spawn(cmd: &str) -> anyhow::Result<Self> {
+        let mut parts = cmd.split_whitespace();
+        let program = parts.next().context("--stdio-cmd is empty")?;
+        let mut child = tokio::process::Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("could not start '{}'", cmd))?;
+
+        let stdin = child.stdin.take().context("child stdin unavailable")?;
+        let stdout = child.stdout.take().context("child stdout unavailable")?;
+        let client = McpClient::Stdio {
+            _child: child,
+            io: Box::new(Mutex::new((stdin, BufReader::new(stdout).lines()))),
+        };
+        client
+            .request("initialize", Some(json!({})))
+            .await
+            .context("initialize handshake failed")?;
+        Ok(client)
+    }
+
+    /// Send a JSON-RPC request and return its result
+    pub async fn request(&self, method: &str, params: Option<Value>) -> anyhow::Result<Value> {
+        let io = match self {
+            McpClient::Http(client) => {
+                return client
+                    .request(method, params)
+                    .await
+                    .with_context(|| format!("{} to {} failed", method, client.endpoint()));
+            }
+            McpClient::Stdio { io, .. } => io,
+        };
+
+        let request = JsonRpcRequest::new(method, params);
+        let mut io = io.lock().await;
+        let (stdin, lines) = &mut *io;
+        let mut line = serde_json::to_string(&request)?;
+        line.push('\n');
+        stdin.write_all(line.as_bytes()).await?;
+        stdin.flush().await?;
+
+        // Skip anything that is not the reply to this request
+        let response = loop {
+            let line = lines
+                .next_line()
+                .await?
+                .context("server closed its stdout")?;
+            if let Ok(response) = serde_json::from_str::<JsonRpcResponse>(&line) {
+                if response.id == request.id {
+                    break response;
+                }
+            }
+        };
+
+        if let Some(error) = response.error {
+            bail!("{} failed: {} (code {})", method, error.message, error.code);
+        }
+        response
+            .result
+            .with_context(|| format!("{} returned neither result nor error", method))
+    }
+
+    /// Call a tool
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> anyhow::Result<CallToolResult> {
+        let result = self
+            .request(
+                "tools/call",
+                Some(json!({"name": name, "arguments": arguments})),
+            )
+            .await?;
+        serde_json::from_value(result).context("invalid tool result")
+    }
+}
+
+/// Run one tool call and pretty-print the result
+pub async fn run(args: ClientArgs) -> anyhow::Result<()> {
+    let client = McpClient::connect(&args.connection).await?;
+    let (name, arguments) = args.call.into_tool_call();
+    let result = client.call_tool(name, arguments).await?;
+
+    let text = result_text(&result);
+    if result.is_error {
+        bail!("{} returned an error: {}", name, text);
+    }
+    match serde_json::from_str::<Value>(&text) {
+        Ok(value) => println!("{}", serde_json::to_string_pretty(&value)?),
+        Err(_) => println!("{}", text),
+    }
+    Ok(())
+}
@@ -0,0 +1,286 @@
+//! Typed client for a server's HTTP transport
+//!
+//! [`ContextMcpClient`] sends JSON-RPC `tools/call` requests to a running
+//! server's `/mcp` endpoint, using the request and response types from
+//! [`crate::protocol`], and maps failures back into [`ContextError`].
+
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use serde_json::{json, Map, Value};
+
+use crate::context::{ContextDomain, ContextId};
+use crate::error::{ContextError, Result};
+use crate::protocol::{
+    CallToolResult, Content, JsonRpcError, JsonRpcRequest, JsonRpcResponse, RequestId,
+};
+
+/// Optional fields for [`ContextMcpClient::store`]
+#[derive(Debug, Clone, Default)]
+pub struct StoreOptions {
+    /// Context domain (default: General)
+    pub domain: Option<ContextDomain>,
+    /// Tags for categorization
+    pub tags: Vec<String>,
+    /// Source of the context
+    pub source: Option<String>,
+    /// Importance 0.0-1.0
+    pub importance: Option<f32>,
+    /// Time to live in hours
+    pub ttl_hours: Option<f64>,
+}
+
+/// Filters for [`ContextMcpClient::query`] and [`ContextMcpClient::retrieve`]
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    /// Only contexts in this domain
+    pub domain: Option<ContextDomain>,
+    /// Only contexts with these tags
+    pub tags: Vec<String>,
+    /// Minimum importance threshold
+    pub min_importance: Option<f32>,
+    /// Maximum age in hours
+    pub max_age_hours: Option<f64>,
+    /// Maximum results
+    pub limit: Option<usize>,
+}
+
+impl QueryOptions {
+    fn into_args(self, args: &mut Map<String, Value>, limit_key: &str) {
+        if let Some(domain) = self.domain {
+            args.insert("domain".into(), json!(domain.to_string()));
+        }
+        if !self.tags.is_empty() {
+            args.insert("tags".into(), json!(self.tags));
+        }
+        if let Some(min_importance) = self.min_importance {
+            args.insert("min_importance".into(), json!(min_importance));
+        }
+        if let Some(max_age_hours) = self.max_age_hours {
+            args.insert("max_age_hours".into(), json!(max_age_hours));
+        }
+        if let Some(limit) = self.limit {
+            args.insert(limit_key.into(), json!(limit));
+        }
+    }
+}
+
+/// Client for a server's HTTP transport
+///
+/// Request IDs are sequential per client. Responses are matched to their
+/// request, so one client can be shared between tasks.
+pub struct ContextMcpClient {
+    http: reqwest::Client,
+    endpoint: String,
+    token: Option<String>,
+    next_id: AtomicI64,
+}
+
+impl ContextMcpClient {
+    /// Client for the server at `url`; requests go to its `/mcp` endpoint
+    pub fn new(url: &str) -> Self {
+        let url = url.trim_end_matches('/');
+        let endpoint = if url.ends_with("/mcp") {
+            url.to_string()
+        } else {
+            format!("{}/mcp", url)
+        };
+        Self {
+            http: reqwest::Client::new(),
+            endpoint,
+            token: None,
+            next_id: AtomicI64::new(1),
+        }
+    }
+
+    /// Send `token` as a bearer token, e.g. for a server behind an authenticating proxy
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Use a preconfigured HTTP client, e.g. one with timeouts or custom TLS roots
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Endpoint requests are sent to
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Send a JSON-RPC request and return its result
+    ///
+    /// JSON-RPC errors are mapped back to the [`ContextError`] the server
+    /// raised; transport failures become [`ContextError::Io`] or
+    /// [`ContextError::Timeout`].
+    pub async fn request(&self, method: &str, params: Option<Value>) -> Result<Value> {
+        let mut request = JsonRpcRequest::new(method, params);
+        request.id = RequestId::Number(self.next_id.fetch_add(1, Ordering::Relaxed));
+
+        let mut builder = self.http.post(self.endpoint.as_str()).json(&request);
+        if let Some(ref token) = self.token {
+            builder = builder.bearer_auth(token);
+        }
+        let response: JsonRpcResponse = builder
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(transport_error)?
+            .json()
+            .await
+            .map_err(transport_error)?;
+
+        if response.id != request.id {
+            return Err(ContextError::Protocol(format!(
+                "response id {:?} does not match request id {:?}",
+                response.id, request.id
+            )));
+        }
+        if let Some(error) = response.error {
+            return Err(error.into());
+        }
+        response.result.ok_or_else(|| {
+            ContextError::Protocol(format!("{} returned neither result nor error", method))
+        })
+    }
+
+    /// Call a tool and return its raw result
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult> {
+        let result = self
+            .request(
+                "tools/call",
+                Some(json!({"name": name, "arguments": arguments})),
+            )
+            .await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Call a tool that answers with JSON, mapping tool errors to [`ContextError`]
+    async fn call_json(&self, name: &str, arguments: Map<String, Value>) -> Result<Value> {
+        let result = self.call_tool(name, Value::Object(arguments)).await?;
+        let text = result_text(&result);
+        if result.is_error {
+            return Err(tool_error(name, &text));
+        }
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Store a context and return its ID
+    pub async fn store(&self, content: &str, options: StoreOptions) -> Result<ContextId> {
+        let mut args = Map::new();
+        args.insert("content".into(), json!(content));
+        if let Some(domain) = options.domain {
+            args.insert("domain".into(), json!(domain.to_string()));
+        }
+        if !options.tags.is_empty() {
+            args.insert("tags".into(), json!(options.tags));
+        }
+        if let Some(source) = options.source {
+            args.insert("source".into(), json!(source));
+        }
+        if let Some(importance) = options.importance {
+            args.insert("importance".into(), json!(importance));
+        }
+        if let Some(ttl_hours) = options.ttl_hours {
+            args.insert("ttl_hours".into(), json!(ttl_hours));
+        }
+
+        let body = self.call_json("store_context", args).await?;
+        let id = body["id"]
+            .as_str()
+            .ok_or_else(|| ContextError::Protocol("store_context returned no id".into()))?;
+        ContextId::parse(id)
+    }
+
+    /// Get a context as the `get_context` tool renders it, or `None` if it does not exist
+    pub async fn get(&self, id: &ContextId) -> Result<Option<Value>> {
+        let mut args = Map::new();
+        args.insert("id".into(), json!(id.to_string()));
+        match self.call_json("get_context", args).await {
+            Ok(body) => Ok(Some(body)),
+            Err(e) if e.is_not_found() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Delete a context, returning whether it existed
+    pub async fn delete(&self, id: &ContextId) -> Result<bool> {
+        let mut args = Map::new();
+        args.insert("id".into(), json!(id.to_string()));
+        match self.call_json("delete_context", args).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.is_not_found() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Query contexts with filters, returning the matching contexts
+    pub async fn query(&self, options: QueryOptions) -> Result<Vec<Value>> {
+        let mut args = Map::new();
+        options.into_args(&mut args, "limit");
+        let body = self.call_json("query_contexts", args).await?;
+        Ok(contexts_of(body))
+    }
+
+    /// Retrieve contexts with RAG scoring, best first
+    pub async fn retrieve(&self, text: &str, options: QueryOptions) -> Result<Vec<Value>> {
+        let mut args = Map::new();
+        args.insert("text".into(), json!(text));
+        options.into_args(&mut args, "max_results");
+        let body = self.call_json("retrieve_contexts", args).await?;
+        Ok(contexts_of(body))
+    }
+
+    /// Storage statistics as reported by the `get_storage_stats` tool
+    pub async fn stats(&self) -> Result<Value> {
+        self.call_json("get_storage_stats", Map::new()).await
+    }
+}
+
+/// Concatenated text content of a tool result
+pub fn result_text(result: &CallToolResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            Content::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn contexts_of(mut body: Value) -> Vec<Value> {
+    match body["contexts"].take() {
+        Value::Array(contexts) => contexts,
+        _ => Vec::new(),
+    }
+}
+
+fn transport_error(err: reqwest::Error) -> ContextError {
+    if err.is_timeout() {
+        ContextError::Timeout(err.to_string())
+    } else {
+        ContextError::Io(std::io::Error::other(err))
+    }
+}
+
+/// Error for a tool result with `is_error` set
+///
+/// Results built by [`CallToolResult::from_error`] carry the structured
+/// error and map back to the original variant; plain messages become
+/// [`ContextError::Internal`].
+fn tool_error(tool: &str, text: &str) -> ContextError {
+    if let Ok(body) = serde_json::from_str::<Value>(text) {
+        if let (Some(code), Some(message)) = (body["code"].as_i64(), body["error"].as_str()) {
+            return JsonRpcError {
+                code: code as i32,
+                message: message.to_string(),
+                data: Some(body["data"].clone()).filter(|data| !data.is_null()),
+            }
+            .into();
+        }
+    }
+    ContextError::Internal(format!("{} failed: {}", tool, text))
+}
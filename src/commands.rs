@@ -0,0 +1,242 @@
+//! Offline maintenance commands run directly against a storage directory
+//!
+//! sled locks its directory while open, so these refuse to run while a
+//! server holds the same path.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Context as _};
+use context_mcp::context::Context;
+use context_mcp::embeddings::EmbeddingGenerator;
+use context_mcp::storage::{
+    list_snapshots_in, ContextStore, ImportMode, StatsInclude, StorageConfig, DEFAULT_SNAPSHOT_DIR,
+};
+use context_mcp::ternary::{SparsityConfig, TernaryEmbeddingGenerator};
+use futures::future::join_all;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncWrite, BufWriter};
+
+/// Open a persisted store for an offline command
+async fn open_store(path: &Path) -> anyhow::Result<ContextStore> {
+    if !path.exists() {
+        bail!("no storage found at {}", path.display());
+    }
+
+    let config = StorageConfig {
+        auto_cleanup: false,
+        ..StorageConfig::with_persistence(1000, path)
+    };
+    ContextStore::open(config).await.with_context(|| {
+        format!(
+            "could not open storage at {} (is a server running against it?)",
+            path.display()
+        )
+    })
+}
+
+/// Print storage statistics, including per-domain counts and sizes
+pub async fn stats(path: &Path) -> anyhow::Result<()> {
+    let store = open_store(path).await?;
+    let stats = store
+        .stats_with(StatsInclude {
+            domains: true,
+            sizes: true,
+        })
+        .await?;
+    println!("{}", serde_json::to_string_pretty(&stats)?);
+    Ok(())
+}
+
+/// Delete every expired context
+pub async fn cleanup(path: &Path) -> anyhow::Result<()> {
+    let store = open_store(path).await?;
+    let removed = store.cleanup_expired().await?;
+    println!("Removed {} expired contexts", removed);
+    Ok(())
+}
+
+/// Write every context as one JSON object per line
+pub async fn export(path: &Path, output: Option<&Path>) -> anyhow::Result<()> {
+    let store = open_store(path).await?;
+    let writer: Box<dyn AsyncWrite + Unpin> = match output {
+        Some(output) => Box::new(BufWriter::new(
+            File::create(output)
+                .await
+                .with_context(|| format!("creating {}", output.display()))?,
+        )),
+        None => Box::new(BufWriter::new(tokio::io::stdout())),
+    };
+
+    let exported = store.export_all(writer).await?;
+    eprintln!("Exported {} contexts", exported);
+    Ok(())
+}
+
+/// Load contexts from a JSONL export
+pub async fn import(path: &Path, input: Option<&Path>, skip_existing: bool) -> anyhow::Result<()> {
+    let store = open_store(path).await?;
+    let reader: Box<dyn AsyncRead + Unpin> = match input {
+        Some(input) => Box::new(
+            File::open(input)
+                .await
+                .with_context(|| format!("opening {}", input.display()))?,
+        ),
+        None => Box::new(tokio::io::stdin()),
+    };
+
+    let mode = if skip_existing {
+        ImportMode::SkipExisting
+    } else {
+        ImportMode::Overwrite
+    };
+    let summary = store.import(reader, mode).await?;
+    println!(
+        "Imported {} contexts, skipped {}",
+        summary.imported, summary.skipped
+    );
+    Ok(())
+}
+
+/// Check that every persisted record decodes
+pub async fn verify(path: &Path) -> anyhow::Result<()> {
+    let store = open_store(path).await?;
+    let (mut readable, mut corrupt) = (0, 0);
+    for record in store.iter_sled() {
+        match record {
+            Ok(_) => readable += 1,
+            Err(e) => {
+                corrupt += 1;
+                eprintln!("corrupt record: {}", e);
+            }
+        }
+    }
+
+    println!("{} records readable, {} corrupt", readable, corrupt);
+    if corrupt > 0 {
+        bail!("{} corrupt records found", corrupt);
+    }
+    Ok(())
+}
+
+/// Replace the stored contexts with those of a named snapshot
+pub async fn restore(
+    path: &Path,
+    snapshot: &str,
+    snapshot_dir: Option<&Path>,
+) -> anyhow::Result<()> {
+    let dir = snapshot_dir
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| path.join(DEFAULT_SNAPSHOT_DIR));
+    let snapshots = list_snapshots_in(&dir)?;
+    let Some(found) = snapshots.iter().find(|s| s.name == snapshot) else {
+        let names: Vec<&str> = snapshots.iter().map(|s| s.name.as_str()).collect();
+        bail!(
+            "no snapshot named {} in {} (available: {})",
+            snapshot,
+            dir.display(),
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(", ")
+            }
+        );
+    };
+
+    let store = open_store(path).await?;
+    store.restore_from_checkpoint(&found.path).await?;
+    println!(
+        "Restored {} contexts from snapshot {}",
+        store.list_all_ids(None).await?.len(),
+        found.name
+    );
+    Ok(())
+}
+
+/// Generate embeddings for every persisted context that lacks one
+///
+/// Contexts are embedded `batch` at a time and each is persisted as soon as
+/// its batch completes, so an interrupted run loses at most one batch and a
+/// rerun picks up where it stopped. Each embedding is also quantized to
+/// sparse ternary form to report how faithfully it reconstructs.
+pub async fn reindex_embeddings(
+    path: &Path,
+    generator: Arc<dyn EmbeddingGenerator>,
+    batch: usize,
+) -> anyhow::Result<()> {
+    let store = open_store(path).await?;
+    let quantizer =
+        TernaryEmbeddingGenerator::with_sparse(generator.dimension(), SparsityConfig::default());
+
+    let total = store
+        .iter_sled()
+        .filter(|record| matches!(record, Ok(ctx) if ctx.embedding.is_none()))
+        .count();
+    eprintln!("{} contexts need embeddings", total);
+
+    let mut pending = store
+        .iter_sled()
+        .filter_map(|record| record.ok())
+        .filter(|ctx| ctx.embedding.is_none());
+    let (mut embedded, mut failed) = (0, 0);
+    let mut fidelity: Vec<f32> = Vec::new();
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(interrupted);
+
+    loop {
+        let chunk: Vec<Context> = pending.by_ref().take(batch.max(1)).collect();
+        if chunk.is_empty() {
+            break;
+        }
+
+        let generated = tokio::select! {
+            generated = join_all(chunk.iter().map(|ctx| generator.generate(&ctx.content))) => generated,
+            _ = &mut interrupted => {
+                eprintln!("Interrupted; rerun to embed the remaining contexts");
+                break;
+            }
+        };
+
+        for (ctx, embedding) in chunk.into_iter().zip(generated) {
+            match embedding {
+                Ok(embedding) => {
+                    let reconstructed = quantizer.dequantize(&quantizer.quantize(&embedding)?)?;
+                    fidelity.push(cosine(&embedding, &reconstructed));
+                    store.store(ctx.with_embedding(embedding)).await?;
+                    embedded += 1;
+                }
+                Err(e) => {
+                    failed += 1;
+                    eprintln!("could not embed {}: {}", ctx.id, e);
+                }
+            }
+        }
+        eprintln!("{}/{} embedded, {} failed", embedded, total, failed);
+    }
+
+    println!("Embedded {} contexts, {} failed", embedded, failed);
+    if !fidelity.is_empty() {
+        let mean = fidelity.iter().sum::<f32>() / fidelity.len() as f32;
+        let min = fidelity.iter().copied().fold(f32::INFINITY, f32::min);
+        println!(
+            "Ternary quantization fidelity (cosine to original): mean {:.4}, min {:.4}",
+            mean, min
+        );
+    }
+    if failed > 0 {
+        bail!("{} contexts could not be embedded", failed);
+    }
+    Ok(())
+}
+
+/// Cosine similarity of two vectors, 0.0 if either is zero
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
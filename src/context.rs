@@ -3,12 +3,34 @@
 //! Inspired by memory-gate's LearningContext pattern with enhancements
 //! for temporal reasoning and MCP integration.
 
+use async_trait::async_trait;
 use base64::Engine;
 use chrono::{DateTime, Duration, Utc};
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
+use crate::error::{ContextError, Result};
+
+/// Number of past revisions kept in [`Context::history`]
+pub const MAX_REVISIONS: usize = 20;
+
+/// Number of access timestamps kept in [`Context::recent_accesses`]
+pub const RECENT_ACCESS_LIMIT: usize = 10;
+
+/// Longest content regex pattern accepted by [`compile_content_regex`]
+pub const MAX_REGEX_PATTERN_LEN: usize = 1024;
+
+/// Compiled size limit for content regexes, in bytes
+pub const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Longest context ID accepted by [`ContextId::parse`], in bytes
+pub const MAX_ID_LEN: usize = 256;
+
+/// URI scheme prefix for context resources
+pub const CONTEXT_URI_PREFIX: &str = "context://";
+
 /// Unique identifier for a context entry
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
 pub struct ContextId(pub String);
@@ -28,10 +50,80 @@ impl ContextId {
     }
 
     /// Create from a string
+    ///
+    /// Performs no validation; use [`Self::parse`] for IDs from untrusted input.
     pub fn from_string(s: String) -> Self {
         Self(s)
     }
 
+    /// Parse an ID from untrusted input
+    ///
+    /// IDs must be non-empty, at most [`MAX_ID_LEN`] bytes and free of
+    /// control characters, since they end up as storage keys and in URIs.
+    pub fn parse(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            return Err(ContextError::InvalidId("must not be empty".into()));
+        }
+        if s.len() > MAX_ID_LEN {
+            return Err(ContextError::InvalidId(format!(
+                "{} bytes exceeds the {} byte limit",
+                s.len(),
+                MAX_ID_LEN
+            )));
+        }
+        if let Some(c) = s.chars().find(|c| c.is_control()) {
+            return Err(ContextError::InvalidId(format!(
+                "contains control character U+{:04X}",
+                c as u32
+            )));
+        }
+        Ok(Self(s.to_string()))
+    }
+
+    /// Resource URI for this context, with the ID percent-encoded
+    pub fn to_uri(&self) -> String {
+        let mut uri = String::from(CONTEXT_URI_PREFIX);
+        for byte in self.0.bytes() {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+                uri.push(byte as char);
+            } else {
+                uri.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        uri
+    }
+
+    /// Parse a resource URI produced by [`Self::to_uri`]
+    pub fn from_uri(uri: &str) -> Result<Self> {
+        let encoded = uri.strip_prefix(CONTEXT_URI_PREFIX).ok_or_else(|| {
+            ContextError::InvalidId(format!("URI must start with {}", CONTEXT_URI_PREFIX))
+        })?;
+
+        let bytes = encoded.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' {
+                let byte = encoded
+                    .get(i + 1..i + 3)
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| {
+                        ContextError::InvalidId(format!("bad percent-escape at byte {}", i))
+                    })?;
+                decoded.push(byte);
+                i += 3;
+            } else {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+
+        let id = String::from_utf8(decoded)
+            .map_err(|_| ContextError::InvalidId("URI does not decode to UTF-8".into()))?;
+        Self::parse(&id)
+    }
+
     pub fn as_str(&self) -> &str {
         &self.0
     }
@@ -73,6 +165,49 @@ pub enum ContextDomain {
     Custom(String),
 }
 
+/// Displays built-in domains by variant name and custom ones as `custom:<name>`
+impl std::fmt::Display for ContextDomain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::General => write!(f, "General"),
+            Self::Code => write!(f, "Code"),
+            Self::Documentation => write!(f, "Documentation"),
+            Self::Conversation => write!(f, "Conversation"),
+            Self::Filesystem => write!(f, "Filesystem"),
+            Self::WebSearch => write!(f, "WebSearch"),
+            Self::Dataset => write!(f, "Dataset"),
+            Self::Research => write!(f, "Research"),
+            Self::Custom(name) => write!(f, "custom:{}", name),
+        }
+    }
+}
+
+impl ContextDomain {
+    /// Parse a domain name as tool callers write it
+    ///
+    /// Built-in names are case-insensitive and have short aliases (`docs`,
+    /// `chat`, `web`, ...). Names that match no built-in domain become
+    /// `Custom`, keeping their case. A `custom:` prefix forces a custom
+    /// domain, so the output of `Display` always parses back to the same
+    /// value.
+    pub fn parse(s: &str) -> Self {
+        if let Some(name) = s.strip_prefix("custom:") {
+            return Self::Custom(name.to_string());
+        }
+        match s.to_lowercase().as_str() {
+            "general" => Self::General,
+            "code" => Self::Code,
+            "documentation" | "docs" => Self::Documentation,
+            "conversation" | "chat" => Self::Conversation,
+            "filesystem" | "files" => Self::Filesystem,
+            "websearch" | "web" => Self::WebSearch,
+            "dataset" | "data" => Self::Dataset,
+            "research" => Self::Research,
+            _ => Self::Custom(s.to_string()),
+        }
+    }
+}
+
 #[allow(clippy::derivable_impls)]
 impl Default for ContextDomain {
     fn default() -> Self {
@@ -81,7 +216,7 @@ impl Default for ContextDomain {
 }
 
 /// Metadata associated with a context entry
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ContextMetadata {
     /// Source of the context (e.g., "user", "web", "file")
     #[serde(default)]
@@ -106,6 +241,14 @@ pub struct ContextMetadata {
     /// Custom key-value pairs
     #[serde(default)]
     pub custom: std::collections::HashMap<String, serde_json::Value>,
+
+    /// Language of the content as an ISO 639-1 code, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    /// Kept in memory and never evicted; see [`crate::storage::ContextStore::pin`]
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 fn default_importance() -> f32 {
@@ -121,6 +264,142 @@ impl Default for ContextMetadata {
             verified: false,
             screening_status: ScreeningStatus::Unscreened,
             custom: std::collections::HashMap::new(),
+            language: None,
+            pinned: false,
+        }
+    }
+}
+
+/// Key in [`ContextMetadata::custom`] holding the [`ImportanceSignals`] of a suggested importance
+pub const IMPORTANCE_SIGNALS_KEY: &str = "importance_signals";
+
+/// Words and phrases that mark content as urgent or prescriptive
+const URGENCY_MARKERS: &[&str] = &[
+    "asap",
+    "always",
+    "critical",
+    "deadline",
+    "do not",
+    "don't",
+    "fixme",
+    "important",
+    "must",
+    "never",
+    "required",
+    "security",
+    "todo",
+    "urgent",
+    "warning",
+];
+
+/// Tag adjustments applied when no configured prior covers the tag
+const DEFAULT_TAG_PRIORS: &[(&str, f32)] = &[
+    ("bug", 0.1),
+    ("decision", 0.15),
+    ("draft", -0.1),
+    ("security", 0.2),
+    ("temporary", -0.15),
+];
+
+/// Word count at which content stops gaining from length
+const FULL_LENGTH_WORDS: f32 = 200.0;
+
+/// Inputs and result of [`ContextMetadata::suggest_importance_with`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportanceSignals {
+    /// Suggested importance, 0.0-1.0
+    pub importance: f32,
+    /// Baseline for the domain
+    pub domain_prior: f32,
+    /// Unique-word ratio scaled by length, 0.0-1.0
+    pub information_density: f32,
+    /// Urgency markers found in the content
+    pub urgency_markers: Vec<String>,
+    /// Sum of the priors of the tags
+    pub tag_prior: f32,
+}
+
+impl ContextMetadata {
+    /// Suggest an importance for content the caller did not rate
+    ///
+    /// See [`Self::suggest_importance_with`]; this variant uses only the
+    /// built-in tag priors.
+    pub fn suggest_importance(content: &str, domain: &ContextDomain, tags: &[String]) -> f32 {
+        Self::suggest_importance_with(content, domain, tags, &Default::default()).importance
+    }
+
+    /// Suggest an importance and report the signals behind it
+    ///
+    /// The score is the sum of:
+    /// - a domain prior, from 0.3 for conversation to 0.5 for code and
+    ///   research;
+    /// - up to 0.25 for information density: the share of distinct words,
+    ///   scaled down for content under 200 words so a one-liner with no
+    ///   repeats does not score as dense;
+    /// - 0.07 per distinct urgency marker ("must", "never", "urgent", ...),
+    ///   capped at 0.2;
+    /// - the priors of the tags, where `tag_priors` overrides the built-in
+    ///   ones ("security" +0.2, "draft" -0.1, ...) and tags are compared
+    ///   case-insensitively,
+    ///
+    /// clamped to 0.0-1.0.
+    pub fn suggest_importance_with(
+        content: &str,
+        domain: &ContextDomain,
+        tags: &[String],
+        tag_priors: &std::collections::HashMap<String, f32>,
+    ) -> ImportanceSignals {
+        let domain_prior = match domain {
+            ContextDomain::Code | ContextDomain::Research => 0.5,
+            ContextDomain::Documentation | ContextDomain::Dataset => 0.45,
+            ContextDomain::General | ContextDomain::Custom(_) => 0.4,
+            ContextDomain::Filesystem | ContextDomain::WebSearch => 0.35,
+            ContextDomain::Conversation => 0.3,
+        };
+
+        let lower = content.to_lowercase();
+        let words: Vec<&str> = lower
+            .split(|c: char| !c.is_alphanumeric() && c != '\'')
+            .filter(|w| !w.is_empty())
+            .collect();
+        let information_density = if words.is_empty() {
+            0.0
+        } else {
+            let unique: std::collections::HashSet<&str> = words.iter().copied().collect();
+            let length = (words.len() as f32).ln_1p() / FULL_LENGTH_WORDS.ln_1p();
+            unique.len() as f32 / words.len() as f32 * length.min(1.0)
+        };
+
+        let padded = format!(" {} ", words.join(" "));
+        let urgency_markers: Vec<String> = URGENCY_MARKERS
+            .iter()
+            .filter(|marker| padded.contains(&format!(" {} ", marker)))
+            .map(|marker| marker.to_string())
+            .collect();
+
+        let tag_prior: f32 = tags
+            .iter()
+            .map(|tag| {
+                let tag = tag.to_lowercase();
+                tag_priors.get(&tag).copied().unwrap_or_else(|| {
+                    DEFAULT_TAG_PRIORS
+                        .iter()
+                        .find(|(name, _)| *name == tag)
+                        .map_or(0.0, |(_, prior)| *prior)
+                })
+            })
+            .sum();
+
+        let urgency = (0.07 * urgency_markers.len() as f32).min(0.2);
+        let importance =
+            (domain_prior + 0.25 * information_density + urgency + tag_prior).clamp(0.0, 1.0);
+
+        ImportanceSignals {
+            importance,
+            domain_prior,
+            information_density,
+            urgency_markers,
+            tag_prior,
         }
     }
 }
@@ -142,6 +421,57 @@ pub enum ScreeningStatus {
     Pending,
 }
 
+impl ScreeningStatus {
+    /// Whether a context may move from this status to `next`
+    ///
+    /// Keeping the current status is always allowed. Under
+    /// [`TransitionStrictness::Standard`] a context can never return to
+    /// `Unscreened`, and a blocked one must go back through `Pending` or
+    /// `Flagged` before it can be marked safe. [`TransitionStrictness::Strict`]
+    /// additionally requires every verdict to be reached from `Pending`.
+    pub fn can_transition_to(
+        &self,
+        next: &ScreeningStatus,
+        strictness: TransitionStrictness,
+    ) -> bool {
+        use ScreeningStatus::*;
+
+        if self == next || strictness == TransitionStrictness::Permissive {
+            return true;
+        }
+        match (self, next) {
+            (_, Unscreened) => false,
+            (_, Pending) => true,
+            (Pending, _) => true,
+            _ if strictness == TransitionStrictness::Strict => false,
+            (Blocked, Safe) => false,
+            _ => true,
+        }
+    }
+}
+
+/// How strictly [`ScreeningStatus::can_transition_to`] checks status changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionStrictness {
+    /// Any change is allowed
+    Permissive,
+    /// No return to `Unscreened` and no direct `Blocked` to `Safe`
+    #[default]
+    Standard,
+    /// Verdicts are only reachable from `Pending`
+    Strict,
+}
+
+/// External scanner consulted when new content is stored
+///
+/// See [`crate::storage::ContextStore::with_screening_hook`].
+#[async_trait]
+pub trait ScreeningHook: Send + Sync {
+    /// Screen a context whose status is `Pending` and return the verdict
+    async fn screen(&self, context: &Context) -> ScreeningStatus;
+}
+
 /// A context entry for storage and retrieval
 ///
 /// Inspired by memory-gate's LearningContext with additions for:
@@ -165,6 +495,22 @@ pub struct Context {
     /// When this context was last accessed
     pub accessed_at: DateTime<Utc>,
 
+    /// When this context was last changed through [`crate::storage::ContextStore::update`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+
+    /// Number of times this context has been accessed
+    #[serde(default)]
+    pub access_count: u64,
+
+    /// Client that last accessed this context, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_accessed_by: Option<String>,
+
+    /// Most recent access times, oldest first, at most [`RECENT_ACCESS_LIMIT`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recent_accesses: Vec<DateTime<Utc>>,
+
     /// Optional expiration time
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expires_at: Option<DateTime<Utc>>,
@@ -175,6 +521,44 @@ pub struct Context {
     /// Optional embedding vector for similarity search
     #[serde(skip_serializing_if = "Option::is_none")]
     pub embedding: Option<Vec<f32>>,
+
+    /// Context this one replies to or follows from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<ContextId>,
+
+    /// Conversation thread this context belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
+
+    /// Structured payload kept alongside the text content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+
+    /// Revision number, bumped each time a stored version is replaced
+    ///
+    /// Records written before versioning load as 0; see
+    /// [`crate::storage::ContextStore::convert_to_versioned`].
+    #[serde(default)]
+    pub version: u32,
+
+    /// Earlier revisions, oldest first, at most [`MAX_REVISIONS`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<ContextRevision>,
+}
+
+/// A replaced version of a context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextRevision {
+    /// Revision number of this version
+    pub version: u32,
+    /// When this version was replaced
+    pub replaced_at: DateTime<Utc>,
+    /// Content at this version
+    pub content: String,
+    /// Domain at this version
+    pub domain: ContextDomain,
+    /// Metadata at this version
+    pub metadata: ContextMetadata,
 }
 
 impl Context {
@@ -188,9 +572,18 @@ impl Context {
             domain,
             created_at: now,
             accessed_at: now,
+            updated_at: None,
+            access_count: 0,
+            last_accessed_by: None,
+            recent_accesses: Vec::new(),
             expires_at: None,
             metadata: ContextMetadata::default(),
             embedding: None,
+            parent_id: None,
+            thread_id: None,
+            data: None,
+            version: 1,
+            history: Vec::new(),
         }
     }
 
@@ -236,12 +629,86 @@ impl Context {
         self
     }
 
+    /// Set the parent context
+    pub fn with_parent(mut self, parent_id: ContextId) -> Self {
+        self.parent_id = Some(parent_id);
+        self
+    }
+
+    /// Set the conversation thread
+    pub fn with_thread(mut self, thread_id: impl Into<String>) -> Self {
+        self.thread_id = Some(thread_id.into());
+        self
+    }
+
+    /// Attach a structured payload
+    pub fn with_data(mut self, data: serde_json::Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// Flatten the structured payload into `path: value` lines
+    ///
+    /// Object keys and array indices are joined with dots, so
+    /// `{"cluster": {"name": "prod"}}` becomes `cluster.name: prod`.
+    pub fn data_text(&self) -> Option<String> {
+        fn flatten(value: &serde_json::Value, path: &str, lines: &mut Vec<String>) {
+            let child = |key: &str| {
+                if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", path, key)
+                }
+            };
+            match value {
+                serde_json::Value::Object(map) => {
+                    for (key, value) in map {
+                        flatten(value, &child(key), lines);
+                    }
+                }
+                serde_json::Value::Array(items) => {
+                    for (i, value) in items.iter().enumerate() {
+                        flatten(value, &child(&i.to_string()), lines);
+                    }
+                }
+                serde_json::Value::String(s) => lines.push(format!("{}: {}", path, s)),
+                other => lines.push(format!("{}: {}", path, other)),
+            }
+        }
+
+        self.data.as_ref().map(|data| {
+            let mut lines = Vec::new();
+            flatten(data, "", &mut lines);
+            lines.join("\n")
+        })
+    }
+
     /// Set TTL (time to live)
+    ///
+    /// A TTL too large to represent as an expiry time falls back to 24 hours;
+    /// use [`Self::try_with_ttl`] to reject it instead.
     pub fn with_ttl(mut self, ttl: std::time::Duration) -> Self {
         self.expires_at = Some(Utc::now() + Duration::from_std(ttl).unwrap_or(Duration::hours(24)));
         self
     }
 
+    /// Set TTL (time to live), rejecting TTLs too large to represent
+    pub fn try_with_ttl(mut self, ttl: std::time::Duration) -> Result<Self> {
+        let expires_at = Duration::from_std(ttl)
+            .ok()
+            .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+            .ok_or_else(|| {
+                ContextError::InvalidQuery(format!("TTL of {}s is out of range", ttl.as_secs()))
+            })?;
+        self.expires_at = Some(expires_at);
+        Ok(self)
+    }
+
+    /// SHA-256 of the content as lowercase hex
+    pub fn content_hash(&self) -> String {
+        hash_content(&self.content)
+    }
+
     /// Check if context has expired
     pub fn is_expired(&self) -> bool {
         self.expires_at.map(|exp| Utc::now() > exp).unwrap_or(false)
@@ -257,12 +724,149 @@ impl Context {
         self.age_seconds() as f64 / 3600.0
     }
 
-    /// Mark as accessed (updates accessed_at)
+    /// Mark as accessed (updates accessed_at, access_count and recent_accesses)
     pub fn mark_accessed(&mut self) {
         self.accessed_at = Utc::now();
+        self.access_count += 1;
+        if self.recent_accesses.len() >= RECENT_ACCESS_LIMIT {
+            self.recent_accesses.remove(0);
+        }
+        self.recent_accesses.push(self.accessed_at);
+    }
+
+    /// Mark as accessed, recording who accessed it when known
+    pub fn mark_accessed_by(&mut self, accessor: Option<&str>) {
+        self.mark_accessed();
+        if let Some(accessor) = accessor {
+            self.last_accessed_by = Some(accessor.to_string());
+        }
+    }
+
+    /// Whether `other` differs from this context in a way worth a revision
+    ///
+    /// Compares content, domain and metadata; access statistics and
+    /// timestamps do not count.
+    pub fn revision_differs(&self, other: &Context) -> bool {
+        self.content != other.content
+            || self.domain != other.domain
+            || self.metadata != other.metadata
+    }
+
+    /// Capture the current version for the revision history
+    pub fn snapshot(&self) -> ContextRevision {
+        ContextRevision {
+            version: self.version,
+            replaced_at: Utc::now(),
+            content: self.content.clone(),
+            domain: self.domain.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Reconstruct this context as it was at `version`
+    ///
+    /// The current version is returned as is; earlier ones come from the
+    /// history with content, domain and metadata restored and no history of
+    /// their own.
+    pub fn at_revision(&self, version: u32) -> Option<Context> {
+        if version == self.version {
+            return Some(self.clone());
+        }
+        self.history
+            .iter()
+            .find(|revision| revision.version == version)
+            .map(|revision| {
+                let mut ctx = self.clone();
+                ctx.version = revision.version;
+                ctx.content = revision.content.clone();
+                ctx.domain = revision.domain.clone();
+                ctx.metadata = revision.metadata.clone();
+                ctx.history.clear();
+                ctx
+            })
+    }
+
+    /// Split long content into overlapping chunks for finer-grained retrieval
+    ///
+    /// Chunks hold at most `max_chars` characters and break at a paragraph,
+    /// sentence or word boundary where one falls in the second half of the
+    /// window. Each chunk after the first repeats up to `overlap` characters
+    /// from the end of the previous one, starting on a word boundary. Chunks copy this context's domain,
+    /// metadata, expiry and thread, and record `chunk_group` and
+    /// `chunk_index` in `metadata.custom`. Chunk IDs derive from the content,
+    /// so splitting the same text twice yields the same IDs. Content that
+    /// already fits, or a `max_chars` of 0, returns this context unchanged.
+    pub fn split_into_chunks(&self, max_chars: usize, overlap: usize) -> Vec<Context> {
+        let text = self.content.as_str();
+        if max_chars == 0 || text.chars().count() <= max_chars {
+            return vec![self.clone()];
+        }
+
+        let overlap = overlap.min(max_chars / 2);
+        let group = ContextId::from_content(text).to_string();
+        let mut chunks = Vec::new();
+        let mut start = 0;
+
+        while start < text.len() {
+            let window_end = text[start..]
+                .char_indices()
+                .nth(max_chars)
+                .map(|(i, _)| start + i)
+                .unwrap_or(text.len());
+            let end = if window_end == text.len() {
+                window_end
+            } else {
+                start + chunk_break(&text[start..window_end])
+            };
+
+            let piece = text[start..end].trim();
+            if !piece.is_empty() {
+                let index = chunks.len();
+                let mut chunk = self.clone();
+                chunk.id = ContextId::from_content(&format!("{}:{}:{}", group, index, piece));
+                chunk.content = piece.to_string();
+                chunk.embedding = None;
+                chunk
+                    .metadata
+                    .custom
+                    .insert("chunk_group".into(), serde_json::Value::from(group.clone()));
+                chunk
+                    .metadata
+                    .custom
+                    .insert("chunk_index".into(), serde_json::Value::from(index));
+                chunks.push(chunk);
+            }
+
+            if end == text.len() {
+                break;
+            }
+
+            // Step back into the previous chunk, always making progress, and
+            // skip ahead to a word boundary rather than start mid-word
+            let back = match overlap {
+                0 => None,
+                n => text[start..end]
+                    .char_indices()
+                    .rev()
+                    .nth(n - 1)
+                    .map(|(i, _)| start + i)
+                    .filter(|&next| next > start),
+            };
+            start = match back {
+                Some(next) if !text[..next].ends_with(char::is_whitespace) => text[next..end]
+                    .find(char::is_whitespace)
+                    .map_or(next, |i| next + i),
+                Some(next) => next,
+                None => end,
+            };
+        }
+
+        chunks
     }
 
     /// Check if context is safe to use (screened)
+    ///
+    /// `Pending` contexts are not safe until screening returns a verdict.
     pub fn is_safe(&self) -> bool {
         matches!(
             self.metadata.screening_status,
@@ -288,8 +892,146 @@ pub struct ContextQuery {
     pub max_age_seconds: Option<i64>,
     /// Only return verified/screened context
     pub verified_only: bool,
+    /// Content must match this regular expression
+    pub content_regex: Option<Regex>,
+    /// Structured data must hold this value at this path
+    pub data_path_filter: Option<DataPathFilter>,
+    /// Order of the returned contexts
+    pub sort: SortOrder,
+    /// Client recorded as the accessor of the returned contexts
+    pub accessor: Option<String>,
+    /// Only contexts in this language (ISO 639-1)
+    pub language_filter: Option<String>,
     /// Maximum results to return
     pub limit: usize,
+    /// Sorted matches to skip before the page starts
+    pub offset: usize,
+    /// Only return matches sorted after this position
+    pub cursor: Option<QueryCursor>,
+}
+
+/// Result ordering for [`ContextQuery`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// Most important first, then most recently accessed
+    #[default]
+    ImportanceDesc,
+    /// Most frequently accessed first, then most recently accessed
+    MostAccessed,
+    /// Newest first
+    CreatedDesc,
+    /// Oldest first, e.g. for archival review
+    CreatedAsc,
+    /// Most recently accessed first
+    AccessedDesc,
+}
+
+impl SortOrder {
+    /// Other names [`SortOrder::parse`] accepts; by age, youngest first is newest first
+    pub const ALIASES: [(&'static str, SortOrder); 2] = [
+        ("importance", SortOrder::ImportanceDesc),
+        ("age_asc", SortOrder::CreatedDesc),
+    ];
+
+    /// Every order, as accepted by [`SortOrder::parse`]
+    pub const ALL: [SortOrder; 5] = [
+        SortOrder::ImportanceDesc,
+        SortOrder::MostAccessed,
+        SortOrder::CreatedDesc,
+        SortOrder::CreatedAsc,
+        SortOrder::AccessedDesc,
+    ];
+
+    /// Name used by tools and the serialized form
+    pub fn name(self) -> &'static str {
+        match self {
+            SortOrder::ImportanceDesc => "importance_desc",
+            SortOrder::MostAccessed => "most_accessed",
+            SortOrder::CreatedDesc => "created_desc",
+            SortOrder::CreatedAsc => "created_asc",
+            SortOrder::AccessedDesc => "accessed_desc",
+        }
+    }
+
+    /// Order for a name from [`SortOrder::name`] or [`SortOrder::ALIASES`]
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALIASES
+            .into_iter()
+            .find(|(alias, _)| *alias == name)
+            .map(|(_, order)| order)
+            .or_else(|| Self::ALL.into_iter().find(|order| order.name() == name))
+    }
+
+    /// Order two positions, earlier first; ties fall back to the ID
+    pub fn compare(self, a: &QueryCursor, b: &QueryCursor) -> std::cmp::Ordering {
+        let primary = match self {
+            SortOrder::ImportanceDesc => b
+                .importance
+                .total_cmp(&a.importance)
+                .then_with(|| b.accessed_at.cmp(&a.accessed_at)),
+            SortOrder::MostAccessed => b
+                .access_count
+                .cmp(&a.access_count)
+                .then_with(|| b.accessed_at.cmp(&a.accessed_at)),
+            SortOrder::CreatedDesc => b.created_at.cmp(&a.created_at),
+            SortOrder::CreatedAsc => a.created_at.cmp(&b.created_at),
+            SortOrder::AccessedDesc => b.accessed_at.cmp(&a.accessed_at),
+        };
+        primary.then_with(|| a.id.cmp(&b.id))
+    }
+}
+
+/// Position of a context in sorted query results, for cursor pagination
+///
+/// Records the sort keys a context had when its page was produced. Listing
+/// a context marks it accessed, which only ever moves it earlier in the
+/// access-based orders, so passing the last position of a page back with
+/// [`ContextQuery::with_cursor`] yields every remaining match exactly once.
+/// Contexts stored meanwhile show up only if they sort after the cursor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryCursor {
+    /// Order the position belongs to
+    pub sort: SortOrder,
+    /// Importance of the context
+    pub importance: f32,
+    /// Access count of the context
+    pub access_count: u64,
+    /// Last access of the context
+    pub accessed_at: DateTime<Utc>,
+    /// Creation time of the context
+    pub created_at: DateTime<Utc>,
+    /// The context
+    pub id: ContextId,
+}
+
+impl QueryCursor {
+    /// Position of `ctx` under `sort`
+    pub fn at(ctx: &Context, sort: SortOrder) -> Self {
+        Self {
+            sort,
+            importance: ctx.metadata.importance,
+            access_count: ctx.access_count,
+            accessed_at: ctx.accessed_at,
+            created_at: ctx.created_at,
+            id: ctx.id.clone(),
+        }
+    }
+
+    /// Opaque string form, for clients
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// Parse a string from [`Self::encode`]
+    pub fn decode(cursor: &str) -> Result<Self> {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| ContextError::InvalidQuery(format!("invalid cursor '{}'", cursor)))
+    }
 }
 
 impl ContextQuery {
@@ -315,6 +1057,11 @@ impl ContextQuery {
         self
     }
 
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source_filter = Some(source.into());
+        self
+    }
+
     pub fn with_min_importance(mut self, importance: f32) -> Self {
         self.min_importance = Some(importance);
         self
@@ -338,6 +1085,16 @@ impl ContextQuery {
         self
     }
 
+    pub fn with_content_regex(mut self, regex: Regex) -> Self {
+        self.content_regex = Some(regex);
+        self
+    }
+
+    pub fn with_data_path_filter(mut self, filter: DataPathFilter) -> Self {
+        self.data_path_filter = Some(filter);
+        self
+    }
+
     pub fn verified_only(mut self) -> Self {
         self.verified_only = true;
         self
@@ -347,12 +1104,513 @@ impl ContextQuery {
         self.limit = limit;
         self
     }
+
+    pub fn with_sort(mut self, sort: SortOrder) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_cursor(mut self, cursor: QueryCursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    pub fn with_accessor(mut self, accessor: impl Into<String>) -> Self {
+        self.accessor = Some(accessor.into());
+        self
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language_filter = Some(language.into());
+        self
+    }
+}
+
+/// SHA-256 of `content` as lowercase hex
+pub fn hash_content(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Compile a user-supplied content regex with size guards
+///
+/// The regex engine matches in linear time, so the remaining risk is a
+/// pattern whose compiled program is huge (e.g. `\w{1000}{1000}`). Patterns
+/// longer than [`MAX_REGEX_PATTERN_LEN`] or compiling past
+/// [`REGEX_SIZE_LIMIT`] are rejected with [`ContextError::InvalidQuery`].
+pub fn compile_content_regex(pattern: &str, case_insensitive: bool) -> Result<Regex> {
+    if pattern.len() > MAX_REGEX_PATTERN_LEN {
+        return Err(ContextError::InvalidQuery(format!(
+            "regex pattern is {} bytes, limit is {}",
+            pattern.len(),
+            MAX_REGEX_PATTERN_LEN
+        )));
+    }
+
+    RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| ContextError::InvalidQuery(e.to_string()))
+}
+
+/// Byte offset at which to end a chunk within `window`
+///
+/// Prefers a paragraph break, then a sentence end, then whitespace, looking
+/// only at the second half of the window so chunks do not come out tiny.
+/// Falls back to the whole window.
+fn chunk_break(window: &str) -> usize {
+    let floor = window.len() / 2;
+
+    if let Some(pos) = window.rfind("\n\n").filter(|&pos| pos >= floor) {
+        return pos + 2;
+    }
+
+    let sentence_end = window
+        .char_indices()
+        .zip(window.chars().skip(1))
+        .filter(|((_, c), next)| {
+            *c == '\n' || (matches!(c, '.' | '!' | '?') && next.is_whitespace())
+        })
+        .map(|((i, c), _)| i + c.len_utf8())
+        .filter(|&pos| pos >= floor)
+        .last();
+    if let Some(pos) = sentence_end {
+        return pos;
+    }
+
+    window
+        .char_indices()
+        .filter(|(i, c)| c.is_whitespace() && *i >= floor && *i > 0)
+        .map(|(i, _)| i)
+        .next_back()
+        .unwrap_or(window.len())
+}
+
+/// Kind of line in a [`ContextDiff`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffOp {
+    /// Present in both versions
+    Equal,
+    /// Only in the old version
+    Delete,
+    /// Only in the new version
+    Insert,
+}
+
+/// One line of a content diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffLine {
+    /// Whether the line was kept, removed or added
+    pub op: DiffOp,
+    /// Line text, without the trailing newline
+    pub text: String,
+}
+
+/// A changed field in a [`ContextDiff`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// Field name, e.g. `importance` or `custom.owner`
+    pub field: String,
+    /// Old value, `null` when the field was added
+    pub old: serde_json::Value,
+    /// New value, `null` when the field was removed
+    pub new: serde_json::Value,
+}
+
+/// Differences between two versions of a context, from [`diff`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextDiff {
+    /// Version of the old side
+    pub from_version: u32,
+    /// Version of the new side
+    pub to_version: u32,
+    /// Line-level diff of the content, including unchanged lines
+    pub content: Vec<DiffLine>,
+    /// Changed fields other than content
+    pub fields: Vec<FieldChange>,
+}
+
+impl ContextDiff {
+    /// Unchanged lines shown around each change by [`Self::render`]
+    pub const CONTEXT_LINES: usize = 3;
+
+    /// Whether the two versions are the same
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.content.iter().all(|line| line.op == DiffOp::Equal)
+    }
+
+    /// Render as a unified diff of the content followed by field changes
+    ///
+    /// Hunks show up to [`Self::CONTEXT_LINES`] unchanged lines around each
+    /// change.
+    pub fn render(&self) -> String {
+        let mut out = format!("--- v{}\n+++ v{}\n", self.from_version, self.to_version);
+
+        let changed: Vec<usize> = self
+            .content
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.op != DiffOp::Equal)
+            .map(|(i, _)| i)
+            .collect();
+        let mut i = 0;
+        while i < changed.len() {
+            let start = changed[i].saturating_sub(Self::CONTEXT_LINES);
+            let mut end = changed[i] + 1;
+            while i + 1 < changed.len() && changed[i + 1] <= end + 2 * Self::CONTEXT_LINES {
+                i += 1;
+                end = changed[i] + 1;
+            }
+            let end = (end + Self::CONTEXT_LINES).min(self.content.len());
+
+            out.push_str("@@\n");
+            for line in &self.content[start..end] {
+                let marker = match line.op {
+                    DiffOp::Equal => ' ',
+                    DiffOp::Delete => '-',
+                    DiffOp::Insert => '+',
+                };
+                out.push(marker);
+                out.push_str(&line.text);
+                out.push('\n');
+            }
+            i += 1;
+        }
+
+        for change in &self.fields {
+            out.push_str(&format!(
+                "{}: {} -> {}\n",
+                change.field, change.old, change.new
+            ));
+        }
+        out
+    }
+}
+
+/// Compare two versions of a context
+///
+/// Content is diffed line by line (longest common subsequence); tags,
+/// importance, domain, screening status and each custom key are compared as
+/// fields.
+pub fn diff(old: &Context, new: &Context) -> ContextDiff {
+    let mut fields = Vec::new();
+    let mut field = |name: &str, old: serde_json::Value, new: serde_json::Value| {
+        if old != new {
+            fields.push(FieldChange {
+                field: name.to_string(),
+                old,
+                new,
+            });
+        }
+    };
+
+    field(
+        "domain",
+        old.domain.to_string().into(),
+        new.domain.to_string().into(),
+    );
+    field(
+        "tags",
+        old.metadata.tags.clone().into(),
+        new.metadata.tags.clone().into(),
+    );
+    field(
+        "importance",
+        old.metadata.importance.into(),
+        new.metadata.importance.into(),
+    );
+    field(
+        "screening_status",
+        serde_json::to_value(&old.metadata.screening_status).unwrap_or_default(),
+        serde_json::to_value(&new.metadata.screening_status).unwrap_or_default(),
+    );
+
+    let mut keys: Vec<&String> = old
+        .metadata
+        .custom
+        .keys()
+        .chain(new.metadata.custom.keys())
+        .collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        field(
+            &format!("custom.{}", key),
+            old.metadata.custom.get(key).cloned().unwrap_or_default(),
+            new.metadata.custom.get(key).cloned().unwrap_or_default(),
+        );
+    }
+
+    ContextDiff {
+        from_version: old.version,
+        to_version: new.version,
+        content: diff_lines(&old.content, &new.content),
+        fields,
+    }
+}
+
+/// Line diff via longest common subsequence
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] is the LCS length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let line = |op, text: &str| DiffLine {
+        op,
+        text: text.to_string(),
+    };
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            lines.push(line(DiffOp::Equal, a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(line(DiffOp::Delete, a[i]));
+            i += 1;
+        } else {
+            lines.push(line(DiffOp::Insert, b[j]));
+            j += 1;
+        }
+    }
+    lines.extend(a[i..].iter().map(|&text| line(DiffOp::Delete, text)));
+    lines.extend(b[j..].iter().map(|&text| line(DiffOp::Insert, text)));
+    lines
+}
+
+/// Equality test on a dotted path into a context's structured data
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataPathFilter {
+    /// Object keys or array indices leading to the value
+    pub path: Vec<String>,
+    /// Value expected at the path
+    pub value: serde_json::Value,
+}
+
+impl DataPathFilter {
+    /// Create a filter from a dotted path and expected value
+    pub fn new(path: &str, value: serde_json::Value) -> Self {
+        Self {
+            path: path
+                .split('.')
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect(),
+            value,
+        }
+    }
+
+    /// Parse an expression like `data.cluster == "prod"`
+    ///
+    /// The leading `data.` is optional. The right-hand side is read as JSON,
+    /// and anything that is not valid JSON is taken as a bare string.
+    pub fn parse(expr: &str) -> Result<Self> {
+        let (path, value) = expr.split_once("==").ok_or_else(|| {
+            ContextError::InvalidQuery(format!(
+                "data filter must look like `path == value`, got `{}`",
+                expr
+            ))
+        })?;
+        let path = path.trim();
+        let path = path.strip_prefix("data.").unwrap_or(path);
+        if path.is_empty() || path == "data" {
+            return Err(ContextError::InvalidQuery(
+                "data filter needs a path such as `data.cluster`".to_string(),
+            ));
+        }
+
+        let value = value.trim();
+        let value = serde_json::from_str(value)
+            .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+        Ok(Self::new(path, value))
+    }
+
+    /// Check a context's structured data against the filter
+    pub fn matches(&self, ctx: &Context) -> bool {
+        let mut current = match ctx.data {
+            Some(ref data) => data,
+            None => return false,
+        };
+        for segment in &self.path {
+            let next = match current {
+                serde_json::Value::Object(map) => map.get(segment),
+                serde_json::Value::Array(items) => {
+                    segment.parse::<usize>().ok().and_then(|i| items.get(i))
+                }
+                _ => None,
+            };
+            match next {
+                Some(value) => current = value,
+                None => return false,
+            }
+        }
+        current == &self.value
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_suggest_importance() {
+        let none: Vec<String> = Vec::new();
+        let chat =
+            ContextMetadata::suggest_importance("ok thanks", &ContextDomain::Conversation, &none);
+        let note = ContextMetadata::suggest_importance(
+            "The cache layer evicts least recently used entries once the configured \
+             capacity is reached, writing access statistics back to disk first.",
+            &ContextDomain::Documentation,
+            &none,
+        );
+        let urgent = ContextMetadata::suggest_importance_with(
+            "Critical: you must never log tokens. Do not merge until this is fixed.",
+            &ContextDomain::Code,
+            &["Security".to_string()],
+            &Default::default(),
+        );
+        assert!(chat < 0.4, "chat scored {}", chat);
+        assert!(chat < note && note < urgent.importance);
+        assert_eq!(urgent.importance, 1.0);
+        assert_eq!(
+            urgent.urgency_markers,
+            ["critical", "do not", "must", "never"]
+        );
+        assert_eq!(urgent.tag_prior, 0.2);
+
+        // Repetition lowers density
+        let repeated = ContextMetadata::suggest_importance_with(
+            &"cache ".repeat(50),
+            &ContextDomain::General,
+            &none,
+            &Default::default(),
+        );
+        assert!(repeated.information_density < 0.05);
+        assert_eq!(
+            ContextMetadata::suggest_importance_with(
+                "",
+                &ContextDomain::General,
+                &none,
+                &Default::default()
+            )
+            .information_density,
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_suggest_importance_tag_priors() {
+        let tags = vec!["security".to_string(), "wip".to_string()];
+        let priors = std::collections::HashMap::from([
+            ("security".to_string(), -0.1),
+            ("wip".to_string(), -0.05),
+        ]);
+        let signals = ContextMetadata::suggest_importance_with(
+            "Rotate keys",
+            &ContextDomain::General,
+            &tags,
+            &priors,
+        );
+        assert!((signals.tag_prior + 0.15).abs() < 1e-6);
+        let default =
+            ContextMetadata::suggest_importance("Rotate keys", &ContextDomain::General, &tags);
+        assert!(signals.importance < default);
+    }
+
+    #[test]
+    fn test_screening_transitions() {
+        use ScreeningStatus::*;
+
+        let standard = TransitionStrictness::Standard;
+        assert!(Blocked.can_transition_to(&Blocked, standard));
+        assert!(!Blocked.can_transition_to(&Safe, standard));
+        assert!(Blocked.can_transition_to(&Pending, standard));
+        assert!(Pending.can_transition_to(&Safe, standard));
+        assert!(Flagged.can_transition_to(&Safe, standard));
+        assert!(!Safe.can_transition_to(&Unscreened, standard));
+
+        let strict = TransitionStrictness::Strict;
+        assert!(!Flagged.can_transition_to(&Safe, strict));
+        assert!(!Unscreened.can_transition_to(&Safe, strict));
+        assert!(Unscreened.can_transition_to(&Pending, strict));
+        assert!(Pending.can_transition_to(&Blocked, strict));
+
+        assert!(Blocked.can_transition_to(&Unscreened, TransitionStrictness::Permissive));
+    }
+
+    #[test]
+    fn test_context_id_parse_and_uri() {
+        assert!(ContextId::parse("").is_err());
+        assert!(ContextId::parse("line\nbreak").is_err());
+        assert!(ContextId::parse(&"x".repeat(MAX_ID_LEN + 1)).is_err());
+
+        let id = ContextId::parse("ab+/c= é").unwrap();
+        let uri = id.to_uri();
+        assert_eq!(uri, "context://ab%2B%2Fc%3D%20%C3%A9");
+        assert_eq!(ContextId::from_uri(&uri).unwrap(), id);
+        assert!(ContextId::from_uri("context://a%2").is_err());
+        assert!(ContextId::from_uri("context://a%+1").is_err());
+        assert!(ContextId::from_uri("other://a").is_err());
+    }
+
+    #[test]
+    fn test_split_into_chunks() {
+        let content = "First paragraph about ownership. It has two sentences.\n\n\
+                       Second paragraph about borrowing and lifetimes. Also two sentences.\n\n\
+                       Third paragraph on traits.";
+        let ctx = Context::new(content, ContextDomain::Code)
+            .with_source("doc")
+            .with_tags(vec!["rust".to_string()]);
+
+        let chunks = ctx.split_into_chunks(60, 10);
+        assert!(chunks.len() > 1);
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert!(chunk.content.chars().count() <= 60);
+            assert_eq!(chunk.metadata.source, "doc");
+            assert_eq!(chunk.metadata.tags, vec!["rust".to_string()]);
+            assert_eq!(chunk.metadata.custom["chunk_index"], i);
+            assert_eq!(
+                chunk.metadata.custom["chunk_group"],
+                chunks[0].metadata.custom["chunk_group"]
+            );
+        }
+        // Breaks at the paragraph boundary
+        assert_eq!(
+            chunks[0].content,
+            "First paragraph about ownership. It has two sentences."
+        );
+
+        // Deterministic IDs, even from a context with a different random ID
+        let again = Context::new(content, ContextDomain::Code).split_into_chunks(60, 10);
+        let ids: Vec<_> = chunks.iter().map(|c| c.id.clone()).collect();
+        let again_ids: Vec<_> = again.iter().map(|c| c.id.clone()).collect();
+        assert_eq!(ids, again_ids);
+
+        // Short content is returned as is
+        let short = ctx.split_into_chunks(1000, 10);
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].id, ctx.id);
+    }
+
     #[test]
     fn test_context_creation() {
         let ctx = Context::new("Test content", ContextDomain::Code);
@@ -391,4 +1649,96 @@ mod tests {
         assert_eq!(query.min_importance, Some(0.5));
         assert_eq!(query.limit, 20);
     }
+
+    #[test]
+    fn test_recent_accesses_are_bounded() {
+        let mut ctx = Context::new("Hot", ContextDomain::General);
+        for _ in 0..RECENT_ACCESS_LIMIT + 3 {
+            ctx.mark_accessed();
+        }
+        ctx.mark_accessed_by(Some("vscode"));
+        ctx.mark_accessed_by(None);
+
+        assert_eq!(ctx.access_count, RECENT_ACCESS_LIMIT as u64 + 5);
+        assert_eq!(ctx.recent_accesses.len(), RECENT_ACCESS_LIMIT);
+        assert_eq!(ctx.recent_accesses.last(), Some(&ctx.accessed_at));
+        assert_eq!(ctx.last_accessed_by.as_deref(), Some("vscode"));
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Context::new("line one\nline two\nline three", ContextDomain::Code)
+            .with_tags(vec!["rust".to_string()]);
+        let mut new = old.clone();
+        new.version = 2;
+        new.content = "line one\nline 2\nline three\nline four".to_string();
+        new.metadata.importance = 0.5;
+        new.metadata
+            .custom
+            .insert("owner".to_string(), serde_json::json!("ana"));
+
+        let d = diff(&old, &new);
+        let ops: Vec<DiffOp> = d.content.iter().map(|line| line.op).collect();
+        assert_eq!(
+            ops,
+            [
+                DiffOp::Equal,
+                DiffOp::Delete,
+                DiffOp::Insert,
+                DiffOp::Equal,
+                DiffOp::Insert
+            ]
+        );
+        let fields: Vec<&str> = d.fields.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(fields, ["importance", "custom.owner"]);
+        assert_eq!(d.fields[1].old, serde_json::Value::Null);
+
+        let text = d.render();
+        assert!(text.starts_with("--- v1\n+++ v2\n@@\n line one\n-line two\n+line 2\n"));
+        assert!(text.contains("importance: 1.0 -> 0.5\n"));
+
+        assert!(diff(&old, &old).is_empty());
+        assert!(serde_json::to_value(&d).is_ok());
+    }
+
+    #[test]
+    fn test_data_path_filter() {
+        let ctx = Context::new("Cluster config", ContextDomain::General).with_data(
+            serde_json::json!({"cluster": "prod", "nodes": [{"cpu": 8}], "replicas": 3}),
+        );
+
+        assert!(DataPathFilter::parse(r#"data.cluster == "prod""#)
+            .unwrap()
+            .matches(&ctx));
+        assert!(DataPathFilter::parse("cluster == prod")
+            .unwrap()
+            .matches(&ctx));
+        assert!(DataPathFilter::parse("data.nodes.0.cpu == 8")
+            .unwrap()
+            .matches(&ctx));
+        assert!(!DataPathFilter::parse("data.replicas == \"3\"")
+            .unwrap()
+            .matches(&ctx));
+        assert!(!DataPathFilter::parse("data.missing == 1")
+            .unwrap()
+            .matches(&ctx));
+        assert!(DataPathFilter::parse("data.cluster").is_err());
+
+        let text = ctx.data_text().unwrap();
+        assert!(text.contains("cluster: prod"));
+        assert!(text.contains("nodes.0.cpu: 8"));
+    }
+
+    #[test]
+    fn test_compile_content_regex_guards() {
+        let re = compile_content_regex(r"FN\s+\w+_handler", true).unwrap();
+        assert!(re.is_match("fn get_handler()"));
+
+        assert!(matches!(
+            compile_content_regex("(unclosed", false),
+            Err(ContextError::InvalidQuery(_))
+        ));
+        assert!(compile_content_regex(&"a".repeat(MAX_REGEX_PATTERN_LEN + 1), false).is_err());
+        assert!(compile_content_regex(r"\w{1000}{1000}", false).is_err());
+    }
 }
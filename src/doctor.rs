@@ -0,0 +1,253 @@
+//! Environment and configuration checks for `context-mcp doctor`
+//!
+//! Each check reports pass, warn or fail with a remediation hint. The
+//! command fails if any check fails, so it can gate deployments.
+
+use std::fmt;
+use std::net::TcpListener;
+use std::path::Path;
+
+use clap::{Args, ValueEnum};
+use context_mcp::context::{Context, ContextDomain};
+use context_mcp::storage::{ContextStore, StorageConfig};
+
+use crate::ServeArgs;
+
+/// Options for the `doctor` subcommand
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+    /// Server options to check, as they would be passed to `serve`
+    #[command(flatten)]
+    serve: ServeArgs,
+
+    /// Embedding endpoint (e.g. an Ollama server) that should be reachable
+    #[arg(long)]
+    pub embedding_url: Option<String>,
+
+    /// Checks to skip, comma-separated
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub skip: Vec<Check>,
+}
+
+/// A doctor check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Check {
+    /// Storage flags and path permissions
+    Storage,
+    /// Whether the HTTP port can be bound
+    Port,
+    /// GPU adapter availability
+    Gpu,
+    /// Embedding endpoint reachability
+    Embeddings,
+    /// Store, get and delete in a temporary store
+    RoundTrip,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Check::Storage => "storage",
+            Check::Port => "port",
+            Check::Gpu => "gpu",
+            Check::Embeddings => "embeddings",
+            Check::RoundTrip => "round-trip",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Outcome of one check
+enum Status {
+    Pass(String),
+    Warn(String, &'static str),
+    Fail(String, &'static str),
+}
+
+/// Run every check that is not skipped and print the results
+pub async fn run(args: DoctorArgs) -> anyhow::Result<()> {
+    let mut failures = 0;
+    for check in [
+        Check::Storage,
+        Check::Port,
+        Check::Gpu,
+        Check::Embeddings,
+        Check::RoundTrip,
+    ] {
+        if args.skip.contains(&check) {
+            println!("[SKIP] {}", check);
+            continue;
+        }
+
+        let statuses = match check {
+            Check::Storage => check_storage(&args.serve),
+            Check::Port => vec![check_port(&args.serve)],
+            Check::Gpu => vec![check_gpu().await],
+            Check::Embeddings => vec![check_embeddings(args.embedding_url.as_deref()).await],
+            Check::RoundTrip => vec![check_round_trip().await],
+        };
+        for status in statuses {
+            match status {
+                Status::Pass(message) => println!("[PASS] {}: {}", check, message),
+                Status::Warn(message, hint) => {
+                    println!("[WARN] {}: {}\n       hint: {}", check, message, hint)
+                }
+                Status::Fail(message, hint) => {
+                    failures += 1;
+                    println!("[FAIL] {}: {}\n       hint: {}", check, message, hint)
+                }
+            }
+        }
+    }
+
+    if failures > 0 {
+        anyhow::bail!("{} check(s) failed", failures);
+    }
+    Ok(())
+}
+
+fn check_storage(serve: &ServeArgs) -> Vec<Status> {
+    let mut statuses = Vec::new();
+    match (serve.persist, &serve.storage_path) {
+        (false, None) => {
+            statuses.push(Status::Pass(
+                "persistence disabled; contexts live in memory".into(),
+            ));
+            return statuses;
+        }
+        (false, Some(path)) => {
+            statuses.push(Status::Warn(
+                format!(
+                    "--storage-path {} is ignored without --persist",
+                    path.display()
+                ),
+                "add --persist to store contexts on disk",
+            ));
+            return statuses;
+        }
+        (true, None) => statuses.push(Status::Warn(
+            "--persist without --storage-path uses ./data/context_store".into(),
+            "pass --storage-path so the location does not depend on the working directory",
+        )),
+        (true, Some(_)) => {}
+    }
+
+    let config = StorageConfig {
+        persist_path: serve.storage_path.clone(),
+        ..Default::default()
+    };
+    statuses.push(check_writable(&config.persist_dir()));
+    statuses
+}
+
+/// Check that `dir`, or the nearest existing ancestor it would be created in, is writable
+fn check_writable(dir: &Path) -> Status {
+    let existing = dir
+        .ancestors()
+        .find(|p| p.exists())
+        .unwrap_or(Path::new("."));
+    let probe = existing.join(format!(".context-mcp-doctor-{}", std::process::id()));
+    match std::fs::write(&probe, b"probe") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            Status::Pass(format!("{} is writable", dir.display()))
+        }
+        Err(e) => Status::Fail(
+            format!("cannot write to {}: {}", existing.display(), e),
+            "fix the directory's permissions or choose another --storage-path",
+        ),
+    }
+}
+
+fn check_port(serve: &ServeArgs) -> Status {
+    if serve.stdio {
+        return Status::Pass("stdio transport needs no port".into());
+    }
+    match TcpListener::bind((serve.host.as_str(), serve.port)) {
+        Ok(_) => Status::Pass(format!("{}:{} is free", serve.host, serve.port)),
+        Err(e) => Status::Fail(
+            format!("cannot bind {}:{}: {}", serve.host, serve.port, e),
+            "stop whatever holds the port or pass a different --port/--host",
+        ),
+    }
+}
+
+#[cfg(feature = "gpu-acceleration")]
+async fn check_gpu() -> Status {
+    match context_mcp::gpu::WgpuBackend::new().await {
+        Ok(_) => Status::Pass("GPU adapter found".into()),
+        Err(e) => Status::Warn(
+            format!("no usable GPU adapter ({}); similarity runs on the CPU", e),
+            "install GPU drivers, or build without gpu-acceleration",
+        ),
+    }
+}
+
+#[cfg(not(feature = "gpu-acceleration"))]
+async fn check_gpu() -> Status {
+    Status::Pass("built without gpu-acceleration; similarity runs on the CPU".into())
+}
+
+#[cfg(feature = "client")]
+async fn check_embeddings(url: Option<&str>) -> Status {
+    let Some(url) = url else {
+        return Status::Pass("no --embedding-url given".into());
+    };
+    match reqwest::get(url).await {
+        Ok(response) if response.status().is_success() => {
+            Status::Pass(format!("{} is reachable", url))
+        }
+        Ok(response) => Status::Fail(
+            format!("{} answered {}", url, response.status()),
+            "check the URL points at the embedding server's root",
+        ),
+        Err(e) => Status::Fail(
+            format!("{} is unreachable: {}", url, e),
+            "start the embedding server or correct --embedding-url",
+        ),
+    }
+}
+
+#[cfg(not(feature = "client"))]
+async fn check_embeddings(url: Option<&str>) -> Status {
+    match url {
+        Some(url) => Status::Warn(
+            format!("cannot reach {} without the `client` feature", url),
+            "rebuild with --features client to check the endpoint",
+        ),
+        None => Status::Pass("no --embedding-url given".into()),
+    }
+}
+
+/// Store, read back and delete a context in a throwaway store
+async fn check_round_trip() -> Status {
+    let dir = std::env::temp_dir().join(format!("context-mcp-doctor-{}", std::process::id()));
+    let result = round_trip(&dir).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    match result {
+        Ok(()) => Status::Pass("store, get and delete succeeded".into()),
+        Err(e) => Status::Fail(
+            format!("round trip failed: {}", e),
+            "check that the temp directory is writable and has free space",
+        ),
+    }
+}
+
+async fn round_trip(dir: &Path) -> anyhow::Result<()> {
+    let config = StorageConfig {
+        auto_cleanup: false,
+        ..StorageConfig::with_persistence(10, dir)
+    };
+    let store = ContextStore::new(config)?;
+    let id = store
+        .store(Context::new("doctor round trip", ContextDomain::General))
+        .await?
+        .id;
+    if store.get(&id).await?.is_none() {
+        anyhow::bail!("stored context could not be read back");
+    }
+    if !store.delete(&id).await? {
+        anyhow::bail!("stored context could not be deleted");
+    }
+    Ok(())
+}
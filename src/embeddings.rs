@@ -5,6 +5,8 @@
 //! - Sparse balanced ternary embeddings (codebook-free and RVQ strategies)
 //! - Quantized embeddings with optional GPU acceleration
 
+#[cfg(feature = "client")]
+use crate::error::ContextError;
 use crate::error::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -99,6 +101,65 @@ impl EmbeddingGenerator for MockEmbeddingGenerator {
     }
 }
 
+/// Embedding generator backed by an Ollama server's `/api/embeddings` endpoint
+#[cfg(feature = "client")]
+pub struct OllamaEmbeddingGenerator {
+    http: reqwest::Client,
+    url: String,
+    model: String,
+    dimension: usize,
+}
+
+#[cfg(feature = "client")]
+impl OllamaEmbeddingGenerator {
+    /// Create a generator for `model`, which must produce `dimension`-sized vectors
+    pub fn new(url: impl Into<String>, model: impl Into<String>, dimension: usize) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            url: url.into().trim_end_matches('/').to_string(),
+            model: model.into(),
+            dimension,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+impl EmbeddingGenerator for OllamaEmbeddingGenerator {
+    async fn generate(&self, text: &str) -> Result<Vec<f32>> {
+        #[derive(Deserialize)]
+        struct EmbeddingResponse {
+            embedding: Vec<f32>,
+        }
+
+        let response: EmbeddingResponse = self
+            .http
+            .post(format!("{}/api/embeddings", self.url))
+            .json(&serde_json::json!({"model": self.model, "prompt": text}))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| ContextError::Protocol(format!("embedding request failed: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| ContextError::Protocol(format!("invalid embedding response: {}", e)))?;
+
+        if response.embedding.len() != self.dimension {
+            return Err(ContextError::Config(format!(
+                "model {} returned {} dimensions, expected {}",
+                self.model,
+                response.embedding.len(),
+                self.dimension
+            )));
+        }
+        Ok(response.embedding)
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+}
+
 /// Ternary embedding generator with configurable quantization strategies
 pub struct TernaryEmbeddingGeneratorWrapper {
     base_generator: Arc<dyn EmbeddingGenerator>,
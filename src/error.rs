@@ -2,6 +2,8 @@
 
 use thiserror::Error;
 
+use crate::context::ContextId;
+
 /// Result type alias for context operations
 pub type Result<T> = std::result::Result<T, ContextError>;
 
@@ -19,6 +21,10 @@ pub enum ContextError {
     #[error("Storage error: {0}")]
     Storage(String),
 
+    /// Storage error that may succeed if the operation is retried
+    #[error("Transient storage error: {0}")]
+    StorageTransient(String),
+
     /// Serialization error
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
@@ -27,6 +33,10 @@ pub enum ContextError {
     #[error("Invalid query: {0}")]
     InvalidQuery(String),
 
+    /// Malformed context ID
+    #[error("Invalid context ID: {0}")]
+    InvalidId(String),
+
     /// Context expired
     #[error("Context has expired: {0}")]
     Expired(String),
@@ -58,22 +68,106 @@ pub enum ContextError {
     /// Internal error
     #[error("Internal error: {0}")]
     Internal(String),
+
+    /// Error raised while performing a named operation, e.g. `store`
+    #[error("{operation}{}: {source}", describe_context(.context_id))]
+    Operation {
+        /// Operation that failed
+        operation: &'static str,
+        /// Context the operation concerned, if any
+        context_id: Option<String>,
+        /// Underlying error
+        source: Box<ContextError>,
+    },
+}
+
+fn describe_context(context_id: &Option<String>) -> String {
+    match context_id {
+        Some(id) => format!("(context={})", id),
+        None => String::new(),
+    }
 }
 
 impl ContextError {
+    /// Record the operation, and optionally the context, this error occurred in
+    ///
+    /// With the `tracing` feature, the failure is also emitted as a warning
+    /// event with the same fields.
+    pub fn with_operation(self, operation: &'static str, id: Option<&ContextId>) -> Self {
+        let context_id = id.map(|id| id.to_string());
+        #[cfg(feature = "tracing")]
+        tracing::warn!(operation, context_id = ?context_id, error = %self, "operation failed");
+        Self::Operation {
+            operation,
+            context_id,
+            source: Box::new(self),
+        }
+    }
+
+    /// The outermost operation this error was raised in, if recorded
+    pub fn operation(&self) -> Option<(&'static str, Option<&str>)> {
+        match self {
+            Self::Operation {
+                operation,
+                context_id,
+                ..
+            } => Some((operation, context_id.as_deref())),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, with any operation context removed
+    pub fn root(&self) -> &ContextError {
+        match self {
+            Self::Operation { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
     /// Check if this is a not found error
     pub fn is_not_found(&self) -> bool {
-        matches!(self, Self::NotFound(_))
+        matches!(self.root(), Self::NotFound(_))
+    }
+
+    /// Check if this is a storage error worth retrying
+    pub fn is_transient(&self) -> bool {
+        matches!(self.root(), Self::StorageTransient(_))
     }
 
     /// Check if this is a security-related error
     pub fn is_security_error(&self) -> bool {
-        matches!(self, Self::ScreeningFailed(_) | Self::Blocked(_))
+        matches!(self.root(), Self::ScreeningFailed(_) | Self::Blocked(_))
+    }
+}
+
+/// Attach operation context to the error of a fallible call
+pub trait OperationContext<T> {
+    /// See [`ContextError::with_operation`]
+    fn with_operation(self, operation: &'static str, id: Option<&ContextId>) -> Result<T>;
+}
+
+impl<T, E: Into<ContextError>> OperationContext<T> for std::result::Result<T, E> {
+    fn with_operation(self, operation: &'static str, id: Option<&ContextId>) -> Result<T> {
+        self.map_err(|e| e.into().with_operation(operation, id))
     }
 }
 #[cfg(feature = "persistence")]
 impl From<sled::Error> for ContextError {
     fn from(err: sled::Error) -> Self {
-        Self::Storage(err.to_string())
+        use std::io::ErrorKind;
+        match err {
+            sled::Error::Io(ref io)
+                if matches!(
+                    io.kind(),
+                    ErrorKind::Interrupted
+                        | ErrorKind::WouldBlock
+                        | ErrorKind::TimedOut
+                        | ErrorKind::OutOfMemory
+                ) =>
+            {
+                Self::StorageTransient(err.to_string())
+            }
+            _ => Self::Storage(err.to_string()),
+        }
     }
 }
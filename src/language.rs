@@ -0,0 +1,264 @@
+//! Lightweight language detection for prose
+//!
+//! Each supported language has a character trigram profile built once from
+//! a short embedded sample. Text is scored against every profile by cosine
+//! similarity plus the share of its words that are the language's
+//! stopwords, and assigned the best language when that one is a clear
+//! winner and at least one of its stopwords occurs. Short or ambiguous
+//! text, such as a title made only of technical terms, gets no language
+//! rather than a guess.
+//!
+//! Detection is meant for natural-language content only. [`is_prose`]
+//! screens out code, logs and other symbol-heavy text, which would
+//! otherwise be filed under whichever language its identifiers resemble.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Languages [`detect`] can report, as ISO 639-1 codes
+pub const SUPPORTED_LANGUAGES: &[&str] = &["de", "en", "es", "fr"];
+
+/// Fewest letters a text needs before detection is attempted
+const MIN_LETTERS: usize = 20;
+
+/// Lowest score of the best language that counts as a match
+const MIN_SCORE: f64 = 0.15;
+
+/// How far the best profile must lead the runner-up, relative to its score
+const MIN_MARGIN: f64 = 0.1;
+
+/// Text each language's trigram profile is built from
+const SAMPLES: &[(&str, &str)] = &[
+    (
+        "de",
+        "Der Server speichert die Daten in einer Datenbank und liefert sie bei einer \
+         Anfrage an den Benutzer zurück. Wenn die Verbindung unterbrochen wird, muss \
+         der Vorgang wiederholt werden. Die Ergebnisse werden nach ihrer Wichtigkeit \
+         sortiert, damit die relevanten Einträge zuerst angezeigt werden. Bitte prüfen \
+         Sie vor der Änderung, ob alle Tests erfolgreich sind und ob die Dokumentation \
+         noch stimmt. Wir haben uns entschieden, die alte Schnittstelle nicht mehr zu \
+         unterstützen, weil sie zu langsam war und für größere Mengen nicht geeignet \
+         ist. Außerdem gibt es jetzt eine Möglichkeit, die Einstellungen über eine \
+         Datei zu ändern. Das ist für die meisten Nutzer einfacher und sicherer.",
+    ),
+    (
+        "en",
+        "The server stores the data in a database and returns it to the user when a \
+         request arrives. If the connection is interrupted, the operation has to be \
+         repeated. The results are sorted by their importance, so that the relevant \
+         entries are shown first. Please check before the change whether all of the \
+         tests pass and whether the documentation is still correct. We have decided \
+         to stop supporting the old interface, because it was too slow and is not \
+         suitable for larger amounts of data. There is also now a way to change the \
+         settings through a file. That is easier and safer for most of the people \
+         who use it, and they should have no trouble with this.",
+    ),
+    (
+        "es",
+        "El servidor guarda los datos en una base de datos y se los devuelve al \
+         usuario cuando llega una petición. Si la conexión se interrumpe, hay que \
+         repetir la operación. Los resultados se ordenan según su importancia, para \
+         que las entradas relevantes se muestren primero. Por favor, compruebe antes \
+         del cambio si todas las pruebas pasan y si la documentación sigue siendo \
+         correcta. Hemos decidido dejar de dar soporte a la interfaz antigua, porque \
+         era demasiado lenta y no es adecuada para grandes cantidades de datos. \
+         Además, ahora hay una forma de cambiar la configuración mediante un archivo. \
+         Esto es más sencillo y más seguro para la mayoría de los usuarios.",
+    ),
+    (
+        "fr",
+        "Le serveur enregistre les données dans une base de données et les renvoie à \
+         l'utilisateur lorsqu'une requête arrive. Si la connexion est interrompue, il \
+         faut répéter l'opération. Les résultats sont triés selon leur importance, \
+         afin que les entrées pertinentes soient affichées en premier. Veuillez \
+         vérifier avant la modification que tous les tests réussissent et que la \
+         documentation est toujours correcte. Nous avons décidé de ne plus prendre en \
+         charge l'ancienne interface, parce qu'elle était trop lente et qu'elle ne \
+         convient pas aux grandes quantités de données. De plus, il est maintenant \
+         possible de modifier les paramètres dans un fichier. C'est plus simple et \
+         plus sûr pour la plupart des utilisateurs.",
+    ),
+];
+
+/// Common function words per language, for stopword filtering
+const STOPWORDS: &[(&str, &[&str])] = &[
+    (
+        "de",
+        &[
+            "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "das", "dass",
+            "dem", "den", "der", "des", "die", "doch", "du", "ein", "eine", "einem", "einen",
+            "einer", "er", "es", "für", "hat", "ich", "ihr", "im", "in", "ist", "ja", "kein",
+            "mit", "nach", "nicht", "noch", "nur", "oder", "sich", "sie", "sind", "so", "über",
+            "um", "und", "uns", "von", "vor", "war", "wie", "wir", "wird", "zu", "zum", "zur",
+        ],
+    ),
+    (
+        "en",
+        &[
+            "a", "about", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "had",
+            "has", "have", "he", "her", "his", "i", "if", "in", "into", "is", "it", "its", "not",
+            "of", "on", "or", "our", "she", "so", "that", "the", "their", "them", "there", "they",
+            "this", "to", "was", "we", "were", "what", "when", "which", "who", "will", "with",
+            "you",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "a", "al", "como", "con", "de", "del", "el", "en", "es", "esta", "este", "la", "las",
+            "le", "lo", "los", "más", "no", "o", "para", "pero", "por", "que", "se", "si", "sin",
+            "su", "sus", "un", "una", "y", "ya",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "à", "au", "aux", "avec", "ce", "cette", "dans", "de", "des", "du", "elle", "en",
+            "est", "et", "il", "je", "la", "le", "les", "leur", "mais", "ne", "nous", "on", "ou",
+            "par", "pas", "pour", "qui", "que", "se", "sont", "sur", "un", "une", "vous",
+        ],
+    ),
+];
+
+type Profile = HashMap<[char; 3], f64>;
+
+/// Trigram profiles of the samples, built on first use
+fn profiles() -> &'static [(&'static str, Profile)] {
+    static PROFILES: OnceLock<Vec<(&'static str, Profile)>> = OnceLock::new();
+    PROFILES.get_or_init(|| {
+        SAMPLES
+            .iter()
+            .map(|(lang, sample)| (*lang, trigram_profile(sample)))
+            .collect()
+    })
+}
+
+/// Relative trigram frequencies of lowercased words padded with spaces
+fn trigram_profile(text: &str) -> Profile {
+    let mut counts: Profile = HashMap::new();
+    for word in text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+    {
+        let padded: Vec<char> = std::iter::once(' ')
+            .chain(word.chars().flat_map(char::to_lowercase))
+            .chain(std::iter::once(' '))
+            .collect();
+        for window in padded.windows(3) {
+            *counts.entry([window[0], window[1], window[2]]).or_default() += 1.0;
+        }
+    }
+    let norm = counts.values().map(|c| c * c).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        counts.values_mut().for_each(|c| *c /= norm);
+    }
+    counts
+}
+
+/// Whether `text` reads like natural language rather than code or data
+///
+/// Letters must make up at least 80% of the non-whitespace characters,
+/// and braces, brackets, semicolons and operators at most 3%.
+pub fn is_prose(text: &str) -> bool {
+    let mut visible = 0usize;
+    let mut letters = 0usize;
+    let mut symbols = 0usize;
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        visible += 1;
+        if c.is_alphabetic() {
+            letters += 1;
+        } else if "{}[]()<>;=|&*#$_\\/`".contains(c) {
+            symbols += 1;
+        }
+    }
+    visible > 0 && letters * 5 >= visible * 4 && symbols * 100 <= visible * 3
+}
+
+/// Detect the language of prose, as an ISO 639-1 code
+///
+/// Returns `None` for text with fewer than 20 letters, text not clearly
+/// closer to one language than the others, and languages outside
+/// [`SUPPORTED_LANGUAGES`]. Callers should check [`is_prose`] first.
+pub fn detect(text: &str) -> Option<&'static str> {
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_LETTERS {
+        return None;
+    }
+    let profile = trigram_profile(text);
+    let lower = text.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut scores: Vec<(&'static str, f64, usize)> = profiles()
+        .iter()
+        .map(|(lang, reference)| {
+            let similarity = profile
+                .iter()
+                .filter_map(|(trigram, weight)| reference.get(trigram).map(|r| r * weight))
+                .sum::<f64>();
+            let stopwords = stopwords(lang);
+            let hits = words.iter().filter(|w| stopwords.contains(w)).count();
+            (*lang, similarity + hits as f64 / words.len() as f64, hits)
+        })
+        .collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let (best, best_score, hits) = scores[0];
+    let runner_up = scores.get(1).map_or(0.0, |s| s.1);
+    (hits > 0 && best_score >= MIN_SCORE && best_score - runner_up >= best_score * MIN_MARGIN)
+        .then_some(best)
+}
+
+/// Stopwords of a supported language; empty for any other
+pub fn stopwords(language: &str) -> &'static [&'static str] {
+    STOPWORDS
+        .iter()
+        .find(|(lang, _)| *lang == language)
+        .map_or(&[], |(_, words)| words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            detect("Die Anfrage konnte nicht bearbeitet werden, weil der Speicher voll ist."),
+            Some("de")
+        );
+        assert_eq!(
+            detect("The request could not be processed because the storage is full."),
+            Some("en")
+        );
+        assert_eq!(
+            detect("La requête n'a pas pu être traitée parce que le stockage est plein."),
+            Some("fr")
+        );
+        assert_eq!(
+            detect("La solicitud no se pudo procesar porque el almacenamiento está lleno."),
+            Some("es")
+        );
+        assert_eq!(detect("ok danke"), None);
+        // No stopwords to confirm the trigram guess
+        assert_eq!(detect("Helm charts package Kubernetes applications"), None);
+    }
+
+    #[test]
+    fn test_is_prose() {
+        assert!(is_prose("Bitte die Tests vor dem Merge ausführen."));
+        assert!(!is_prose(
+            "fn main() { let x = vec![1, 2]; println!(\"{:?}\", x); }"
+        ));
+        assert!(!is_prose("2024-01-01T00:00:00Z 500 /api/v1/items 12ms"));
+        assert!(!is_prose(""));
+    }
+
+    #[test]
+    fn test_stopwords() {
+        assert!(stopwords("de").contains(&"und"));
+        assert!(stopwords("en").contains(&"the"));
+        assert!(stopwords("xx").is_empty());
+    }
+}
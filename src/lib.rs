@@ -22,24 +22,96 @@
 //! │ • curl/tools    │    │ • Text Matching  │    │ • Indexes       │
 //! └─────────────────┘    └──────────────────┘    └─────────────────┘
 //! ```
+//!
+//! ## WebAssembly
+//!
+//! Built with `--no-default-features --features core`, only the data model
+//! ([`context`], [`error`], [`temporal`]), ternary quantization
+//! ([`ternary`]) and the mock embedding generator are compiled. That subset
+//! has no tokio, sled or axum dependency and builds for
+//! `wasm32-unknown-unknown`, so quantization and similarity scoring can run
+//! client-side. The store, retrieval and tools need the `storage` feature,
+//! which every other feature pulls in.
+//!
+//! ## Tracing
+//!
+//! With the default-on `tracing` feature the library emits spans through the
+//! [`tracing`](https://docs.rs/tracing) crate; it never installs a subscriber
+//! itself. Spans, all at `debug` level unless noted:
+//!
+//! | Span                      | Fields                                                   |
+//! |---------------------------|----------------------------------------------------------|
+//! | `store`                   | `id`, `domain`, `replaced`                               |
+//! | `get`                     | `id`, `cache_hit`, `found`                               |
+//! | `delete`                  | `id`, `found`                                            |
+//! | `query`                   | `limit`, `results`                                       |
+//! | `cleanup_expired`         | `removed`                                                |
+//! | `retrieve`                | `max_per_domain`, `candidates`, `results`, `duration_ms` |
+//! | `quantize`                | `strategy`, `dimension`                                  |
+//! | `sparse_quantize` (trace) | `dimension`                                              |
+//! | `rvq_quantize` (trace)    | `dimension`                                              |
+//! | `tool`                    | `tool`, `is_error`, `duration_ms`                        |
+//!
+//! An embedder attaches its own subscriber and filters on the crate name:
+//!
+//! ```
+//! # #[cfg(all(feature = "tracing", feature = "server"))]
+//! # tokio_test::block_on(async {
+//! use context_mcp::context::ContextDomain;
+//! use context_mcp::{Context, ContextStore, StorageConfig};
+//! use tracing_subscriber::fmt::format::FmtSpan;
+//!
+//! tracing_subscriber::fmt()
+//!     .with_env_filter("context_mcp=debug")
+//!     .with_span_events(FmtSpan::CLOSE)
+//!     .init();
+//!
+//! let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+//! let id = store
+//!     .store(Context::new("traced", ContextDomain::General))
+//!     .await
+//!     .unwrap()
+//!     .id;
+//! // Logs a `get` span closing with cache_hit=true found=true
+//! store.get(&id).await.unwrap();
+//! # });
+//! ```
 
+#[cfg(feature = "client")]
+pub mod client;
 pub mod context;
 pub mod embeddings;
 pub mod error;
 #[cfg(feature = "gpu-acceleration")]
 pub mod gpu;
+pub mod language;
+#[cfg(feature = "storage")]
+pub mod observer;
+#[cfg(feature = "storage")]
 pub mod protocol;
+#[cfg(feature = "storage")]
 pub mod rag;
 #[cfg(feature = "server")]
 pub mod server;
+#[cfg(feature = "storage")]
 pub mod storage;
+#[cfg(feature = "storage")]
+pub mod tagging;
+#[cfg(feature = "storage")]
+mod telemetry;
 pub mod temporal;
 pub mod ternary;
+#[cfg(feature = "storage")]
 pub mod tools;
 
-pub use context::{Context, ContextId, ContextMetadata};
+pub use context::{Context, ContextId, ContextMetadata, DataPathFilter};
 pub use error::{ContextError, Result};
 #[cfg(feature = "server")]
 pub use server::{McpServer, ServerConfig};
-pub use storage::{ContextStore, StorageConfig};
+#[cfg(feature = "storage")]
+pub use storage::{
+    ContextStore, FlushPolicy, ImportMode, ImportSummary, MergeStrategy, Page, PaginationDir,
+    RecordFormat, SnapshotPolicy, StorageConfig, StorageConfigBuilder, StoreEvent, StoreEventKind,
+    StoreOutcome,
+};
 pub use temporal::TemporalQuery;
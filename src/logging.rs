@@ -0,0 +1,150 @@
+//! Log output setup for the binary
+//!
+//! Logs never go to stdout, which belongs to the stdio transport and to
+//! command output; they go to stderr or to an append-only file that can be
+//! reopened on SIGHUP after external rotation.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context as _;
+use clap::ValueEnum;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::EnvFilter;
+
+/// Log line format
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Log file opened for appending, reopenable after rotation
+#[derive(Clone)]
+pub struct LogFile {
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+}
+
+impl LogFile {
+    /// Open `path` for appending, creating it if needed
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Arc::new(Mutex::new(open_append(path)?)),
+        })
+    }
+
+    /// Reopen the file at the same path, e.g. after logrotate moved it away
+    pub fn reopen(&self) -> io::Result<()> {
+        let file = open_append(&self.path)?;
+        *self
+            .file
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = file;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Writer handed to the subscriber for each log line
+pub struct LogFileWriter(Arc<Mutex<File>>);
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogFile {
+    type Writer = LogFileWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogFileWriter(self.file.clone())
+    }
+}
+
+/// Install the global tracing subscriber
+///
+/// `level` takes any `RUST_LOG` directive string and overrides the
+/// environment; without it `RUST_LOG` is used, defaulting to `info`.
+/// Returns the log file, if any, so the caller can reopen it on SIGHUP.
+pub fn init(
+    format: LogFormat,
+    level: Option<&str>,
+    file: Option<&Path>,
+) -> anyhow::Result<Option<LogFile>> {
+    let filter = match level {
+        Some(level) => {
+            EnvFilter::try_new(level).with_context(|| format!("invalid --log-level '{}'", level))?
+        }
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+
+    let log_file = file
+        .map(|path| {
+            LogFile::open(path).with_context(|| format!("opening log file {}", path.display()))
+        })
+        .transpose()?;
+    let writer = match log_file {
+        Some(ref log_file) => BoxMakeWriter::new(log_file.clone()),
+        None => BoxMakeWriter::new(io::stderr),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(log_file.is_none());
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+
+    Ok(log_file)
+}
+
+/// Reopen the log file whenever the process receives SIGHUP
+#[cfg(unix)]
+pub async fn reopen_on_sighup(log_file: LogFile) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::warn!(
+                "Cannot listen for SIGHUP, log file will not be reopened: {}",
+                e
+            );
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        match log_file.reopen() {
+            Ok(()) => tracing::info!("Reopened log file {}", log_file.path.display()),
+            Err(e) => tracing::error!(
+                "Failed to reopen log file {}: {}",
+                log_file.path.display(),
+                e
+            ),
+        }
+    }
+}
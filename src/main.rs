@@ -20,22 +20,150 @@
 //! ```bash
 //! context-mcp --stdio
 //! ```
+//!
+//! Check a deployment's flags without serving:
+//! ```bash
+//! context-mcp --persist --storage-path /var/lib/context --print-config
+//! context-mcp --persist --storage-path /var/lib/context --dry-run
+//! ```
+//!
+//! Run maintenance against a storage directory while no server holds it:
+//! ```bash
+//! context-mcp stats --storage-path ./data/context_store
+//! context-mcp export --storage-path ./data/context_store --output backup.jsonl
+//! context-mcp restore --storage-path ./data/context_store --snapshot 20250101T000000.000000Z
+//! ```
+//!
+//! Call tools on a running server (requires the `client` feature):
+//! ```bash
+//! context-mcp client --url http://127.0.0.1:3000 store --content "..." --domain code --tags a,b
+//! context-mcp client --stdio-cmd "context-mcp --stdio" retrieve "borrow checker"
+//! context-mcp bench --url http://127.0.0.1:3000 --writers 4 --readers 16 --duration 60s
+//! ```
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
+use std::sync::Arc;
 
+#[cfg(feature = "client")]
+use context_mcp::embeddings::OllamaEmbeddingGenerator;
 use context_mcp::{
+    embeddings::{EmbeddingGenerator, MockEmbeddingGenerator},
     rag::RagConfig,
     server::{McpServer, ServerConfig, StdioTransport},
-    storage::StorageConfig,
+    storage::{ContextStore, SnapshotPolicy, StorageConfig, DEFAULT_SNAPSHOT_DIR},
 };
 
+#[cfg(feature = "client")]
+mod bench;
+#[cfg(feature = "client")]
+mod cli_client;
+mod commands;
+mod doctor;
+mod logging;
+
+use logging::LogFormat;
+
 /// MCP Context Management Server
 #[derive(Parser, Debug)]
 #[command(name = "context-mcp")]
 #[command(about = "Context management MCP server with temporal reasoning")]
 #[command(version)]
-struct Args {
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Server options, used when no subcommand is given
+    #[command(flatten)]
+    serve: ServeArgs,
+
+    /// Log line format
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    log_format: LogFormat,
+
+    /// Append logs to this file instead of stderr; reopened on SIGHUP
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+
+    /// Log level or RUST_LOG-style directives, overriding RUST_LOG
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the MCP server (the default)
+    Serve(ServeArgs),
+    /// Print storage statistics and per-domain counts
+    Stats(StoreArgs),
+    /// Delete expired contexts
+    Cleanup(StoreArgs),
+    /// Write every context as one JSON object per line
+    Export {
+        #[command(flatten)]
+        store: StoreArgs,
+        /// Output file (default: stdout)
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Load contexts from a JSONL export
+    Import {
+        #[command(flatten)]
+        store: StoreArgs,
+        /// Input file (default: stdin)
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Keep contexts that already exist instead of overwriting them
+        #[arg(long)]
+        skip_existing: bool,
+    },
+    /// Check that every stored record can be read back
+    Verify(StoreArgs),
+    /// Replace the stored contexts with those of a scheduled snapshot
+    Restore {
+        #[command(flatten)]
+        store: StoreArgs,
+        /// Snapshot name, as listed by the `list_snapshots` tool
+        #[arg(long)]
+        snapshot: String,
+        /// Directory holding the snapshots [default: <storage-path>/snapshots]
+        #[arg(long)]
+        snapshot_dir: Option<PathBuf>,
+    },
+    /// Check configuration and environment, failing if anything is broken
+    Doctor(doctor::DoctorArgs),
+    /// Generate embeddings for stored contexts that lack one
+    ReindexEmbeddings {
+        #[command(flatten)]
+        store: StoreArgs,
+        /// Embedding backend
+        #[arg(long, value_enum, default_value = "ollama")]
+        embedding_backend: EmbeddingBackend,
+        /// Embedding model name (ollama only)
+        #[arg(long, default_value = "nomic-embed-text")]
+        model: String,
+        /// Ollama server URL
+        #[arg(long, default_value = "http://127.0.0.1:11434")]
+        ollama_url: String,
+        /// Dimension of the model's embeddings
+        #[arg(long, default_value = "768")]
+        dimension: usize,
+        /// Contexts embedded per batch
+        #[arg(long, default_value = "64")]
+        batch: usize,
+    },
+    /// Call a tool on a running server and print the result
+    #[cfg(feature = "client")]
+    Client(cli_client::ClientArgs),
+    /// Load-test a running server with a mixed workload
+    #[cfg(feature = "client")]
+    Bench(bench::BenchArgs),
+}
+
+/// Options for running the server
+#[derive(Args, Debug)]
+struct ServeArgs {
     /// Use stdio transport instead of HTTP
     #[arg(long)]
     stdio: bool,
@@ -67,55 +195,285 @@ struct Args {
     /// Disable temporal decay scoring
     #[arg(long)]
     no_decay: bool,
+
+    /// Half-life of temporal decay in hours [default: 24]
+    #[arg(long, value_parser = parse_positive)]
+    decay_half_life_hours: Option<f64>,
+
+    /// Maximum contexts returned per retrieval [default: 10]
+    #[arg(long, value_parser = parse_max_results)]
+    max_results: Option<usize>,
+
+    /// Minimum relevance score, 0.0-1.0, for a context to be returned [default: 0.1]
+    #[arg(long, value_parser = parse_unit_interval)]
+    min_relevance: Option<f64>,
+
+    /// Weight, 0.0-1.0, of semantic similarity in the final score [default: 0.2]
+    #[arg(long, value_parser = parse_unit_interval)]
+    semantic_weight: Option<f64>,
+
+    /// Also retrieve contexts that are not screened safe
+    #[arg(long)]
+    unsafe_allowed: bool,
+
+    /// Maximum results a single tool call may request
+    #[arg(long, default_value = "1000")]
+    max_query_limit: usize,
+
+    /// Snapshot the store every this many seconds (requires --persist)
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    snapshot_interval_secs: Option<u64>,
+
+    /// Directory for scheduled snapshots [default: <storage-path>/snapshots]
+    #[arg(long, requires = "snapshot_interval_secs")]
+    snapshot_dir: Option<PathBuf>,
+
+    /// Scheduled snapshots to keep
+    #[arg(long, default_value = "7", value_parser = parse_max_results)]
+    snapshot_keep: usize,
+
+    /// Print the effective configuration as TOML and exit
+    #[arg(long)]
+    print_config: bool,
+
+    /// Validate the configuration, storage and port, then exit
+    #[arg(long, conflicts_with = "print_config")]
+    dry_run: bool,
+}
+
+/// Source of embeddings for `reindex-embeddings`
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum EmbeddingBackend {
+    /// Deterministic hash-based embeddings, for testing
+    Mock,
+    /// An Ollama server (requires the `client` feature)
+    Ollama,
+}
+
+/// Options for offline commands
+#[derive(Args, Debug)]
+struct StoreArgs {
+    /// Storage directory; must not be open in a running server
+    #[arg(long)]
+    storage_path: PathBuf,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::INFO.into()),
-        )
-        .init();
-
-    let args = Args::parse();
-
-    // Build configuration
-    let storage_config = StorageConfig {
+    let cli = Cli::parse();
+
+    // Logs never go to stdout, which belongs to the stdio transport and command output
+    let log_file = logging::init(
+        cli.log_format,
+        cli.log_level.as_deref(),
+        cli.log_file.as_deref(),
+    )?;
+    #[cfg(unix)]
+    if let Some(log_file) = log_file {
+        tokio::spawn(logging::reopen_on_sighup(log_file));
+    }
+    #[cfg(not(unix))]
+    drop(log_file);
+
+    match cli.command {
+        None => serve(cli.serve).await,
+        Some(Command::Serve(args)) => serve(args).await,
+        Some(Command::Stats(args)) => commands::stats(&args.storage_path).await,
+        Some(Command::Cleanup(args)) => commands::cleanup(&args.storage_path).await,
+        Some(Command::Export { store, output }) => {
+            commands::export(&store.storage_path, output.as_deref()).await
+        }
+        Some(Command::Import {
+            store,
+            input,
+            skip_existing,
+        }) => commands::import(&store.storage_path, input.as_deref(), skip_existing).await,
+        Some(Command::Verify(args)) => commands::verify(&args.storage_path).await,
+        Some(Command::Restore {
+            store,
+            snapshot,
+            snapshot_dir,
+        }) => commands::restore(&store.storage_path, &snapshot, snapshot_dir.as_deref()).await,
+        Some(Command::Doctor(args)) => doctor::run(args).await,
+        Some(Command::ReindexEmbeddings {
+            store,
+            embedding_backend,
+            model,
+            ollama_url,
+            dimension,
+            batch,
+        }) => {
+            let generator: Arc<dyn EmbeddingGenerator> = match embedding_backend {
+                EmbeddingBackend::Mock => Arc::new(MockEmbeddingGenerator::new(dimension)),
+                #[cfg(feature = "client")]
+                EmbeddingBackend::Ollama => {
+                    Arc::new(OllamaEmbeddingGenerator::new(ollama_url, model, dimension))
+                }
+                #[cfg(not(feature = "client"))]
+                EmbeddingBackend::Ollama => {
+                    let _ = (ollama_url, model);
+                    anyhow::bail!("the ollama backend needs a build with the `client` feature")
+                }
+            };
+            commands::reindex_embeddings(&store.storage_path, generator, batch).await
+        }
+        #[cfg(feature = "client")]
+        Some(Command::Client(args)) => cli_client::run(args).await,
+        #[cfg(feature = "client")]
+        Some(Command::Bench(args)) => bench::run(args).await,
+    }
+}
+
+/// Run the MCP server
+async fn serve(args: ServeArgs) -> anyhow::Result<()> {
+    let (print_config, dry_run, stdio) = (args.print_config, args.dry_run, args.stdio);
+    let server_config = build_config(args)?;
+
+    if print_config {
+        print!("{}", config_toml(&server_config)?);
+        return Ok(());
+    }
+    if dry_run {
+        return check_startup(&server_config, stdio);
+    }
+    tracing::info!("RAG configuration: {:?}", server_config.rag);
+
+    if stdio {
+        tracing::info!("Starting MCP Context Server in stdio mode");
+        let transport = StdioTransport::new(server_config)?;
+        transport.run().await?;
+    } else {
+        tracing::info!(
+            "Starting MCP Context Server on {}:{}",
+            server_config.host,
+            server_config.port
+        );
+        let server = McpServer::new(server_config)?;
+        server.run().await?;
+    }
+
+    Ok(())
+}
+
+/// Merge server flags with defaults into the effective configuration
+fn build_config(args: ServeArgs) -> anyhow::Result<ServerConfig> {
+    let mut storage_config = StorageConfig {
         memory_cache_size: args.cache_size,
         persist_path: args.storage_path,
         enable_persistence: args.persist,
         auto_cleanup: true,
         cleanup_interval_secs: 300,
+        ..Default::default()
     };
+    if let Some(secs) = args.snapshot_interval_secs {
+        let directory = args
+            .snapshot_dir
+            .unwrap_or_else(|| storage_config.persist_dir().join(DEFAULT_SNAPSHOT_DIR));
+        storage_config.snapshot = Some(SnapshotPolicy {
+            interval: std::time::Duration::from_secs(secs),
+            directory,
+            keep_last: args.snapshot_keep,
+        });
+    }
 
+    let defaults = RagConfig::default();
     let rag_config = RagConfig {
         num_threads: args.threads,
         temporal_decay: !args.no_decay,
-        ..Default::default()
+        decay_half_life_hours: args
+            .decay_half_life_hours
+            .unwrap_or(defaults.decay_half_life_hours),
+        max_results: args.max_results.unwrap_or(defaults.max_results),
+        min_relevance: args.min_relevance.unwrap_or(defaults.min_relevance),
+        semantic_weight: args.semantic_weight.unwrap_or(defaults.semantic_weight),
+        safe_only: !args.unsafe_allowed,
+        ..defaults
     };
+    if rag_config.max_results > args.max_query_limit {
+        anyhow::bail!(
+            "--max-results {} exceeds --max-query-limit {}",
+            rag_config.max_results,
+            args.max_query_limit
+        );
+    }
 
-    let server_config = ServerConfig {
+    Ok(ServerConfig {
         host: args.host,
         port: args.port,
         storage: storage_config,
         rag: rag_config,
-    };
+        max_query_limit: args.max_query_limit,
+    })
+}
 
-    if args.stdio {
-        tracing::info!("Starting MCP Context Server in stdio mode");
-        let transport = StdioTransport::new(server_config)?;
-        transport.run().await?;
-    } else {
-        tracing::info!(
-            "Starting MCP Context Server on {}:{}",
-            server_config.host,
-            server_config.port
-        );
-        let server = McpServer::new(server_config)?;
-        server.run().await?;
+/// Render a configuration as TOML, redacting anything secret-looking
+fn config_toml(config: &ServerConfig) -> anyhow::Result<String> {
+    let mut value = toml::Value::try_from(config)?;
+    redact_secrets(&mut value);
+    Ok(toml::to_string_pretty(&value)?)
+}
+
+/// Replace values whose key names a credential with a placeholder
+fn redact_secrets(value: &mut toml::Value) {
+    const SECRET_MARKERS: &[&str] = &["password", "secret", "token", "api_key", "credential"];
+
+    if let toml::Value::Table(table) = value {
+        for (key, value) in table.iter_mut() {
+            let key = key.to_ascii_lowercase();
+            if SECRET_MARKERS.iter().any(|marker| key.contains(marker)) {
+                *value = toml::Value::String("<redacted>".to_string());
+            } else {
+                redact_secrets(value);
+            }
+        }
     }
+}
+
+/// Open and close storage and check the port can be bound, without serving
+fn check_startup(config: &ServerConfig, stdio: bool) -> anyhow::Result<()> {
+    let store = ContextStore::new(StorageConfig {
+        auto_cleanup: false,
+        ..config.storage.clone()
+    })
+    .map_err(|e| anyhow::anyhow!("storage check failed: {}", e))?;
+    drop(store);
+    eprintln!("storage: ok");
 
+    if !stdio {
+        std::net::TcpListener::bind((config.host.as_str(), config.port))
+            .map_err(|e| anyhow::anyhow!("cannot bind {}:{}: {}", config.host, config.port, e))?;
+        eprintln!("port {}:{}: ok", config.host, config.port);
+    }
+
+    eprintln!("configuration is valid");
     Ok(())
 }
+
+/// Parse a number in `0.0..=1.0`
+fn parse_unit_interval(s: &str) -> Result<f64, String> {
+    let value: f64 = s.parse().map_err(|_| format!("'{}' is not a number", s))?;
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("{} is not between 0.0 and 1.0", value))
+    }
+}
+
+/// Parse a finite number greater than zero
+fn parse_positive(s: &str) -> Result<f64, String> {
+    let value: f64 = s.parse().map_err(|_| format!("'{}' is not a number", s))?;
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(format!("{} must be greater than zero", value))
+    }
+}
+
+/// Parse a result count of at least one
+fn parse_max_results(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(value) => Ok(value),
+        Err(_) => Err(format!("'{}' is not a positive integer", s)),
+    }
+}
@@ -0,0 +1,222 @@
+//! Callbacks for embedders that want operation metrics without a metrics stack
+//!
+//! A [`StoreObserver`] registered with
+//! [`ContextStore::with_observer`](crate::storage::ContextStore::with_observer)
+//! or [`RagProcessor::with_observer`](crate::rag::RagProcessor::with_observer)
+//! is called synchronously on the operation's task, so implementations must
+//! be cheap: bump a counter or push to a channel, never block or await.
+//!
+//! Every store also feeds a built-in [`CountingObserver`], which backs the
+//! operation stats tools; retrievals are counted there too. This tree has
+//! no metrics exporter of its own, so embedders wanting one build it on
+//! this trait.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::context::{Context, ContextId};
+
+/// Operation callbacks; every method defaults to a no-op
+pub trait StoreObserver: Send + Sync {
+    /// A context was stored
+    fn on_store(&self, _context: &Context) {}
+
+    /// A context was read; `hit` is whether the memory cache served it
+    fn on_get(&self, _hit: bool) {}
+
+    /// A delete finished; `found` is whether the context existed
+    fn on_delete(&self, _id: &ContextId, _found: bool) {}
+
+    /// A filter query returned `results` contexts
+    fn on_query(&self, _duration: Duration, _results: usize) {}
+
+    /// A retrieval scored `candidates` contexts and returned `results`
+    fn on_retrieve(&self, _duration: Duration, _candidates: usize, _results: usize) {}
+
+    /// A scheduled snapshot finished; `bytes` is its size, 0 when it failed
+    fn on_snapshot(&self, _duration: Duration, _bytes: u64, _ok: bool) {}
+}
+
+/// Observer that ignores every callback
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopObserver;
+
+impl StoreObserver for NoopObserver {}
+
+/// Observer that aggregates operation counts and durations
+#[derive(Debug)]
+pub struct CountingObserver {
+    stores: AtomicU64,
+    gets: AtomicU64,
+    cache_hits: AtomicU64,
+    deletes: AtomicU64,
+    queries: AtomicU64,
+    query_micros: AtomicU64,
+    retrievals: AtomicU64,
+    retrieve_micros: AtomicU64,
+    retrieve_candidates: AtomicU64,
+    snapshots: AtomicU64,
+    snapshot_failures: AtomicU64,
+    since: Mutex<Instant>,
+}
+
+impl Default for CountingObserver {
+    fn default() -> Self {
+        Self {
+            stores: AtomicU64::new(0),
+            gets: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            deletes: AtomicU64::new(0),
+            queries: AtomicU64::new(0),
+            query_micros: AtomicU64::new(0),
+            retrievals: AtomicU64::new(0),
+            retrieve_micros: AtomicU64::new(0),
+            retrieve_candidates: AtomicU64::new(0),
+            snapshots: AtomicU64::new(0),
+            snapshot_failures: AtomicU64::new(0),
+            since: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl CountingObserver {
+    /// Create an observer with zeroed counters
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts accumulated since creation or the last [`Self::reset`]
+    pub fn snapshot(&self) -> OperationCounts {
+        let since = *self.since.lock().unwrap_or_else(|p| p.into_inner());
+        OperationCounts {
+            stores: self.stores.load(Ordering::Relaxed),
+            gets: self.gets.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            queries: self.queries.load(Ordering::Relaxed),
+            query_micros: self.query_micros.load(Ordering::Relaxed),
+            retrievals: self.retrievals.load(Ordering::Relaxed),
+            retrieve_micros: self.retrieve_micros.load(Ordering::Relaxed),
+            retrieve_candidates: self.retrieve_candidates.load(Ordering::Relaxed),
+            snapshots: self.snapshots.load(Ordering::Relaxed),
+            snapshot_failures: self.snapshot_failures.load(Ordering::Relaxed),
+            window_secs: since.elapsed().as_secs(),
+        }
+    }
+
+    /// Zero the counters and start a new window
+    pub fn reset(&self) {
+        let mut since = self.since.lock().unwrap_or_else(|p| p.into_inner());
+        for counter in [
+            &self.stores,
+            &self.gets,
+            &self.cache_hits,
+            &self.deletes,
+            &self.queries,
+            &self.query_micros,
+            &self.retrievals,
+            &self.retrieve_micros,
+            &self.retrieve_candidates,
+            &self.snapshots,
+            &self.snapshot_failures,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+        *since = Instant::now();
+    }
+}
+
+impl StoreObserver for CountingObserver {
+    fn on_store(&self, _context: &Context) {
+        self.stores.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_get(&self, hit: bool) {
+        self.gets.fetch_add(1, Ordering::Relaxed);
+        if hit {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn on_delete(&self, _id: &ContextId, _found: bool) {
+        self.deletes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_query(&self, duration: Duration, _results: usize) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        self.query_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn on_retrieve(&self, duration: Duration, candidates: usize, _results: usize) {
+        self.retrievals.fetch_add(1, Ordering::Relaxed);
+        self.retrieve_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.retrieve_candidates
+            .fetch_add(candidates as u64, Ordering::Relaxed);
+    }
+
+    fn on_snapshot(&self, _duration: Duration, _bytes: u64, ok: bool) {
+        if ok {
+            self.snapshots.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.snapshot_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Totals reported by [`CountingObserver::snapshot`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperationCounts {
+    /// Contexts stored
+    pub stores: u64,
+    /// Context reads
+    pub gets: u64,
+    /// Reads served from the memory cache
+    pub cache_hits: u64,
+    /// Delete calls
+    pub deletes: u64,
+    /// Filter queries
+    pub queries: u64,
+    /// Total time spent in filter queries, in microseconds
+    pub query_micros: u64,
+    /// Retrievals
+    pub retrievals: u64,
+    /// Total time spent in retrievals, in microseconds
+    pub retrieve_micros: u64,
+    /// Contexts scored across all retrievals
+    pub retrieve_candidates: u64,
+    /// Scheduled snapshots taken
+    pub snapshots: u64,
+    /// Scheduled snapshots that failed
+    pub snapshot_failures: u64,
+    /// Length of the window in seconds
+    pub window_secs: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ContextDomain;
+
+    #[test]
+    fn test_counting_observer() {
+        let observer = CountingObserver::new();
+        observer.on_store(&Context::new("counted", ContextDomain::General));
+        observer.on_get(true);
+        observer.on_get(false);
+        observer.on_query(Duration::from_micros(40), 3);
+        observer.on_retrieve(Duration::from_micros(100), 12, 5);
+
+        let counts = observer.snapshot();
+        assert_eq!((counts.stores, counts.gets, counts.cache_hits), (1, 2, 1));
+        assert_eq!((counts.queries, counts.query_micros), (1, 40));
+        assert_eq!(counts.retrieve_candidates, 12);
+
+        observer.reset();
+        assert_eq!(observer.snapshot().gets, 0);
+    }
+}
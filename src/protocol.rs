@@ -4,9 +4,11 @@
 //! communication between AI assistants and context servers.
 
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::HashMap;
 
+use crate::error::ContextError;
+
 /// JSON-RPC version constant
 pub const JSONRPC_VERSION: &str = "2.0";
 
@@ -85,13 +87,146 @@ pub struct JsonRpcError {
     pub data: Option<Value>,
 }
 
-/// Standard JSON-RPC error codes
+/// JSON-RPC error codes
+///
+/// The standard codes come from the JSON-RPC 2.0 specification. Server
+/// errors map [`ContextError`] variants to stable codes, and the error's
+/// `data` carries `{"kind": ..., "detail": ...}`, plus `"id"` for
+/// `not_found`. Errors wrapped in [`ContextError::Operation`] take the code
+/// of the underlying error and add `"operation"` and `"context_id"`:
+///
+/// | Code   | Kind                | Variant                            |
+/// |--------|---------------------|------------------------------------|
+/// | -32001 | `not_found`         | [`ContextError::NotFound`]         |
+/// | -32002 | `expired`           | [`ContextError::Expired`]          |
+/// | -32003 | `blocked`           | [`ContextError::Blocked`]          |
+/// | -32004 | `screening_failed`  | [`ContextError::ScreeningFailed`]  |
+/// | -32005 | `timeout`           | [`ContextError::Timeout`]          |
+/// | -32010 | `storage`           | [`ContextError::Storage`]          |
+/// | -32010 | `storage_transient` | [`ContextError::StorageTransient`] |
+/// | -32011 | `serialization`     | [`ContextError::Serialization`]    |
+/// | -32012 | `io`                | [`ContextError::Io`]               |
+/// | -32013 | `config`            | [`ContextError::Config`]           |
+/// | -32602 | `invalid_query`     | [`ContextError::InvalidQuery`]     |
+/// | -32602 | `invalid_id`        | [`ContextError::InvalidId`]        |
+/// | -32600 | `protocol`          | [`ContextError::Protocol`]         |
+/// | -32603 | `internal`          | [`ContextError::Internal`]         |
 pub mod error_codes {
     pub const PARSE_ERROR: i32 = -32700;
     pub const INVALID_REQUEST: i32 = -32600;
     pub const METHOD_NOT_FOUND: i32 = -32601;
     pub const INVALID_PARAMS: i32 = -32602;
     pub const INTERNAL_ERROR: i32 = -32603;
+
+    pub const NOT_FOUND: i32 = -32001;
+    pub const EXPIRED: i32 = -32002;
+    pub const BLOCKED: i32 = -32003;
+    pub const SCREENING_FAILED: i32 = -32004;
+    pub const TIMEOUT: i32 = -32005;
+    pub const STORAGE: i32 = -32010;
+    pub const SERIALIZATION: i32 = -32011;
+    pub const IO: i32 = -32012;
+    pub const CONFIG: i32 = -32013;
+}
+
+impl From<&ContextError> for JsonRpcError {
+    fn from(err: &ContextError) -> Self {
+        let (code, kind, detail) = match err.root() {
+            ContextError::NotFound(d) => (error_codes::NOT_FOUND, "not_found", d.clone()),
+            ContextError::Expired(d) => (error_codes::EXPIRED, "expired", d.clone()),
+            ContextError::Blocked(d) => (error_codes::BLOCKED, "blocked", d.clone()),
+            ContextError::ScreeningFailed(d) => {
+                (error_codes::SCREENING_FAILED, "screening_failed", d.clone())
+            }
+            ContextError::Timeout(d) => (error_codes::TIMEOUT, "timeout", d.clone()),
+            ContextError::Storage(d) => (error_codes::STORAGE, "storage", d.clone()),
+            ContextError::StorageTransient(d) => {
+                (error_codes::STORAGE, "storage_transient", d.clone())
+            }
+            ContextError::Serialization(e) => {
+                (error_codes::SERIALIZATION, "serialization", e.to_string())
+            }
+            ContextError::Io(e) => (error_codes::IO, "io", e.to_string()),
+            ContextError::Config(d) => (error_codes::CONFIG, "config", d.clone()),
+            ContextError::InvalidQuery(d) => {
+                (error_codes::INVALID_PARAMS, "invalid_query", d.clone())
+            }
+            ContextError::InvalidId(d) => (error_codes::INVALID_PARAMS, "invalid_id", d.clone()),
+            ContextError::Protocol(d) => (error_codes::INVALID_REQUEST, "protocol", d.clone()),
+            ContextError::Internal(d) => (error_codes::INTERNAL_ERROR, "internal", d.clone()),
+            ContextError::Operation { .. } => unreachable!("root() strips operation context"),
+        };
+        let mut data = json!({ "kind": kind, "detail": detail });
+        if let ContextError::NotFound(id) = err.root() {
+            data["id"] = json!(id);
+        }
+        if let Some((operation, context_id)) = err.operation() {
+            data["operation"] = json!(operation);
+            if let Some(context_id) = context_id {
+                data["context_id"] = json!(context_id);
+            }
+        }
+        Self {
+            code,
+            message: err.to_string(),
+            data: Some(data),
+        }
+    }
+}
+
+impl From<JsonRpcError> for ContextError {
+    /// Rebuild the error a server raised from its JSON-RPC form
+    ///
+    /// Uses the `kind` in the error's data when present, else the code.
+    /// Operation context is folded into the detail.
+    fn from(err: JsonRpcError) -> Self {
+        let data = err.data.as_ref();
+        let kind = data
+            .and_then(|d| d["kind"].as_str())
+            .unwrap_or(match err.code {
+                error_codes::NOT_FOUND => "not_found",
+                error_codes::EXPIRED => "expired",
+                error_codes::BLOCKED => "blocked",
+                error_codes::SCREENING_FAILED => "screening_failed",
+                error_codes::TIMEOUT => "timeout",
+                error_codes::STORAGE => "storage",
+                error_codes::SERIALIZATION => "serialization",
+                error_codes::IO => "io",
+                error_codes::CONFIG => "config",
+                error_codes::INVALID_PARAMS => "invalid_query",
+                error_codes::INVALID_REQUEST | error_codes::METHOD_NOT_FOUND => "protocol",
+                _ => "internal",
+            });
+        let mut detail = data
+            .and_then(|d| d["detail"].as_str())
+            .map(str::to_string)
+            .unwrap_or(err.message);
+        if let Some(operation) = data.and_then(|d| d["operation"].as_str()) {
+            detail = match data.and_then(|d| d["context_id"].as_str()) {
+                Some(id) => format!("{}(context={}): {}", operation, id, detail),
+                None => format!("{}: {}", operation, detail),
+            };
+        }
+
+        match kind {
+            "not_found" => ContextError::NotFound(detail),
+            "expired" => ContextError::Expired(detail),
+            "blocked" => ContextError::Blocked(detail),
+            "screening_failed" => ContextError::ScreeningFailed(detail),
+            "timeout" => ContextError::Timeout(detail),
+            "storage" => ContextError::Storage(detail),
+            "storage_transient" => ContextError::StorageTransient(detail),
+            "serialization" => {
+                ContextError::Serialization(<serde_json::Error as serde::de::Error>::custom(detail))
+            }
+            "io" => ContextError::Io(std::io::Error::other(detail)),
+            "config" => ContextError::Config(detail),
+            "invalid_query" => ContextError::InvalidQuery(detail),
+            "invalid_id" => ContextError::InvalidId(detail),
+            "protocol" => ContextError::Protocol(detail),
+            _ => ContextError::Internal(detail),
+        }
+    }
 }
 
 impl JsonRpcError {
@@ -134,6 +269,12 @@ impl JsonRpcError {
             data: None,
         }
     }
+
+    /// Attach structured error data
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
 }
 
 /// MCP server capabilities
@@ -238,6 +379,10 @@ pub struct PropertySchema {
     pub default: Option<Value>,
     #[serde(rename = "enum", skip_serializing_if = "Option::is_none")]
     pub enum_values: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
 }
 
 impl PropertySchema {
@@ -247,6 +392,8 @@ impl PropertySchema {
             description: Some(description.into()),
             default: None,
             enum_values: None,
+            minimum: None,
+            maximum: None,
         }
     }
 
@@ -256,6 +403,8 @@ impl PropertySchema {
             description: Some(description.into()),
             default: None,
             enum_values: None,
+            minimum: None,
+            maximum: None,
         }
     }
 
@@ -265,6 +414,8 @@ impl PropertySchema {
             description: Some(description.into()),
             default: None,
             enum_values: None,
+            minimum: None,
+            maximum: None,
         }
     }
 
@@ -274,6 +425,19 @@ impl PropertySchema {
             description: Some(description.into()),
             default: None,
             enum_values: None,
+            minimum: None,
+            maximum: None,
+        }
+    }
+
+    pub fn object(description: impl Into<String>) -> Self {
+        Self {
+            schema_type: "object".to_string(),
+            description: Some(description.into()),
+            default: None,
+            enum_values: None,
+            minimum: None,
+            maximum: None,
         }
     }
 
@@ -286,6 +450,20 @@ impl PropertySchema {
         self.enum_values = Some(values.into_iter().map(|s| s.to_string()).collect());
         self
     }
+
+    pub fn with_minimum(mut self, minimum: f64) -> Self {
+        self.minimum = Some(minimum);
+        self
+    }
+
+    pub fn with_maximum(mut self, maximum: f64) -> Self {
+        self.maximum = Some(maximum);
+        self
+    }
+
+    pub fn with_range(self, minimum: f64, maximum: f64) -> Self {
+        self.with_minimum(minimum).with_maximum(maximum)
+    }
 }
 
 /// MCP tool call request
@@ -321,6 +499,22 @@ impl CallToolResult {
         }
     }
 
+    /// Create an error result from a [`ContextError`]
+    ///
+    /// The text is the JSON form of the matching [`JsonRpcError`], so clients
+    /// get the same code and structured data as for protocol-level errors.
+    pub fn from_error(err: &ContextError) -> Self {
+        let rpc = JsonRpcError::from(err);
+        Self::error(
+            serde_json::to_string_pretty(&json!({
+                "error": rpc.message,
+                "code": rpc.code,
+                "data": rpc.data,
+            }))
+            .unwrap_or_default(),
+        )
+    }
+
     /// Create a JSON result
     pub fn json(value: Value) -> Self {
         Self {
@@ -425,6 +619,24 @@ impl Notification {
     pub fn resources_list_changed() -> Self {
         Self::new("notifications/resources/list_changed", None)
     }
+
+    /// Progress notification for a long-running request
+    pub fn progress(
+        token: impl Into<String>,
+        progress: usize,
+        total: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        Self::new(
+            "notifications/progress",
+            Some(json!({
+                "progressToken": token.into(),
+                "progress": progress,
+                "total": total,
+                "message": message.into()
+            })),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -452,6 +664,108 @@ mod tests {
         assert!(schema.properties.contains_key("domain"));
     }
 
+    #[test]
+    fn test_context_error_codes() {
+        let cases = [
+            (ContextError::NotFound("x".into()), -32001, "not_found"),
+            (ContextError::Expired("x".into()), -32002, "expired"),
+            (ContextError::Blocked("x".into()), -32003, "blocked"),
+            (
+                ContextError::ScreeningFailed("x".into()),
+                -32004,
+                "screening_failed",
+            ),
+            (ContextError::Timeout("x".into()), -32005, "timeout"),
+            (ContextError::Storage("x".into()), -32010, "storage"),
+            (
+                ContextError::StorageTransient("x".into()),
+                -32010,
+                "storage_transient",
+            ),
+            (ContextError::Io(std::io::Error::other("x")), -32012, "io"),
+            (ContextError::Config("x".into()), -32013, "config"),
+            (
+                ContextError::InvalidQuery("x".into()),
+                -32602,
+                "invalid_query",
+            ),
+            (ContextError::InvalidId("x".into()), -32602, "invalid_id"),
+            (ContextError::Protocol("x".into()), -32600, "protocol"),
+            (ContextError::Internal("x".into()), -32603, "internal"),
+        ];
+        for (err, code, kind) in cases {
+            let rpc = JsonRpcError::from(&err);
+            assert_eq!(rpc.code, code, "{:?}", err);
+            assert_eq!(rpc.data.unwrap()["kind"], kind);
+        }
+
+        let serialization = serde_json::from_str::<Value>("{").unwrap_err();
+        let rpc = JsonRpcError::from(&ContextError::Serialization(serialization));
+        assert_eq!(rpc.code, -32011);
+    }
+
+    #[test]
+    fn test_operation_context_in_error_data() {
+        let id = crate::context::ContextId::new();
+        let err =
+            ContextError::Storage("sled insert failed".into()).with_operation("store", Some(&id));
+        assert_eq!(
+            err.to_string(),
+            format!("store(context={}): Storage error: sled insert failed", id)
+        );
+
+        let rpc = JsonRpcError::from(&err);
+        assert_eq!(rpc.code, -32010);
+        let data = rpc.data.unwrap();
+        assert_eq!(data["kind"], "storage");
+        assert_eq!(data["operation"], "store");
+        assert_eq!(data["context_id"], id.to_string());
+
+        let result = CallToolResult::from_error(&err);
+        let Content::Text { text } = &result.content[0] else {
+            panic!("expected text content");
+        };
+        let body: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(body["data"]["operation"], "store");
+        assert_eq!(body["data"]["context_id"], id.to_string());
+    }
+
+    #[test]
+    fn test_json_rpc_error_round_trip() {
+        let errors = [
+            ContextError::NotFound("abc".into()),
+            ContextError::InvalidId("abc".into()),
+            ContextError::StorageTransient("busy".into()),
+        ];
+        for err in errors {
+            let back = ContextError::from(JsonRpcError::from(&err));
+            assert_eq!(back.to_string(), err.to_string());
+        }
+
+        let wrapped = ContextError::Storage("disk".into()).with_operation("store", None);
+        let back = ContextError::from(JsonRpcError::from(&wrapped));
+        assert!(matches!(back, ContextError::Storage(_)));
+        assert_eq!(back.to_string(), "Storage error: store: disk");
+
+        let bare = ContextError::from(JsonRpcError::method_not_found("nope"));
+        assert!(matches!(bare, ContextError::Protocol(_)));
+    }
+
+    #[test]
+    fn test_not_found_carries_id() {
+        let err = ContextError::NotFound("abc".into());
+        assert_eq!(JsonRpcError::from(&err).data.unwrap()["id"], "abc");
+
+        let result = CallToolResult::from_error(&err);
+        assert!(result.is_error);
+        let Content::Text { text } = &result.content[0] else {
+            panic!("expected text content");
+        };
+        let body: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(body["code"], -32001);
+        assert_eq!(body["data"]["id"], "abc");
+    }
+
     #[test]
     fn test_tool_result() {
         let result = CallToolResult::text("Success");
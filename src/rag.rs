@@ -6,12 +6,15 @@
 
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-use crate::context::{Context, ContextDomain, ContextQuery};
+use crate::context::{Context, ContextDomain, ContextId, ContextQuery, ScreeningStatus};
 use crate::embeddings::QuantizedEmbeddingGenerator;
-use crate::error::ContextResult;
+use crate::error::{ContextError, ContextResult, OperationContext};
+use crate::observer::StoreObserver;
 use crate::storage::ContextStore;
+use crate::telemetry;
 use crate::temporal::{TemporalQuery, TemporalStats};
 
 /// RAG processor configuration
@@ -35,6 +38,25 @@ pub struct RagConfig {
     pub embedding_strategy: String,
     /// Weight for semantic similarity in final score
     pub semantic_weight: f64,
+    /// Append flattened structured data to the content used for text matching
+    #[serde(default)]
+    pub include_data_in_text: bool,
+    /// Half-life of temporal decay, in hours, for queries without their own temporal settings
+    #[serde(default = "default_decay_half_life_hours")]
+    pub decay_half_life_hours: f64,
+    /// Importance adjustment per lowercase tag for suggested importances,
+    /// overriding the built-in priors of
+    /// [`ContextMetadata::suggest_importance_with`](crate::context::ContextMetadata::suggest_importance_with)
+    #[serde(default)]
+    pub tag_importance_priors: HashMap<String, f32>,
+    /// Drop stopwords before text similarity scoring, using the stopword
+    /// list of each context's detected language
+    #[serde(default)]
+    pub language_stopwords: bool,
+}
+
+fn default_decay_half_life_hours() -> f64 {
+    24.0
 }
 
 impl Default for RagConfig {
@@ -49,6 +71,10 @@ impl Default for RagConfig {
             chunk_size: 1000,
             embedding_strategy: "sparse".to_string(),
             semantic_weight: 0.2,
+            include_data_in_text: false,
+            decay_half_life_hours: default_decay_half_life_hours(),
+            tag_importance_priors: HashMap::new(),
+            language_stopwords: false,
         }
     }
 }
@@ -92,6 +118,275 @@ pub struct RetrievalResult {
     pub candidates_considered: usize,
     /// Temporal statistics
     pub temporal_stats: TemporalStats,
+    /// Number of distinct domains among the returned contexts
+    #[serde(default)]
+    pub domains_represented: usize,
+}
+
+/// One extracted sentence and the contexts it was found in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestLine {
+    /// Extracted sentence
+    pub text: String,
+    /// IDs of the contexts containing the sentence
+    pub sources: Vec<ContextId>,
+}
+
+/// Extracted sentences from one domain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestSection {
+    /// Domain of the cited contexts
+    pub domain: ContextDomain,
+    /// Extracted sentences, highest scoring context first
+    pub lines: Vec<DigestLine>,
+}
+
+/// Extractive digest of a retrieval result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractiveSummary {
+    /// Sections in order of first appearance
+    pub sections: Vec<DigestSection>,
+    /// Rendered digest, at most `max_chars` characters
+    pub text: String,
+    /// Whether lines were dropped to stay within `max_chars`
+    pub truncated: bool,
+}
+
+impl RetrievalResult {
+    /// Compress the retrieved contexts into a single digest
+    ///
+    /// Extracts sentences containing any query term (or the first sentence of
+    /// each context when the query has no terms), deduplicates them across
+    /// contexts, groups them by domain and cites the source IDs on each line.
+    /// Lines are added in score order until `max_chars` would be exceeded.
+    pub fn extractive_summary(&self, query: &str, max_chars: usize) -> ExtractiveSummary {
+        let terms: HashSet<String> = words(query).collect();
+
+        let mut sections: Vec<DigestSection> = Vec::new();
+        // Normalized sentence -> (section, line) for deduplication
+        let mut seen: HashMap<String, (usize, usize)> = HashMap::new();
+
+        for scored in &self.contexts {
+            let ctx = &scored.context;
+            let sentences = split_sentences(&ctx.content);
+            let picked: Vec<&str> = if terms.is_empty() {
+                sentences.into_iter().take(1).collect()
+            } else {
+                sentences
+                    .into_iter()
+                    .filter(|s| words(s).any(|w| terms.contains(&w)))
+                    .collect()
+            };
+
+            for sentence in picked {
+                let key = sentence
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .to_lowercase();
+
+                if let Some(&(section, line)) = seen.get(&key) {
+                    let sources = &mut sections[section].lines[line].sources;
+                    if !sources.contains(&ctx.id) {
+                        sources.push(ctx.id.clone());
+                    }
+                    continue;
+                }
+
+                let section = match sections.iter().position(|s| s.domain == ctx.domain) {
+                    Some(i) => i,
+                    None => {
+                        sections.push(DigestSection {
+                            domain: ctx.domain.clone(),
+                            lines: Vec::new(),
+                        });
+                        sections.len() - 1
+                    }
+                };
+                sections[section].lines.push(DigestLine {
+                    text: sentence.to_string(),
+                    sources: vec![ctx.id.clone()],
+                });
+                seen.insert(key, (section, sections[section].lines.len() - 1));
+            }
+        }
+
+        // Render within the budget, keeping the structured sections in sync with the text
+        let mut text = String::new();
+        let mut used = 0;
+        let mut truncated = false;
+        let mut kept = Vec::new();
+
+        for section in sections {
+            if truncated {
+                break;
+            }
+
+            let header = format!("## {}\n", section.domain);
+            let mut lines = Vec::new();
+            for line in section.lines {
+                let citations: Vec<&str> = line.sources.iter().map(|id| id.as_str()).collect();
+                let rendered = format!("- {} [{}]\n", line.text, citations.join(", "));
+                let header_cost = if lines.is_empty() {
+                    header.chars().count()
+                } else {
+                    0
+                };
+                let cost = header_cost + rendered.chars().count();
+
+                if used + cost > max_chars {
+                    truncated = true;
+                    break;
+                }
+
+                if lines.is_empty() {
+                    text.push_str(&header);
+                }
+                text.push_str(&rendered);
+                used += cost;
+                lines.push(line);
+            }
+
+            if !lines.is_empty() {
+                kept.push(DigestSection {
+                    domain: section.domain,
+                    lines,
+                });
+            }
+        }
+
+        ExtractiveSummary {
+            sections: kept,
+            text,
+            truncated,
+        }
+    }
+
+    /// Merge results that are adjacent chunks of the same document
+    ///
+    /// Runs of consecutive `chunk_index` values within a `chunk_group` (see
+    /// [`Context::split_into_chunks`]) collapse into one result holding the
+    /// joined text with overlaps removed and the best score of the run. The
+    /// merged result takes the place of the run's best-scoring chunk and
+    /// lists the merged IDs under `metadata.custom["chunk_ids"]`.
+    pub fn coalesce_chunks(mut self) -> Self {
+        let mut groups: HashMap<String, Vec<(u64, usize)>> = HashMap::new();
+        for (pos, sc) in self.contexts.iter().enumerate() {
+            let custom = &sc.context.metadata.custom;
+            if let (Some(group), Some(index)) = (
+                custom.get("chunk_group").and_then(|v| v.as_str()),
+                custom.get("chunk_index").and_then(|v| v.as_u64()),
+            ) {
+                groups
+                    .entry(group.to_string())
+                    .or_default()
+                    .push((index, pos));
+            }
+        }
+
+        let mut merged: HashMap<usize, ScoredContext> = HashMap::new();
+        let mut absorbed: HashSet<usize> = HashSet::new();
+        for chunks in groups.values_mut() {
+            chunks.sort();
+            let mut run_start = 0;
+            for i in 1..=chunks.len() {
+                if i < chunks.len() && chunks[i].0 == chunks[i - 1].0 + 1 {
+                    continue;
+                }
+                let run: Vec<usize> = chunks[run_start..i].iter().map(|&(_, pos)| pos).collect();
+                run_start = i;
+                if run.len() < 2 {
+                    continue;
+                }
+
+                let best = *run
+                    .iter()
+                    .max_by(|&&a, &&b| {
+                        self.contexts[a]
+                            .score
+                            .total_cmp(&self.contexts[b].score)
+                            .then(b.cmp(&a))
+                    })
+                    .expect("run has at least two chunks");
+                let mut combined = self.contexts[best].clone();
+                combined.context.content = run.iter().skip(1).fold(
+                    self.contexts[run[0]].context.content.clone(),
+                    |text, &pos| join_overlapping(&text, &self.contexts[pos].context.content),
+                );
+                let ids: Vec<&str> = run
+                    .iter()
+                    .map(|&pos| self.contexts[pos].context.id.as_str())
+                    .collect();
+                combined
+                    .context
+                    .metadata
+                    .custom
+                    .insert("chunk_ids".into(), serde_json::json!(ids));
+
+                absorbed.extend(run.iter().filter(|&&pos| pos != best));
+                merged.insert(best, combined);
+            }
+        }
+
+        self.contexts = std::mem::take(&mut self.contexts)
+            .into_iter()
+            .enumerate()
+            .filter(|(pos, _)| !absorbed.contains(pos))
+            .map(|(pos, sc)| merged.remove(&pos).unwrap_or(sc))
+            .collect();
+        self
+    }
+}
+
+/// Join two chunks, dropping the text `b` repeats from the end of `a`
+fn join_overlapping(a: &str, b: &str) -> String {
+    let overlap = (1..=a.len().min(b.len()))
+        .rev()
+        .filter(|&k| b.is_char_boundary(k))
+        .find(|&k| a.ends_with(&b[..k]))
+        .unwrap_or(0);
+    if overlap > 0 {
+        format!("{}{}", a, &b[overlap..])
+    } else {
+        format!("{}\n{}", a, b)
+    }
+}
+
+/// Lowercased alphanumeric words of a text
+pub(crate) fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+}
+
+/// Split text into sentences at `.`, `!` or `?` followed by whitespace, and at newlines
+fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let boundary = match c {
+            '\n' => true,
+            '.' | '!' | '?' => chars.peek().map(|(_, n)| n.is_whitespace()).unwrap_or(true),
+            _ => false,
+        };
+
+        if boundary {
+            let end = i + c.len_utf8();
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = end;
+        }
+    }
+
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+    sentences
 }
 
 /// CPU-optimized RAG processor
@@ -99,6 +394,7 @@ pub struct RagProcessor {
     config: RagConfig,
     store: Arc<ContextStore>,
     embedding_generator: Option<Arc<dyn QuantizedEmbeddingGenerator>>,
+    observer: Option<Arc<dyn StoreObserver>>,
 }
 
 impl RagProcessor {
@@ -116,6 +412,7 @@ impl RagProcessor {
             config,
             store,
             embedding_generator: None,
+            observer: None,
         }
     }
 
@@ -137,9 +434,19 @@ impl RagProcessor {
             config,
             store,
             embedding_generator: Some(embedding_generator),
+            observer: None,
         }
     }
 
+    /// Report retrievals to `observer`
+    ///
+    /// Retrievals are also counted by the store's built-in counters, so
+    /// the stats tools see them whether or not an observer is registered.
+    pub fn with_observer(mut self, observer: Arc<dyn StoreObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
     /// Create with default configuration
     pub fn with_defaults(store: Arc<ContextStore>) -> Self {
         Self::new(store, RagConfig::default())
@@ -147,6 +454,47 @@ impl RagProcessor {
 
     /// Retrieve contexts using a query
     pub async fn retrieve(&self, query: &RetrievalQuery) -> ContextResult<RetrievalResult> {
+        self.retrieve_sampled(query, None).await
+    }
+
+    /// Retrieve contexts, taking at most `max_per_domain` from each domain
+    ///
+    /// Candidates are scored as in [`Self::retrieve`]; the best
+    /// `max_per_domain` of each domain are kept before the global sort and
+    /// truncation, so one dominant domain cannot crowd out the others.
+    /// Contexts in `always_include` are not subject to the quota.
+    pub async fn retrieve_with_diversity_sampling(
+        &self,
+        query: &RetrievalQuery,
+        max_per_domain: usize,
+    ) -> ContextResult<RetrievalResult> {
+        if max_per_domain == 0 {
+            return Err(ContextError::InvalidQuery(
+                "max_per_domain must be at least 1".to_string(),
+            ));
+        }
+        self.retrieve_sampled(query, Some(max_per_domain)).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "retrieve",
+            level = "debug",
+            skip_all,
+            fields(
+                max_per_domain = ?max_per_domain,
+                candidates = tracing::field::Empty,
+                results = tracing::field::Empty,
+                duration_ms = tracing::field::Empty
+            )
+        )
+    )]
+    async fn retrieve_sampled(
+        &self,
+        query: &RetrievalQuery,
+        max_per_domain: Option<usize>,
+    ) -> ContextResult<RetrievalResult> {
         let start = std::time::Instant::now();
 
         // Build context query
@@ -164,12 +512,27 @@ impl RagProcessor {
             ctx_query = ctx_query.with_min_importance(min_importance);
         }
 
+        if let Some(accessor) = &query.accessor {
+            ctx_query = ctx_query.with_accessor(accessor.clone());
+        }
+
+        if let Some(language) = &query.language {
+            ctx_query = ctx_query.with_language(language.clone());
+        }
+
         // Get candidates from storage
-        let candidates: Vec<Context> = self.store.query(&ctx_query).await?;
+        let candidates: Vec<Context> = self
+            .store
+            .query(&ctx_query)
+            .await
+            .with_operation("retrieve", None)?;
         let candidates_count = candidates.len();
 
         // Apply temporal filtering
-        let temporal_query = query.temporal.clone().unwrap_or_default();
+        let temporal_query = query.temporal.clone().unwrap_or_else(|| TemporalQuery {
+            decay_half_life_hours: self.config.decay_half_life_hours,
+            ..Default::default()
+        });
         let filtered: Vec<Context> = candidates
             .into_iter()
             .filter(|c| temporal_query.matches(c))
@@ -194,6 +557,25 @@ impl RagProcessor {
                 .partial_cmp(&a.score)
                 .unwrap_or(std::cmp::Ordering::Equal)
         });
+
+        // Results are sorted, so the first `quota` seen per domain are its best
+        if let Some(quota) = max_per_domain {
+            let mut taken: HashMap<ContextDomain, usize> = HashMap::new();
+            results.retain(|s| {
+                let count = taken.entry(s.context.domain.clone()).or_insert(0);
+                *count += 1;
+                *count <= quota
+            });
+        }
+
+        if !query.always_include.is_empty() {
+            let pinned = self
+                .fetch_always_included(&query.always_include, query.accessor.as_deref())
+                .await?;
+            let pinned_ids: HashSet<&ContextId> = pinned.iter().map(|s| &s.context.id).collect();
+            results.retain(|s| !pinned_ids.contains(&s.context.id));
+            results.splice(0..0, pinned);
+        }
         results.truncate(self.config.max_results);
 
         let temporal_stats = TemporalStats::from_contexts(
@@ -202,16 +584,75 @@ impl RagProcessor {
                 .map(|s| s.context.clone())
                 .collect::<Vec<_>>(),
         );
+        let domains_represented = results
+            .iter()
+            .map(|s| &s.context.domain)
+            .collect::<HashSet<_>>()
+            .len();
+        let elapsed = start.elapsed();
+        self.store
+            .counters()
+            .on_retrieve(elapsed, candidates_count, results.len());
+        if let Some(ref observer) = self.observer {
+            observer.on_retrieve(elapsed, candidates_count, results.len());
+        }
+        let processing_time_ms = elapsed.as_millis() as u64;
+        telemetry::record("candidates", candidates_count);
+        telemetry::record("results", results.len());
+        telemetry::record("duration_ms", processing_time_ms);
 
         Ok(RetrievalResult {
             contexts: results,
             query_summary: query.to_string(),
-            processing_time_ms: start.elapsed().as_millis() as u64,
+            processing_time_ms,
             candidates_considered: candidates_count,
             temporal_stats,
+            domains_represented,
         })
     }
 
+    /// Fetch contexts that must appear in results, in the order requested
+    ///
+    /// Missing, expired and blocked contexts are dropped, as are unsafe ones
+    /// when `safe_only` is set. Each one carries an infinite score.
+    async fn fetch_always_included(
+        &self,
+        ids: &[ContextId],
+        accessor: Option<&str>,
+    ) -> ContextResult<Vec<ScoredContext>> {
+        let mut seen = HashSet::new();
+        let mut pinned = Vec::new();
+        for id in ids {
+            if !seen.insert(id) {
+                continue;
+            }
+            let Some(ctx) = self
+                .store
+                .get_as(id, accessor)
+                .await
+                .with_operation("retrieve", Some(id))?
+            else {
+                continue;
+            };
+            let blocked = ctx.metadata.screening_status == ScreeningStatus::Blocked;
+            if ctx.is_expired() || blocked || (self.config.safe_only && !ctx.is_safe()) {
+                continue;
+            }
+            pinned.push(ScoredContext {
+                context: ctx,
+                score: f64::INFINITY,
+                score_breakdown: ScoreBreakdown {
+                    temporal: f64::INFINITY,
+                    importance: f64::INFINITY,
+                    domain_match: f64::INFINITY,
+                    tag_match: f64::INFINITY,
+                    similarity: None,
+                },
+            });
+        }
+        Ok(pinned)
+    }
+
     /// Score contexts in parallel using rayon
     fn score_parallel(
         &self,
@@ -238,6 +679,17 @@ impl RagProcessor {
             .collect()
     }
 
+    /// Text a context is matched on: its content, plus flattened data when
+    /// `include_data_in_text` is set
+    fn scoring_text<'a>(&self, ctx: &'a Context) -> std::borrow::Cow<'a, str> {
+        if self.config.include_data_in_text {
+            if let Some(data) = ctx.data_text() {
+                return format!("{}\n{}", ctx.content, data).into();
+            }
+        }
+        ctx.content.as_str().into()
+    }
+
     /// Score a single context
     fn score_context(
         &self,
@@ -275,13 +727,20 @@ impl RagProcessor {
         // Optional semantic similarity using quantized embeddings
         let similarity_score: Option<f64> =
             if let (Some(text_query), Some(_)) = (&query.text, &self.embedding_generator) {
+                // Query and context drop the stopwords of the context's language
+                let stopwords = match ctx.metadata.language {
+                    Some(ref language) if self.config.language_stopwords => {
+                        crate::language::stopwords(language)
+                    }
+                    _ => &[],
+                };
                 // Compute embeddings for query and context
                 // Note: In production, these would be cached during retrieval
                 if let (Ok(query_embedding), Ok(ctx_embedding)) = (
                     // For now, use a simple text hash-based pseudo-embedding
                     // In production, use actual embedding generator
-                    self.text_to_pseudo_embedding(text_query),
-                    self.text_to_pseudo_embedding(&ctx.content),
+                    self.text_to_pseudo_embedding(text_query, stopwords),
+                    self.text_to_pseudo_embedding(&self.scoring_text(ctx), stopwords),
                 ) {
                     // Compute cosine similarity (simplified)
                     let sim = self
@@ -323,13 +782,21 @@ impl RagProcessor {
     }
 
     /// Convert text to a simple pseudo-embedding for similarity computation
-    fn text_to_pseudo_embedding(&self, text: &str) -> Result<Vec<f32>, String> {
+    ///
+    /// Words in `stopwords` are skipped, ignoring case.
+    fn text_to_pseudo_embedding(&self, text: &str, stopwords: &[&str]) -> Result<Vec<f32>, String> {
         // Warning: This is a placeholder pseudo-embedding for demonstration only.
         // In production, use actual embedding models for meaningful semantic similarity.
         eprintln!("Warning: Using pseudo-embeddings for similarity computation. This is for demonstration only and does not provide real semantic meaning. Use actual embedding models in production.");
 
         // Simple hash-based pseudo-embedding: split into words and create feature vector
-        let words: Vec<&str> = text.split_whitespace().take(100).collect();
+        let words: Vec<&str> = text
+            .split_whitespace()
+            .filter(|word| {
+                stopwords.is_empty() || !stopwords.contains(&word.to_lowercase().as_str())
+            })
+            .take(100)
+            .collect();
         let dim = 64;
         let mut embedding = vec![0.0f32; dim];
 
@@ -385,6 +852,48 @@ impl RagProcessor {
     pub fn config(&self) -> &RagConfig {
         &self.config
     }
+
+    /// Regenerate the stored embedding of every context
+    ///
+    /// Embeddings come from the configured generator and are stored in
+    /// reconstructed (dense) form. Returns the number of contexts updated.
+    pub async fn reindex_embeddings(&self) -> ContextResult<usize> {
+        let generator = self
+            .embedding_generator
+            .as_ref()
+            .ok_or_else(|| ContextError::Config("No embedding generator configured".into()))?;
+
+        let mut updated = 0;
+        let contexts = self
+            .store
+            .all_contexts()
+            .await
+            .with_operation("reindex_embeddings", None)?;
+        for mut ctx in contexts {
+            let id = ctx.id.clone();
+            let quantized = generator
+                .generate_quantized(&ctx.content)
+                .await
+                .with_operation("reindex_embeddings", Some(&id))?;
+            ctx.embedding = Some(
+                generator
+                    .reconstruct(&quantized)
+                    .await
+                    .with_operation("reindex_embeddings", Some(&id))?,
+            );
+            self.store
+                .store(ctx)
+                .await
+                .with_operation("reindex_embeddings", Some(&id))?;
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
+    /// Get the embedding generator, if one was configured
+    pub fn embedding_generator(&self) -> Option<&Arc<dyn QuantizedEmbeddingGenerator>> {
+        self.embedding_generator.as_ref()
+    }
 }
 
 /// Query for RAG retrieval
@@ -402,6 +911,15 @@ pub struct RetrievalQuery {
     pub temporal: Option<TemporalQuery>,
     /// Maximum results
     pub max_results: Option<usize>,
+    /// Contexts included ahead of scored results regardless of relevance
+    #[serde(default)]
+    pub always_include: Vec<ContextId>,
+    /// Client recorded as the accessor of retrieved contexts
+    #[serde(default)]
+    pub accessor: Option<String>,
+    /// Only contexts in this language (ISO 639-1)
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
 impl RetrievalQuery {
@@ -442,6 +960,24 @@ impl RetrievalQuery {
         self
     }
 
+    /// Always include a context, ahead of scored results
+    pub fn with_always_include(mut self, id: ContextId) -> Self {
+        self.always_include.push(id);
+        self
+    }
+
+    /// Record a client as the accessor of retrieved contexts
+    pub fn with_accessor(mut self, accessor: impl Into<String>) -> Self {
+        self.accessor = Some(accessor.into());
+        self
+    }
+
+    /// Only retrieve contexts in a language
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
     /// Query for recent contexts
     pub fn recent(hours: i64) -> Self {
         Self::new().with_temporal(TemporalQuery::recent(hours))
@@ -456,7 +992,7 @@ impl std::fmt::Display for RetrievalQuery {
             parts.push(format!("text: '{}'", text));
         }
         if let Some(domain) = &self.domain {
-            parts.push(format!("domain: {:?}", domain));
+            parts.push(format!("domain: {}", domain));
         }
         if !self.tags.is_empty() {
             parts.push(format!("tags: {:?}", self.tags));
@@ -464,6 +1000,9 @@ impl std::fmt::Display for RetrievalQuery {
         if let Some(importance) = self.min_importance {
             parts.push(format!("min_importance: {}", importance));
         }
+        if let Some(language) = &self.language {
+            parts.push(format!("language: {}", language));
+        }
 
         if parts.is_empty() {
             write!(f, "all contexts")
@@ -538,4 +1077,201 @@ mod tests {
         let result = processor.retrieve(&RetrievalQuery::new()).await.unwrap();
         assert_eq!(result.candidates_considered, 1);
     }
+
+    #[tokio::test]
+    async fn test_always_include() {
+        let (store, _temp) = create_test_store();
+        let processor = RagProcessor::with_defaults(store.clone());
+
+        let profile = Context::new("User profile", ContextDomain::General);
+        let profile_id = profile.id.clone();
+        store.store(profile).await.unwrap();
+        store
+            .store(Context::new("Rust ownership", ContextDomain::Code))
+            .await
+            .unwrap();
+        let mut blocked = Context::new("Blocked note", ContextDomain::Code);
+        blocked.metadata.screening_status = ScreeningStatus::Blocked;
+        let blocked_id = blocked.id.clone();
+        store.store(blocked).await.unwrap();
+
+        let query = RetrievalQuery::new()
+            .with_domain(ContextDomain::Code)
+            .with_always_include(profile_id.clone())
+            .with_always_include(blocked_id.clone())
+            .with_always_include(ContextId::new());
+        let result = processor.retrieve(&query).await.unwrap();
+
+        assert_eq!(result.contexts[0].context.id, profile_id);
+        assert_eq!(result.contexts[0].score, f64::INFINITY);
+        assert!(result.contexts.iter().all(|s| s.context.id != blocked_id));
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_with_diversity_sampling() {
+        let (store, _temp) = create_test_store();
+        let config = RagConfig {
+            safe_only: false,
+            min_relevance: 0.0,
+            ..Default::default()
+        };
+        let processor = RagProcessor::new(store.clone(), config);
+        for i in 0..5 {
+            store
+                .store(Context::new(
+                    format!("code note {}", i),
+                    ContextDomain::Code,
+                ))
+                .await
+                .unwrap();
+        }
+        store
+            .store(Context::new("design note", ContextDomain::Documentation))
+            .await
+            .unwrap();
+
+        let result = processor
+            .retrieve_with_diversity_sampling(&RetrievalQuery::new(), 2)
+            .await
+            .unwrap();
+        let code = result
+            .contexts
+            .iter()
+            .filter(|s| s.context.domain == ContextDomain::Code)
+            .count();
+        assert_eq!(code, 2);
+        assert_eq!(result.contexts.len(), 3);
+        assert_eq!(result.domains_represented, 2);
+
+        assert!(processor
+            .retrieve_with_diversity_sampling(&RetrievalQuery::new(), 0)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pending_follows_safe_only() {
+        let (store, _temp) = create_test_store();
+        let mut pending = Context::new("Rust ownership pending review", ContextDomain::Code);
+        pending.metadata.screening_status = ScreeningStatus::Pending;
+        let pending_id = pending.id.clone();
+        store.store(pending).await.unwrap();
+
+        let query = RetrievalQuery::new().with_domain(ContextDomain::Code);
+        let safe = RagProcessor::with_defaults(store.clone());
+        let result = safe.retrieve(&query).await.unwrap();
+        assert!(result.contexts.iter().all(|s| s.context.id != pending_id));
+
+        let config = RagConfig {
+            safe_only: false,
+            min_relevance: 0.0,
+            ..Default::default()
+        };
+        let permissive = RagProcessor::new(store, config);
+        let result = permissive.retrieve(&query).await.unwrap();
+        assert!(result.contexts.iter().any(|s| s.context.id == pending_id));
+    }
+
+    fn scored(content: &str, domain: ContextDomain) -> ScoredContext {
+        ScoredContext {
+            context: Context::new(content, domain),
+            score: 1.0,
+            score_breakdown: ScoreBreakdown::default(),
+        }
+    }
+
+    fn result_of(contexts: Vec<ScoredContext>) -> RetrievalResult {
+        RetrievalResult {
+            contexts,
+            query_summary: String::new(),
+            processing_time_ms: 0,
+            candidates_considered: 0,
+            temporal_stats: TemporalStats::from_contexts(&[]),
+            domains_represented: 0,
+        }
+    }
+
+    #[test]
+    fn test_coalesce_chunks() {
+        let doc = Context::new(
+            "Alpha section text. Beta section text. Gamma section text. Delta section text.",
+            ContextDomain::Documentation,
+        );
+        let chunks = doc.split_into_chunks(40, 10);
+        assert_eq!(chunks.len(), 3);
+
+        let as_scored = |ctx: &Context, score: f64| ScoredContext {
+            context: ctx.clone(),
+            score,
+            score_breakdown: ScoreBreakdown::default(),
+        };
+        let other = scored("Unrelated", ContextDomain::Code);
+        let result = result_of(vec![
+            as_scored(&chunks[1], 0.9),
+            other,
+            as_scored(&chunks[0], 0.5),
+        ])
+        .coalesce_chunks();
+
+        assert_eq!(result.contexts.len(), 2);
+        let combined = &result.contexts[0];
+        assert_eq!(combined.score, 0.9);
+        assert!(combined.context.content.starts_with("Alpha section text."));
+        assert_eq!(combined.context.content.matches("Beta").count(), 1);
+        assert_eq!(
+            combined.context.metadata.custom["chunk_ids"],
+            serde_json::json!([chunks[0].id.as_str(), chunks[1].id.as_str()])
+        );
+        assert_eq!(result.contexts[1].context.content, "Unrelated");
+    }
+
+    #[test]
+    fn test_extractive_summary_citations() {
+        let a = scored("Rust is fast. Python is slow.", ContextDomain::Code);
+        let b = scored("Cats are nice.\nRust is  fast.", ContextDomain::Code);
+        let c = scored(
+            "Rust ownership prevents data races.",
+            ContextDomain::Documentation,
+        );
+        let (id_a, id_b, id_c) = (
+            a.context.id.clone(),
+            b.context.id.clone(),
+            c.context.id.clone(),
+        );
+
+        let summary = result_of(vec![a, b, c]).extractive_summary("rust", 10_000);
+        assert!(!summary.truncated);
+        assert_eq!(summary.sections.len(), 2);
+
+        let code = &summary.sections[0];
+        assert_eq!(code.domain, ContextDomain::Code);
+        assert_eq!(code.lines.len(), 1);
+        assert_eq!(code.lines[0].text, "Rust is fast.");
+        assert_eq!(code.lines[0].sources, vec![id_a.clone(), id_b]);
+
+        let docs = &summary.sections[1];
+        assert_eq!(docs.lines[0].sources, vec![id_c.clone()]);
+
+        assert!(summary
+            .text
+            .contains(&format!("- Rust is fast. [{}, ", id_a)));
+        assert!(!summary.text.contains("Cats"));
+        assert!(!summary.text.contains("Python"));
+    }
+
+    #[test]
+    fn test_extractive_summary_length_bound() {
+        let contexts = (0..20)
+            .map(|i| scored(&format!("Rust fact number {}.", i), ContextDomain::Code))
+            .collect();
+        let result = result_of(contexts);
+
+        let summary = result.extractive_summary("rust", 200);
+        assert!(summary.truncated);
+        assert!(summary.text.chars().count() <= 200);
+        let rendered_lines = summary.text.lines().filter(|l| l.starts_with("- ")).count();
+        assert_eq!(rendered_lines, summary.sections[0].lines.len());
+
+        assert!(result.extractive_summary("rust", 5).sections.is_empty());
+    }
 }
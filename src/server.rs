@@ -5,26 +5,29 @@
 #[cfg(feature = "server")]
 use axum::{
     extract::{Json, State},
+    http::StatusCode,
     response::{IntoResponse, Sse},
     routing::{get, post},
     Router,
 };
-use futures::stream::{self, Stream};
+use futures::stream::{self, Stream, StreamExt};
+use serde::Serialize;
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::convert::Infallible;
 use std::sync::Arc;
 
 use crate::error::ContextResult;
 use crate::protocol::{
-    CallToolRequest, InitializeResult, JsonRpcError, JsonRpcRequest, JsonRpcResponse, RequestId,
-    ServerCapabilities, ServerInfo, ToolsCapability, MCP_VERSION,
+    CallToolRequest, CallToolResult, InitializeResult, JsonRpcError, JsonRpcRequest,
+    JsonRpcResponse, RequestId, ServerCapabilities, ServerInfo, ToolsCapability, MCP_VERSION,
 };
 use crate::rag::{RagConfig, RagProcessor};
 use crate::storage::{ContextStore, StorageConfig};
 use crate::tools::ToolRegistry;
 
 /// Server configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ServerConfig {
     /// Server host
     pub host: String,
@@ -34,6 +37,8 @@ pub struct ServerConfig {
     pub storage: StorageConfig,
     /// RAG configuration
     pub rag: RagConfig,
+    /// Maximum results a single tool call may request via `limit` or `max_results`
+    pub max_query_limit: usize,
 }
 
 impl Default for ServerConfig {
@@ -43,6 +48,7 @@ impl Default for ServerConfig {
             port: 3000,
             storage: StorageConfig::default(),
             rag: RagConfig::default(),
+            max_query_limit: 1000,
         }
     }
 }
@@ -53,6 +59,7 @@ pub struct ServerState {
     store: Arc<ContextStore>,
     rag: Arc<RagProcessor>,
     tools: Arc<ToolRegistry>,
+    max_query_limit: usize,
 }
 
 impl ServerState {
@@ -61,8 +68,30 @@ impl ServerState {
         let store = Arc::new(ContextStore::new(config.storage.clone())?);
         let rag = Arc::new(RagProcessor::new(store.clone(), config.rag.clone()));
         let tools = Arc::new(ToolRegistry::new(store.clone(), rag.clone()));
+        // The tasks stop on their own once the store is dropped
+        if tokio::runtime::Handle::try_current().is_ok() {
+            // Queries scan sled until the indexes are loaded
+            let loading = store.clone();
+            tokio::spawn(async move {
+                match loading.load_indexes().await {
+                    Ok(0) => {}
+                    Ok(skipped) => {
+                        tracing::warn!("skipped {} undecodable records while indexing", skipped)
+                    }
+                    Err(e) => tracing::error!("failed to load indexes: {}", e),
+                }
+            });
+            ContextStore::spawn_flush_task(&store);
+            ContextStore::spawn_snapshot_task(&store);
+            ContextStore::spawn_cleanup_task(&store);
+        }
 
-        Ok(Self { store, rag, tools })
+        Ok(Self {
+            store,
+            rag,
+            tools,
+            max_query_limit: config.max_query_limit,
+        })
     }
 }
 
@@ -89,6 +118,7 @@ impl McpServer {
         Router::new()
             .route("/", get(health))
             .route("/health", get(health))
+            .route("/ready", get(ready))
             .route("/mcp", post(handle_mcp_request))
             .route("/sse", get(sse_handler))
             .with_state(self.state.clone())
@@ -103,6 +133,14 @@ impl McpServer {
 
         tracing::info!("MCP Context Server listening on {}", addr);
 
+        self.serve(listener).await
+    }
+
+    /// Serve HTTP requests on an already bound listener
+    ///
+    /// Useful for binding an ephemeral port (`127.0.0.1:0`) and reading the
+    /// chosen address back before serving.
+    pub async fn serve(&self, listener: tokio::net::TcpListener) -> ContextResult<()> {
         axum::serve(listener, self.router())
             .await
             .map_err(|e| crate::error::ContextError::Internal(e.to_string()))?;
@@ -114,15 +152,49 @@ impl McpServer {
     pub fn address(&self) -> String {
         format!("{}:{}", self.config.host, self.config.port)
     }
+
+    /// Tool registry, for registering additional tools
+    pub fn tools(&self) -> &Arc<ToolRegistry> {
+        &self.state.tools
+    }
+
+    /// Process a single JSON-RPC request without going through a transport
+    pub async fn handle_request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+        process_request(&self.state, request).await
+    }
 }
 
 /// Health check endpoint
-async fn health() -> impl IntoResponse {
-    Json(json!({
+///
+/// Always answers 200 while the server runs; a failing snapshot schedule
+/// shows up as `"status": "degraded"`.
+async fn health(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    let mut body = json!({
         "status": "ok",
         "server": "context-mcp",
         "version": env!("CARGO_PKG_VERSION")
-    }))
+    });
+    if state.store.config().snapshot.is_some() {
+        let snapshots = state.store.snapshot_status();
+        if !snapshots.is_healthy() {
+            body["status"] = json!("degraded");
+        }
+        body["snapshots"] = json!(snapshots);
+    }
+    Json(body)
+}
+
+/// Readiness endpoint, 503 while the last scheduled snapshot has failed
+async fn ready(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    let snapshots = state.store.snapshot_status();
+    if snapshots.is_healthy() {
+        (StatusCode::OK, Json(json!({"ready": true})))
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({"ready": false, "snapshot_error": snapshots.last_error})),
+        )
+    }
 }
 
 /// Handle MCP JSON-RPC request
@@ -196,27 +268,77 @@ async fn handle_call_tool(
         }
     };
 
-    let result = state
+    if let Err(violations) = state
         .tools
-        .execute(&call_request.name, call_request.arguments)
-        .await;
+        .validate(&call_request.name, &call_request.arguments)
+    {
+        return JsonRpcResponse::error(
+            id,
+            JsonRpcError::invalid_params(format!(
+                "Invalid arguments for tool '{}'",
+                call_request.name
+            ))
+            .with_data(json!({ "violations": violations })),
+        );
+    }
+
+    let result = match requested_limit(&call_request.arguments) {
+        Some(limit) if limit > state.max_query_limit as u64 => CallToolResult::error(format!(
+            "limit {} exceeds server maximum {}",
+            limit, state.max_query_limit
+        )),
+        _ => match state
+            .tools
+            .dispatch(&call_request.name, call_request.arguments)
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => return JsonRpcResponse::error(id, JsonRpcError::from(&e)),
+        },
+    };
     JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
 }
 
+/// Largest result count requested through a tool's `limit` or `max_results` argument
+fn requested_limit(arguments: &HashMap<String, Value>) -> Option<u64> {
+    ["limit", "max_results"]
+        .iter()
+        .filter_map(|key| arguments.get(*key).and_then(|v| v.as_u64()))
+        .max()
+}
+
 /// Handle ping request
 fn handle_ping(id: RequestId) -> JsonRpcResponse {
     JsonRpcResponse::success(id, json!({}))
 }
 
 /// SSE handler for streaming updates
+///
+/// Sends a `connected` event followed by server notifications such as
+/// `notifications/tools/list_changed`.
 async fn sse_handler(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
 ) -> Sse<impl Stream<Item = Result<axum::response::sse::Event, Infallible>>> {
-    let stream = stream::iter(vec![Ok(axum::response::sse::Event::default()
+    let connected = stream::iter(vec![Ok(axum::response::sse::Event::default()
         .event("connected")
         .data("MCP Context Server connected"))]);
 
-    Sse::new(stream)
+    let notifications = stream::unfold(state.tools.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(notification) => {
+                    let event = axum::response::sse::Event::default()
+                        .event("notification")
+                        .data(serde_json::to_string(&notification).unwrap_or_default());
+                    return Some((Ok::<_, Infallible>(event), receiver));
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(connected.chain(notifications))
 }
 
 /// Stdio transport for MCP
@@ -283,8 +405,45 @@ mod tests {
 
     #[tokio::test]
     async fn test_health_endpoint() {
-        let _response = health().await;
-        // Basic test that it responds
+        let server = McpServer::new(ServerConfig {
+            storage: StorageConfig::memory_only(100),
+            ..Default::default()
+        })
+        .unwrap();
+        let response = health(State(server.state.clone())).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = ready(State(server.state.clone())).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_ready_reports_failed_snapshots() {
+        use crate::storage::SnapshotPolicy;
+
+        let dir = tempfile::tempdir().unwrap();
+        // A file where the snapshot directory should be makes every snapshot fail
+        let blocked = dir.path().join("snapshots");
+        std::fs::write(&blocked, b"").unwrap();
+        let server = McpServer::new(ServerConfig {
+            storage: StorageConfig::builder()
+                .persist_path(dir.path().join("store"))
+                .snapshot(SnapshotPolicy {
+                    interval: std::time::Duration::from_millis(20),
+                    directory: blocked,
+                    keep_last: 1,
+                })
+                .build(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let status = server.state.store.snapshot_status();
+        assert!(status.consecutive_failures > 0);
+        let response = ready(State(server.state.clone())).await.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(server.state.store.counters().snapshot().snapshot_failures > 0);
     }
 
     #[test]
@@ -293,4 +452,52 @@ mod tests {
         assert_eq!(config.host, "127.0.0.1");
         assert_eq!(config.port, 3000);
     }
+
+    #[tokio::test]
+    async fn test_max_query_limit() {
+        let server = McpServer::new(ServerConfig {
+            storage: StorageConfig::memory_only(100),
+            max_query_limit: 50,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let call = |limit: u64| {
+            JsonRpcRequest::new(
+                "tools/call",
+                Some(json!({"name": "query_contexts", "arguments": {"limit": limit}})),
+            )
+        };
+
+        let response = server.handle_request(call(100)).await;
+        let result = response.result.unwrap();
+        assert_eq!(result["is_error"], true);
+        assert_eq!(
+            result["content"][0]["text"],
+            "limit 100 exceeds server maximum 50"
+        );
+
+        let response = server.handle_request(call(50)).await;
+        assert_eq!(response.result.unwrap()["is_error"], false);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tool_is_protocol_error() {
+        let server = McpServer::new(ServerConfig {
+            storage: StorageConfig::memory_only(100),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let response = server
+            .handle_request(JsonRpcRequest::new(
+                "tools/call",
+                Some(json!({"name": "no_such_tool", "arguments": {}})),
+            ))
+            .await;
+        assert!(response.result.is_none());
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32602);
+        assert_eq!(error.data.unwrap()["kind"], "invalid_query");
+    }
 }
@@ -5,20 +5,40 @@
 //! 2. Sled embedded database for persistence
 //! 3. Optional vector index for similarity search
 
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use chrono::Utc;
-use lru::LruCache;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
 
 #[cfg(feature = "persistence")]
 use sled;
 
-use crate::context::{Context, ContextDomain, ContextId, ContextQuery};
-use crate::error::{ContextError, Result};
+use crate::context::{
+    Context, ContextDomain, ContextId, ContextQuery, ContextRevision, QueryCursor, ScreeningHook,
+    ScreeningStatus, TransitionStrictness, MAX_REVISIONS, RECENT_ACCESS_LIMIT,
+};
+use crate::embeddings::EmbeddingGenerator;
+use crate::error::{ContextError, OperationContext, Result};
+use crate::language;
+use crate::observer::{CountingObserver, StoreObserver};
+use crate::rag::RagProcessor;
+use crate::tagging::AutoTagger;
+use crate::telemetry;
+
+pub mod blocking;
+mod cache;
+pub mod record;
+
+use cache::MemoryCache;
+pub use record::RecordFormat;
 
 /// Storage configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +53,107 @@ pub struct StorageConfig {
     pub cleanup_interval_secs: u64,
     /// Enable disk persistence
     pub enable_persistence: bool,
+    /// Upper bound accepted for `memory_cache_size`
+    #[serde(default = "default_max_memory_cache_size")]
+    pub max_memory_cache_size: usize,
+    /// Longest TTL, in hours, accepted from tool callers
+    #[serde(default = "default_max_ttl_hours")]
+    pub max_ttl_hours: u64,
+    /// Share of index entries live-checked by
+    /// [`ContextStore::dump_index_statistics`]; values outside `(0, 1]` check every entry
+    #[serde(default = "default_index_stats_sample_fraction")]
+    pub index_stats_sample_fraction: f64,
+    /// Screening status changes allowed by [`ContextStore::update_screening_status`]
+    #[serde(default)]
+    pub screening_strictness: TransitionStrictness,
+    /// Most embedded contexts [`ContextStore::content_similarity_clusters`] compares
+    #[serde(default = "default_max_cluster_contexts")]
+    pub max_cluster_contexts: usize,
+    /// Cache evictions remembered by [`ContextStore::get_eviction_history`]
+    #[serde(default = "default_eviction_history_size")]
+    pub eviction_history_size: usize,
+    /// Suggest an importance for tool-stored contexts whose caller gives none
+    #[serde(default)]
+    pub auto_importance: bool,
+    /// Detect the language of prose contexts without one when they are stored
+    #[serde(default = "default_detect_language")]
+    pub detect_language: bool,
+    /// Scheduled snapshots taken by [`ContextStore::spawn_snapshot_task`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<SnapshotPolicy>,
+    /// When writes are flushed to disk
+    #[serde(default)]
+    pub flush_policy: FlushPolicy,
+    /// Payload format of newly written records; existing records stay readable
+    #[serde(default)]
+    pub record_format: RecordFormat,
+    /// Byte budget of the memory cache, on top of `memory_cache_size` entries
+    ///
+    /// Sizes are estimates covering content, embedding and metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cache_bytes: Option<usize>,
+    /// Compress newly written records with zstd; existing records stay readable
+    #[serde(default)]
+    pub enable_compression: bool,
+    /// zstd level used when `enable_compression` is set
+    #[serde(default = "default_compression_level")]
+    pub compression_level: i32,
+}
+
+/// When persisted writes are flushed to disk
+///
+/// Unflushed writes survive a clean shutdown but may be lost on a crash.
+/// [`ContextStore::flush`] flushes on demand under any policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlushPolicy {
+    /// Every store waits for its write to be flushed
+    #[default]
+    EveryWrite,
+    /// A background task flushes at this interval; see [`ContextStore::spawn_flush_task`]
+    Interval(std::time::Duration),
+    /// Only sled's own background flushing, every 500ms by default
+    Never,
+}
+
+/// When and where the store is snapshotted, and how many snapshots are kept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotPolicy {
+    /// Time between snapshots
+    pub interval: std::time::Duration,
+    /// Directory holding one subdirectory per snapshot
+    pub directory: PathBuf,
+    /// Snapshots kept; older ones are deleted after each new snapshot
+    pub keep_last: usize,
+}
+
+fn default_compression_level() -> i32 {
+    zstd::DEFAULT_COMPRESSION_LEVEL
+}
+
+fn default_detect_language() -> bool {
+    true
+}
+
+fn default_max_memory_cache_size() -> usize {
+    1_000_000
+}
+
+fn default_max_cluster_contexts() -> usize {
+    5_000
+}
+
+fn default_eviction_history_size() -> usize {
+    1_000
+}
+
+fn default_index_stats_sample_fraction() -> f64 {
+    0.1
+}
+
+fn default_max_ttl_hours() -> u64 {
+    // Ten years
+    87_600
 }
 
 impl Default for StorageConfig {
@@ -43,6 +164,20 @@ impl Default for StorageConfig {
             auto_cleanup: true,
             cleanup_interval_secs: 3600,
             enable_persistence: true,
+            max_memory_cache_size: default_max_memory_cache_size(),
+            max_ttl_hours: default_max_ttl_hours(),
+            index_stats_sample_fraction: default_index_stats_sample_fraction(),
+            screening_strictness: TransitionStrictness::default(),
+            max_cluster_contexts: default_max_cluster_contexts(),
+            eviction_history_size: default_eviction_history_size(),
+            auto_importance: false,
+            detect_language: true,
+            snapshot: None,
+            flush_policy: FlushPolicy::default(),
+            record_format: RecordFormat::default(),
+            max_cache_bytes: None,
+            enable_compression: false,
+            compression_level: default_compression_level(),
         }
     }
 }
@@ -56,9 +191,30 @@ impl StorageConfig {
             auto_cleanup: true,
             cleanup_interval_secs: 3600,
             enable_persistence: false,
+            max_memory_cache_size: default_max_memory_cache_size(),
+            max_ttl_hours: default_max_ttl_hours(),
+            index_stats_sample_fraction: default_index_stats_sample_fraction(),
+            screening_strictness: TransitionStrictness::default(),
+            max_cluster_contexts: default_max_cluster_contexts(),
+            eviction_history_size: default_eviction_history_size(),
+            auto_importance: false,
+            detect_language: true,
+            snapshot: None,
+            flush_policy: FlushPolicy::default(),
+            record_format: RecordFormat::default(),
+            max_cache_bytes: None,
+            enable_compression: false,
+            compression_level: default_compression_level(),
         }
     }
 
+    /// Directory sled is opened in, falling back to `./data/context_store`
+    pub fn persist_dir(&self) -> PathBuf {
+        self.persist_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("./data/context_store"))
+    }
+
     /// Create config with disk persistence
     pub fn with_persistence(cache_size: usize, path: impl Into<PathBuf>) -> Self {
         Self {
@@ -67,21 +223,201 @@ impl StorageConfig {
             auto_cleanup: true,
             cleanup_interval_secs: 3600,
             enable_persistence: true,
+            max_memory_cache_size: default_max_memory_cache_size(),
+            max_ttl_hours: default_max_ttl_hours(),
+            index_stats_sample_fraction: default_index_stats_sample_fraction(),
+            screening_strictness: TransitionStrictness::default(),
+            max_cluster_contexts: default_max_cluster_contexts(),
+            eviction_history_size: default_eviction_history_size(),
+            auto_importance: false,
+            detect_language: true,
+            snapshot: None,
+            flush_policy: FlushPolicy::default(),
+            record_format: RecordFormat::default(),
+            max_cache_bytes: None,
+            enable_compression: false,
+            compression_level: default_compression_level(),
         }
     }
+
+    /// Start a builder from the defaults
+    pub fn builder() -> StorageConfigBuilder {
+        StorageConfigBuilder::default()
+    }
+}
+
+/// Builder for [`StorageConfig`]; unset fields keep their defaults
+#[derive(Debug, Clone, Default)]
+pub struct StorageConfigBuilder {
+    config: StorageConfig,
+}
+
+impl StorageConfigBuilder {
+    /// Maximum items in the memory cache
+    pub fn memory_cache_size(mut self, size: usize) -> Self {
+        self.config.memory_cache_size = size;
+        self
+    }
+
+    /// Persist to `path`, enabling persistence
+    pub fn persist_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.persist_path = Some(path.into());
+        self.config.enable_persistence = true;
+        self
+    }
+
+    /// Keep contexts in memory only
+    pub fn memory_only(mut self) -> Self {
+        self.config.persist_path = None;
+        self.config.enable_persistence = false;
+        self
+    }
+
+    /// Whether expired contexts are cleaned up automatically
+    pub fn auto_cleanup(mut self, enabled: bool) -> Self {
+        self.config.auto_cleanup = enabled;
+        self
+    }
+
+    /// Seconds between automatic cleanups
+    pub fn cleanup_interval_secs(mut self, secs: u64) -> Self {
+        self.config.cleanup_interval_secs = secs;
+        self
+    }
+
+    /// Upper bound accepted for the cache size
+    pub fn max_memory_cache_size(mut self, size: usize) -> Self {
+        self.config.max_memory_cache_size = size;
+        self
+    }
+
+    /// Longest TTL, in hours, accepted from tool callers
+    pub fn max_ttl_hours(mut self, hours: u64) -> Self {
+        self.config.max_ttl_hours = hours;
+        self
+    }
+
+    /// Screening status changes allowed
+    pub fn screening_strictness(mut self, strictness: TransitionStrictness) -> Self {
+        self.config.screening_strictness = strictness;
+        self
+    }
+
+    /// Cache evictions remembered
+    pub fn eviction_history_size(mut self, size: usize) -> Self {
+        self.config.eviction_history_size = size;
+        self
+    }
+
+    /// Suggest importances for tool-stored contexts that lack one
+    pub fn auto_importance(mut self, enabled: bool) -> Self {
+        self.config.auto_importance = enabled;
+        self
+    }
+
+    /// Whether stored prose is tagged with its detected language
+    pub fn detect_language(mut self, enabled: bool) -> Self {
+        self.config.detect_language = enabled;
+        self
+    }
+
+    /// When writes are flushed to disk
+    pub fn flush_policy(mut self, policy: FlushPolicy) -> Self {
+        self.config.flush_policy = policy;
+        self
+    }
+
+    /// Evict cached contexts to keep their estimated size under `bytes`
+    pub fn max_cache_bytes(mut self, bytes: usize) -> Self {
+        self.config.max_cache_bytes = Some(bytes);
+        self
+    }
+
+    /// Write new records in `format`
+    pub fn record_format(mut self, format: RecordFormat) -> Self {
+        self.config.record_format = format;
+        self
+    }
+
+    /// Compress new records with zstd at `level`
+    pub fn compression(mut self, level: i32) -> Self {
+        self.config.enable_compression = true;
+        self.config.compression_level = level;
+        self
+    }
+
+    /// Take scheduled snapshots under `policy`
+    pub fn snapshot(mut self, policy: SnapshotPolicy) -> Self {
+        self.config.snapshot = Some(policy);
+        self
+    }
+
+    /// Finish the configuration
+    pub fn build(self) -> StorageConfig {
+        self.config
+    }
 }
 
+/// Evicted context IDs and when each left the cache, oldest first
+type EvictionHistory = VecDeque<(ContextId, DateTime<Utc>)>;
+
+/// Importance thresholds and the channels to notify when a context drops below one
+type ImportanceWatchers = Vec<(f32, mpsc::Sender<ContextId>)>;
+
 /// Multi-tier context storage
 pub struct ContextStore {
     /// In-memory LRU cache
-    memory_cache: Arc<RwLock<LruCache<ContextId, Context>>>,
+    memory_cache: Arc<RwLock<MemoryCache>>,
     /// Persistent storage (sled)
     #[cfg(feature = "persistence")]
     disk_store: Option<sled::Db>,
     /// Domain index for fast filtering
-    domain_index: Arc<RwLock<HashMap<ContextDomain, Vec<ContextId>>>>,
+    domain_index: Arc<RwLock<HashMap<ContextDomain, HashSet<ContextId>>>>,
     /// Tag index for fast filtering
-    tag_index: Arc<RwLock<HashMap<String, Vec<ContextId>>>>,
+    tag_index: Arc<RwLock<HashMap<String, HashSet<ContextId>>>>,
+    /// Source index for fast filtering
+    source_index: Arc<RwLock<HashMap<String, HashSet<ContextId>>>>,
+    /// Importance index for top-N lookups
+    importance_index: Arc<RwLock<BTreeMap<ImportanceKey, Vec<ContextId>>>>,
+    /// Thread index for conversation lookups
+    thread_index: Arc<RwLock<HashMap<String, Vec<ContextId>>>>,
+    /// Last-access index, oldest first, for staleness lookups
+    access_index: Arc<RwLock<BTreeMap<DateTime<Utc>, Vec<ContextId>>>>,
+    /// Creation-time index, oldest first, for timeline pagination
+    created_index: Arc<RwLock<BTreeMap<DateTime<Utc>, Vec<ContextId>>>>,
+    /// Expiry index, soonest first, for cleanup; mirrored in sled for persisted stores
+    expiry_index: Arc<RwLock<BTreeMap<DateTime<Utc>, Vec<ContextId>>>>,
+    /// Content hash (SHA-256 hex) index for deduplication lookups
+    content_hash_index: Arc<RwLock<HashMap<String, ContextId>>>,
+    /// Operation counters behind the stats tools
+    counters: Arc<CountingObserver>,
+    /// Embedder's operation callbacks
+    observer: Option<Arc<dyn StoreObserver>>,
+    /// IDs of merged-away contexts and the context they were merged into
+    redirects: Arc<RwLock<HashMap<ContextId, ContextId>>>,
+    /// Store event sequencing, plus the event log when persistence is off
+    audit: Arc<Mutex<AuditLog>>,
+    /// Live store events
+    events: broadcast::Sender<StoreEvent>,
+    /// Most recent cache evictions, oldest first
+    eviction_history: Arc<Mutex<EvictionHistory>>,
+    /// Importance thresholds and the channels notified when a context drops below one
+    importance_watchers: Arc<Mutex<ImportanceWatchers>>,
+    /// Scanner consulted when new content is stored
+    screening_hook: Option<Arc<dyn ScreeningHook>>,
+    /// Outcome of the most recent scheduled snapshots
+    snapshot_status: Arc<Mutex<SnapshotStatus>>,
+    /// Held across each read-modify-write of [`ContextStore::update`]
+    update_lock: tokio::sync::Mutex<()>,
+    /// Errors the next disk writes fail with, before touching sled
+    #[cfg(all(test, feature = "persistence"))]
+    injected_faults: Arc<Mutex<VecDeque<sled::Error>>>,
+    /// Expiry index candidates examined by [`ContextStore::cleanup_expired`]
+    #[cfg(test)]
+    cleanup_checked: std::sync::atomic::AtomicUsize,
+    /// Whether the in-memory indexes cover every persisted context; see
+    /// [`ContextStore::load_indexes`]
+    indexes_complete: std::sync::atomic::AtomicBool,
     /// Configuration
     config: StorageConfig,
 }
@@ -89,30 +425,92 @@ pub struct ContextStore {
 impl ContextStore {
     /// Create a new context store
     pub fn new(config: StorageConfig) -> Result<Self> {
-        let memory_cache = Arc::new(RwLock::new(LruCache::new(
+        if config.memory_cache_size > config.max_memory_cache_size {
+            return Err(ContextError::Config(format!(
+                "Cache size {} exceeds maximum {}",
+                config.memory_cache_size, config.max_memory_cache_size
+            )));
+        }
+
+        if let Some(ref policy) = config.snapshot {
+            if policy.interval.is_zero() || policy.keep_last == 0 {
+                return Err(ContextError::Config(
+                    "Snapshot interval and keep_last must be > 0".into(),
+                ));
+            }
+            if !config.enable_persistence {
+                return Err(ContextError::Config(
+                    "Snapshots require disk persistence".into(),
+                ));
+            }
+        }
+
+        if config.flush_policy == FlushPolicy::Interval(std::time::Duration::ZERO) {
+            return Err(ContextError::Config("Flush interval must be > 0".into()));
+        }
+
+        if config.auto_cleanup && config.cleanup_interval_secs == 0 {
+            return Err(ContextError::Config("Cleanup interval must be > 0".into()));
+        }
+
+        let levels = zstd::compression_level_range();
+        if config.enable_compression && !levels.contains(&config.compression_level) {
+            return Err(ContextError::Config(format!(
+                "Compression level {} is outside {}..={}",
+                config.compression_level,
+                levels.start(),
+                levels.end()
+            )));
+        }
+
+        if config.max_cache_bytes == Some(0) {
+            return Err(ContextError::Config("Cache byte budget must be > 0".into()));
+        }
+        let memory_cache = Arc::new(RwLock::new(MemoryCache::new(
             std::num::NonZeroUsize::new(config.memory_cache_size)
                 .ok_or_else(|| ContextError::Config("Cache size must be > 0".into()))?,
+            config.max_cache_bytes,
         )));
 
         #[cfg(feature = "persistence")]
         let disk_store = if config.enable_persistence {
-            let path = config
-                .persist_path
-                .clone()
-                .unwrap_or_else(|| PathBuf::from("./data/context_store"));
+            let path = config.persist_dir();
 
             // Ensure directory exists
             if let Some(parent) = path.parent() {
-                std::fs::create_dir_all(parent)?;
+                std::fs::create_dir_all(parent).with_operation("open", None)?;
             }
 
-            Some(sled::open(&path)?)
+            Some(sled::open(&path).with_operation("open", None)?)
         } else {
             None
         };
 
+        #[cfg(feature = "persistence")]
+        let redirects = match disk_store {
+            Some(ref db) => load_redirects(db).with_operation("open", None)?,
+            None => HashMap::new(),
+        };
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = disk_store {
+            backfill_time_trees(db).with_operation("open", None)?;
+        }
+        #[cfg(not(feature = "persistence"))]
+        let redirects = HashMap::new();
+
+        #[cfg(feature = "persistence")]
+        let next_seq = match disk_store {
+            Some(ref db) => last_audit_seq(db).with_operation("open", None)? + 1,
+            None => 1,
+        };
+        #[cfg(not(feature = "persistence"))]
+        let next_seq = 1;
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+
+        #[cfg(feature = "persistence")]
+        let indexes_complete = disk_store.as_ref().map_or(true, |db| db.is_empty());
         #[cfg(not(feature = "persistence"))]
-        let _disk_store = ();
+        let indexes_complete = true;
 
         Ok(Self {
             memory_cache,
@@ -120,389 +518,7727 @@ impl ContextStore {
             disk_store,
             domain_index: Arc::new(RwLock::new(HashMap::new())),
             tag_index: Arc::new(RwLock::new(HashMap::new())),
+            source_index: Arc::new(RwLock::new(HashMap::new())),
+            importance_index: Arc::new(RwLock::new(BTreeMap::new())),
+            thread_index: Arc::new(RwLock::new(HashMap::new())),
+            access_index: Arc::new(RwLock::new(BTreeMap::new())),
+            created_index: Arc::new(RwLock::new(BTreeMap::new())),
+            expiry_index: Arc::new(RwLock::new(BTreeMap::new())),
+            content_hash_index: Arc::new(RwLock::new(HashMap::new())),
+            counters: Arc::new(CountingObserver::new()),
+            observer: None,
+            redirects: Arc::new(RwLock::new(redirects)),
+            audit: Arc::new(Mutex::new(AuditLog {
+                next_seq,
+                memory: VecDeque::new(),
+            })),
+            events,
+            eviction_history: Arc::new(Mutex::new(VecDeque::new())),
+            importance_watchers: Arc::new(Mutex::new(Vec::new())),
+            screening_hook: None,
+            snapshot_status: Arc::new(Mutex::new(SnapshotStatus::default())),
+            update_lock: tokio::sync::Mutex::new(()),
+            #[cfg(all(test, feature = "persistence"))]
+            injected_faults: Arc::new(Mutex::new(VecDeque::new())),
+            #[cfg(test)]
+            cleanup_checked: std::sync::atomic::AtomicUsize::new(0),
+            indexes_complete: std::sync::atomic::AtomicBool::new(indexes_complete),
             config,
         })
     }
 
-    /// Store a context entry
-    pub async fn store(&self, context: Context) -> Result<ContextId> {
-        let id = context.id.clone();
-
-        // Update indices
-        {
-            let mut domain_idx = self.domain_index.write().await;
-            domain_idx
-                .entry(context.domain.clone())
-                .or_default()
-                .push(id.clone());
-        }
+    /// Create a store and index its persisted contexts before returning it
+    ///
+    /// Equivalent to [`Self::new`] followed by [`Self::load_indexes`].
+    pub async fn open(config: StorageConfig) -> Result<Self> {
+        let store = Self::new(config)?;
+        store.load_indexes().await?;
+        Ok(store)
+    }
 
-        {
-            let mut tag_idx = self.tag_index.write().await;
-            for tag in &context.metadata.tags {
-                tag_idx.entry(tag.clone()).or_default().push(id.clone());
+    /// Add every persisted context to the in-memory indexes
+    ///
+    /// The indexes are not persisted, so until this has run, queries that
+    /// filter by domain, tag or source also scan sled, and lookups answered
+    /// from an index alone, such as [`Self::get_thread`], run it first. Records that fail to
+    /// decode are skipped and logged rather than failing the load, and their
+    /// number is returned. Contexts already indexed, such as those stored
+    /// since the store was opened, are left as they are. Contexts deleted
+    /// meanwhile may leave extra index entries, which queries drop when the
+    /// lookup misses.
+    pub async fn load_indexes(&self) -> Result<usize> {
+        let skipped = 0;
+        #[cfg(feature = "persistence")]
+        let mut skipped = skipped;
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            for (i, entry) in db.iter().enumerate() {
+                let (key, value) = entry.with_operation("load_indexes", None)?;
+                match decode_context(&value) {
+                    Ok(ctx) => {
+                        let indexed = self
+                            .domain_index
+                            .read()
+                            .await
+                            .get(&ctx.domain)
+                            .is_some_and(|ids| ids.contains(&ctx.id));
+                        if !indexed {
+                            self.index_in_memory(&ctx).await;
+                        }
+                    }
+                    Err(e) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(
+                            id = %String::from_utf8_lossy(&key),
+                            error = %e,
+                            "skipping undecodable record while indexing"
+                        );
+                        let _ = (key, e);
+                        skipped += 1;
+                    }
+                }
+                // Large stores are indexed without starving other tasks
+                if (i + 1) % MIGRATION_CHUNK_SIZE == 0 {
+                    tokio::task::yield_now().await;
+                }
             }
         }
+        self.indexes_complete
+            .store(true, std::sync::atomic::Ordering::Release);
+        Ok(skipped)
+    }
 
-        // Store in memory cache
+    /// Run [`Self::load_indexes`] unless the indexes are already complete
+    ///
+    /// For lookups answered from the indexes alone, with no sled fallback.
+    async fn ensure_indexes_loaded(&self) -> Result<()> {
+        if !self
+            .indexes_complete
+            .load(std::sync::atomic::Ordering::Acquire)
         {
-            let mut cache = self.memory_cache.write().await;
-            cache.put(id.clone(), context.clone());
+            self.load_indexes().await?;
         }
+        Ok(())
+    }
 
-        // Persist to disk if enabled
-        #[cfg(feature = "persistence")]
-        if let Some(ref db) = self.disk_store {
-            let serialized = serde_json::to_vec(&context)?;
-            db.insert(id.as_str().as_bytes(), serialized)?;
-            db.flush_async().await?;
-        }
+    /// Screen new content with `hook` before it is stored
+    ///
+    /// Contexts that are new, or whose content changed, are set to `Pending`
+    /// and stored with the status the hook returns. A verdict the configured
+    /// strictness does not allow from `Pending` fails the store.
+    pub fn with_screening_hook(mut self, hook: Arc<dyn ScreeningHook>) -> Self {
+        self.screening_hook = Some(hook);
+        self
+    }
 
-        Ok(id)
+    /// Report operations to `observer`
+    ///
+    /// Callbacks run synchronously on the calling task; see
+    /// [`crate::observer`] for what they may do.
+    pub fn with_observer(mut self, observer: Arc<dyn StoreObserver>) -> Self {
+        self.observer = Some(observer);
+        self
     }
 
-    /// Retrieve a context by ID
-    pub async fn get(&self, id: &ContextId) -> Result<Option<Context>> {
-        // Check memory cache first
-        {
-            let mut cache = self.memory_cache.write().await;
-            if let Some(ctx) = cache.get_mut(id) {
-                ctx.mark_accessed();
-                return Ok(Some(ctx.clone()));
-            }
+    /// Built-in counters, fed alongside any registered observer
+    pub fn counters(&self) -> &CountingObserver {
+        &self.counters
+    }
+
+    /// Pass an operation to the built-in counters and the registered observer
+    pub(crate) fn observe(&self, callback: impl Fn(&dyn StoreObserver)) {
+        callback(self.counters.as_ref());
+        if let Some(ref observer) = self.observer {
+            callback(observer.as_ref());
         }
+    }
 
-        // Check disk storage
+    /// Configuration the store was created with
+    pub fn config(&self) -> &StorageConfig {
+        &self.config
+    }
+
+    /// Flush every persisted write to disk
+    ///
+    /// Does nothing without persistence. Under [`FlushPolicy::Interval`] and
+    /// [`FlushPolicy::Never`] this is how a caller makes its writes durable.
+    pub async fn flush(&self) -> Result<()> {
         #[cfg(feature = "persistence")]
         if let Some(ref db) = self.disk_store {
-            if let Some(data) = db.get(id.as_str().as_bytes())? {
-                let mut context: Context = serde_json::from_slice(&data)?;
-                context.mark_accessed();
-
-                // Promote to memory cache
-                let mut cache = self.memory_cache.write().await;
-                cache.put(id.clone(), context.clone());
-
-                return Ok(Some(context));
-            }
+            db.flush_async().await.with_operation("flush", None)?;
         }
-
-        Ok(None)
+        Ok(())
     }
 
-    /// Delete a context by ID
-    pub async fn delete(&self, id: &ContextId) -> Result<bool> {
-        let mut found = false;
+    /// Store a context entry
+    ///
+    /// Replaces any context already stored under the same ID; the outcome
+    /// carries the replaced version.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(id = %context.id, domain = %context.domain, replaced = tracing::field::Empty)
+        )
+    )]
+    pub async fn store(&self, mut context: Context) -> Result<StoreOutcome> {
+        let id = context.id.clone();
 
-        // First, get the context to extract domain and tags before deletion
-        let context_data = self.get(id).await?;
+        // Re-storing an existing ID must not leave index entries for the old version
+        let previous = self.peek(&id).await?;
+        telemetry::record("replaced", previous.is_some());
 
-        // Remove from memory cache
-        {
-            let mut cache = self.memory_cache.write().await;
-            if cache.pop(id).is_some() {
-                found = true;
+        if let Some(ref hook) = self.screening_hook {
+            let content_changed = previous
+                .as_ref()
+                .map_or(true, |previous| previous.content != context.content);
+            if content_changed {
+                self.screen(hook.as_ref(), &mut context).await?;
             }
         }
+        if self.config.detect_language {
+            detect_language(&mut context);
+        }
+        if let Some(ref previous) = previous {
+            record_revision(previous, &mut context);
+            self.unindex(previous).await;
+        }
+        self.index(&context).await;
+
+        // Store in memory cache
+        self.cache_insert(id.clone(), context.clone()).await?;
 
-        // Remove from disk
+        // Persist to disk if enabled
         #[cfg(feature = "persistence")]
         if let Some(ref db) = self.disk_store {
-            if db.remove(id.as_str().as_bytes())?.is_some() {
-                found = true;
+            let key = id.as_str().as_bytes();
+            let serialized =
+                encode_context(&context, &self.config).with_operation("store", Some(&id))?;
+            let flush = self.config.flush_policy == FlushPolicy::EveryWrite;
+            let persisted = self
+                .retry_disk(|| {
+                    let value = serialized.clone();
+                    async move {
+                        db.insert(key, value)?;
+                        if flush {
+                            db.flush_async().await?;
+                        }
+                        Ok(())
+                    }
+                })
+                .await;
+            if let Err(e) = persisted {
+                self.rollback_store(db, &context, previous.as_ref()).await;
+                return Err(e.with_operation("store", Some(&id)));
             }
         }
 
-        // Clean up indices if context was found
-        if let Some(ctx) = context_data {
-            // Remove from domain index
-            {
-                let mut domain_idx = self.domain_index.write().await;
-                if let Some(ids) = domain_idx.get_mut(&ctx.domain) {
-                    ids.retain(|stored_id| stored_id != id);
-                    // Remove empty domain entries to prevent unbounded growth
-                    if ids.is_empty() {
-                        domain_idx.remove(&ctx.domain);
-                    }
-                }
-            }
+        self.record_event(StoreEventKind::Stored, &id)?;
+        if let Some(ref previous) = previous {
+            self.notify_importance_watchers(
+                &id,
+                previous.metadata.importance,
+                context.metadata.importance,
+            );
+        }
+        self.observe(|observer| observer.on_store(&context));
+        Ok(StoreOutcome { id, previous })
+    }
 
-            // Remove from tag index
-            {
-                let mut tag_idx = self.tag_index.write().await;
-                for tag in &ctx.metadata.tags {
-                    if let Some(ids) = tag_idx.get_mut(tag) {
-                        ids.retain(|stored_id| stored_id != id);
-                        // Remove empty tag entries to prevent unbounded growth
-                        if ids.is_empty() {
-                            tag_idx.remove(tag);
-                        }
-                    }
+    /// Undo the memory and index changes of a store whose disk write failed
+    ///
+    /// The disk is restored on a best-effort basis, since the write may have
+    /// landed before the flush failed.
+    #[cfg(feature = "persistence")]
+    async fn rollback_store(&self, db: &sled::Db, context: &Context, previous: Option<&Context>) {
+        self.unindex(context).await;
+        let key = context.id.as_str().as_bytes();
+        match previous {
+            Some(previous) => {
+                self.index(previous).await;
+                self.memory_cache
+                    .write()
+                    .await
+                    .push(previous.id.clone(), previous.clone());
+                if let Ok(value) = encode_context(previous, &self.config) {
+                    let _ = db.insert(key, value);
                 }
             }
+            None => {
+                self.memory_cache.write().await.pop(&context.id);
+                let _ = db.remove(key);
+            }
         }
-
-        Ok(found)
     }
 
-    /// Query contexts based on criteria
-    pub async fn query(&self, query: &ContextQuery) -> Result<Vec<Context>> {
-        let mut results = Vec::new();
-
-        // Get candidate IDs from indices
-        let candidate_ids = self.get_candidate_ids(query).await;
-
-        // Fetch and filter contexts
-        for id in candidate_ids {
-            if let Some(ctx) = self.get(&id).await? {
-                if self.matches_query(&ctx, query) {
-                    results.push(ctx);
-                }
+    /// Run a disk operation, retrying transient sled errors with exponential backoff
+    ///
+    /// Gives up after [`DISK_WRITE_ATTEMPTS`] attempts, returning the last
+    /// error; fatal errors are returned immediately.
+    #[cfg(feature = "persistence")]
+    async fn retry_disk<T, F, Fut>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = sled::Result<T>>,
+    {
+        let mut backoff = DISK_RETRY_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            #[cfg(test)]
+            let injected = self
+                .injected_faults
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .pop_front();
+            #[cfg(test)]
+            let outcome = match injected {
+                Some(fault) => Err(fault),
+                None => op().await,
+            };
+            #[cfg(not(test))]
+            let outcome = op().await;
 
-                if results.len() >= query.limit {
-                    break;
+            match outcome.map_err(ContextError::from) {
+                Err(e) if e.is_transient() && attempt < DISK_WRITE_ATTEMPTS => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(attempt, error = %e, "retrying disk write");
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
                 }
+                outcome => return outcome,
             }
         }
+    }
 
-        // Sort by importance and recency
-        results.sort_by(|a, b| {
-            let importance_cmp = b
-                .metadata
-                .importance
-                .partial_cmp(&a.metadata.importance)
-                .unwrap_or(std::cmp::Ordering::Equal);
+    /// Run a screening hook on a context and apply its verdict
+    async fn screen(&self, hook: &dyn ScreeningHook, context: &mut Context) -> Result<()> {
+        context.metadata.screening_status = ScreeningStatus::Pending;
+        let verdict = hook.screen(context).await;
+        if !ScreeningStatus::Pending.can_transition_to(&verdict, self.config.screening_strictness) {
+            return Err(ContextError::ScreeningFailed(format!(
+                "hook returned {:?}, which is not allowed after Pending",
+                verdict
+            )));
+        }
+        context.metadata.screening_status = verdict;
+        Ok(())
+    }
 
-            if importance_cmp == std::cmp::Ordering::Equal {
-                b.accessed_at.cmp(&a.accessed_at)
-            } else {
-                importance_cmp
+    /// Change a context's screening status, returning the previous one
+    ///
+    /// Fails with [`ContextError::ScreeningFailed`] when the configured
+    /// [`StorageConfig::screening_strictness`] does not allow the change.
+    /// The previous status stays in the context's revision history.
+    pub async fn update_screening_status(
+        &self,
+        id: &ContextId,
+        status: ScreeningStatus,
+    ) -> Result<ScreeningStatus> {
+        let strictness = self.config.screening_strictness;
+        let mut previous = ScreeningStatus::default();
+        self.try_update(id, |ctx| {
+            if !ctx
+                .metadata
+                .screening_status
+                .can_transition_to(&status, strictness)
+            {
+                return Err(ContextError::ScreeningFailed(format!(
+                    "cannot change screening status from {:?} to {:?}",
+                    ctx.metadata.screening_status, status
+                )));
             }
-        });
-
-        results.truncate(query.limit);
-        Ok(results)
+            previous = std::mem::replace(&mut ctx.metadata.screening_status, status);
+            Ok(())
+        })
+        .await?;
+        Ok(previous)
     }
 
-    /// Retrieve relevant context for RAG
-    pub async fn retrieve_context(
+    /// Modify a stored context in one step
+    ///
+    /// Reads the context, applies `f` and stores the result. Updates hold a
+    /// store-wide lock from read to write, so concurrent updates to the same
+    /// context apply one after another instead of overwriting each other.
+    /// The ID and `created_at` are kept whatever `f` does, `updated_at` is
+    /// set to now, and the indexes move to the new domain and tags. Fails
+    /// with [`ContextError::NotFound`] if the context does not exist.
+    pub async fn update(
         &self,
-        query_text: &str,
-        limit: usize,
-        domain_filter: Option<&ContextDomain>,
-    ) -> Result<Vec<Context>> {
-        // Build query
-        let _ctx_query = ContextQuery::new().with_limit(limit);
+        id: &ContextId,
+        f: impl FnOnce(&mut Context),
+    ) -> Result<StoreOutcome> {
+        self.try_update(id, |ctx| {
+            f(ctx);
+            Ok(())
+        })
+        .await
+    }
 
-        if let Some(_domain) = domain_filter {
-            // ctx_query = ctx_query.with_domain(domain.clone());
-        }
+    /// Like [`Self::update`], storing nothing if `f` fails
+    async fn try_update(
+        &self,
+        id: &ContextId,
+        f: impl FnOnce(&mut Context) -> Result<()>,
+    ) -> Result<StoreOutcome> {
+        let _guard = self.update_lock.lock().await;
+        let mut ctx = self
+            .peek(id)
+            .await?
+            .ok_or_else(|| ContextError::NotFound(id.to_string()))?;
+        let created_at = ctx.created_at;
 
-        // For now, simple text matching
-        // TODO: Implement vector similarity when embeddings are available
-        let query_lower = query_text.to_lowercase();
-        let mut results = Vec::new();
+        f(&mut ctx)?;
+        ctx.id = id.clone();
+        ctx.created_at = created_at;
+        ctx.updated_at = Some(Utc::now());
+        self.store(ctx).await
+    }
 
-        let cache = self.memory_cache.read().await;
-        for (_, ctx) in cache.iter() {
-            if ctx.content.to_lowercase().contains(&query_lower) {
-                if let Some(domain) = domain_filter {
-                    if &ctx.domain != domain {
-                        continue;
-                    }
-                }
-                results.push(ctx.clone());
-                if results.len() >= limit {
-                    break;
-                }
-            }
-        }
+    /// Keep a context in memory, exempt from cache eviction
+    ///
+    /// Sets [`ContextMetadata::pinned`](crate::context::ContextMetadata::pinned),
+    /// so the pin is persisted: after a restart the context is pinned in
+    /// memory again the first time it is read. Pinned contexts still expire
+    /// at their own `expires_at`. Fails with [`ContextError::NotFound`] if
+    /// the context does not exist.
+    pub async fn pin(&self, id: &ContextId) -> Result<()> {
+        self.set_pinned(id, true).await
+    }
 
-        // Sort by importance
-        results.sort_by(|a, b| {
-            b.metadata
-                .importance
-                .partial_cmp(&a.metadata.importance)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+    /// Return a pinned context to the LRU cache
+    pub async fn unpin(&self, id: &ContextId) -> Result<()> {
+        self.set_pinned(id, false).await
+    }
 
-        Ok(results)
+    async fn set_pinned(&self, id: &ContextId, pinned: bool) -> Result<()> {
+        let _guard = self.update_lock.lock().await;
+        let mut ctx = self
+            .peek(id)
+            .await?
+            .ok_or_else(|| ContextError::NotFound(id.to_string()))?;
+        if ctx.metadata.pinned != pinned {
+            ctx.metadata.pinned = pinned;
+            self.store(ctx).await?;
+        }
+        Ok(())
     }
 
-    /// Get candidate IDs from indices based on query filters
-    async fn get_candidate_ids(&self, query: &ContextQuery) -> Vec<ContextId> {
-        let mut candidates = Vec::new();
+    /// Bring a context stored before versioning up to version 1
+    ///
+    /// Contexts that are already versioned are left untouched.
+    pub async fn convert_to_versioned(&self, id: &ContextId) -> Result<()> {
+        let ctx = self
+            .peek(id)
+            .await?
+            .ok_or_else(|| ContextError::NotFound(id.to_string()))?;
+        if ctx.version == 0 {
+            self.store(ctx).await?;
+        }
+        Ok(())
+    }
 
-        // If domain filter specified, use domain index
-        if let Some(ref domain) = query.domain_filter {
-            let domain_idx = self.domain_index.read().await;
-            if let Some(ids) = domain_idx.get(domain) {
-                candidates.extend(ids.iter().cloned());
-            }
+    /// Restore a context's content from an earlier revision
+    ///
+    /// The rollback is itself a new revision: the current content goes into
+    /// the history and the version moves forward, so it can be undone the
+    /// same way. Fails with [`ContextError::NotFound`] if the context or the
+    /// revision does not exist.
+    pub async fn rollback_to_revision(&self, id: &ContextId, version: u32) -> Result<()> {
+        let mut ctx = self
+            .peek(id)
+            .await?
+            .ok_or_else(|| ContextError::NotFound(id.to_string()))?;
+        let revision = ctx
+            .history
+            .iter()
+            .find(|revision| revision.version == version)
+            .ok_or_else(|| ContextError::NotFound(format!("revision {} of {}", version, id)))?;
+        if revision.content != ctx.content {
+            ctx.content = revision.content.clone();
+            ctx.embedding = None;
         }
+        self.store(ctx).await?;
+        Ok(())
+    }
 
-        // If tag filter specified, use tag index
-        if let Some(ref tags) = query.tag_filter {
-            let tag_idx = self.tag_index.read().await;
-            for tag in tags {
-                if let Some(ids) = tag_idx.get(tag) {
-                    candidates.extend(ids.iter().cloned());
-                }
+    /// Bring every context stored before versioning up to version 1
+    ///
+    /// Returns the number of contexts converted; running it again converts none.
+    pub async fn migrate_all_to_versioned(&self) -> Result<usize> {
+        let mut converted = 0;
+        for ctx in self.all_contexts().await? {
+            if ctx.version == 0 {
+                self.store(ctx).await?;
+                converted += 1;
             }
         }
+        Ok(converted)
+    }
 
-        // If no filters, get all from cache
-        if candidates.is_empty() && query.domain_filter.is_none() && query.tag_filter.is_none() {
-            let cache = self.memory_cache.read().await;
-            candidates = cache.iter().map(|(id, _)| id.clone()).collect();
+    /// Store a context only if its ID is not already taken
+    ///
+    /// When a context exists, nothing is written and the outcome carries the
+    /// existing version. The check and the write are not atomic, so concurrent
+    /// stores of the same ID can still replace each other.
+    pub async fn store_if_absent(&self, context: Context) -> Result<StoreOutcome> {
+        if let Some(existing) = self.peek(&context.id).await? {
+            return Ok(StoreOutcome {
+                id: context.id,
+                previous: Some(existing),
+            });
         }
+        self.store(context).await
+    }
 
-        // Deduplicate
-        candidates.sort();
-        candidates.dedup();
+    /// Retrieve a context by ID
+    pub async fn get(&self, id: &ContextId) -> Result<Option<Context>> {
+        self.get_as(id, None).await
+    }
 
-        candidates
+    /// Retrieve a context by ID, failing with [`ContextError::NotFound`] if it is absent
+    pub async fn get_required(&self, id: &ContextId) -> Result<Context> {
+        self.get_required_as(id, None).await
     }
 
-    /// Check if a context matches the query criteria
-    fn matches_query(&self, ctx: &Context, query: &ContextQuery) -> bool {
-        // Check expiration
-        if ctx.is_expired() {
-            return false;
-        }
+    /// Like [`Self::get_required`], recording `accessor` as the last accessor
+    pub async fn get_required_as(&self, id: &ContextId, accessor: Option<&str>) -> Result<Context> {
+        self.get_as(id, accessor)
+            .await?
+            .ok_or_else(|| ContextError::NotFound(id.to_string()))
+    }
 
-        // Check domain
-        if let Some(ref domain) = query.domain_filter {
-            if &ctx.domain != domain {
-                return false;
-            }
-        }
+    /// Retrieve a context by ID, recording `accessor` as its last accessor
+    ///
+    /// IDs of contexts merged away by [`Self::merge`] resolve to the context
+    /// they were merged into.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "get",
+            level = "debug",
+            skip_all,
+            fields(id = %id, cache_hit = tracing::field::Empty, found = tracing::field::Empty)
+        )
+    )]
+    pub async fn get_as(&self, id: &ContextId, accessor: Option<&str>) -> Result<Option<Context>> {
+        let found = match self.get_direct(id, accessor).await? {
+            Some(found) => Some(found),
+            None => match self.resolve_redirect(id).await {
+                Some(target) => self.get_direct(&target, accessor).await?,
+                None => None,
+            },
+        };
+        telemetry::record("found", found.is_some());
+        let hit = found.as_ref().is_some_and(|(_, hit)| *hit);
+        self.observe(|observer| observer.on_get(hit));
+        Ok(found.map(|(ctx, _)| ctx))
+    }
 
-        // Check source
-        if let Some(ref source) = query.source_filter {
-            if &ctx.metadata.source != source {
-                return false;
+    /// Follow merge redirects from `id` to the context that absorbed it
+    async fn resolve_redirect(&self, id: &ContextId) -> Option<ContextId> {
+        let redirects = self.redirects.read().await;
+        let mut current = redirects.get(id)?;
+        // Bounded so a corrupted cycle cannot hang the lookup
+        for _ in 0..redirects.len() {
+            match redirects.get(current) {
+                Some(next) => current = next,
+                None => break,
             }
         }
+        Some(current.clone())
+    }
 
-        // Check importance
-        if let Some(min_importance) = query.min_importance {
-            if ctx.metadata.importance < min_importance {
-                return false;
+    /// Look up a context by its own ID, marking it accessed
+    ///
+    /// The flag is whether the memory cache served it.
+    async fn get_direct(
+        &self,
+        id: &ContextId,
+        accessor: Option<&str>,
+    ) -> Result<Option<(Context, bool)>> {
+        // Check memory cache first
+        let cached = {
+            let mut cache = self.memory_cache.write().await;
+            cache.get_mut(id).map(|ctx| {
+                let previous = ctx.accessed_at;
+                ctx.mark_accessed_by(accessor);
+                (previous, ctx.clone())
+            })
+        };
+        telemetry::record("cache_hit", cached.is_some());
+        if let Some((previous, ctx)) = cached {
+            self.move_access(id, previous, ctx.accessed_at).await;
+            return Ok(Some((ctx, true)));
+        }
+
+        // Check disk storage
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            if let Some(data) = db
+                .get(id.as_str().as_bytes())
+                .with_operation("get", Some(id))?
+            {
+                let mut context = decode_context(&data).with_operation("get", Some(id))?;
+                let previous = context.accessed_at;
+                context.mark_accessed_by(accessor);
+
+                // Promote to memory cache
+                self.cache_insert(id.clone(), context.clone()).await?;
+                self.move_access(id, previous, context.accessed_at).await;
+
+                return Ok(Some((context, false)));
             }
         }
 
-        // Check age
-        if let Some(max_age) = query.max_age_seconds {
-            if ctx.age_seconds() > max_age {
-                return false;
+        Ok(None)
+    }
+
+    /// Insert into the memory cache, writing back the entry it evicts
+    ///
+    /// Access statistics are only updated in the cache, so a context pushed
+    /// out of the LRU is written to disk to keep them.
+    async fn cache_insert(&self, id: ContextId, context: Context) -> Result<()> {
+        let evicted = self.memory_cache.write().await.push(id, context);
+        for (evicted_id, _) in &evicted {
+            self.record_eviction(evicted_id);
+        }
+
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            for (evicted_id, evicted) in evicted {
+                let key = evicted_id.as_str().as_bytes();
+                let write_back = || -> Result<()> {
+                    if db.contains_key(key)? {
+                        db.insert(key, encode_context(&evicted, &self.config)?)?;
+                    }
+                    Ok(())
+                };
+                write_back().with_operation("evict", Some(&evicted_id))?;
             }
         }
+        #[cfg(not(feature = "persistence"))]
+        drop(evicted);
 
-        // Check verified status
-        if query.verified_only && !ctx.metadata.verified {
-            return false;
+        Ok(())
+    }
+
+    /// Remember that `id` was evicted from the memory cache
+    fn record_eviction(&self, id: &ContextId) {
+        let limit = self.config.eviction_history_size;
+        if limit == 0 {
+            return;
+        }
+        let mut history = self
+            .eviction_history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        while history.len() >= limit {
+            history.pop_front();
         }
+        history.push_back((id.clone(), Utc::now()));
+    }
 
-        // Check text query (simple contains for now)
-        if let Some(ref text) = query.query {
-            if !ctx.content.to_lowercase().contains(&text.to_lowercase()) {
-                return false;
+    /// The last `n` contexts evicted from the memory cache, oldest first
+    ///
+    /// Tells a cache miss caused by eviction apart from one for a context
+    /// that was never stored. With persistence an evicted context is still
+    /// on disk; without it, it is gone. Only the most recent
+    /// [`StorageConfig::eviction_history_size`] evictions are kept.
+    pub fn get_eviction_history(&self, n: usize) -> Vec<(ContextId, DateTime<Utc>)> {
+        let history = self
+            .eviction_history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        history
+            .iter()
+            .skip(history.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    }
+
+    /// Move a context to a new position in the access index
+    async fn move_access(&self, id: &ContextId, from: DateTime<Utc>, to: DateTime<Utc>) {
+        let mut access_idx = self.access_index.write().await;
+        if let Some(ids) = access_idx.get_mut(&from) {
+            ids.retain(|stored_id| stored_id != id);
+            if ids.is_empty() {
+                access_idx.remove(&from);
             }
         }
+        access_idx.entry(to).or_default().push(id.clone());
+    }
 
-        true
+    /// Look up a context without updating its access time or LRU position
+    async fn peek(&self, id: &ContextId) -> Result<Option<Context>> {
+        {
+            let cache = self.memory_cache.read().await;
+            if let Some(ctx) = cache.peek(id) {
+                return Ok(Some(ctx.clone()));
+            }
+        }
+
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            if let Some(data) = db
+                .get(id.as_str().as_bytes())
+                .with_operation("get", Some(id))?
+            {
+                return Ok(Some(decode_context(&data).with_operation("get", Some(id))?));
+            }
+        }
+
+        Ok(None)
     }
 
-    /// Get storage statistics
-    pub async fn stats(&self) -> StorageStats {
-        let cache = self.memory_cache.read().await;
-        let memory_count = cache.len();
+    /// Whether a context is stored under `id`
+    ///
+    /// Checks the memory cache, then key presence in sled, without decoding
+    /// or marking anything accessed. Expired contexts exist until cleaned up.
+    pub async fn exists(&self, id: &ContextId) -> Result<bool> {
+        if self.memory_cache.read().await.contains(id) {
+            return Ok(true);
+        }
 
         #[cfg(feature = "persistence")]
-        let disk_count = self.disk_store.as_ref().map(|db| db.len()).unwrap_or(0);
+        if let Some(ref db) = self.disk_store {
+            return db
+                .contains_key(id.as_str().as_bytes())
+                .with_operation("exists", Some(id));
+        }
 
-        #[cfg(not(feature = "persistence"))]
-        let disk_count = 0;
+        Ok(false)
+    }
 
-        StorageStats {
-            memory_count,
-            disk_count,
-            cache_capacity: self.config.memory_cache_size,
+    /// Find contexts sharing tags with a context, most shared tags first
+    ///
+    /// Candidates come from the tag index. Each is paired with the number of
+    /// tags it shares with the source, and only those sharing at least
+    /// `min_shared_tags` (and at least one) are returned. The source itself
+    /// and expired contexts are excluded; ties are ordered by importance.
+    pub async fn get_related_by_tags(
+        &self,
+        id: &ContextId,
+        min_shared_tags: usize,
+        limit: usize,
+    ) -> Result<Vec<(Context, usize)>> {
+        self.ensure_indexes_loaded().await?;
+        let source = self
+            .peek(id)
+            .await?
+            .ok_or_else(|| ContextError::NotFound(id.to_string()))?;
+
+        let mut shared: HashMap<ContextId, usize> = HashMap::new();
+        {
+            let tag_idx = self.tag_index.read().await;
+            let tags: HashSet<&String> = source.metadata.tags.iter().collect();
+            for tag in tags {
+                for other in tag_idx.get(tag).into_iter().flatten() {
+                    if other != id {
+                        *shared.entry(other.clone()).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        let mut related = Vec::new();
+        for (other, count) in shared {
+            if count < min_shared_tags.max(1) {
+                continue;
+            }
+            if let Some(ctx) = self.peek(&other).await? {
+                if !ctx.is_expired() {
+                    related.push((ctx, count));
+                }
+            }
         }
+
+        related.sort_by(|(a, a_count), (b, b_count)| {
+            b_count
+                .cmp(a_count)
+                .then_with(|| b.metadata.importance.total_cmp(&a.metadata.importance))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        related.truncate(limit);
+        Ok(related)
     }
 
-    /// Cleanup expired contexts
-    pub async fn cleanup_expired(&self) -> Result<usize> {
-        let mut removed = 0;
-        let now = Utc::now();
+    /// Add tags suggested by `tagger` to a context
+    ///
+    /// Suggestions already on the context are skipped. The context is
+    /// re-stored only when something was added. Returns the added tags.
+    pub async fn auto_tag(&self, id: &ContextId, tagger: &dyn AutoTagger) -> Result<Vec<String>> {
+        let mut ctx = self
+            .peek(id)
+            .await?
+            .ok_or_else(|| ContextError::NotFound(id.to_string()))?;
 
-        // Collect expired IDs
-        let expired_ids: Vec<ContextId> = {
-            let cache = self.memory_cache.read().await;
-            cache
-                .iter()
-                .filter(|(_, ctx)| ctx.expires_at.map(|exp| now > exp).unwrap_or(false))
-                .map(|(id, _)| id.clone())
-                .collect()
+        let mut added = Vec::new();
+        for tag in tagger.suggest_tags(&ctx.content, &ctx.metadata.tags) {
+            if !ctx.metadata.tags.contains(&tag) && !added.contains(&tag) {
+                added.push(tag);
+            }
+        }
+
+        if !added.is_empty() {
+            ctx.metadata.tags.extend(added.iter().cloned());
+            self.store(ctx).await?;
+        }
+        Ok(added)
+    }
+
+    /// Work out the result of merging `secondary` into `primary` without
+    /// writing anything
+    ///
+    /// Tags are unioned and the higher importance, earliest creation time and
+    /// latest access are kept. Access counts add up. Content follows
+    /// `strategy`. Thread, parent and structured data come from the primary,
+    /// falling back to the secondary's. The merged context keeps the
+    /// primary's ID.
+    pub async fn plan_merge(
+        &self,
+        primary: &ContextId,
+        secondary: &ContextId,
+        strategy: MergeStrategy,
+    ) -> Result<Context> {
+        if primary == secondary {
+            return Err(ContextError::InvalidQuery(
+                "cannot merge a context into itself".to_string(),
+            ));
+        }
+        let mut merged = self
+            .peek(primary)
+            .await?
+            .ok_or_else(|| ContextError::NotFound(primary.to_string()))?;
+        let other = self
+            .peek(secondary)
+            .await?
+            .ok_or_else(|| ContextError::NotFound(secondary.to_string()))?;
+
+        match strategy {
+            MergeStrategy::Concatenate => {
+                if merged.content != other.content {
+                    merged.content = format!("{}\n\n{}", merged.content, other.content);
+                    merged.embedding = None;
+                }
+            }
+            MergeStrategy::PreferPrimary => {}
+            MergeStrategy::PreferSecondary => {
+                merged.content = other.content.clone();
+                merged.embedding = other.embedding.clone();
+            }
+        }
+
+        for tag in &other.metadata.tags {
+            if !merged.metadata.tags.contains(tag) {
+                merged.metadata.tags.push(tag.clone());
+            }
+        }
+        for (key, value) in &other.metadata.custom {
+            merged
+                .metadata
+                .custom
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        merged.metadata.importance = merged.metadata.importance.max(other.metadata.importance);
+        merged.created_at = merged.created_at.min(other.created_at);
+        merged.accessed_at = merged.accessed_at.max(other.accessed_at);
+        merged.access_count += other.access_count;
+        merged.expires_at = match (merged.expires_at, other.expires_at) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            _ => None,
         };
+        if merged.thread_id.is_none() {
+            merged.thread_id = other.thread_id;
+        }
+        if merged.parent_id.is_none() && other.parent_id.as_ref() != Some(primary) {
+            merged.parent_id = other.parent_id;
+        }
+        if merged.data.is_none() {
+            merged.data = other.data;
+        }
 
-        // Remove expired contexts
-        for id in expired_ids {
-            if self.delete(&id).await? {
-                removed += 1;
+        Ok(merged)
+    }
+
+    /// Merge `secondary` into `primary`
+    ///
+    /// Stores the result of [`Self::plan_merge`] under the primary's ID,
+    /// re-parents contexts that replied to the secondary, deletes the
+    /// secondary and leaves a redirect so its ID keeps resolving through
+    /// [`Self::get`].
+    pub async fn merge(
+        &self,
+        primary: &ContextId,
+        secondary: &ContextId,
+        strategy: MergeStrategy,
+    ) -> Result<Context> {
+        let merged = self.plan_merge(primary, secondary, strategy).await?;
+        self.store(merged.clone()).await?;
+
+        for mut child in self.all_contexts().await? {
+            if child.parent_id.as_ref() == Some(secondary) && &child.id != primary {
+                child.parent_id = Some(primary.clone());
+                self.store(child).await?;
             }
         }
 
-        Ok(removed)
+        self.delete(secondary).await?;
+        self.add_redirect(secondary, primary).await?;
+
+        Ok(merged)
     }
-}
 
-/// Storage statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StorageStats {
-    /// Number of items in memory cache
-    pub memory_count: usize,
-    /// Number of items on disk
-    pub disk_count: usize,
-    /// Memory cache capacity
-    pub cache_capacity: usize,
-}
+    /// Make lookups of `from` resolve to `to`
+    async fn add_redirect(&self, from: &ContextId, to: &ContextId) -> Result<()> {
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            db.open_tree(REDIRECT_TREE)
+                .and_then(|tree| tree.insert(from.as_str().as_bytes(), to.as_str().as_bytes()))
+                .with_operation("redirect", Some(from))?;
+        }
+        self.redirects
+            .write()
+            .await
+            .insert(from.clone(), to.clone());
+        Ok(())
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Copy every context and merge redirect into a new store
+    ///
+    /// The source stays readable and writable throughout. Contexts are
+    /// copied from a snapshot in chunks of [`MIGRATION_CHUNK_SIZE`]; stores
+    /// and deletes that happen meanwhile are then replayed from the audit
+    /// log. Writes after this returns are not copied, so swap the caller's
+    /// `Arc<ContextStore>` right away.
+    pub async fn clone_to_new_store(&self, dest_config: StorageConfig) -> Result<ContextStore> {
+        let dest = ContextStore::new(dest_config)?;
+        let start_seq = self
+            .audit
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .next_seq;
 
-    #[tokio::test]
-    async fn test_store_and_retrieve() {
-        let config = StorageConfig::memory_only(100);
-        let store = ContextStore::new(config).unwrap();
+        let contexts = self.all_contexts().await?;
+        for chunk in contexts.chunks(MIGRATION_CHUNK_SIZE) {
+            dest.store_batch(chunk.to_vec()).await?;
+        }
 
-        let ctx = Context::new("Test content", ContextDomain::Code);
-        let id = ctx.id.clone();
+        let redirects = self.redirects.read().await.clone();
+        for (from, to) in &redirects {
+            dest.add_redirect(from, to).await?;
+        }
 
-        store.store(ctx).await.unwrap();
+        // Catch up with writes made while copying; the latest state wins
+        let mut touched: Vec<ContextId> = Vec::new();
+        for event in self.audit_entries_since(start_seq) {
+            if !touched.contains(&event.id) {
+                touched.push(event.id);
+            }
+        }
+        for id in touched {
+            match self.peek(&id).await? {
+                Some(ctx) => {
+                    dest.store(ctx).await?;
+                }
+                None => {
+                    dest.delete(&id).await?;
+                }
+            }
+        }
 
-        let retrieved = store.get(&id).await.unwrap();
-        assert!(retrieved.is_some());
-        assert_eq!(retrieved.unwrap().content, "Test content");
+        Ok(dest)
     }
 
-    #[tokio::test]
-    async fn test_query_by_domain() {
-        let config = StorageConfig::memory_only(100);
-        let store = ContextStore::new(config).unwrap();
+    /// Delete a context by ID
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(id = %id, found = tracing::field::Empty)
+        )
+    )]
+    pub async fn delete(&self, id: &ContextId) -> Result<bool> {
+        let mut found = false;
 
-        let ctx1 = Context::new("Code content", ContextDomain::Code);
-        let ctx2 = Context::new("Doc content", ContextDomain::Documentation);
+        // First, get the context to extract domain and tags before deletion
+        let context_data = self.peek(id).await?;
 
-        store.store(ctx1).await.unwrap();
-        store.store(ctx2).await.unwrap();
+        // Remove from memory cache
+        let cached = self.memory_cache.write().await.pop(id);
+        if cached.is_some() {
+            found = true;
+        }
 
-        let query = ContextQuery::new().with_domain(ContextDomain::Code);
-        let results = store.query(&query).await.unwrap();
+        // Remove from disk, putting the cached copy back if that fails
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            let key = id.as_str().as_bytes();
+            match self.retry_disk(|| async move { db.remove(key) }).await {
+                Ok(removed) => found |= removed.is_some(),
+                Err(e) => {
+                    if let Some(ctx) = cached {
+                        self.memory_cache.write().await.push(id.clone(), ctx);
+                    }
+                    return Err(e.with_operation("delete", Some(id)));
+                }
+            }
+        }
 
-        assert_eq!(results.len(), 1);
-        assert_eq!(results[0].domain, ContextDomain::Code);
+        // Clean up indices; a context evicted without persistence can no
+        // longer be read, so its ID is swept from every domain and tag
+        match context_data {
+            Some(ctx) => self.unindex(&ctx).await,
+            None => self.unindex_unknown(id).await,
+        }
+
+        if found {
+            self.record_event(StoreEventKind::Deleted, id)?;
+        }
+        telemetry::record("found", found);
+        self.observe(|observer| observer.on_delete(id, found));
+        Ok(found)
+    }
+
+    /// Remove `id` from the domain, tag and source indexes without knowing its context
+    async fn unindex_unknown(&self, id: &ContextId) {
+        self.domain_index.write().await.retain(|_, ids| {
+            ids.remove(id);
+            !ids.is_empty()
+        });
+        self.tag_index.write().await.retain(|_, ids| {
+            ids.remove(id);
+            !ids.is_empty()
+        });
+        self.source_index.write().await.retain(|_, ids| {
+            ids.remove(id);
+            !ids.is_empty()
+        });
+    }
+
+    /// Append a store event to the audit log and publish it to live subscribers
+    ///
+    /// The audit lock is held until the event is published so events are
+    /// logged and broadcast in sequence order.
+    fn record_event(&self, kind: StoreEventKind, id: &ContextId) -> Result<()> {
+        let mut audit = self
+            .audit
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let event = StoreEvent {
+            seq: audit.next_seq,
+            kind,
+            id: id.clone(),
+            at: Utc::now(),
+        };
+
+        #[cfg(feature = "persistence")]
+        let persisted = match self.disk_store {
+            Some(ref db) => {
+                let record = serde_json::to_vec(&event).with_operation("audit", Some(id))?;
+                db.open_tree(AUDIT_TREE)
+                    .and_then(|tree| tree.insert(event.seq.to_be_bytes(), record))
+                    .with_operation("audit", Some(id))?;
+                true
+            }
+            None => false,
+        };
+        #[cfg(not(feature = "persistence"))]
+        let persisted = false;
+
+        if !persisted {
+            if audit.memory.len() == AUDIT_MEMORY_LIMIT {
+                audit.memory.pop_front();
+            }
+            audit.memory.push_back(event.clone());
+        }
+        audit.next_seq += 1;
+
+        // No receivers is not an error
+        let _ = self.events.send(event);
+        Ok(())
+    }
+
+    /// Subscribe to store events as they happen
+    pub fn subscribe(&self) -> broadcast::Receiver<StoreEvent> {
+        self.events.subscribe()
+    }
+
+    /// Send a context's ID to `sender` whenever its importance drops below `threshold`
+    ///
+    /// Fires when a stored context replaces a version at or above the
+    /// threshold with one below it, as [`Self::decay_importance`] and the
+    /// other importance updates do. The watcher is removed once the
+    /// receiver is dropped. Notifications are not buffered beyond the
+    /// channel's capacity: if it is full, the ID is dropped.
+    pub fn watch_importance_threshold(&self, threshold: f32, sender: mpsc::Sender<ContextId>) {
+        self.importance_watchers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push((threshold, sender));
+    }
+
+    /// Notify watchers whose threshold lies in `(after, before]`
+    fn notify_importance_watchers(&self, id: &ContextId, before: f32, after: f32) {
+        let mut watchers = self
+            .importance_watchers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        watchers.retain(|(threshold, sender)| {
+            if sender.is_closed() {
+                return false;
+            }
+            if before < *threshold || after >= *threshold {
+                return true;
+            }
+            match sender.try_send(id.clone()) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(%id, threshold, "importance watcher is full; dropping notification");
+                    true
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            }
+        });
+    }
+
+    /// Stream store events from sequence number `seq` onwards
+    ///
+    /// Replays the audit log entries with a sequence number of at least `seq`,
+    /// then continues with live events. The live channel is subscribed before
+    /// the log is read and events already replayed are skipped, so nothing is
+    /// lost or repeated at the switch-over. A subscriber that falls more than
+    /// the channel capacity behind misses the events it lagged over.
+    ///
+    /// Without persistence only the last `AUDIT_MEMORY_LIMIT` events can be
+    /// replayed.
+    pub fn stream_updates_since(
+        &self,
+        seq: u64,
+    ) -> impl Stream<Item = StoreEvent> + Send + 'static {
+        let live = BroadcastStream::new(self.events.subscribe());
+        let replay = self.audit_entries_since(seq);
+        let last_replayed = replay.last().map(|event| event.seq);
+
+        let live = live.filter_map(move |event| match event {
+            Ok(event) if event.seq >= seq && Some(event.seq) > last_replayed => Some(event),
+            _ => None,
+        });
+        tokio_stream::iter(replay).chain(live)
+    }
+
+    /// Read audit log entries with a sequence number of at least `seq`
+    fn audit_entries_since(&self, seq: u64) -> Vec<StoreEvent> {
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            return match db.open_tree(AUDIT_TREE) {
+                Ok(tree) => tree
+                    .range(seq.to_be_bytes()..)
+                    .values()
+                    .filter_map(|value| value.ok())
+                    .filter_map(|value| serde_json::from_slice(&value).ok())
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+        }
+
+        let audit = self
+            .audit
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        audit
+            .memory
+            .iter()
+            .filter(|event| event.seq >= seq)
+            .cloned()
+            .collect()
+    }
+
+    /// Add a context to the secondary indexes, and to the persisted timestamp trees
+    async fn index(&self, ctx: &Context) {
+        self.index_in_memory(ctx).await;
+        self.persist_time_entry(CREATED_TREE, ctx.created_at, &ctx.id, true);
+        if let Some(expires_at) = ctx.expires_at {
+            self.persist_time_entry(EXPIRY_TREE, expires_at, &ctx.id, true);
+        }
+    }
+
+    /// Add a context to the in-memory secondary indexes only
+    async fn index_in_memory(&self, ctx: &Context) {
+        {
+            let mut domain_idx = self.domain_index.write().await;
+            domain_idx
+                .entry(ctx.domain.clone())
+                .or_default()
+                .insert(ctx.id.clone());
+        }
+
+        {
+            let mut tag_idx = self.tag_index.write().await;
+            for tag in &ctx.metadata.tags {
+                tag_idx
+                    .entry(tag.clone())
+                    .or_default()
+                    .insert(ctx.id.clone());
+            }
+        }
+
+        self.source_index
+            .write()
+            .await
+            .entry(ctx.metadata.source.clone())
+            .or_default()
+            .insert(ctx.id.clone());
+
+        {
+            let mut importance_idx = self.importance_index.write().await;
+            importance_idx
+                .entry(ImportanceKey(ctx.metadata.importance))
+                .or_default()
+                .push(ctx.id.clone());
+        }
+
+        if let Some(ref thread_id) = ctx.thread_id {
+            let mut thread_idx = self.thread_index.write().await;
+            thread_idx
+                .entry(thread_id.clone())
+                .or_default()
+                .push(ctx.id.clone());
+        }
+
+        {
+            let mut access_idx = self.access_index.write().await;
+            access_idx
+                .entry(ctx.accessed_at)
+                .or_default()
+                .push(ctx.id.clone());
+        }
+
+        {
+            let mut created_idx = self.created_index.write().await;
+            created_idx
+                .entry(ctx.created_at)
+                .or_default()
+                .push(ctx.id.clone());
+        }
+
+        if let Some(expires_at) = ctx.expires_at {
+            self.expiry_index
+                .write()
+                .await
+                .entry(expires_at)
+                .or_default()
+                .push(ctx.id.clone());
+        }
+
+        self.content_hash_index
+            .write()
+            .await
+            .insert(ctx.content_hash(), ctx.id.clone());
+    }
+
+    /// Remove a context from the secondary indexes
+    ///
+    /// Empty entries are dropped to prevent unbounded growth.
+    async fn unindex(&self, ctx: &Context) {
+        let id = &ctx.id;
+
+        {
+            let mut domain_idx = self.domain_index.write().await;
+            if let Some(ids) = domain_idx.get_mut(&ctx.domain) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    domain_idx.remove(&ctx.domain);
+                }
+            }
+        }
+
+        {
+            let mut tag_idx = self.tag_index.write().await;
+            for tag in &ctx.metadata.tags {
+                if let Some(ids) = tag_idx.get_mut(tag) {
+                    ids.remove(id);
+                    if ids.is_empty() {
+                        tag_idx.remove(tag);
+                    }
+                }
+            }
+        }
+
+        {
+            let mut source_idx = self.source_index.write().await;
+            if let Some(ids) = source_idx.get_mut(&ctx.metadata.source) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    source_idx.remove(&ctx.metadata.source);
+                }
+            }
+        }
+
+        {
+            let key = ImportanceKey(ctx.metadata.importance);
+            let mut importance_idx = self.importance_index.write().await;
+            if let Some(ids) = importance_idx.get_mut(&key) {
+                ids.retain(|stored_id| stored_id != id);
+                if ids.is_empty() {
+                    importance_idx.remove(&key);
+                }
+            }
+        }
+
+        if let Some(ref thread_id) = ctx.thread_id {
+            let mut thread_idx = self.thread_index.write().await;
+            if let Some(ids) = thread_idx.get_mut(thread_id) {
+                ids.retain(|stored_id| stored_id != id);
+                if ids.is_empty() {
+                    thread_idx.remove(thread_id);
+                }
+            }
+        }
+
+        {
+            let mut access_idx = self.access_index.write().await;
+            if let Some(ids) = access_idx.get_mut(&ctx.accessed_at) {
+                ids.retain(|stored_id| stored_id != id);
+                if ids.is_empty() {
+                    access_idx.remove(&ctx.accessed_at);
+                }
+            }
+        }
+
+        {
+            let mut created_idx = self.created_index.write().await;
+            if let Some(ids) = created_idx.get_mut(&ctx.created_at) {
+                ids.retain(|stored_id| stored_id != id);
+                if ids.is_empty() {
+                    created_idx.remove(&ctx.created_at);
+                }
+            }
+        }
+        self.persist_time_entry(CREATED_TREE, ctx.created_at, id, false);
+
+        if let Some(expires_at) = ctx.expires_at {
+            self.unindex_expiry(expires_at, id).await;
+        }
+
+        {
+            // Another context with the same content may own the entry now
+            let hash = ctx.content_hash();
+            let mut hash_idx = self.content_hash_index.write().await;
+            if hash_idx.get(&hash) == Some(id) {
+                hash_idx.remove(&hash);
+            }
+        }
+    }
+
+    /// Drop one entry from the expiry index and its sled mirror
+    async fn unindex_expiry(&self, expires_at: DateTime<Utc>, id: &ContextId) {
+        let mut expiry_idx = self.expiry_index.write().await;
+        if let Some(ids) = expiry_idx.get_mut(&expires_at) {
+            ids.retain(|stored_id| stored_id != id);
+            if ids.is_empty() {
+                expiry_idx.remove(&expires_at);
+            }
+        }
+        drop(expiry_idx);
+        self.persist_time_entry(EXPIRY_TREE, expires_at, id, false);
+    }
+
+    /// Add or remove an entry of a persisted timestamp tree
+    ///
+    /// Failures are only logged: a missing entry leaves the context to the
+    /// next [`Self::rebuild_indexes`], and a stale one is skipped by readers,
+    /// which look the context up before using it.
+    fn persist_time_entry(&self, tree: &str, at: DateTime<Utc>, id: &ContextId, present: bool) {
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            let key = timestamp_key(at, id);
+            let written = db.open_tree(tree).and_then(|tree| {
+                if present {
+                    tree.insert(key, &[])?;
+                } else {
+                    tree.remove(key)?;
+                }
+                Ok(())
+            });
+            if let Err(e) = written {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(id = %id, tree, error = %e, "failed to update a timestamp tree");
+                let _ = e;
+            }
+        }
+        #[cfg(not(feature = "persistence"))]
+        let _ = (tree, at, id, present);
+    }
+
+    /// Get the most important contexts, optionally within one domain
+    ///
+    /// Walks the importance index from the top instead of scanning every
+    /// context. Ties are ordered by most recent access, as in [`Self::query`].
+    /// Expired contexts are skipped.
+    pub async fn get_top_n_by_importance(
+        &self,
+        n: usize,
+        domain: Option<&ContextDomain>,
+    ) -> Result<Vec<Context>> {
+        self.ensure_indexes_loaded().await?;
+        let mut results: Vec<Context> = Vec::new();
+        let importance_idx = self.importance_index.read().await;
+
+        for ids in importance_idx.values().rev() {
+            // Finish the current importance level so ties are ordered correctly
+            if results.len() >= n {
+                break;
+            }
+
+            for id in ids {
+                if let Some(ctx) = self.peek(id).await? {
+                    let in_domain = domain.map(|d| &ctx.domain == d).unwrap_or(true);
+                    if in_domain && !ctx.is_expired() {
+                        results.push(ctx);
+                    }
+                }
+            }
+        }
+
+        results.sort_by(|a, b| {
+            b.metadata
+                .importance
+                .total_cmp(&a.metadata.importance)
+                .then_with(|| b.accessed_at.cmp(&a.accessed_at))
+        });
+        results.truncate(n);
+        Ok(results)
+    }
+
+    /// Recompute importance scores within a domain
+    ///
+    /// Useful when every context still carries the default importance of 1.0
+    /// and the score no longer discriminates between them. Expired contexts
+    /// are left alone. Returns the number of contexts whose importance changed.
+    pub async fn rebalance_importance(
+        &self,
+        domain: &ContextDomain,
+        strategy: RebalanceStrategy,
+    ) -> Result<usize> {
+        let mut contexts: Vec<Context> = self
+            .all_contexts()
+            .await?
+            .into_iter()
+            .filter(|ctx| &ctx.domain == domain && !ctx.is_expired())
+            .collect();
+
+        if contexts.is_empty() {
+            return Ok(0);
+        }
+
+        let scores = strategy.compute(&mut contexts);
+
+        let mut changed = 0;
+        for (mut ctx, importance) in contexts.into_iter().zip(scores) {
+            if (ctx.metadata.importance - importance).abs() > f32::EPSILON {
+                ctx.metadata.importance = importance;
+                self.store(ctx).await?;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Get the contexts that have gone longest without being accessed
+    ///
+    /// Walks the access index from the oldest entry. Each context is paired
+    /// with the hours since its last access, stalest first. Expired contexts
+    /// are skipped.
+    pub async fn get_stalest_contexts(&self, n: usize) -> Result<Vec<(Context, f64)>> {
+        self.ensure_indexes_loaded().await?;
+        let now = Utc::now();
+        let mut results = Vec::new();
+        let access_idx = self.access_index.read().await;
+
+        'walk: for ids in access_idx.values() {
+            for id in ids {
+                if results.len() >= n {
+                    break 'walk;
+                }
+                if let Some(ctx) = self.peek(id).await? {
+                    if !ctx.is_expired() {
+                        let hours = (now - ctx.accessed_at).num_milliseconds() as f64 / 3_600_000.0;
+                        results.push((ctx, hours));
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Group near-duplicate contexts by embedding similarity
+    ///
+    /// Compares every pair of unexpired contexts with an embedding, in
+    /// `domain` or across the store, and joins pairs whose cosine similarity
+    /// exceeds `threshold` with union-find. Similarities are computed on the
+    /// CPU and the comparison is O(N²) in the number of embedded contexts, so
+    /// more than [`StorageConfig::max_cluster_contexts`] is refused.
+    /// Embeddings whose dimension differs from the first one seen are
+    /// skipped. Only clusters of two or more contexts are returned, largest
+    /// first, each sorted by ID.
+    pub async fn content_similarity_clusters(
+        &self,
+        threshold: f64,
+        domain: Option<&ContextDomain>,
+    ) -> Result<Vec<Vec<ContextId>>> {
+        let mut embedded: Vec<(ContextId, Vec<f32>)> = self
+            .all_contexts()
+            .await?
+            .into_iter()
+            .filter(|ctx| !ctx.is_expired() && !domain.is_some_and(|d| &ctx.domain != d))
+            .filter_map(|ctx| ctx.embedding.map(|embedding| (ctx.id, embedding)))
+            .collect();
+        let dim = embedded.first().map_or(0, |(_, embedding)| embedding.len());
+        embedded.retain(|(_, embedding)| embedding.len() == dim);
+
+        if embedded.len() > self.config.max_cluster_contexts {
+            return Err(ContextError::InvalidQuery(format!(
+                "{} embedded contexts exceeds the clustering limit of {}",
+                embedded.len(),
+                self.config.max_cluster_contexts
+            )));
+        }
+
+        let n = embedded.len();
+        let widened: Vec<Vec<f64>> = embedded
+            .iter()
+            .map(|(_, embedding)| embedding.iter().map(|&v| v as f64).collect())
+            .collect();
+        let mut parent: Vec<usize> = (0..n).collect();
+        for (i, (_, embedding)) in embedded.iter().enumerate() {
+            for (j, other) in widened.iter().enumerate().skip(i + 1) {
+                if cosine_similarity(embedding, other) > threshold {
+                    let (a, b) = (find_root(&mut parent, i), find_root(&mut parent, j));
+                    if a != b {
+                        parent[b] = a;
+                    }
+                }
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<ContextId>> = HashMap::new();
+        for (i, (id, _)) in embedded.into_iter().enumerate() {
+            groups
+                .entry(find_root(&mut parent, i))
+                .or_default()
+                .push(id);
+        }
+        let mut clusters: Vec<Vec<ContextId>> = groups
+            .into_values()
+            .filter(|cluster| cluster.len() > 1)
+            .map(|mut cluster| {
+                cluster.sort();
+                cluster
+            })
+            .collect();
+        clusters.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+        Ok(clusters)
+    }
+
+    /// Stream a domain's contexts one at a time
+    ///
+    /// The domain's IDs are snapshotted from the index when this is called;
+    /// contexts added to the domain afterwards are not yielded, and ones
+    /// deleted before the stream reaches them are skipped. Each context is
+    /// read from the cache or disk only as the stream is polled, and reads do
+    /// not count as accesses. Contexts come in ID order, and expired ones are
+    /// included.
+    pub async fn stream_domain(
+        &self,
+        domain: &ContextDomain,
+    ) -> impl Stream<Item = Result<Context>> + Send + Unpin + '_ {
+        // A failed load is yielded first, then whatever the index holds
+        let failed = self.ensure_indexes_loaded().await.err();
+        let mut ids: Vec<ContextId> = self
+            .domain_index
+            .read()
+            .await
+            .get(domain)
+            .map(|ids| ids.iter().cloned().collect())
+            .unwrap_or_default();
+        ids.sort();
+
+        Box::pin(
+            futures::stream::iter(failed.map(Err)).chain(futures::stream::unfold(
+                ids.into_iter(),
+                move |mut ids| async move {
+                    loop {
+                        let id = ids.next()?;
+                        match self.peek(&id).await {
+                            Ok(Some(ctx)) => return Some((Ok(ctx), ids)),
+                            Ok(None) => continue,
+                            Err(e) => return Some((Err(e), ids)),
+                        }
+                    }
+                },
+            )),
+        )
+    }
+
+    /// Jaccard similarity of the tag vocabularies of two domains
+    ///
+    /// A domain's vocabulary is every tag on any of its live contexts. The
+    /// result is `|A ∩ B| / |A ∪ B|`, and 0.0 when neither domain has tags.
+    pub async fn compute_domain_overlap(
+        &self,
+        a: &ContextDomain,
+        b: &ContextDomain,
+    ) -> Result<f64> {
+        let tags_a = self.domain_tags(a).await?;
+        let tags_b = self.domain_tags(b).await?;
+
+        let union = tags_a.union(&tags_b).count();
+        if union == 0 {
+            return Ok(0.0);
+        }
+        Ok(tags_a.intersection(&tags_b).count() as f64 / union as f64)
+    }
+
+    /// Measure how well `rag` retrieves known-relevant contexts
+    ///
+    /// Each ground-truth pair is a query text and the IDs that should be
+    /// retrieved for it. Recall@k is the share of those IDs found in the
+    /// top k results; the reciprocal rank is 1/rank of the first relevant
+    /// result, or 0.0 when none is returned. Both are averaged over the
+    /// queries. A query with no relevant IDs fails with
+    /// [`ContextError::InvalidQuery`], since its recall is undefined.
+    pub async fn compute_retrieval_recall(
+        &self,
+        ground_truth: &[(String, Vec<ContextId>)],
+        rag: &RagProcessor,
+    ) -> Result<RecallReport> {
+        let mut report = RecallReport::default();
+        if ground_truth.is_empty() {
+            return Ok(report);
+        }
+
+        for (query, relevant) in ground_truth {
+            if relevant.is_empty() {
+                return Err(ContextError::InvalidQuery(format!(
+                    "no relevant IDs given for query '{}'",
+                    query
+                )));
+            }
+            let relevant: HashSet<&ContextId> = relevant.iter().collect();
+            let retrieved: Vec<ContextId> = rag
+                .retrieve_by_text(query)
+                .await?
+                .contexts
+                .into_iter()
+                .map(|scored| scored.context.id)
+                .collect();
+
+            let recall_at = |k: usize| {
+                let found = retrieved
+                    .iter()
+                    .take(k)
+                    .filter(|id| relevant.contains(id))
+                    .count();
+                found as f64 / relevant.len() as f64
+            };
+            report.avg_recall_at_1 += recall_at(1);
+            report.avg_recall_at_5 += recall_at(5);
+            report.avg_recall_at_10 += recall_at(10);
+            if let Some(rank) = retrieved.iter().position(|id| relevant.contains(id)) {
+                report.mrr += 1.0 / (rank + 1) as f64;
+            }
+        }
+
+        let n = ground_truth.len() as f64;
+        report.queries = ground_truth.len();
+        report.avg_recall_at_1 /= n;
+        report.avg_recall_at_5 /= n;
+        report.avg_recall_at_10 /= n;
+        report.mrr /= n;
+        Ok(report)
+    }
+
+    /// Time `n` store, get and query operations on synthetic contexts
+    ///
+    /// The contexts share a tag unique to this run, which the queries filter
+    /// on, and are deleted again before returning, including when an
+    /// operation fails. They still pass through the operation counters and
+    /// the audit log. The sled latency is that of the whole `store` call on
+    /// a persistent store, flush included, and zero for a memory-only one.
+    pub async fn benchmark_store_read_write(&self, n: usize) -> Result<BenchmarkResult> {
+        if n == 0 {
+            return Err(ContextError::InvalidQuery(
+                "benchmark needs at least one operation".to_string(),
+            ));
+        }
+        let tag = format!("benchmark-{}", uuid::Uuid::new_v4().simple());
+        let mut ids = Vec::with_capacity(n);
+        let result = self.run_benchmark(n, &tag, &mut ids).await;
+        self.delete_batch(&ids).await?;
+        result
+    }
+
+    async fn run_benchmark(
+        &self,
+        n: usize,
+        tag: &str,
+        ids: &mut Vec<ContextId>,
+    ) -> Result<BenchmarkResult> {
+        let ops_per_sec = |elapsed: std::time::Duration| n as f64 / elapsed.as_secs_f64().max(1e-9);
+
+        let mut store_latencies = Vec::with_capacity(n);
+        let started = Instant::now();
+        for _ in 0..n {
+            let content = format!("benchmark context {:016x}", rand::random::<u64>());
+            let context =
+                Context::new(content, ContextDomain::General).with_tags(vec![tag.to_string()]);
+            let op_started = Instant::now();
+            ids.push(self.store(context).await?.id);
+            store_latencies.push(op_started.elapsed());
+        }
+        let store_ops_per_sec = ops_per_sec(started.elapsed());
+
+        let started = Instant::now();
+        for id in ids.iter() {
+            self.get(id).await?;
+        }
+        let get_ops_per_sec = ops_per_sec(started.elapsed());
+
+        let query = ContextQuery::new().with_tag(tag.to_string()).with_limit(10);
+        let started = Instant::now();
+        for _ in 0..n {
+            self.query(&query).await?;
+        }
+        let query_ops_per_sec = ops_per_sec(started.elapsed());
+
+        let sled_write_latency_p99_us = if self.has_disk_store() {
+            store_latencies.sort();
+            let rank = ((store_latencies.len() - 1) as f64 * 0.99).round() as usize;
+            store_latencies[rank].as_micros() as u64
+        } else {
+            0
+        };
+
+        Ok(BenchmarkResult {
+            store_ops_per_sec,
+            get_ops_per_sec,
+            query_ops_per_sec,
+            sled_write_latency_p99_us,
+        })
+    }
+
+    /// Unique tags across the live contexts of a domain
+    async fn domain_tags(&self, domain: &ContextDomain) -> Result<HashSet<String>> {
+        let mut tags = HashSet::new();
+        let mut contexts = self.stream_domain(domain).await;
+        while let Some(ctx) = contexts.next().await {
+            let ctx = ctx?;
+            if !ctx.is_expired() {
+                tags.extend(ctx.metadata.tags);
+            }
+        }
+        Ok(tags)
+    }
+
+    /// Rank a domain's contexts by how close they sit to its centroid
+    ///
+    /// The centroid is the element-wise mean of every embedding in the
+    /// domain; each context scores the cosine similarity between its own
+    /// embedding and the centroid. Contexts without an embedding, or whose
+    /// embedding has a different dimension from the first one seen, score
+    /// 0.0. Pairs are returned highest score first.
+    pub async fn sort_by_semantic_coherence(
+        &self,
+        domain: &ContextDomain,
+    ) -> Result<Vec<(ContextId, f64)>> {
+        self.ensure_indexes_loaded().await?;
+        let mut ids: Vec<ContextId> = self
+            .domain_index
+            .read()
+            .await
+            .get(domain)
+            .map(|ids| ids.iter().cloned().collect())
+            .unwrap_or_default();
+        ids.sort();
+
+        let mut contexts = Vec::with_capacity(ids.len());
+        for id in &ids {
+            if let Some(ctx) = self.peek(id).await? {
+                if !ctx.is_expired() {
+                    contexts.push(ctx);
+                }
+            }
+        }
+
+        let dim = contexts
+            .iter()
+            .find_map(|ctx| ctx.embedding.as_ref().map(Vec::len))
+            .unwrap_or(0);
+        let mut centroid = vec![0.0f64; dim];
+        let mut count = 0usize;
+        for embedding in contexts.iter().filter_map(|ctx| ctx.embedding.as_ref()) {
+            if embedding.len() == dim {
+                for (sum, v) in centroid.iter_mut().zip(embedding) {
+                    *sum += *v as f64;
+                }
+                count += 1;
+            }
+        }
+        if count > 0 {
+            centroid.iter_mut().for_each(|sum| *sum /= count as f64);
+        }
+
+        let mut scores: Vec<(ContextId, f64)> = contexts
+            .into_iter()
+            .map(|ctx| {
+                let score = match ctx.embedding {
+                    Some(ref embedding) if embedding.len() == dim && dim > 0 => {
+                        cosine_similarity(embedding, &centroid)
+                    }
+                    _ => 0.0,
+                };
+                (ctx.id, score)
+            })
+            .collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(scores)
+    }
+
+    /// Page through contexts in creation order
+    ///
+    /// `Forward` walks from oldest to newest and `Backward` from newest to
+    /// oldest. Without a cursor a page starts at the oldest (or newest)
+    /// context; with one it starts just past that timestamp. Contexts that
+    /// share a creation time are never split across pages, so a page can hold
+    /// more than `page_size` items when ties straddle its end. Expired
+    /// contexts are skipped but still counted in `total_count`.
+    pub async fn paginate_by_created_at(
+        &self,
+        page_size: usize,
+        cursor: Option<DateTime<Utc>>,
+        direction: PaginationDir,
+    ) -> Result<Page<Context>> {
+        self.ensure_indexes_loaded().await?;
+        let page_size = page_size.max(1);
+        let created_idx = self.created_index.read().await;
+        let total_count = created_idx.values().map(Vec::len).sum();
+
+        let groups: Box<dyn Iterator<Item = (&DateTime<Utc>, &Vec<ContextId>)> + Send + '_> =
+            match (direction, cursor) {
+                (PaginationDir::Forward, None) => Box::new(created_idx.iter()),
+                (PaginationDir::Forward, Some(after)) => {
+                    Box::new(created_idx.range((Bound::Excluded(after), Bound::Unbounded)))
+                }
+                (PaginationDir::Backward, None) => Box::new(created_idx.iter().rev()),
+                (PaginationDir::Backward, Some(before)) => Box::new(
+                    created_idx
+                        .range((Bound::Unbounded, Bound::Excluded(before)))
+                        .rev(),
+                ),
+            };
+
+        let mut items = Vec::new();
+        let mut more = false;
+        for (_, ids) in groups {
+            if items.len() >= page_size {
+                more = true;
+                break;
+            }
+            for id in ids {
+                if let Some(ctx) = self.peek(id).await? {
+                    if !ctx.is_expired() {
+                        items.push(ctx);
+                    }
+                }
+            }
+        }
+
+        let next_cursor = if more {
+            items.last().map(|ctx| ctx.created_at)
+        } else {
+            None
+        };
+        let prev_cursor = items.first().map(|ctx| ctx.created_at).filter(|&first| {
+            let mut before = match direction {
+                PaginationDir::Forward => {
+                    created_idx.range((Bound::Unbounded, Bound::Excluded(first)))
+                }
+                PaginationDir::Backward => {
+                    created_idx.range((Bound::Excluded(first), Bound::Unbounded))
+                }
+            };
+            before.next().is_some()
+        });
+
+        Ok(Page {
+            items,
+            next_cursor,
+            prev_cursor,
+            total_count,
+        })
+    }
+
+    /// Contexts created between `start` and `end` inclusive, oldest first
+    ///
+    /// Matching IDs come from a range over the creation-time index, so no
+    /// other contexts are read. Contexts created at the same instant are
+    /// ordered by ID, keeping results stable between calls. Expired contexts
+    /// are skipped and at most `limit` are returned.
+    pub async fn search_by_creation_window(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<Context>> {
+        self.ensure_indexes_loaded().await?;
+        if start > end {
+            return Err(ContextError::InvalidQuery(format!(
+                "window start {} is after its end {}",
+                start.to_rfc3339(),
+                end.to_rfc3339()
+            )));
+        }
+
+        let created_idx = self.created_index.read().await;
+        let mut results = Vec::new();
+        'groups: for (_, ids) in created_idx.range((Bound::Included(start), Bound::Included(end))) {
+            let mut ids = ids.clone();
+            ids.sort();
+            for id in &ids {
+                if results.len() >= limit {
+                    break 'groups;
+                }
+                if let Some(ctx) = self.peek(id).await? {
+                    if !ctx.is_expired() {
+                        results.push(ctx);
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Get the turns of a conversation thread, oldest first
+    ///
+    /// Turns are found through the thread index, so they stay reachable
+    /// when their parent is deleted. Expired turns are skipped.
+    pub async fn get_thread(&self, thread_id: &str) -> Result<Vec<Context>> {
+        self.ensure_indexes_loaded().await?;
+        let ids = self
+            .thread_index
+            .read()
+            .await
+            .get(thread_id)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut turns = Vec::with_capacity(ids.len());
+        for id in &ids {
+            if let Some(ctx) = self.peek(id).await? {
+                if !ctx.is_expired() {
+                    turns.push(ctx);
+                }
+            }
+        }
+
+        turns.sort_by_key(|c| c.created_at);
+        Ok(turns)
+    }
+
+    /// Store several contexts, returning their IDs in order
+    ///
+    /// Each context is screened and prepared as by [`Self::store`], then all
+    /// of them are written to sled in one atomic batch, followed by a single
+    /// flush under [`FlushPolicy::EveryWrite`]. If that write fails nothing is stored, in memory or on disk.
+    /// A context repeated within the batch replaces its earlier copy.
+    pub async fn store_batch(&self, contexts: Vec<Context>) -> Result<Vec<ContextId>> {
+        let mut ids = Vec::with_capacity(contexts.len());
+        let mut prepared: Vec<(Context, Option<Context>)> = Vec::with_capacity(contexts.len());
+        let mut latest: HashMap<ContextId, usize> = HashMap::new();
+
+        for mut context in contexts {
+            let id = context.id.clone();
+            let previous = match latest.get(&id) {
+                Some(&index) => Some(prepared[index].0.clone()),
+                None => self.peek(&id).await?,
+            };
+            if let Some(ref hook) = self.screening_hook {
+                let content_changed = previous
+                    .as_ref()
+                    .map_or(true, |previous| previous.content != context.content);
+                if content_changed {
+                    self.screen(hook.as_ref(), &mut context).await?;
+                }
+            }
+            if self.config.detect_language {
+                detect_language(&mut context);
+            }
+            if let Some(ref previous) = previous {
+                record_revision(previous, &mut context);
+            }
+            latest.insert(id.clone(), prepared.len());
+            ids.push(id);
+            prepared.push((context, previous));
+        }
+
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            let mut batch = sled::Batch::default();
+            for (context, _) in &prepared {
+                let value = encode_context(context, &self.config)
+                    .with_operation("store_batch", Some(&context.id))?;
+                batch.insert(context.id.as_str().as_bytes(), value);
+            }
+            let flush = self.config.flush_policy == FlushPolicy::EveryWrite;
+            self.retry_disk(|| {
+                let batch = batch.clone();
+                async move {
+                    db.apply_batch(batch)?;
+                    if flush {
+                        db.flush_async().await?;
+                    }
+                    Ok(())
+                }
+            })
+            .await
+            .with_operation("store_batch", None)?;
+        }
+
+        for (context, previous) in prepared {
+            if let Some(ref previous) = previous {
+                self.unindex(previous).await;
+            }
+            self.index(&context).await;
+            self.cache_insert(context.id.clone(), context.clone())
+                .await?;
+
+            self.record_event(StoreEventKind::Stored, &context.id)?;
+            if let Some(ref previous) = previous {
+                self.notify_importance_watchers(
+                    &context.id,
+                    previous.metadata.importance,
+                    context.metadata.importance,
+                );
+            }
+            self.observe(|observer| observer.on_store(&context));
+        }
+        Ok(ids)
+    }
+
+    /// Delete several contexts, returning how many existed
+    pub async fn delete_batch(&self, ids: &[ContextId]) -> Result<usize> {
+        let mut removed = 0;
+        for id in ids {
+            if self.delete(id).await? {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Find contexts whose source matches a pattern
+    ///
+    /// The pattern is an exact source or a glob using `*` (any run of
+    /// characters) and `?` (any single character), matched against the
+    /// source index so no contexts are loaded. Returns `(id, source)` pairs
+    /// sorted by ID.
+    pub async fn find_by_source(&self, source_pattern: &str) -> Result<Vec<(ContextId, String)>> {
+        self.ensure_indexes_loaded().await?;
+        let mut found: Vec<(ContextId, String)> = self
+            .source_index
+            .read()
+            .await
+            .iter()
+            .filter(|(source, _)| glob_match(source_pattern, source))
+            .flat_map(|(source, ids)| ids.iter().map(move |id| (id.clone(), source.clone())))
+            .collect();
+        found.sort();
+        Ok(found)
+    }
+
+    /// Delete every context whose source matches a pattern
+    ///
+    /// Used to retire a decommissioned data source. See [`Self::find_by_source`]
+    /// for the pattern syntax. Returns the number of contexts deleted.
+    pub async fn cleanup_source(&self, source_pattern: &str) -> Result<usize> {
+        let ids: Vec<ContextId> = self
+            .find_by_source(source_pattern)
+            .await?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        self.delete_batch(&ids).await
+    }
+
+    /// Delete every context whose source is not on an allowlist
+    ///
+    /// See [`SourceMatchMode`] for how entries match; only the source index
+    /// keys are checked, so no contexts are loaded. With `dry_run` nothing
+    /// is deleted. Returns the number of contexts deleted (or that would be).
+    /// An empty allowlist is rejected rather than deleting everything.
+    pub async fn enforce_source_allowlist(
+        &self,
+        allowed_sources: &[&str],
+        mode: SourceMatchMode,
+        dry_run: bool,
+    ) -> Result<usize> {
+        if allowed_sources.is_empty() {
+            return Err(ContextError::InvalidQuery(
+                "source allowlist is empty".to_string(),
+            ));
+        }
+
+        self.ensure_indexes_loaded().await?;
+        let ids: Vec<ContextId> = self
+            .source_index
+            .read()
+            .await
+            .iter()
+            .filter(|(source, _)| !mode.allows(allowed_sources, source))
+            .flat_map(|(_, ids)| ids.iter().cloned())
+            .collect();
+        if dry_run {
+            return Ok(ids.len());
+        }
+        self.delete_batch(&ids).await
+    }
+
+    /// Get the context whose content has the given SHA-256 hex digest
+    pub async fn get_by_content_hash(&self, hash: &str) -> Result<Option<Context>> {
+        self.ensure_indexes_loaded().await?;
+        let id = self
+            .content_hash_index
+            .read()
+            .await
+            .get(&hash.to_ascii_lowercase())
+            .cloned();
+        match id {
+            Some(id) => self.get(&id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Find unexpired contexts that have no embedding
+    ///
+    /// With a domain, candidates come from the domain index; otherwise every
+    /// stored context is scanned. Either way the IDs are returned sorted.
+    pub async fn find_contexts_without_embeddings(
+        &self,
+        domain: Option<&ContextDomain>,
+    ) -> Result<Vec<ContextId>> {
+        self.ensure_indexes_loaded().await?;
+        let missing = |ctx: &Context| ctx.embedding.is_none() && !ctx.is_expired();
+
+        match domain {
+            Some(domain) => {
+                let mut candidates: Vec<ContextId> = self
+                    .domain_index
+                    .read()
+                    .await
+                    .get(domain)
+                    .map(|ids| ids.iter().cloned().collect())
+                    .unwrap_or_default();
+                candidates.sort();
+                let mut ids = Vec::new();
+                for id in candidates {
+                    if let Some(ctx) = self.peek(&id).await? {
+                        if missing(&ctx) {
+                            ids.push(id);
+                        }
+                    }
+                }
+                Ok(ids)
+            }
+            None => {
+                let mut ids: Vec<ContextId> = self
+                    .all_contexts()
+                    .await?
+                    .into_iter()
+                    .filter(missing)
+                    .map(|ctx| ctx.id)
+                    .collect();
+                ids.sort();
+                Ok(ids)
+            }
+        }
+    }
+
+    /// Generate and store embeddings for contexts that lack one
+    ///
+    /// Embeddings are generated concurrently `batch_size` at a time, and each
+    /// batch is stored before the next starts, so a failure keeps the batches
+    /// already done. Returns the number of contexts embedded.
+    pub async fn backfill_embeddings(
+        &self,
+        domain: Option<&ContextDomain>,
+        batch_size: usize,
+        generator: Arc<dyn EmbeddingGenerator>,
+    ) -> Result<usize> {
+        if batch_size == 0 {
+            return Err(ContextError::InvalidQuery(
+                "batch size must be at least 1".into(),
+            ));
+        }
+
+        let mut embedded = 0;
+        for ids in self
+            .find_contexts_without_embeddings(domain)
+            .await?
+            .chunks(batch_size)
+        {
+            let mut batch = Vec::with_capacity(ids.len());
+            for id in ids {
+                // Skip contexts deleted or embedded since the scan
+                if let Some(ctx) = self.peek(id).await? {
+                    if ctx.embedding.is_none() {
+                        batch.push(ctx);
+                    }
+                }
+            }
+
+            let embeddings =
+                futures::future::join_all(batch.iter().map(|ctx| generator.generate(&ctx.content)))
+                    .await;
+            for (ctx, embedding) in batch.into_iter().zip(embeddings) {
+                self.store(ctx.with_embedding(embedding?)).await?;
+                embedded += 1;
+            }
+        }
+        Ok(embedded)
+    }
+
+    /// Assemble a context's provenance: its revisions, ancestors and descendants
+    ///
+    /// Ancestors follow `parent_id` links upwards, nearest first; descendants
+    /// are collected breadth-first, children before grandchildren. Both stop
+    /// after `max_depth` levels. Lookups do not count as accesses.
+    pub async fn get_context_lineage(
+        &self,
+        id: &ContextId,
+        max_depth: usize,
+    ) -> Result<ContextLineage> {
+        let context = self
+            .peek(id)
+            .await?
+            .ok_or_else(|| ContextError::NotFound(id.to_string()))?;
+
+        let (ancestors, descendants) = tokio::join!(
+            self.lineage_ancestors(&context, max_depth),
+            self.lineage_descendants(&context.id, max_depth)
+        );
+
+        Ok(ContextLineage {
+            revisions: context.history.clone(),
+            context,
+            ancestors: ancestors?,
+            descendants: descendants?,
+        })
+    }
+
+    /// Walk `parent_id` links up from `context`, nearest ancestor first
+    async fn lineage_ancestors(&self, context: &Context, max_depth: usize) -> Result<Vec<Context>> {
+        let mut ancestors: Vec<Context> = Vec::new();
+        let mut seen: HashSet<ContextId> = HashSet::from([context.id.clone()]);
+        let mut next = context.parent_id.clone();
+
+        while let Some(parent_id) = next {
+            // A corrupted cycle must not loop forever
+            if ancestors.len() >= max_depth || !seen.insert(parent_id.clone()) {
+                break;
+            }
+            match self.peek(&parent_id).await? {
+                Some(parent) => {
+                    next = parent.parent_id.clone();
+                    ancestors.push(parent);
+                }
+                None => break,
+            }
+        }
+        Ok(ancestors)
+    }
+
+    /// Collect the contexts descending from `id`, breadth-first
+    async fn lineage_descendants(&self, id: &ContextId, max_depth: usize) -> Result<Vec<Context>> {
+        let mut children: HashMap<ContextId, Vec<Context>> = HashMap::new();
+        for ctx in self.all_contexts().await? {
+            if let Some(parent_id) = ctx.parent_id.clone() {
+                children.entry(parent_id).or_default().push(ctx);
+            }
+        }
+
+        let mut descendants = Vec::new();
+        let mut seen: HashSet<ContextId> = HashSet::from([id.clone()]);
+        let mut level = vec![id.clone()];
+        for _ in 0..max_depth {
+            let mut next_level = Vec::new();
+            for parent_id in &level {
+                for child in children.remove(parent_id).unwrap_or_default() {
+                    if seen.insert(child.id.clone()) {
+                        next_level.push(child.id.clone());
+                        descendants.push(child);
+                    }
+                }
+            }
+            if next_level.is_empty() {
+                break;
+            }
+            level = next_level;
+        }
+        Ok(descendants)
+    }
+
+    /// Compare the stored context IDs with those of a remote store
+    ///
+    /// Every stored context counts, expired or not. Only IDs are read, via
+    /// [`Self::list_all_ids`]. Each list is sorted.
+    pub async fn reconcile_with_remote(&self, remote_ids: &[ContextId]) -> Result<ReconcileStats> {
+        let local: HashSet<ContextId> = self.list_all_ids(None).await?.into_iter().collect();
+        let remote: HashSet<&ContextId> = remote_ids.iter().collect();
+
+        let mut stats = ReconcileStats::default();
+        for id in &local {
+            if remote.contains(id) {
+                stats.shared.push(id.clone());
+            } else {
+                stats.local_only.push(id.clone());
+            }
+        }
+        stats.remote_only = remote
+            .into_iter()
+            .filter(|id| !local.contains(*id))
+            .cloned()
+            .collect();
+
+        stats.local_only.sort();
+        stats.remote_only.sort();
+        stats.shared.sort();
+        Ok(stats)
+    }
+
+    /// Collect every stored context from the cache and disk, deduplicated by ID
+    ///
+    /// Cached copies win over persisted ones since they carry the latest
+    /// access time.
+    pub async fn all_contexts(&self) -> Result<Vec<Context>> {
+        let mut all: HashMap<ContextId, Context> = {
+            let cache = self.memory_cache.read().await;
+            cache
+                .iter()
+                .map(|(id, ctx)| (id.clone(), ctx.clone()))
+                .collect()
+        };
+
+        for ctx in self.iter_sled() {
+            let ctx = ctx?;
+            all.entry(ctx.id.clone()).or_insert(ctx);
+        }
+
+        Ok(all.into_values().collect())
+    }
+
+    /// Write every stored context as one JSON object per line
+    ///
+    /// Cached contexts are written first, then the persisted ones not among
+    /// them, so each context appears once without loading the whole disk
+    /// store. Returns the number of contexts written.
+    pub async fn export_all(&self, writer: impl AsyncWrite + Unpin) -> Result<usize> {
+        let mut writer = writer;
+        let cached: Vec<Context> = {
+            let cache = self.memory_cache.read().await;
+            cache.iter().map(|(_, ctx)| ctx.clone()).collect()
+        };
+        let seen: HashSet<ContextId> = cached.iter().map(|ctx| ctx.id.clone()).collect();
+
+        let mut written = 0;
+        let mut line = Vec::new();
+        for ctx in cached {
+            write_json_line(&mut writer, &mut line, &ctx).await?;
+            written += 1;
+        }
+        for ctx in self.iter_sled() {
+            let ctx = ctx?;
+            if !seen.contains(&ctx.id) {
+                write_json_line(&mut writer, &mut line, &ctx).await?;
+                written += 1;
+            }
+        }
+        writer.flush().await?;
+        Ok(written)
+    }
+
+    /// Load contexts written by [`Self::export_all`]
+    ///
+    /// Contexts keep their IDs, timestamps and metadata, and are stored in
+    /// batches of [`MIGRATION_CHUNK_SIZE`] through [`Self::store_batch`], so
+    /// indexes are rebuilt and any screening hook sees new content. Blank
+    /// lines are ignored; a line that is not a context fails the import
+    /// with [`std::io::ErrorKind::InvalidData`], keeping earlier batches.
+    pub async fn import(
+        &self,
+        reader: impl AsyncRead + Unpin,
+        mode: ImportMode,
+    ) -> Result<ImportSummary> {
+        let mut lines = BufReader::new(reader).lines();
+        let mut summary = ImportSummary::default();
+        let mut batch = Vec::new();
+        let mut number = 0;
+        while let Some(line) = lines.next_line().await? {
+            number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let ctx: Context = serde_json::from_str(&line).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("line {} is not a valid context: {}", number, e),
+                )
+            })?;
+            let taken = |ctx: &Context| batch.iter().any(|queued: &Context| queued.id == ctx.id);
+            if mode == ImportMode::SkipExisting && (taken(&ctx) || self.exists(&ctx.id).await?) {
+                summary.skipped += 1;
+                continue;
+            }
+            batch.push(ctx);
+            if batch.len() == MIGRATION_CHUNK_SIZE {
+                summary.imported += batch.len();
+                self.store_batch(std::mem::take(&mut batch)).await?;
+            }
+        }
+        summary.imported += batch.len();
+        if !batch.is_empty() {
+            self.store_batch(batch).await?;
+        }
+        Ok(summary)
+    }
+
+    /// IDs of every stored context, or of one domain's, sorted
+    ///
+    /// No content is loaded: without a domain the IDs come from the memory
+    /// cache and the sled keys, with one they come from the domain index.
+    pub async fn list_all_ids(&self, domain: Option<&ContextDomain>) -> Result<Vec<ContextId>> {
+        if let Some(domain) = domain {
+            self.ensure_indexes_loaded().await?;
+            let mut ids: Vec<ContextId> = self
+                .domain_index
+                .read()
+                .await
+                .get(domain)
+                .map(|ids| ids.iter().cloned().collect())
+                .unwrap_or_default();
+            ids.sort();
+            return Ok(ids);
+        }
+
+        let ids: HashSet<ContextId> = self
+            .memory_cache
+            .read()
+            .await
+            .iter()
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        #[cfg(feature = "persistence")]
+        let mut ids = ids;
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            for key in db.iter().keys() {
+                let key = key.with_operation("list_ids", None)?;
+                ids.insert(ContextId::from_string(
+                    String::from_utf8_lossy(&key).into_owned(),
+                ));
+            }
+        }
+
+        let mut ids: Vec<ContextId> = ids.into_iter().collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Count word frequencies across the content of every live context
+    ///
+    /// Content is split on non-alphanumeric characters and lowercased. Words
+    /// seen fewer than `min_count` times, or listed in `stop_words`, are
+    /// dropped, and only the `max_words` most frequent are kept (ties broken
+    /// alphabetically).
+    pub async fn get_content_vocabulary(
+        &self,
+        min_count: u32,
+        max_words: usize,
+        stop_words: Option<HashSet<String>>,
+    ) -> Result<HashMap<String, u32>> {
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        for ctx in self.all_contexts().await? {
+            if ctx.is_expired() {
+                continue;
+            }
+            for word in ctx
+                .content
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|w| !w.is_empty())
+            {
+                *counts.entry(word.to_lowercase()).or_default() += 1;
+            }
+        }
+
+        let mut words: Vec<(String, u32)> = counts
+            .into_iter()
+            .filter(|(word, count)| {
+                *count >= min_count
+                    && !stop_words
+                        .as_ref()
+                        .is_some_and(|stop| stop.contains(word.as_str()))
+            })
+            .collect();
+        words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        words.truncate(max_words);
+        Ok(words.into_iter().collect())
+    }
+
+    /// Iterate over every context persisted in sled
+    ///
+    /// Records are decoded lazily as the iterator advances.
+    #[cfg(feature = "persistence")]
+    pub fn iter_sled(&self) -> impl Iterator<Item = Result<Context>> + '_ {
+        self.disk_store
+            .iter()
+            .flat_map(|db| db.iter())
+            .map(|entry| -> Result<Context> {
+                let (key, value) = entry.with_operation("iter_sled", None)?;
+                decode_context(&value).with_operation(
+                    "iter_sled",
+                    Some(&ContextId::from_string(
+                        String::from_utf8_lossy(&key).into_owned(),
+                    )),
+                )
+            })
+    }
+
+    /// Iterate over every context persisted in sled (always empty without persistence)
+    #[cfg(not(feature = "persistence"))]
+    pub fn iter_sled(&self) -> impl Iterator<Item = Result<Context>> + '_ {
+        std::iter::empty()
+    }
+
+    /// Like [`Self::iter_sled`], but records whose key satisfies `skip` are
+    /// never decoded
+    #[cfg(feature = "persistence")]
+    fn iter_sled_except<'a>(
+        &'a self,
+        skip: impl Fn(&ContextId) -> bool + 'a,
+    ) -> impl Iterator<Item = Result<Context>> + 'a {
+        self.disk_store
+            .iter()
+            .flat_map(|db| db.iter())
+            .filter_map(move |entry| {
+                let (key, value) = match entry.with_operation("iter_sled", None) {
+                    Ok(entry) => entry,
+                    Err(e) => return Some(Err(e)),
+                };
+                let id = ContextId::from_string(String::from_utf8_lossy(&key).into_owned());
+                if skip(&id) {
+                    return None;
+                }
+                Some(decode_context(&value).with_operation("iter_sled", Some(&id)))
+            })
+    }
+
+    #[cfg(not(feature = "persistence"))]
+    fn iter_sled_except<'a>(
+        &'a self,
+        _skip: impl Fn(&ContextId) -> bool + 'a,
+    ) -> impl Iterator<Item = Result<Context>> + 'a {
+        std::iter::empty()
+    }
+
+    /// Query contexts based on criteria
+    ///
+    /// Returns the contexts of [`Self::query_page`].
+    pub async fn query(&self, query: &ContextQuery) -> Result<Vec<Context>> {
+        Ok(self.query_page(query).await?.contexts)
+    }
+
+    /// Query one page of contexts, with a cursor to the next
+    ///
+    /// Candidates come from the domain, tag, source and creation-time indexes
+    /// when those filters are set, and from the memory cache otherwise. When
+    /// no index narrowed the search and the indexes are not yet loaded (see
+    /// [`Self::load_indexes`]), the persisted contexts are streamed from
+    /// sled as well, so evicted contexts stay queryable. Every match is
+    /// sorted before the page is cut: matches up to [`ContextQuery::cursor`] are dropped, then
+    /// [`ContextQuery::offset`] more are skipped and up to `limit` returned.
+    /// Only the returned contexts are marked accessed.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "query",
+            level = "debug",
+            skip_all,
+            fields(limit = query.limit, results = tracing::field::Empty)
+        )
+    )]
+    pub async fn query_page(&self, query: &ContextQuery) -> Result<QueryPage> {
+        let started = Instant::now();
+        if let Some(ref cursor) = query.cursor {
+            if cursor.sort != query.sort {
+                return Err(ContextError::InvalidQuery(format!(
+                    "cursor is for sort order {:?}, not {:?}",
+                    cursor.sort, query.sort
+                )));
+            }
+        }
+
+        // Get candidate IDs from indices
+        let Candidates {
+            ids: candidate_ids,
+            scan_disk,
+        } = self.get_candidate_ids(query).await?;
+        let mut seen: HashSet<ContextId> = HashSet::new();
+
+        // Keep only the sort keys of matches, so memory stays small on large stores
+        let mut positions = Vec::new();
+        for id in candidate_ids {
+            if let Some(ctx) = self.peek(&id).await? {
+                if self.matches_query(&ctx, query) {
+                    positions.push(QueryCursor::at(&ctx, query.sort));
+                }
+            }
+            seen.insert(id);
+        }
+        if scan_disk {
+            for ctx in self.iter_sled_except(|id| seen.contains(id)) {
+                let ctx = ctx?;
+                if self.matches_query(&ctx, query) {
+                    positions.push(QueryCursor::at(&ctx, query.sort));
+                }
+            }
+        }
+
+        let total_matches = positions.len();
+        positions.sort_by(|a, b| query.sort.compare(a, b));
+        let start = match query.cursor {
+            Some(ref cursor) => positions
+                .partition_point(|p| query.sort.compare(p, cursor) != std::cmp::Ordering::Greater),
+            None => 0,
+        }
+        .saturating_add(query.offset)
+        .min(total_matches);
+        let end = start.saturating_add(query.limit).min(total_matches);
+        let page = &positions[start..end];
+        let next_cursor = if end < total_matches {
+            page.last().cloned()
+        } else {
+            None
+        };
+
+        // Fetch through `get_as` so access tracking and promotion apply
+        let mut contexts = Vec::with_capacity(page.len());
+        for position in page {
+            if let Some(ctx) = self.get_as(&position.id, query.accessor.as_deref()).await? {
+                contexts.push(ctx);
+            }
+        }
+
+        telemetry::record("results", contexts.len());
+        self.observe(|observer| observer.on_query(started.elapsed(), contexts.len()));
+        Ok(QueryPage {
+            contexts,
+            next_cursor,
+            total_matches,
+        })
+    }
+
+    /// Count the contexts matching `query`
+    ///
+    /// Uses the same candidates and filters as [`Self::query_page`], but
+    /// checks cached contexts in place and keeps nothing, so no contexts are
+    /// cloned or marked accessed. The cursor, offset and limit are ignored.
+    pub async fn count(&self, query: &ContextQuery) -> Result<usize> {
+        let Candidates {
+            ids: candidate_ids,
+            scan_disk,
+        } = self.get_candidate_ids(query).await?;
+
+        let mut count = 0;
+        let mut uncached = Vec::new();
+        {
+            let cache = self.memory_cache.read().await;
+            for id in &candidate_ids {
+                match cache.peek(id) {
+                    Some(ctx) => count += usize::from(self.matches_query(ctx, query)),
+                    None => uncached.push(id),
+                }
+            }
+        }
+        for id in uncached {
+            if let Some(ctx) = self.peek(id).await? {
+                count += usize::from(self.matches_query(&ctx, query));
+            }
+        }
+        if scan_disk {
+            let seen: HashSet<&ContextId> = candidate_ids.iter().collect();
+            for ctx in self.iter_sled_except(|id| seen.contains(id)) {
+                if self.matches_query(&ctx?, query) {
+                    count += 1;
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Find up to `limit` contexts containing `text`, most important first
+    ///
+    /// A shorthand for [`Self::query`] with a text filter and an optional
+    /// domain filter, matching case-insensitively. For embedding-based
+    /// scoring use [`RagProcessor`] instead.
+    pub async fn query_text(
+        &self,
+        text: &str,
+        limit: usize,
+        domain: Option<&ContextDomain>,
+    ) -> Result<Vec<Context>> {
+        let mut query = ContextQuery::new().with_text(text).with_limit(limit);
+        if let Some(domain) = domain {
+            query = query.with_domain(domain.clone());
+        }
+        self.query(&query).await
+    }
+
+    /// Retrieve relevant context for RAG
+    ///
+    /// Equivalent to [`Self::query_text`].
+    pub async fn retrieve_context(
+        &self,
+        query_text: &str,
+        limit: usize,
+        domain_filter: Option<&ContextDomain>,
+    ) -> Result<Vec<Context>> {
+        self.query_text(query_text, limit, domain_filter).await
+    }
+
+    /// Get candidate IDs from indices based on query filters
+    ///
+    /// Each filter yields a set of IDs: the domain's entries in the domain
+    /// index, the union of every listed tag's entries in the tag index, the
+    /// source's entries in the source index, and for
+    /// [`ContextQuery::max_age_seconds`] a range scan of the creation-time
+    /// index. The candidates are the intersection of the sets; with no filter
+    /// they are everything in the memory cache, and sled must be scanned too.
+    /// Any index filter makes that scan unnecessary: the indexes are rebuilt
+    /// from sled when the store is opened.
+    async fn get_candidate_ids(&self, query: &ContextQuery) -> Result<Candidates> {
+        let domain_ids: Option<HashSet<ContextId>> = match query.domain_filter {
+            Some(ref domain) => {
+                let domain_idx = self.domain_index.read().await;
+                Some(
+                    domain_idx
+                        .get(domain)
+                        .map(|ids| ids.iter().cloned().collect())
+                        .unwrap_or_default(),
+                )
+            }
+            None => None,
+        };
+
+        // Contexts with any of the requested tags
+        let tag_ids: Option<HashSet<ContextId>> = match query.tag_filter {
+            Some(ref tags) => {
+                let tag_idx = self.tag_index.read().await;
+                Some(
+                    tags.iter()
+                        .filter_map(|tag| tag_idx.get(tag))
+                        .flat_map(|ids| ids.iter().cloned())
+                        .collect(),
+                )
+            }
+            None => None,
+        };
+
+        let source_ids: Option<HashSet<ContextId>> = match query.source_filter {
+            Some(ref source) => {
+                let source_idx = self.source_index.read().await;
+                Some(source_idx.get(source).cloned().unwrap_or_default())
+            }
+            None => None,
+        };
+
+        let indexed_ids = [domain_ids, tag_ids, source_ids]
+            .into_iter()
+            .flatten()
+            .reduce(intersect_ids);
+
+        // One extra second, since ages are compared in whole seconds
+        let created_ids = match query
+            .max_age_seconds
+            .and_then(|max_age| Duration::try_seconds(max_age.saturating_add(1)))
+            .and_then(|window| Utc::now().checked_sub_signed(window))
+        {
+            Some(cutoff) => Some(self.created_since(cutoff).await?),
+            None => None,
+        };
+
+        // Until `load_indexes` has run, the in-memory indexes may miss
+        // persisted contexts; the created tree in sled never does
+        let complete = self
+            .indexes_complete
+            .load(std::sync::atomic::Ordering::Acquire);
+        let (mut ids, scan_disk): (Vec<ContextId>, bool) = match (created_ids, indexed_ids) {
+            (Some(created_ids), Some(indexed_ids)) if complete => (
+                intersect_ids(created_ids, indexed_ids)
+                    .into_iter()
+                    .collect(),
+                false,
+            ),
+            (Some(created_ids), _) => (created_ids.into_iter().collect(), false),
+            (None, Some(indexed_ids)) => (indexed_ids.into_iter().collect(), !complete),
+            // Complete indexes list every stored context, evicted ones included
+            (None, None) if complete => {
+                let created_idx = self.created_index.read().await;
+                (created_idx.values().flatten().cloned().collect(), false)
+            }
+            (None, None) => {
+                let cache = self.memory_cache.read().await;
+                (cache.iter().map(|(id, _)| id.clone()).collect(), true)
+            }
+        };
+
+        ids.sort();
+        ids.dedup();
+        Ok(Candidates { ids, scan_disk })
+    }
+
+    /// IDs of contexts created at or after `cutoff`, in memory and in sled
+    ///
+    /// May include contexts deleted since, if removing their entry failed.
+    async fn created_since(&self, cutoff: DateTime<Utc>) -> Result<HashSet<ContextId>> {
+        let ids: HashSet<ContextId> = self
+            .created_index
+            .read()
+            .await
+            .range(cutoff..)
+            .flat_map(|(_, ids)| ids.iter().cloned())
+            .collect();
+        #[cfg(feature = "persistence")]
+        let mut ids = ids;
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            let tree = db
+                .open_tree(CREATED_TREE)
+                .with_operation("created_since", None)?;
+            for entry in tree.range(timestamp_prefix(cutoff)..) {
+                let (key, _) = entry.with_operation("created_since", None)?;
+                ids.extend(parse_timestamp_key(&key).map(|(_, id)| id));
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Check if a context matches the query criteria
+    fn matches_query(&self, ctx: &Context, query: &ContextQuery) -> bool {
+        // Check expiration
+        if ctx.is_expired() {
+            return false;
+        }
+
+        // Check domain
+        if let Some(ref domain) = query.domain_filter {
+            if &ctx.domain != domain {
+                return false;
+            }
+        }
+
+        // Check tags, any of which may match
+        if let Some(ref tags) = query.tag_filter {
+            if !tags.iter().any(|tag| ctx.metadata.tags.contains(tag)) {
+                return false;
+            }
+        }
+
+        // Check source
+        if let Some(ref source) = query.source_filter {
+            if &ctx.metadata.source != source {
+                return false;
+            }
+        }
+
+        // Check importance
+        if let Some(min_importance) = query.min_importance {
+            if ctx.metadata.importance < min_importance {
+                return false;
+            }
+        }
+
+        // Check age
+        if let Some(max_age) = query.max_age_seconds {
+            if ctx.age_seconds() > max_age {
+                return false;
+            }
+        }
+
+        // Check verified status
+        if query.verified_only && !ctx.metadata.verified {
+            return false;
+        }
+
+        // Check content pattern
+        if let Some(ref regex) = query.content_regex {
+            if !regex.is_match(&ctx.content) {
+                return false;
+            }
+        }
+
+        // Check structured data
+        if let Some(ref filter) = query.data_path_filter {
+            if !filter.matches(ctx) {
+                return false;
+            }
+        }
+
+        // Check language
+        if let Some(ref language) = query.language_filter {
+            if ctx.metadata.language.as_ref() != Some(language) {
+                return false;
+            }
+        }
+
+        // Check text query (simple contains for now)
+        if let Some(ref text) = query.query {
+            if !ctx.content.to_lowercase().contains(&text.to_lowercase()) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Check that the domain index agrees with the stored contexts
+    ///
+    /// Reports index entries whose context is missing, filed under the wrong
+    /// domain or expired, and stored contexts missing from the index. Nothing
+    /// is repaired; use [`Self::rebuild_indexes`] for that.
+    pub async fn assert_domain_invariants(&self) -> Result<Vec<InvariantViolation>> {
+        self.ensure_indexes_loaded().await?;
+        let domain_idx = self.domain_index.read().await.clone();
+        let mut violations = Vec::new();
+        let mut indexed: HashSet<ContextId> = HashSet::new();
+
+        for (domain, ids) in &domain_idx {
+            for id in ids {
+                indexed.insert(id.clone());
+                match self.peek(id).await? {
+                    None => violations.push(InvariantViolation {
+                        kind: ViolationKind::DanglingIndexEntry,
+                        context_id: Some(id.clone()),
+                        description: format!(
+                            "indexed under {:?} but not in the cache or on disk",
+                            domain
+                        ),
+                    }),
+                    Some(ctx) if &ctx.domain != domain => violations.push(InvariantViolation {
+                        kind: ViolationKind::DomainMismatch,
+                        context_id: Some(id.clone()),
+                        description: format!(
+                            "indexed under {} but stored with domain {}",
+                            domain, ctx.domain
+                        ),
+                    }),
+                    Some(ctx) if ctx.is_expired() => violations.push(InvariantViolation {
+                        kind: ViolationKind::ExpiredInIndex,
+                        context_id: Some(id.clone()),
+                        description: format!(
+                            "expired at {} but still indexed",
+                            ctx.expires_at.map(|e| e.to_rfc3339()).unwrap_or_default()
+                        ),
+                    }),
+                    Some(_) => {}
+                }
+            }
+        }
+
+        for ctx in self.all_contexts().await? {
+            if !indexed.contains(&ctx.id) {
+                violations.push(InvariantViolation {
+                    kind: ViolationKind::UnindexedContext,
+                    context_id: Some(ctx.id),
+                    description: format!("stored with domain {} but not indexed", ctx.domain),
+                });
+            }
+        }
+
+        Ok(violations)
+    }
+
+    /// Get operation counts since the last [`Self::reset_counters`]
+    ///
+    /// `ratio` is `reads / (writes + deletes)`, treating a window without
+    /// writes or deletes as having one so the value stays finite.
+    pub fn get_read_write_ratio(&self) -> Result<ReadWriteRatio> {
+        let counts = self.counters.snapshot();
+        Ok(ReadWriteRatio {
+            reads: counts.gets,
+            writes: counts.stores,
+            deletes: counts.deletes,
+            window_secs: counts.window_secs,
+            ratio: counts.gets as f64 / (counts.stores + counts.deletes).max(1) as f64,
+        })
+    }
+
+    /// Zero the operation counters and start a new window
+    pub fn reset_counters(&self) {
+        self.counters.reset();
+    }
+
+    /// Get storage statistics
+    ///
+    /// Only the cheap counters are filled in; see [`Self::stats_with`] for
+    /// the optional sections.
+    pub async fn stats(&self) -> StorageStats {
+        let (memory_count, pinned_count, cache_bytes, evictions) = {
+            let cache = self.memory_cache.read().await;
+            (
+                cache.len(),
+                cache.pinned_len(),
+                cache.bytes(),
+                cache.evictions(),
+            )
+        };
+        // Stats are best-effort; an unreadable expiry tree reports nothing pending
+        let expired_pending_cleanup = match self.expired_entries(Utc::now()).await {
+            Ok(entries) => entries.len(),
+            Err(e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %e, "failed to count expired contexts");
+                let _ = e;
+                0
+            }
+        };
+
+        #[cfg(feature = "persistence")]
+        let disk_count = self.disk_store.as_ref().map(|db| db.len()).unwrap_or(0);
+
+        #[cfg(not(feature = "persistence"))]
+        let disk_count = 0;
+
+        let counts = self.counters.snapshot();
+        let cache_hit_rate =
+            (counts.gets > 0).then(|| counts.cache_hits as f64 / counts.gets as f64);
+
+        StorageStats {
+            memory_count,
+            pinned_count,
+            disk_count,
+            cache_capacity: self.config.memory_cache_size,
+            cache_hit_rate,
+            cache_hits: counts.cache_hits,
+            cache_misses: counts.gets.saturating_sub(counts.cache_hits),
+            expired_pending_cleanup,
+            cache_bytes,
+            max_cache_bytes: self.config.max_cache_bytes,
+            evictions,
+            memory_bytes: None,
+            disk_bytes: None,
+            counts_by_domain: None,
+        }
+    }
+
+    /// Get storage statistics including the requested optional sections
+    ///
+    /// `sizes` asks sled for its size on disk; `domains` reads every stored
+    /// context, including those only on disk.
+    pub async fn stats_with(&self, include: StatsInclude) -> Result<StorageStats> {
+        let mut stats = self.stats().await;
+
+        if include.sizes {
+            stats.memory_bytes = Some(stats.cache_bytes);
+
+            #[cfg(feature = "persistence")]
+            if let Some(ref db) = self.disk_store {
+                stats.disk_bytes = Some(db.size_on_disk().with_operation("stats", None)?);
+            }
+        }
+
+        if include.domains {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for ctx in self.all_contexts().await? {
+                *counts.entry(ctx.domain.to_string()).or_default() += 1;
+            }
+            stats.counts_by_domain = Some(counts);
+        }
+
+        Ok(stats)
+    }
+
+    /// Summarize how content length, in characters, is distributed
+    ///
+    /// Covers every stored context, expired or not. Persisted records are
+    /// read one at a time and only their content is decoded.
+    pub async fn get_context_size_histogram(&self) -> Result<SizeHistogram> {
+        let mut sizes: Vec<usize> = Vec::new();
+        let cached: HashSet<ContextId> = {
+            let cache = self.memory_cache.read().await;
+            for (_, ctx) in cache.iter() {
+                sizes.push(ctx.content.chars().count());
+            }
+            cache.iter().map(|(id, _)| id.clone()).collect()
+        };
+
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            for entry in db.iter() {
+                let (key, value) = entry.with_operation("size_histogram", None)?;
+                let id = ContextId::from_string(String::from_utf8_lossy(&key).into_owned());
+                if cached.contains(&id) {
+                    continue;
+                }
+                let record: ContentOnly =
+                    record::decode(&value).with_operation("size_histogram", Some(&id))?;
+                sizes.push(record.content.chars().count());
+            }
+        }
+        #[cfg(not(feature = "persistence"))]
+        drop(cached);
+
+        Ok(SizeHistogram::from_sizes(sizes))
+    }
+
+    /// Measure how much of the domain and tag indexes points at stored contexts
+    ///
+    /// Totals are exact. Only every n-th entry is checked against the store,
+    /// with n set by [`StorageConfig::index_stats_sample_fraction`], so the
+    /// live ratios and wasted bytes are estimates.
+    pub async fn dump_index_statistics(&self) -> Result<IndexStatistics> {
+        self.ensure_indexes_loaded().await?;
+        let fraction = self.config.index_stats_sample_fraction;
+        let stride = if fraction > 0.0 && fraction <= 1.0 {
+            (1.0 / fraction).round() as usize
+        } else {
+            1
+        };
+
+        let (domain_total, domain_sample) = {
+            let domain_idx = self.domain_index.read().await;
+            let total = domain_idx.values().map(HashSet::len).sum::<usize>();
+            let sample: Vec<ContextId> = domain_idx
+                .values()
+                .flatten()
+                .step_by(stride)
+                .cloned()
+                .collect();
+            (total, sample)
+        };
+        let (tag_total, tag_sample) = {
+            let tag_idx = self.tag_index.read().await;
+            let total = tag_idx.values().map(HashSet::len).sum::<usize>();
+            let sample: Vec<ContextId> = tag_idx
+                .values()
+                .flatten()
+                .step_by(stride)
+                .cloned()
+                .collect();
+            (total, sample)
+        };
+
+        let domain_live_ratio = self.live_ratio(&domain_sample).await?;
+        let tag_live_ratio = self.live_ratio(&tag_sample).await?;
+
+        Ok(IndexStatistics {
+            domain_index_total_entries: domain_total,
+            domain_index_live_ratio: domain_live_ratio,
+            tag_index_total_entries: tag_total,
+            tag_index_live_ratio: tag_live_ratio,
+            estimated_wasted_bytes: wasted_bytes(domain_total, domain_live_ratio, &domain_sample)
+                + wasted_bytes(tag_total, tag_live_ratio, &tag_sample),
+        })
+    }
+
+    /// Share of `ids` still stored in the cache or on disk (1.0 when empty)
+    async fn live_ratio(&self, ids: &[ContextId]) -> Result<f64> {
+        if ids.is_empty() {
+            return Ok(1.0);
+        }
+
+        let mut live = 0;
+        for id in ids {
+            let cached = self.memory_cache.read().await.contains(id);
+            #[cfg(feature = "persistence")]
+            let stored = match self.disk_store {
+                Some(ref db) => {
+                    cached
+                        || db
+                            .contains_key(id.as_str().as_bytes())
+                            .with_operation("index_statistics", Some(id))?
+                }
+                None => cached,
+            };
+            #[cfg(not(feature = "persistence"))]
+            let stored = cached;
+
+            if stored {
+                live += 1;
+            }
+        }
+        Ok(live as f64 / ids.len() as f64)
+    }
+
+    /// Get persisted bytes per domain
+    ///
+    /// Walks the sled tree and only deserializes the `domain` field of each
+    /// record, skipping bincode embeddings entirely; the byte count is the
+    /// length of the stored value. The JSON part of a record is still parsed
+    /// in full, and a compressed record decompressed up to its end. Returns an empty
+    /// map when persistence is disabled.
+    pub fn get_disk_usage_by_domain(&self) -> Result<HashMap<String, usize>> {
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            let mut usage: HashMap<String, usize> = HashMap::new();
+            for entry in db.iter() {
+                let (_, value) = entry.with_operation("disk_usage_by_domain", None)?;
+                let record: DomainOnly = record::decode_without_embedding(&value)
+                    .with_operation("disk_usage_by_domain", None)?;
+                *usage.entry(record.domain.to_string()).or_default() += value.len();
+            }
+            return Ok(usage);
+        }
+
+        Ok(HashMap::new())
+    }
+
+    /// Copy the sled files to a new timestamped checkpoint directory
+    ///
+    /// Flushes sled, then copies its files into
+    /// `<persist_path>/checkpoints/<timestamp>/` and returns that path.
+    /// Fails when persistence is disabled.
+    pub fn create_sled_checkpoint(&self) -> Result<PathBuf> {
+        self.checkpoint_into(&self.config.persist_dir().join(CHECKPOINT_DIR))
+            .with_operation("checkpoint", None)
+    }
+
+    /// Flush sled and copy its files into a new timestamped directory in `dir`
+    fn checkpoint_into(&self, dir: &Path) -> Result<PathBuf> {
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            db.flush()?;
+
+            let root = self.config.persist_dir();
+            std::fs::create_dir_all(dir)?;
+
+            let stamp = timestamp();
+            let mut target = dir.join(&stamp);
+            let mut n = 1;
+            while target.exists() {
+                target = dir.join(format!("{}-{}", stamp, n));
+                n += 1;
+            }
+
+            // Never copy earlier checkpoints or snapshots into the new one
+            let mut skip = vec![std::ffi::OsStr::new(CHECKPOINT_DIR)];
+            if let Some(std::path::Component::Normal(name)) = dir
+                .strip_prefix(&root)
+                .ok()
+                .and_then(|rel| rel.components().next())
+            {
+                skip.push(name);
+            }
+            copy_dir(&root, &target, &skip)?;
+            return Ok(target);
+        }
+
+        let _ = dir;
+        Err(ContextError::Config(
+            "Checkpoints require disk persistence".into(),
+        ))
+    }
+
+    /// Take a snapshot under the configured [`SnapshotPolicy`] now
+    ///
+    /// Writes a checkpoint into the policy's directory, deletes snapshots
+    /// beyond `keep_last`, and records the outcome in
+    /// [`ContextStore::snapshot_status`] and the observers. Blocks on file
+    /// I/O, so async callers should use `spawn_blocking`.
+    pub fn take_snapshot(&self) -> Result<SnapshotInfo> {
+        let policy = self
+            .config
+            .snapshot
+            .as_ref()
+            .ok_or_else(|| ContextError::Config("No snapshot policy is configured".into()))?;
+
+        let started = Instant::now();
+        let result = self.checkpoint_into(&policy.directory).and_then(|path| {
+            let info = SnapshotInfo::read(&path)?;
+            self.prune_snapshots()?;
+            Ok(info)
+        });
+        let elapsed = started.elapsed();
+
+        let mut status = self
+            .snapshot_status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        status.last_attempt = Some(Utc::now());
+        status.last_duration_ms = elapsed.as_millis() as u64;
+        match result {
+            Ok(ref info) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!(
+                    snapshot = %info.name,
+                    size_bytes = info.size_bytes,
+                    duration_ms = status.last_duration_ms,
+                    "snapshot taken"
+                );
+                status.last_snapshot = Some(info.name.clone());
+                status.last_size_bytes = info.size_bytes;
+                status.last_error = None;
+                status.consecutive_failures = 0;
+            }
+            Err(ref e) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!(
+                    error = %e,
+                    duration_ms = status.last_duration_ms,
+                    "snapshot failed"
+                );
+                status.last_error = Some(e.to_string());
+                status.consecutive_failures += 1;
+            }
+        }
+        drop(status);
+
+        let bytes = result.as_ref().map_or(0, |info| info.size_bytes);
+        self.observe(|o| o.on_snapshot(elapsed, bytes, result.is_ok()));
+        result.with_operation("snapshot", None)
+    }
+
+    /// Snapshots in the configured directory, oldest first
+    ///
+    /// Empty when no policy is configured or nothing was snapshotted yet.
+    pub fn list_snapshots(&self) -> Result<Vec<SnapshotInfo>> {
+        match self.config.snapshot {
+            Some(ref policy) => list_snapshots_in(&policy.directory),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Delete the oldest snapshots beyond the policy's `keep_last`
+    ///
+    /// Returns the names of the deleted snapshots.
+    pub fn prune_snapshots(&self) -> Result<Vec<String>> {
+        let Some(ref policy) = self.config.snapshot else {
+            return Ok(Vec::new());
+        };
+        let snapshots = list_snapshots_in(&policy.directory)?;
+        let excess = snapshots.len().saturating_sub(policy.keep_last);
+
+        let mut pruned = Vec::with_capacity(excess);
+        for snapshot in snapshots.into_iter().take(excess) {
+            std::fs::remove_dir_all(&snapshot.path).with_operation("prune_snapshots", None)?;
+            pruned.push(snapshot.name);
+        }
+        Ok(pruned)
+    }
+
+    /// Outcome of the most recent snapshots
+    pub fn snapshot_status(&self) -> SnapshotStatus {
+        self.snapshot_status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Flush to disk at the [`FlushPolicy::Interval`] until the store is dropped
+    ///
+    /// Returns `None` under any other policy. A failed flush is logged and
+    /// retried at the next tick.
+    pub fn spawn_flush_task(store: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let FlushPolicy::Interval(interval) = store.config.flush_policy else {
+            return None;
+        };
+        let store = Arc::downgrade(store);
+
+        Some(tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let Some(store) = store.upgrade() else {
+                    break;
+                };
+                if let Err(e) = store.flush().await {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!(error = %e, "periodic flush failed");
+                    let _ = e;
+                }
+            }
+        }))
+    }
+
+    /// Remove expired contexts every `cleanup_interval_secs` until the store is dropped
+    ///
+    /// Returns `None` unless [`StorageConfig::auto_cleanup`] is set. The first
+    /// cleanup runs one interval after the call; abort the returned handle to
+    /// stop earlier. A failed cleanup is logged and retried at the next tick.
+    pub fn spawn_cleanup_task(store: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !store.config.auto_cleanup {
+            return None;
+        }
+        let interval = std::time::Duration::from_secs(store.config.cleanup_interval_secs);
+        let store = Arc::downgrade(store);
+
+        Some(tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let Some(store) = store.upgrade() else {
+                    break;
+                };
+                match store.cleanup_expired().await {
+                    Ok(removed) => {
+                        #[cfg(feature = "tracing")]
+                        if removed > 0 {
+                            tracing::info!(removed, "removed expired contexts");
+                        }
+                        let _ = removed;
+                    }
+                    Err(e) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::error!(error = %e, "periodic cleanup failed");
+                        let _ = e;
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Take snapshots every policy interval until the store is dropped
+    ///
+    /// Returns `None` when no snapshot policy is configured. The first
+    /// snapshot is taken one interval after the call. A failed snapshot is
+    /// logged and recorded in [`ContextStore::snapshot_status`], and the task
+    /// carries on with the next one.
+    pub fn spawn_snapshot_task(store: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let interval = store.config.snapshot.as_ref()?.interval;
+        let store = Arc::downgrade(store);
+
+        Some(tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let Some(store) = store.upgrade() else {
+                    break;
+                };
+                // Outcomes are recorded by take_snapshot; only a panic ends up here
+                if let Err(e) = tokio::task::spawn_blocking(move || store.take_snapshot()).await {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!(error = %e, "snapshot task panicked");
+                    let _ = e;
+                }
+            }
+        }))
+    }
+
+    /// Replace the persisted contexts with the contents of a checkpoint
+    ///
+    /// The checkpoint is copied aside and opened as a separate database so it
+    /// stays reusable; its trees then replace the live ones, merge redirects
+    /// and the audit log included, and the memory cache, redirects, event
+    /// sequence and indexes are reloaded from disk. Callers must ensure
+    /// nothing writes to the store while the restore runs.
+    pub async fn restore_from_checkpoint(&self, path: &Path) -> Result<()> {
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            if !path.is_dir() {
+                return Err(ContextError::NotFound(format!(
+                    "Checkpoint not found: {}",
+                    path.display()
+                )));
+            }
+
+            let scratch = self
+                .config
+                .persist_dir()
+                .join(CHECKPOINT_DIR)
+                .join(format!(".restore-{}", uuid::Uuid::new_v4()));
+            copy_dir(path, &scratch, &[]).with_operation("restore_checkpoint", None)?;
+
+            let copied = (|| -> Result<()> {
+                let source = sled::open(&scratch)?;
+                // Trees the checkpoint lacks end up empty; the time trees
+                // are refilled by the index rebuild below
+                for name in db.tree_names() {
+                    db.open_tree(name)?.clear()?;
+                }
+                for name in source.tree_names() {
+                    let live = db.open_tree(&name)?;
+                    for entry in source.open_tree(&name)?.iter() {
+                        let (key, value) = entry?;
+                        live.insert(key, value)?;
+                    }
+                }
+                Ok(())
+            })();
+            std::fs::remove_dir_all(&scratch).with_operation("restore_checkpoint", None)?;
+            copied.with_operation("restore_checkpoint", None)?;
+            db.flush_async()
+                .await
+                .with_operation("restore_checkpoint", None)?;
+
+            *self.redirects.write().await =
+                load_redirects(db).with_operation("restore_checkpoint", None)?;
+            let next_seq = last_audit_seq(db).with_operation("restore_checkpoint", None)? + 1;
+            self.audit
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .next_seq = next_seq;
+            self.memory_cache.write().await.clear();
+            self.rebuild_indexes().await?;
+            return Ok(());
+        }
+
+        let _ = path;
+        Err(ContextError::Config(
+            "Checkpoints require disk persistence".into(),
+        ))
+    }
+
+    /// Rebuild the domain, tag and importance indexes from every stored context
+    ///
+    /// Returns the number of contexts indexed.
+    pub async fn rebuild_indexes(&self) -> Result<usize> {
+        let contexts = self.all_contexts().await?;
+
+        self.domain_index.write().await.clear();
+        self.tag_index.write().await.clear();
+        self.source_index.write().await.clear();
+        self.importance_index.write().await.clear();
+        self.thread_index.write().await.clear();
+        self.access_index.write().await.clear();
+        self.created_index.write().await.clear();
+        self.expiry_index.write().await.clear();
+        self.content_hash_index.write().await.clear();
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            for tree in [CREATED_TREE, EXPIRY_TREE] {
+                db.open_tree(tree)
+                    .and_then(|tree| tree.clear())
+                    .with_operation("rebuild_indexes", None)?;
+            }
+        }
+
+        for ctx in &contexts {
+            self.index(ctx).await;
+        }
+        self.indexes_complete
+            .store(true, std::sync::atomic::Ordering::Release);
+        Ok(contexts.len())
+    }
+
+    /// Multiply the importance of contexts idle for longer than `idle` by `factor`
+    ///
+    /// Each call applies one decay step. Expired contexts are skipped.
+    /// Returns the number of contexts whose importance changed.
+    pub async fn decay_importance(&self, factor: f32, idle: Duration) -> Result<usize> {
+        let cutoff = Utc::now() - idle;
+        let mut changed = 0;
+
+        for mut ctx in self.all_contexts().await? {
+            if ctx.accessed_at >= cutoff || ctx.is_expired() {
+                continue;
+            }
+
+            let decayed = (ctx.metadata.importance * factor).clamp(0.0, 1.0);
+            if (decayed - ctx.metadata.importance).abs() > f32::EPSILON {
+                ctx.metadata.importance = decayed;
+                self.store(ctx).await?;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Recompute importance scores with a named decay strategy
+    ///
+    /// Applies to every live context, or only those in `domain` when given.
+    /// With `dry_run` nothing is written. Returns the number of contexts
+    /// whose importance changes (or would change).
+    pub async fn apply_decay_strategy(
+        &self,
+        strategy: DecayStrategy,
+        domain: Option<&ContextDomain>,
+        dry_run: bool,
+    ) -> Result<usize> {
+        strategy.validate()?;
+        let now = Utc::now();
+        let mut changed = 0;
+
+        for mut ctx in self.all_contexts().await? {
+            if ctx.is_expired() || domain.is_some_and(|domain| &ctx.domain != domain) {
+                continue;
+            }
+
+            let importance = strategy.apply(&ctx, now);
+            if (importance - ctx.metadata.importance).abs() > f32::EPSILON {
+                changed += 1;
+                if !dry_run {
+                    ctx.metadata.importance = importance;
+                    self.store(ctx).await?;
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Extend every expiration closer than `min_ttl` to `now + min_ttl`
+    ///
+    /// Only contexts with an `expires_at` are considered; contexts that have
+    /// expired but not yet been cleaned up are extended too. Returns the
+    /// number of contexts modified.
+    pub async fn enforce_ttl_minimum(&self, min_ttl: Duration) -> Result<usize> {
+        let deadline = Utc::now() + min_ttl;
+        let mut modified = 0;
+
+        for mut ctx in self.all_contexts().await? {
+            if matches!(ctx.expires_at, Some(expires_at) if expires_at < deadline) {
+                ctx.expires_at = Some(deadline);
+                self.store(ctx).await?;
+                modified += 1;
+            }
+        }
+        Ok(modified)
+    }
+
+    /// Shorten every expiration further than `max_ttl` away to `now + max_ttl`
+    ///
+    /// Contexts without an `expires_at` would otherwise be retained forever,
+    /// so they are given one as well, except pinned ones. Returns the number
+    /// of contexts modified.
+    pub async fn enforce_ttl_maximum(&self, max_ttl: Duration) -> Result<usize> {
+        let deadline = Utc::now() + max_ttl;
+        let mut modified = 0;
+
+        for mut ctx in self.all_contexts().await? {
+            if ctx
+                .expires_at
+                .map(|exp| exp > deadline)
+                .unwrap_or(!ctx.metadata.pinned)
+            {
+                ctx.expires_at = Some(deadline);
+                self.store(ctx).await?;
+                modified += 1;
+            }
+        }
+        Ok(modified)
+    }
+
+    /// Move contexts created more than `max_age` ago out of the store
+    ///
+    /// Matching contexts are written as JSON lines to
+    /// `<persist_path>/archive/<timestamp>.jsonl` and then deleted. Returns the
+    /// number archived and the archive file, if one was written. Fails when
+    /// persistence is disabled.
+    pub async fn archive_older_than(&self, max_age: Duration) -> Result<(usize, Option<PathBuf>)> {
+        if !self.has_disk_store() {
+            return Err(ContextError::Config(
+                "Archiving requires disk persistence".into(),
+            ));
+        }
+
+        let cutoff = Utc::now() - max_age;
+        let old: Vec<Context> = self
+            .all_contexts()
+            .await?
+            .into_iter()
+            .filter(|ctx| ctx.created_at < cutoff)
+            .collect();
+
+        if old.is_empty() {
+            return Ok((0, None));
+        }
+
+        let dir = self.config.persist_dir().join(ARCHIVE_DIR);
+        let path = dir.join(format!("{}.jsonl", timestamp()));
+
+        let write_archive = || -> Result<()> {
+            use std::io::Write;
+            std::fs::create_dir_all(&dir)?;
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(&path)?);
+            for ctx in &old {
+                serde_json::to_writer(&mut writer, ctx)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+            Ok(())
+        };
+        write_archive().with_operation("archive", None)?;
+
+        let ids: Vec<ContextId> = old.into_iter().map(|ctx| ctx.id).collect();
+        let archived = self.delete_batch(&ids).await?;
+        Ok((archived, Some(path)))
+    }
+
+    /// Whether a sled database is open
+    #[cfg(feature = "persistence")]
+    fn has_disk_store(&self) -> bool {
+        self.disk_store.is_some()
+    }
+
+    /// Whether a sled database is open (never without persistence)
+    #[cfg(not(feature = "persistence"))]
+    fn has_disk_store(&self) -> bool {
+        false
+    }
+
+    /// Expiry index entries, in memory and in sled, that expire before `now`
+    ///
+    /// One entry per context, soonest first; entries whose context has since
+    /// been deleted or given a later expiry may still be included. Sled keys
+    /// hold microseconds, so the in-memory timestamp wins when both exist.
+    async fn expired_entries(&self, now: DateTime<Utc>) -> Result<Vec<(DateTime<Utc>, ContextId)>> {
+        let mut entries: BTreeMap<ContextId, DateTime<Utc>> = BTreeMap::new();
+        for (expires_at, ids) in self.expiry_index.read().await.range(..now) {
+            entries.extend(ids.iter().map(|id| (id.clone(), *expires_at)));
+        }
+        #[cfg(feature = "persistence")]
+        if let Some(ref db) = self.disk_store {
+            let tree = db
+                .open_tree(EXPIRY_TREE)
+                .with_operation("expired_entries", None)?;
+            for entry in tree.range(..timestamp_prefix(now)) {
+                let (key, _) = entry.with_operation("expired_entries", None)?;
+                if let Some((expires_at, id)) = parse_timestamp_key(&key) {
+                    entries.entry(id).or_insert(expires_at);
+                }
+            }
+        }
+        let mut entries: Vec<(DateTime<Utc>, ContextId)> = entries
+            .into_iter()
+            .map(|(id, expires_at)| (expires_at, id))
+            .collect();
+        entries.sort();
+        Ok(entries)
+    }
+
+    /// Cleanup expired contexts
+    ///
+    /// Candidates come from the expiry index and, for persisted stores, the
+    /// sled expiry tree, which also covers contexts evicted from the memory
+    /// cache or written before the last restart. Only entries expiring
+    /// before now are visited, so contexts without a TTL cost nothing.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(removed = tracing::field::Empty))
+    )]
+    pub async fn cleanup_expired(&self) -> Result<usize> {
+        let mut removed = 0;
+        let now = Utc::now();
+
+        for (expires_at, id) in self.expired_entries(now).await? {
+            #[cfg(test)]
+            self.cleanup_checked
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            match self.peek(&id).await? {
+                // The TTL may have been extended since the entry was indexed
+                Some(ctx) if !ctx.expires_at.is_some_and(|exp| now > exp) => {}
+                Some(_) => {
+                    if self.delete(&id).await? {
+                        removed += 1;
+                    }
+                }
+                // Stale entry left behind by a failed write
+                None => self.unindex_expiry(expires_at, &id).await,
+            }
+        }
+
+        telemetry::record("removed", removed);
+        Ok(removed)
+    }
+}
+
+/// Strategy for recomputing importance scores within a domain
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RebalanceStrategy {
+    /// Keep the top fraction (by current importance, then recency) at 1.0
+    /// and scale the rest down linearly by rank
+    Percentile { top_frac: f32 },
+    /// Scale by access count relative to the most accessed context
+    AccessBased,
+    /// Scale linearly from 1.0 for the newest context to 0.0 for the oldest
+    AgeBased,
+}
+
+impl RebalanceStrategy {
+    /// Compute new importance scores, one per context in the (possibly reordered) slice
+    fn compute(&self, contexts: &mut [Context]) -> Vec<f32> {
+        let n = contexts.len();
+        match *self {
+            Self::Percentile { top_frac } => {
+                contexts.sort_by(|a, b| {
+                    b.metadata
+                        .importance
+                        .total_cmp(&a.metadata.importance)
+                        .then_with(|| b.accessed_at.cmp(&a.accessed_at))
+                });
+                let top = (n as f32 * top_frac.clamp(0.0, 1.0)).ceil() as usize;
+                (0..n)
+                    .map(|rank| {
+                        if rank < top {
+                            1.0
+                        } else {
+                            (n - rank) as f32 / n as f32
+                        }
+                    })
+                    .collect()
+            }
+            Self::AccessBased => {
+                let max = contexts.iter().map(|c| c.access_count).max().unwrap_or(0);
+                contexts
+                    .iter()
+                    .map(|c| (c.access_count + 1) as f32 / (max + 1) as f32)
+                    .collect()
+            }
+            Self::AgeBased => {
+                let newest = contexts.iter().map(|c| c.created_at).max();
+                let oldest = contexts.iter().map(|c| c.created_at).min();
+                match (newest, oldest) {
+                    (Some(newest), Some(oldest)) if newest > oldest => {
+                        let span = (newest - oldest).num_milliseconds() as f64;
+                        contexts
+                            .iter()
+                            .map(|c| {
+                                let age = (newest - c.created_at).num_milliseconds() as f64;
+                                (1.0 - age / span) as f32
+                            })
+                            .collect()
+                    }
+                    _ => vec![1.0; n],
+                }
+            }
+        }
+    }
+}
+
+/// Named strategy for [`ContextStore::apply_decay_strategy`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DecayStrategy {
+    /// Halve importance for every `half_life_hours` of age
+    ExponentialByAge { half_life_hours: f64 },
+    /// Scale importance by the share of the recent-access ring that falls
+    /// within the last `window_hours`, so a context accessed
+    /// [`RECENT_ACCESS_LIMIT`] times in the window keeps its importance
+    MultiplyByAccessRate { window_hours: u64 },
+    /// Zero the importance of contexts older than `threshold_hours`
+    SetToZeroIfOlderThan { threshold_hours: f64 },
+}
+
+impl DecayStrategy {
+    /// Check the strategy parameters
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Self::ExponentialByAge { half_life_hours }
+                if !(half_life_hours.is_finite() && half_life_hours > 0.0) =>
+            {
+                Err(ContextError::InvalidQuery(format!(
+                    "half_life_hours must be positive, got {}",
+                    half_life_hours
+                )))
+            }
+            Self::SetToZeroIfOlderThan { threshold_hours }
+                if !(threshold_hours.is_finite() && threshold_hours >= 0.0) =>
+            {
+                Err(ContextError::InvalidQuery(format!(
+                    "threshold_hours must be non-negative, got {}",
+                    threshold_hours
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// New importance for a context as of `now`
+    fn apply(&self, ctx: &Context, now: DateTime<Utc>) -> f32 {
+        let importance = ctx.metadata.importance;
+        let age_hours = (now - ctx.created_at).num_milliseconds().max(0) as f64 / 3_600_000.0;
+
+        let decayed = match *self {
+            Self::ExponentialByAge { half_life_hours } => {
+                (importance as f64 * 0.5_f64.powf(age_hours / half_life_hours)) as f32
+            }
+            Self::MultiplyByAccessRate { window_hours } => {
+                // A window too large to represent covers every recorded access
+                let since = i64::try_from(window_hours)
+                    .ok()
+                    .and_then(Duration::try_hours)
+                    .and_then(|window| now.checked_sub_signed(window))
+                    .unwrap_or(DateTime::<Utc>::MIN_UTC);
+                let recent = ctx
+                    .recent_accesses
+                    .iter()
+                    .filter(|&&at| at >= since)
+                    .count();
+                importance * (recent as f32 / RECENT_ACCESS_LIMIT as f32).min(1.0)
+            }
+            Self::SetToZeroIfOlderThan { threshold_hours } => {
+                if age_hours > threshold_hours {
+                    0.0
+                } else {
+                    importance
+                }
+            }
+        };
+        decayed.clamp(0.0, 1.0)
+    }
+}
+
+/// Importance score with a total order, usable as a `BTreeMap` key
+///
+/// Ordering follows [`f32::total_cmp`], so NaN sorts above every real score.
+#[derive(Debug, Clone, Copy)]
+struct ImportanceKey(f32);
+
+impl PartialEq for ImportanceKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for ImportanceKey {}
+
+impl PartialOrd for ImportanceKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ImportanceKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Serialize a context for persistence as `config` asks, behind a [`record`] envelope
+#[cfg_attr(not(feature = "persistence"), allow(dead_code))]
+fn encode_context(context: &Context, config: &StorageConfig) -> Result<Vec<u8>> {
+    let level = config
+        .enable_compression
+        .then_some(config.compression_level);
+    record::encode_with(context, config.record_format, level)
+}
+
+/// Deserialize a persisted context, enveloped or legacy bare JSON
+#[cfg_attr(not(feature = "persistence"), allow(dead_code))]
+fn decode_context(bytes: &[u8]) -> Result<Context> {
+    record::decode(bytes)
+}
+
+/// Fill in the language of a prose context that has none
+///
+/// Code contexts and content that does not read as prose are left alone,
+/// as is content whose language is not clearly detected.
+fn detect_language(context: &mut Context) {
+    if context.metadata.language.is_some()
+        || context.domain == ContextDomain::Code
+        || !language::is_prose(&context.content)
+    {
+        return;
+    }
+    context.metadata.language = language::detect(&context.content).map(str::to_string);
+}
+
+/// Carry the revision history of `previous` over to its replacement
+///
+/// A new revision is recorded only when content, domain or metadata
+/// changed; re-storing an identical context keeps the version as is,
+/// except that an unversioned (version 0) context becomes version 1.
+fn record_revision(previous: &Context, context: &mut Context) {
+    context.history = previous.history.clone();
+    context.version = previous.version.max(1);
+    if previous.revision_differs(context) {
+        context.history.push(previous.snapshot());
+        if context.history.len() > MAX_REVISIONS {
+            let excess = context.history.len() - MAX_REVISIONS;
+            context.history.drain(..excess);
+        }
+        context.version = previous.version + 1;
+    }
+}
+
+/// Serialize `ctx` into `buf` and write it as one line
+async fn write_json_line(
+    writer: &mut (impl AsyncWrite + Unpin),
+    buf: &mut Vec<u8>,
+    ctx: &Context,
+) -> Result<()> {
+    buf.clear();
+    serde_json::to_writer(&mut *buf, ctx)?;
+    buf.push(b'\n');
+    writer.write_all(buf).await?;
+    Ok(())
+}
+
+/// Contexts copied per batch by [`ContextStore::clone_to_new_store`]
+pub const MIGRATION_CHUNK_SIZE: usize = 500;
+
+/// Sled tree holding merge redirects
+#[cfg(feature = "persistence")]
+const REDIRECT_TREE: &str = "redirects";
+
+/// Load merge redirects persisted by [`ContextStore::merge`]
+#[cfg(feature = "persistence")]
+fn load_redirects(db: &sled::Db) -> Result<HashMap<ContextId, ContextId>> {
+    let mut redirects = HashMap::new();
+    for entry in db.open_tree(REDIRECT_TREE)?.iter() {
+        let (from, to) = entry?;
+        redirects.insert(
+            ContextId::from_string(String::from_utf8_lossy(&from).into_owned()),
+            ContextId::from_string(String::from_utf8_lossy(&to).into_owned()),
+        );
+    }
+    Ok(redirects)
+}
+
+/// Candidate IDs for a query, from [`ContextStore::get_candidate_ids`]
+struct Candidates {
+    /// Sorted IDs to look up
+    ids: Vec<ContextId>,
+    /// Whether sled may hold matches the indexes missed
+    scan_disk: bool,
+}
+
+/// IDs in both sets
+fn intersect_ids(a: HashSet<ContextId>, b: HashSet<ContextId>) -> HashSet<ContextId> {
+    let (smaller, larger) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    smaller
+        .into_iter()
+        .filter(|id| larger.contains(id))
+        .collect()
+}
+
+/// Sled tree indexing contexts by creation time; see [`timestamp_key`]
+#[cfg_attr(not(feature = "persistence"), allow(dead_code))]
+const CREATED_TREE: &str = "created";
+
+/// Sled tree indexing contexts by expiry; see [`timestamp_key`]
+#[cfg_attr(not(feature = "persistence"), allow(dead_code))]
+const EXPIRY_TREE: &str = "expiry";
+
+/// Sortable key of a timestamp alone, for range scans of the timestamp trees
+#[cfg(feature = "persistence")]
+fn timestamp_prefix(at: DateTime<Utc>) -> [u8; 8] {
+    // Flipping the sign bit makes big-endian order match numeric order
+    ((at.timestamp_micros() as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+/// Key of a context in a timestamp tree: its timestamp prefix, then its ID
+#[cfg(feature = "persistence")]
+fn timestamp_key(at: DateTime<Utc>, id: &ContextId) -> Vec<u8> {
+    let mut key = timestamp_prefix(at).to_vec();
+    key.extend_from_slice(id.as_str().as_bytes());
+    key
+}
+
+/// Timestamp and context ID of a [`timestamp_key`]
+#[cfg(feature = "persistence")]
+fn parse_timestamp_key(key: &[u8]) -> Option<(DateTime<Utc>, ContextId)> {
+    let prefix: [u8; 8] = key.get(..8)?.try_into().ok()?;
+    let at = DateTime::from_timestamp_micros((u64::from_be_bytes(prefix) ^ (1 << 63)) as i64)?;
+    let id = ContextId::from_string(String::from_utf8_lossy(&key[8..]).into_owned());
+    Some((at, id))
+}
+
+/// Build the timestamp trees a database was written without
+#[cfg(feature = "persistence")]
+fn backfill_time_trees(db: &sled::Db) -> Result<()> {
+    let names = db.tree_names();
+    let missing = |tree: &str| !names.iter().any(|name| name.as_ref() == tree.as_bytes());
+    let (fill_created, fill_expiry) = (missing(CREATED_TREE), missing(EXPIRY_TREE));
+    let created = db.open_tree(CREATED_TREE)?;
+    let expiry = db.open_tree(EXPIRY_TREE)?;
+    if !fill_created && !fill_expiry {
+        return Ok(());
+    }
+    for entry in db.iter() {
+        let (_, value) = entry?;
+        // Undecodable records are left for `load_indexes` to report
+        let Ok(ctx) = decode_context(&value) else {
+            continue;
+        };
+        if fill_created {
+            created.insert(timestamp_key(ctx.created_at, &ctx.id), &[])?;
+        }
+        if let (true, Some(expires_at)) = (fill_expiry, ctx.expires_at) {
+            expiry.insert(timestamp_key(expires_at, &ctx.id), &[])?;
+        }
+    }
+    Ok(())
+}
+
+/// Sled tree holding the store event audit log, keyed by big-endian sequence number
+#[cfg(feature = "persistence")]
+const AUDIT_TREE: &str = "audit";
+
+/// Highest sequence number in the persisted audit log, or 0 when empty
+#[cfg(feature = "persistence")]
+fn last_audit_seq(db: &sled::Db) -> Result<u64> {
+    match db.open_tree(AUDIT_TREE)?.last()? {
+        Some((key, _)) => {
+            let bytes: [u8; 8] = key
+                .as_ref()
+                .try_into()
+                .map_err(|_| ContextError::Internal("malformed audit log key".into()))?;
+            Ok(u64::from_be_bytes(bytes))
+        }
+        None => Ok(0),
+    }
+}
+
+/// Attempts made at a disk write before a transient error is returned
+#[cfg(feature = "persistence")]
+const DISK_WRITE_ATTEMPTS: u32 = 4;
+
+/// Wait before the first disk write retry, doubled for each later one
+#[cfg(feature = "persistence")]
+const DISK_RETRY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// Events kept in memory for replay when persistence is off
+const AUDIT_MEMORY_LIMIT: usize = 10_000;
+
+/// Live store events buffered per subscriber before it lags
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Store event sequencing state
+struct AuditLog {
+    /// Sequence number of the next event
+    next_seq: u64,
+    /// Recent events, used for replay when persistence is off
+    memory: VecDeque<StoreEvent>,
+}
+
+/// Name of the checkpoint directory inside the persist path
+#[cfg_attr(not(feature = "persistence"), allow(dead_code))]
+const CHECKPOINT_DIR: &str = "checkpoints";
+
+/// Snapshot directory inside the persist path the CLI uses when none is given
+pub const DEFAULT_SNAPSHOT_DIR: &str = "snapshots";
+
+/// Rough in-memory footprint of a context
+///
+/// Counts the heap data of the content, embedding, source, tags and custom
+/// metadata plus the struct itself; allocator overhead is ignored.
+fn estimated_size(ctx: &Context) -> usize {
+    std::mem::size_of::<Context>()
+        + ctx.id.as_str().len()
+        + ctx.content.len()
+        + ctx.metadata.source.len()
+        + ctx.metadata.tags.iter().map(String::len).sum::<usize>()
+        + ctx
+            .metadata
+            .custom
+            .iter()
+            .map(|(k, v)| k.len() + v.to_string().len())
+            .sum::<usize>()
+        + ctx
+            .embedding
+            .as_ref()
+            .map(|e| e.len() * std::mem::size_of::<f32>())
+            .unwrap_or(0)
+}
+
+/// Name of the archive directory inside the persist path
+const ARCHIVE_DIR: &str = "archive";
+
+/// UTC timestamp used to name checkpoints and archives
+fn timestamp() -> String {
+    Utc::now().format("%Y%m%dT%H%M%S%.6fZ").to_string()
+}
+
+/// Recursively copy a directory, skipping the named top-level entries
+#[cfg_attr(not(feature = "persistence"), allow(dead_code))]
+fn copy_dir(src: &Path, dst: &Path, skip: &[&std::ffi::OsStr]) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        if skip.contains(&entry.file_name().as_os_str()) {
+            continue;
+        }
+
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target, &[])?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Total size of the files under a directory
+fn dir_size(path: &Path) -> std::io::Result<u64> {
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        total += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
+
+/// Cosine similarity between an embedding and a centroid, 0.0 for zero vectors
+fn cosine_similarity(a: &[f32], b: &[f64]) -> f64 {
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        let x = *x as f64;
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+}
+
+/// Find the union-find root of `i`, halving the path on the way
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Match text against a glob pattern supporting `*` and `?`
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen and the text position it is currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Partial view of a persisted context used when only the domain is needed
+#[cfg(feature = "persistence")]
+#[derive(Deserialize)]
+struct DomainOnly {
+    domain: ContextDomain,
+}
+
+/// Result of [`ContextStore::store`]
+#[derive(Debug, Clone)]
+pub struct StoreOutcome {
+    /// ID the context is stored under
+    pub id: ContextId,
+    /// The context previously stored under this ID, if any
+    pub previous: Option<Context>,
+}
+
+impl StoreOutcome {
+    /// Whether a context already existed under this ID
+    pub fn existed(&self) -> bool {
+        self.previous.is_some()
+    }
+}
+
+/// What happened to a context in a [`StoreEvent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreEventKind {
+    /// The context was stored or replaced
+    Stored,
+    /// The context was deleted
+    Deleted,
+}
+
+/// A change to the store, as recorded in the audit log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoreEvent {
+    /// Sequence number, increasing by one per event
+    pub seq: u64,
+    /// What happened
+    pub kind: StoreEventKind,
+    /// Context the event applies to
+    pub id: ContextId,
+    /// When the event was recorded
+    pub at: DateTime<Utc>,
+}
+
+/// Provenance of a context, from [`ContextStore::get_context_lineage`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextLineage {
+    /// The context itself
+    pub context: Context,
+    /// Parent, grandparent and so on, nearest first
+    pub ancestors: Vec<Context>,
+    /// Children, grandchildren and so on, nearest generation first
+    pub descendants: Vec<Context>,
+    /// Earlier versions of the context, oldest first
+    pub revisions: Vec<ContextRevision>,
+}
+
+/// Result of [`ContextStore::reconcile_with_remote`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReconcileStats {
+    /// IDs stored here but not on the remote
+    pub local_only: Vec<ContextId>,
+    /// IDs on the remote but not stored here
+    pub remote_only: Vec<ContextId>,
+    /// IDs present on both sides
+    pub shared: Vec<ContextId>,
+}
+
+/// How [`ContextStore::enforce_source_allowlist`] matches sources
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceMatchMode {
+    /// Each entry is a prefix of the allowed sources; a trailing `*` is ignored
+    #[default]
+    Prefix,
+    /// Entries must match exactly, except those ending in `*`, which match as prefixes
+    Exact,
+}
+
+impl SourceMatchMode {
+    /// Whether `source` matches any entry of the allowlist
+    fn allows(&self, allowed: &[&str], source: &str) -> bool {
+        allowed.iter().any(|entry| match entry.strip_suffix('*') {
+            Some(prefix) => source.starts_with(prefix),
+            None if *self == Self::Prefix => source.starts_with(entry),
+            None => source == *entry,
+        })
+    }
+}
+
+/// How [`ContextStore::merge`] combines the content of two contexts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Primary content, a blank line, then the secondary's
+    #[default]
+    Concatenate,
+    /// Keep the primary's content
+    PreferPrimary,
+    /// Take the secondary's content
+    PreferSecondary,
+}
+
+/// What [`ContextStore::import`] does with contexts whose ID is taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Replace the stored context with the imported one
+    #[default]
+    Overwrite,
+    /// Keep the stored context and skip the imported one
+    SkipExisting,
+}
+
+/// Result of [`ContextStore::import`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    /// Contexts stored
+    pub imported: usize,
+    /// Contexts skipped because their ID was taken
+    pub skipped: usize,
+}
+
+/// Direction for [`ContextStore::paginate_by_created_at`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaginationDir {
+    /// Oldest to newest
+    Forward,
+    /// Newest to oldest
+    Backward,
+}
+
+/// One page of [`ContextStore::query_page`] results
+#[derive(Debug, Clone)]
+pub struct QueryPage {
+    /// Contexts on this page, in sort order
+    pub contexts: Vec<Context>,
+    /// Position to continue after, if more matches follow this page
+    pub next_cursor: Option<QueryCursor>,
+    /// Matches of the query's filters across all pages
+    pub total_matches: usize,
+}
+
+/// One page of a cursor-paginated listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    /// Items on this page, in the requested direction
+    pub items: Vec<T>,
+    /// Cursor for the following page, if there is one
+    pub next_cursor: Option<DateTime<Utc>>,
+    /// Cursor for the preceding page (pass it with the opposite direction),
+    /// if there is one
+    pub prev_cursor: Option<DateTime<Utc>>,
+    /// Total number of items across all pages
+    pub total_count: usize,
+}
+
+/// A broken storage invariant found by [`ContextStore::assert_domain_invariants`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvariantViolation {
+    /// Which invariant is broken
+    pub kind: ViolationKind,
+    /// The context involved, if any
+    pub context_id: Option<ContextId>,
+    /// Human-readable details
+    pub description: String,
+}
+
+/// Kinds of storage invariant violations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    /// The domain index references a context that is neither cached nor persisted
+    DanglingIndexEntry,
+    /// A context is indexed under a different domain than it is stored with
+    DomainMismatch,
+    /// An expired context is still indexed
+    ExpiredInIndex,
+    /// A stored context is missing from the domain index
+    UnindexedContext,
+}
+
+/// Retrieval quality over a set of ground-truth queries
+///
+/// See [`ContextStore::compute_retrieval_recall`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecallReport {
+    /// Queries evaluated
+    pub queries: usize,
+    /// Mean share of relevant contexts ranked first
+    pub avg_recall_at_1: f64,
+    /// Mean share of relevant contexts in the top 5
+    pub avg_recall_at_5: f64,
+    /// Mean share of relevant contexts in the top 10
+    pub avg_recall_at_10: f64,
+    /// Mean reciprocal rank of the first relevant context
+    pub mrr: f64,
+}
+
+/// A snapshot directory written by [`ContextStore::take_snapshot`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    /// Directory name, a UTC timestamp; what `restore --snapshot` takes
+    pub name: String,
+    /// Full path of the snapshot directory
+    pub path: PathBuf,
+    /// Total size of the snapshot's files
+    pub size_bytes: u64,
+    /// When the snapshot was last modified
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+impl SnapshotInfo {
+    /// Describe the snapshot directory at `path`
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path: path.to_path_buf(),
+            size_bytes: dir_size(path)?,
+            modified_at: std::fs::metadata(path)?.modified().ok().map(DateTime::from),
+        })
+    }
+}
+
+/// Outcome of the most recent scheduled snapshots
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnapshotStatus {
+    /// When a snapshot was last attempted
+    pub last_attempt: Option<DateTime<Utc>>,
+    /// Name of the last successful snapshot
+    pub last_snapshot: Option<String>,
+    /// Size of the last successful snapshot
+    pub last_size_bytes: u64,
+    /// How long the last attempt took
+    pub last_duration_ms: u64,
+    /// Error of the last attempt, if it failed
+    pub last_error: Option<String>,
+    /// Attempts failed in a row since the last success
+    pub consecutive_failures: u64,
+}
+
+impl SnapshotStatus {
+    /// Whether the last attempt, if any, succeeded
+    pub fn is_healthy(&self) -> bool {
+        self.last_error.is_none()
+    }
+}
+
+/// Snapshots in `dir`, oldest first; empty when `dir` does not exist
+///
+/// Snapshot names are timestamps, so name order is creation order. Hidden
+/// entries and plain files are ignored.
+pub fn list_snapshots_in(dir: &Path) -> Result<Vec<SnapshotInfo>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_operation("list_snapshots", None),
+    };
+
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let entry = entry.with_operation("list_snapshots", None)?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if hidden || !entry.path().is_dir() {
+            continue;
+        }
+        snapshots.push(SnapshotInfo::read(&entry.path()).with_operation("list_snapshots", None)?);
+    }
+    snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(snapshots)
+}
+
+/// Throughput and latency measured by [`ContextStore::benchmark_store_read_write`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    /// Stores per second
+    pub store_ops_per_sec: f64,
+    /// Gets per second
+    pub get_ops_per_sec: f64,
+    /// Tag queries per second
+    pub query_ops_per_sec: f64,
+    /// 99th percentile store latency on disk, in microseconds (0 without persistence)
+    pub sled_write_latency_p99_us: u64,
+}
+
+/// Operation counts over the window since the counters were last reset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadWriteRatio {
+    /// Context reads
+    pub reads: u64,
+    /// Context writes
+    pub writes: u64,
+    /// Context deletes
+    pub deletes: u64,
+    /// Length of the window in seconds
+    pub window_secs: u64,
+    /// Reads per write or delete
+    pub ratio: f64,
+}
+
+/// Storage statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageStats {
+    /// Number of items in memory cache
+    pub memory_count: usize,
+    /// Pinned contexts among the cached ones, exempt from eviction
+    pub pinned_count: usize,
+    /// Number of items on disk
+    pub disk_count: usize,
+    /// Memory cache capacity
+    pub cache_capacity: usize,
+    /// Share of reads served from the memory cache since the operation
+    /// counters were last reset (None before the first read)
+    pub cache_hit_rate: Option<f64>,
+    /// Reads served from the memory cache since the operation counters were last reset
+    pub cache_hits: u64,
+    /// Reads that missed the memory cache since the operation counters were last reset
+    pub cache_misses: u64,
+    /// Expired contexts awaiting cleanup, from the expiry index
+    pub expired_pending_cleanup: usize,
+    /// Estimated bytes held by the memory cache
+    pub cache_bytes: usize,
+    /// Byte budget of the memory cache, if any
+    pub max_cache_bytes: Option<usize>,
+    /// Contexts evicted from the memory cache since the store was opened
+    pub evictions: u64,
+    /// Estimated bytes held by the memory cache (requested with `sizes`)
+    pub memory_bytes: Option<usize>,
+    /// Bytes sled occupies on disk (requested with `sizes`)
+    pub disk_bytes: Option<u64>,
+    /// Context count per domain (requested with `domains`)
+    pub counts_by_domain: Option<HashMap<String, usize>>,
+}
+
+/// Upper bounds (exclusive) of the [`SizeHistogram`] buckets, in characters
+const SIZE_BUCKET_BOUNDS: [usize; 4] = [100, 1_000, 10_000, 100_000];
+
+/// Just the content of a persisted context, for cheap size scans
+#[cfg_attr(not(feature = "persistence"), allow(dead_code))]
+#[derive(Deserialize)]
+struct ContentOnly {
+    content: String,
+}
+
+/// Content length distribution from [`ContextStore::get_context_size_histogram`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeHistogram {
+    /// Counts for 0-100, 100-1k, 1k-10k, 10k-100k and 100k+ characters
+    pub buckets: Vec<SizeBucket>,
+    /// Mean content length
+    pub mean_chars: f64,
+    /// Median content length
+    pub median_chars: usize,
+    /// 95th percentile content length
+    pub p95_chars: usize,
+    /// Longest content
+    pub max_chars: usize,
+}
+
+/// One [`SizeHistogram`] bucket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeBucket {
+    /// Smallest length counted, inclusive
+    pub lower_chars: usize,
+    /// Largest length counted, exclusive; `usize::MAX` for the last bucket
+    pub upper_chars: usize,
+    /// Contexts whose length falls in the bucket
+    pub count: usize,
+}
+
+impl SizeHistogram {
+    /// Build a histogram from content lengths in characters
+    fn from_sizes(mut sizes: Vec<usize>) -> Self {
+        sizes.sort_unstable();
+
+        let mut lower = 0;
+        let mut buckets = Vec::with_capacity(SIZE_BUCKET_BOUNDS.len() + 1);
+        for upper in SIZE_BUCKET_BOUNDS.into_iter().chain([usize::MAX]) {
+            let count = sizes
+                .iter()
+                .filter(|&&s| s >= lower && (s < upper || upper == usize::MAX))
+                .count();
+            buckets.push(SizeBucket {
+                lower_chars: lower,
+                upper_chars: upper,
+                count,
+            });
+            lower = upper;
+        }
+
+        // Nearest-rank percentile
+        let percentile = |p: f64| match sizes.len() {
+            0 => 0,
+            n => sizes[((p * n as f64).ceil() as usize).clamp(1, n) - 1],
+        };
+        let mean_chars = if sizes.is_empty() {
+            0.0
+        } else {
+            sizes.iter().sum::<usize>() as f64 / sizes.len() as f64
+        };
+
+        Self {
+            buckets,
+            mean_chars,
+            median_chars: percentile(0.5),
+            p95_chars: percentile(0.95),
+            max_chars: sizes.last().copied().unwrap_or(0),
+        }
+    }
+}
+
+/// Index health report from [`ContextStore::dump_index_statistics`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexStatistics {
+    /// ID entries across all domain index buckets
+    pub domain_index_total_entries: usize,
+    /// Share of sampled domain index entries whose context still exists
+    pub domain_index_live_ratio: f64,
+    /// ID entries across all tag index buckets
+    pub tag_index_total_entries: usize,
+    /// Share of sampled tag index entries whose context still exists
+    pub tag_index_live_ratio: f64,
+    /// Estimated bytes held by orphan index entries
+    pub estimated_wasted_bytes: usize,
+}
+
+/// Estimated bytes held by the dead share of an index's entries
+///
+/// Each entry costs a `ContextId` plus its string data, averaged over the sample.
+fn wasted_bytes(total: usize, live_ratio: f64, sample: &[ContextId]) -> usize {
+    if sample.is_empty() {
+        return 0;
+    }
+    let avg_id_len =
+        sample.iter().map(|id| id.as_str().len()).sum::<usize>() as f64 / sample.len() as f64;
+    let dead = total as f64 * (1.0 - live_ratio);
+    (dead * (std::mem::size_of::<ContextId>() as f64 + avg_id_len)).round() as usize
+}
+
+/// Optional, more expensive sections of [`StorageStats`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatsInclude {
+    /// Per-domain context counts
+    pub domains: bool,
+    /// Memory and disk byte sizes
+    pub sizes: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_store_and_retrieve() {
+        let config = StorageConfig::memory_only(100);
+        let store = ContextStore::new(config).unwrap();
+
+        let ctx = Context::new("Test content", ContextDomain::Code);
+        let id = ctx.id.clone();
+
+        store.store(ctx).await.unwrap();
+
+        let retrieved = store.get(&id).await.unwrap();
+        assert!(retrieved.is_some());
+        assert_eq!(retrieved.unwrap().content, "Test content");
+    }
+
+    #[tokio::test]
+    async fn test_query_by_domain() {
+        let config = StorageConfig::memory_only(100);
+        let store = ContextStore::new(config).unwrap();
+
+        let ctx1 = Context::new("Code content", ContextDomain::Code);
+        let ctx2 = Context::new("Doc content", ContextDomain::Documentation);
+
+        store.store(ctx1).await.unwrap();
+        store.store(ctx2).await.unwrap();
+
+        let query = ContextQuery::new().with_domain(ContextDomain::Code);
+        let results = store.query(&query).await.unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].domain, ContextDomain::Code);
+    }
+
+    #[tokio::test]
+    async fn test_update_moves_indexes() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let ctx = Context::new("to be updated", ContextDomain::Code)
+            .with_tags(vec!["old".to_string(), "kept".to_string()]);
+        let created_at = ctx.created_at;
+        let id = store.store(ctx).await.unwrap().id;
+
+        let outcome = store
+            .update(&id, |ctx| {
+                ctx.domain = ContextDomain::Documentation;
+                ctx.metadata.tags = vec!["kept".to_string(), "new".to_string()];
+                ctx.created_at = Utc::now();
+            })
+            .await
+            .unwrap();
+        assert_eq!(outcome.previous.unwrap().domain, ContextDomain::Code);
+
+        let updated = store.get(&id).await.unwrap().unwrap();
+        assert_eq!(updated.created_at, created_at);
+        assert!(updated.updated_at.is_some());
+
+        let in_domain = |domain| ContextQuery::new().with_domain(domain).with_limit(10);
+        assert!(store
+            .query(&in_domain(ContextDomain::Code))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store
+                .query(&in_domain(ContextDomain::Documentation))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        let tag_idx = store.tag_index.read().await;
+        assert!(!tag_idx.contains_key("old"));
+        assert!(tag_idx["kept"].contains(&id) && tag_idx["new"].contains(&id));
+        drop(tag_idx);
+
+        let missing = ContextId::from_string("missing".into());
+        assert!(matches!(
+            store.update(&missing, |_| {}).await,
+            Err(ContextError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_updates_are_not_lost() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let id = store
+            .store(Context::new("counted", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+
+        let updates = (0..20).map(|i| {
+            let store = &store;
+            let id = &id;
+            async move {
+                store
+                    .update(id, |ctx| ctx.metadata.tags.push(format!("tag-{}", i)))
+                    .await
+                    .unwrap();
+            }
+        });
+        futures::future::join_all(updates).await;
+
+        let ctx = store.get(&id).await.unwrap().unwrap();
+        assert_eq!(ctx.metadata.tags.len(), 20);
+        assert_eq!(store.tag_index.read().await.len(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_query_cursor_pagination() {
+        let store = ContextStore::new(StorageConfig::memory_only(1000)).unwrap();
+        let mut originals = HashSet::new();
+        for i in 0..250 {
+            let ctx = Context::new(format!("paged {}", i), ContextDomain::General)
+                .with_importance((i % 5) as f32 / 5.0);
+            originals.insert(store.store(ctx).await.unwrap().id);
+        }
+
+        let mut seen = Vec::new();
+        let mut query = ContextQuery::new().with_limit(50);
+        let mut pages = 0;
+        loop {
+            let page = store.query_page(&query).await.unwrap();
+            pages += 1;
+            seen.extend(page.contexts.into_iter().map(|ctx| ctx.id));
+            // New contexts arriving between pages must not shift the rest
+            let late = Context::new(format!("late {}", pages), ContextDomain::General)
+                .with_importance(0.9);
+            store.store(late).await.unwrap();
+            match page.next_cursor {
+                Some(cursor) => query = query.with_cursor(cursor),
+                None => break,
+            }
+        }
+
+        let paged: Vec<&ContextId> = seen.iter().filter(|id| originals.contains(*id)).collect();
+        assert_eq!(paged.len(), 250);
+        assert_eq!(paged.iter().collect::<HashSet<_>>().len(), 250);
+
+        let offset = store
+            .query_page(&ContextQuery::new().with_limit(10).with_offset(300))
+            .await
+            .unwrap();
+        assert!(offset.contexts.is_empty() && offset.next_cursor.is_none());
+        assert_eq!(offset.total_matches, 250 + pages);
+
+        let first = store.get(&seen[0]).await.unwrap().unwrap();
+        let cursor = QueryCursor::at(&first, crate::context::SortOrder::MostAccessed);
+        assert_eq!(QueryCursor::decode(&cursor.encode()).unwrap(), cursor);
+        assert!(matches!(
+            store
+                .query_page(&ContextQuery::new().with_cursor(cursor))
+                .await,
+            Err(ContextError::InvalidQuery(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_query_sort_orders() {
+        use crate::context::SortOrder;
+
+        // (created hours ago, accessed hours ago, importance, access count)
+        let keys = [
+            (4, 3, 0.5, 9),
+            (3, 5, 0.9, 4),
+            (2, 1, 0.1, 2),
+            (1, 4, 0.7, 6),
+        ];
+        let expected = [
+            (SortOrder::ImportanceDesc, [1, 3, 0, 2]),
+            (SortOrder::MostAccessed, [0, 3, 1, 2]),
+            (SortOrder::CreatedDesc, [3, 2, 1, 0]),
+            (SortOrder::CreatedAsc, [0, 1, 2, 3]),
+            (SortOrder::AccessedDesc, [2, 0, 3, 1]),
+        ];
+        let now = Utc::now();
+        for (sort, order) in expected {
+            // Querying touches results, so each order gets a fresh store
+            let store = ContextStore::new(StorageConfig::memory_only(10)).unwrap();
+            let mut ids = Vec::new();
+            for (i, (created, accessed, importance, count)) in keys.into_iter().enumerate() {
+                let mut ctx = Context::new(format!("sorted {}", i), ContextDomain::General)
+                    .with_importance(importance);
+                ctx.created_at = now - Duration::hours(created);
+                ctx.accessed_at = now - Duration::hours(accessed);
+                ctx.access_count = count;
+                ids.push(store.store(ctx).await.unwrap().id);
+            }
+
+            let results = store
+                .query(&ContextQuery::new().with_sort(sort))
+                .await
+                .unwrap();
+            let got: Vec<ContextId> = results.into_iter().map(|ctx| ctx.id).collect();
+            let want: Vec<ContextId> = order.iter().map(|&i| ids[i].clone()).collect();
+            assert_eq!(got, want, "{:?}", sort);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_count_and_exists() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let tagged = vec!["tagged".to_string()];
+        for i in 0..6 {
+            let domain = if i % 2 == 0 {
+                ContextDomain::Code
+            } else {
+                ContextDomain::General
+            };
+            let mut ctx = Context::new(format!("counted {}", i), domain);
+            if i < 3 {
+                ctx = ctx.with_tags(tagged.clone());
+            }
+            store.store(ctx).await.unwrap();
+        }
+        let mut expired = Context::new("expired", ContextDomain::Code);
+        expired.expires_at = Some(Utc::now() - Duration::seconds(1));
+        let expired_id = store.store(expired).await.unwrap().id;
+
+        assert_eq!(store.count(&ContextQuery::new()).await.unwrap(), 6);
+        let code = ContextQuery::new().with_domain(ContextDomain::Code);
+        assert_eq!(store.count(&code).await.unwrap(), 3);
+        let code_tagged = code.with_tag("tagged".to_string());
+        assert_eq!(store.count(&code_tagged).await.unwrap(), 2);
+        // Counting agrees with querying and touches nothing
+        let all = store
+            .query(&ContextQuery::new().with_limit(100))
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 6);
+        assert!(all.iter().all(|ctx| ctx.access_count == 1));
+
+        assert!(store.exists(&all[0].id).await.unwrap());
+        assert!(store.exists(&expired_id).await.unwrap());
+        assert!(!store.exists(&ContextId::new()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let source = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        for i in 0..5 {
+            let mut ctx = Context::new(format!("exported {}", i), ContextDomain::Code)
+                .with_tags(vec![format!("tag{}", i % 2)])
+                .with_importance(0.1 * i as f32)
+                .with_source("export-test")
+                .with_embedding(vec![0.25, -1.0 / 3.0, i as f32]);
+            ctx.created_at = Utc::now() - Duration::days(i);
+            ctx.accessed_at = Utc::now() - Duration::hours(i);
+            ctx.expires_at = Some(Utc::now() + Duration::days(30));
+            ctx.metadata.screening_status = ScreeningStatus::Safe;
+            ctx.metadata
+                .custom
+                .insert("n".to_string(), serde_json::json!(i));
+            source.store(ctx).await.unwrap();
+        }
+
+        let mut exported = Vec::new();
+        assert_eq!(source.export_all(&mut exported).await.unwrap(), 5);
+        assert_eq!(exported.iter().filter(|&&b| b == b'\n').count(), 5);
+
+        let dest = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let summary = dest
+            .import(exported.as_slice(), ImportMode::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                imported: 5,
+                skipped: 0
+            }
+        );
+        for id in source.list_all_ids(None).await.unwrap() {
+            let original = source.peek(&id).await.unwrap().unwrap();
+            let imported = dest.peek(&id).await.unwrap().unwrap();
+            assert_eq!(
+                serde_json::to_value(&original).unwrap(),
+                serde_json::to_value(&imported).unwrap()
+            );
+        }
+
+        // Indexes are rebuilt on import
+        let tagged = ContextQuery::new()
+            .with_domain(ContextDomain::Code)
+            .with_tag("tag0".to_string());
+        assert_eq!(dest.count(&tagged).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_import_skip_existing() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let kept = Context::new("original", ContextDomain::General);
+        let id = store.store(kept.clone()).await.unwrap().id;
+
+        let mut replacement = kept;
+        replacement.content = "replacement".to_string();
+        let fresh = Context::new("fresh", ContextDomain::General);
+        let mut input = Vec::new();
+        for ctx in [&replacement, &fresh, &fresh] {
+            input.extend(serde_json::to_vec(ctx).unwrap());
+            input.extend(b"\n\n");
+        }
+
+        let summary = store
+            .import(input.as_slice(), ImportMode::SkipExisting)
+            .await
+            .unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                imported: 1,
+                skipped: 2
+            }
+        );
+        assert_eq!(store.peek(&id).await.unwrap().unwrap().content, "original");
+
+        store
+            .import(input.as_slice(), ImportMode::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.peek(&id).await.unwrap().unwrap().content,
+            "replacement"
+        );
+
+        let err = store
+            .import(&b"{\"not\": \"a context\"}\n"[..], ImportMode::Overwrite)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+    }
+
+    #[tokio::test]
+    async fn test_query_intersects_domain_and_tags() {
+        let store = ContextStore::new(StorageConfig::memory_only(200)).unwrap();
+        let shared = vec!["shared".to_string()];
+        for i in 0..50 {
+            let ctx = Context::new(format!("general {}", i), ContextDomain::General)
+                .with_tags(shared.clone());
+            store.store(ctx).await.unwrap();
+        }
+        for i in 0..3 {
+            let ctx =
+                Context::new(format!("code {}", i), ContextDomain::Code).with_tags(shared.clone());
+            store.store(ctx).await.unwrap();
+        }
+        store
+            .store(Context::new("untagged code", ContextDomain::Code))
+            .await
+            .unwrap();
+
+        let gets_before = store.counters().snapshot().gets;
+        let query = ContextQuery::new()
+            .with_domain(ContextDomain::Code)
+            .with_tags(shared.clone())
+            .with_limit(3);
+        let results = store.query(&query).await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results
+            .iter()
+            .all(|ctx| ctx.domain == ContextDomain::Code && ctx.metadata.tags == shared));
+        // Only the intersection is fetched
+        assert_eq!(store.counters().snapshot().gets - gets_before, 3);
+
+        // Several tags are still unioned before intersecting with the domain
+        let either = ContextQuery::new()
+            .with_domain(ContextDomain::General)
+            .with_tags(vec!["shared".into(), "missing".into()])
+            .with_limit(100);
+        assert_eq!(store.query(&either).await.unwrap().len(), 50);
+    }
+
+    #[tokio::test]
+    async fn test_delete_removes_index_entries() {
+        let store = ContextStore::new(StorageConfig::memory_only(1)).unwrap();
+        let tags = vec!["gone".to_string()];
+        let cached = store
+            .store(Context::new("cached", ContextDomain::Code).with_tags(tags.clone()))
+            .await
+            .unwrap()
+            .id;
+        assert!(store.delete(&cached).await.unwrap());
+        assert!(!store
+            .domain_index
+            .read()
+            .await
+            .contains_key(&ContextDomain::Code));
+        assert!(!store.tag_index.read().await.contains_key("gone"));
+
+        // Evicted without persistence, the context is unreadable but indexed
+        let evicted = store
+            .store(Context::new("evicted", ContextDomain::Code).with_tags(tags))
+            .await
+            .unwrap()
+            .id;
+        store
+            .store(Context::new("evictor", ContextDomain::General))
+            .await
+            .unwrap();
+        assert!(!store.delete(&evicted).await.unwrap());
+        let domain_idx = store.domain_index.read().await;
+        assert!(domain_idx.values().all(|ids| !ids.contains(&evicted)));
+        assert_eq!(domain_idx[&ContextDomain::General].len(), 1);
+        assert!(store.tag_index.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_repeated_store_does_not_duplicate_index_entries() {
+        // Small enough that the context is evicted between stores, so the
+        // replaced version is not found to unindex
+        let store = ContextStore::new(StorageConfig::memory_only(1)).unwrap();
+        let ctx = Context::new("stored repeatedly", ContextDomain::Code)
+            .with_tags(vec!["repeat".to_string()]);
+        for i in 0..100 {
+            store.store(ctx.clone()).await.unwrap();
+            store
+                .store(Context::new(
+                    format!("evictor {}", i),
+                    ContextDomain::General,
+                ))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            store.domain_index.read().await[&ContextDomain::Code].len(),
+            1
+        );
+        assert_eq!(store.tag_index.read().await["repeat"].len(), 1);
+
+        store.store(ctx.clone()).await.unwrap();
+        let gets_before = store.counters().snapshot().gets;
+        let query = ContextQuery::new()
+            .with_domain(ContextDomain::Code)
+            .with_limit(100);
+        assert_eq!(store.query(&query).await.unwrap().len(), 1);
+        assert_eq!(store.counters().snapshot().gets - gets_before, 1);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_access_stats_written_back_on_eviction() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(1, temp_dir.path());
+        let store = ContextStore::new(config).unwrap();
+
+        let a = store
+            .store(Context::new("a", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        let b = store
+            .store(Context::new("b", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+
+        // Promoting `a` evicts `b`; promoting `b` then evicts `a` with its stats
+        store.get_as(&a, Some("cli")).await.unwrap();
+        store.get(&b).await.unwrap();
+
+        let a = store.get(&a).await.unwrap().unwrap();
+        assert_eq!(a.access_count, 2);
+        assert_eq!(a.last_accessed_by.as_deref(), Some("cli"));
+        assert_eq!(a.recent_accesses.len(), 2);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_list_all_ids() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(1, temp_dir.path());
+        let store = ContextStore::new(config).unwrap();
+
+        let mut all = Vec::new();
+        let mut code = Vec::new();
+        for (content, domain) in [
+            ("a", ContextDomain::Code),
+            ("b", ContextDomain::General),
+            ("c", ContextDomain::Code),
+        ] {
+            let id = store
+                .store(Context::new(content, domain.clone()))
+                .await
+                .unwrap()
+                .id;
+            if domain == ContextDomain::Code {
+                code.push(id.clone());
+            }
+            all.push(id);
+        }
+        all.sort();
+        code.sort();
+
+        // Only one context fits in the cache; the rest are listed from sled keys
+        assert_eq!(store.list_all_ids(None).await.unwrap(), all);
+        assert_eq!(
+            store
+                .list_all_ids(Some(&ContextDomain::Code))
+                .await
+                .unwrap(),
+            code
+        );
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_mixed_envelope_and_legacy_records() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(10, temp_dir.path());
+        let store = ContextStore::new(config).unwrap();
+
+        let current = store
+            .store(Context::new("enveloped", ContextDomain::Code))
+            .await
+            .unwrap()
+            .id;
+        let legacy = Context::new("bare json", ContextDomain::General);
+        let db = store.disk_store.as_ref().unwrap();
+        db.insert(legacy.id.as_str(), serde_json::to_vec(&legacy).unwrap())
+            .unwrap();
+
+        let raw = db.get(current.as_str()).unwrap().unwrap();
+        assert!(raw.starts_with(&record::MAGIC));
+        assert_eq!(
+            store.get(&legacy.id).await.unwrap().unwrap().content,
+            "bare json"
+        );
+        let usage = store.get_disk_usage_by_domain().unwrap();
+        assert_eq!(usage.len(), 2);
+        // "bare json" is only on disk, so the histogram decodes its legacy value
+        assert_eq!(
+            store.get_context_size_histogram().await.unwrap().max_chars,
+            9
+        );
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_switching_to_bincode_reads_json_records() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let embedding: Vec<f32> = (0..384).map(|i| (i as f32).sin()).collect();
+        let json_config = StorageConfig::with_persistence(10, temp_dir.path());
+        let store = ContextStore::new(json_config.clone()).unwrap();
+        let old = store
+            .store(
+                Context::new("written as json", ContextDomain::Code)
+                    .with_embedding(embedding.clone()),
+            )
+            .await
+            .unwrap()
+            .id;
+        drop(store);
+
+        let bincode_config = StorageConfig {
+            record_format: RecordFormat::Bincode,
+            ..json_config
+        };
+        let store = ContextStore::new(bincode_config).unwrap();
+        let new = store
+            .store(
+                Context::new("written as bincode", ContextDomain::Code)
+                    .with_embedding(embedding.clone()),
+            )
+            .await
+            .unwrap()
+            .id;
+
+        let db = store.disk_store.as_ref().unwrap();
+        let format = |id: &ContextId| {
+            let raw = db.get(id.as_str()).unwrap().unwrap();
+            record::RecordHeader::parse(&raw).unwrap().0.format
+        };
+        assert_eq!(format(&old), RecordFormat::Json);
+        assert_eq!(format(&new), RecordFormat::Bincode);
+
+        for ctx in store.iter_sled() {
+            assert_eq!(ctx.unwrap().embedding.as_ref(), Some(&embedding));
+        }
+        assert_eq!(store.get_disk_usage_by_domain().unwrap()["Code"], {
+            let raw = |id: &ContextId| db.get(id.as_str()).unwrap().unwrap().len();
+            raw(&old) + raw(&new)
+        });
+        // With the cache cleared, the histogram decodes both records from disk
+        store.memory_cache.write().await.clear();
+        assert_eq!(
+            store.get_context_size_histogram().await.unwrap().max_chars,
+            18
+        );
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_compression_toggled_between_runs() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let content = "Repetitive documentation paragraph. ".repeat(100 * 1024 / 36);
+        let plain_config = StorageConfig::with_persistence(10, temp_dir.path());
+        let compressed_config = StorageConfig {
+            enable_compression: true,
+            ..plain_config.clone()
+        };
+
+        let store = ContextStore::new(compressed_config.clone()).unwrap();
+        let compressed = store
+            .store(Context::new(content.clone(), ContextDomain::Documentation))
+            .await
+            .unwrap()
+            .id;
+        let raw = store
+            .disk_store
+            .as_ref()
+            .unwrap()
+            .get(compressed.as_str())
+            .unwrap()
+            .unwrap();
+        assert!(raw.len() < content.len() / 20);
+        drop(store);
+
+        // Compressed records stay readable with compression off, and the reverse
+        let store = ContextStore::new(plain_config).unwrap();
+        assert_eq!(
+            store.get(&compressed).await.unwrap().unwrap().content,
+            content
+        );
+        let plain_content = format!("Uncompressed. {}", content);
+        let plain = store
+            .store(Context::new(
+                plain_content.clone(),
+                ContextDomain::Documentation,
+            ))
+            .await
+            .unwrap()
+            .id;
+        drop(store);
+
+        let store = ContextStore::new(compressed_config).unwrap();
+        let mut contents: Vec<String> = store.iter_sled().map(|ctx| ctx.unwrap().content).collect();
+        contents.sort();
+        assert_eq!(contents, [content.clone(), plain_content.clone()]);
+        assert_eq!(
+            store.get(&plain).await.unwrap().unwrap().content,
+            plain_content
+        );
+
+        let invalid = StorageConfig::builder().compression(99).build();
+        assert!(matches!(
+            ContextStore::new(invalid),
+            Err(ContextError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cache_evicts_by_bytes() {
+        let config = StorageConfig {
+            max_cache_bytes: Some(64 * 1024),
+            ..StorageConfig::memory_only(100)
+        };
+        let store = ContextStore::new(config).unwrap();
+        let mut ids = Vec::new();
+        for i in 0..10 {
+            let content = format!("{} {}", i, "large ".repeat(16 * 1024 / 6));
+            ids.push(
+                store
+                    .store(Context::new(content, ContextDomain::General))
+                    .await
+                    .unwrap()
+                    .id,
+            );
+        }
+
+        let stats = store.stats().await;
+        assert!(stats.memory_count < 10);
+        assert!(stats.cache_bytes <= 64 * 1024);
+        assert_eq!(stats.evictions as usize, 10 - stats.memory_count);
+        // The oldest went first
+        let history = store.get_eviction_history(10);
+        assert_eq!(history[0].0, ids[0]);
+        assert!(store.memory_cache.read().await.contains(&ids[9]));
+    }
+
+    #[tokio::test]
+    async fn test_pinned_contexts_stay_in_memory() {
+        let store = ContextStore::new(StorageConfig::memory_only(5)).unwrap();
+        let pinned = store
+            .store(Context::new("project conventions", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        store.pin(&pinned).await.unwrap();
+        for i in 0..20 {
+            let filler = Context::new(format!("filler {}", i), ContextDomain::General);
+            store.store(filler).await.unwrap();
+        }
+
+        store.counters().reset();
+        let ctx = store.get(&pinned).await.unwrap().unwrap();
+        assert!(ctx.metadata.pinned);
+        assert_eq!(store.counters().snapshot().cache_hits, 1);
+        let stats = store.stats().await;
+        assert_eq!((stats.memory_count, stats.pinned_count), (6, 1));
+
+        store.unpin(&pinned).await.unwrap();
+        for i in 0..5 {
+            let filler = Context::new(format!("more filler {}", i), ContextDomain::General);
+            store.store(filler).await.unwrap();
+        }
+        assert!(store.get(&pinned).await.unwrap().is_none());
+        assert!(matches!(
+            store.pin(&ContextId::new()).await,
+            Err(ContextError::NotFound(_))
+        ));
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_pins_survive_restart() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(3, temp_dir.path());
+        let store = ContextStore::new(config.clone()).unwrap();
+        let pinned = store
+            .store(Context::new("system prompt", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        store.pin(&pinned).await.unwrap();
+        drop(store);
+
+        let store = ContextStore::new(config).unwrap();
+        assert!(store.get(&pinned).await.unwrap().unwrap().metadata.pinned);
+        for i in 0..10 {
+            let filler = Context::new(format!("filler {}", i), ContextDomain::General);
+            store.store(filler).await.unwrap();
+        }
+        assert!(store.memory_cache.read().await.contains(&pinned));
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_domain_filter_sees_contexts_from_before_restart() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(100, temp_dir.path());
+        let store = ContextStore::new(config.clone()).unwrap();
+        for i in 0..5 {
+            let ctx = Context::new(format!("fn f{}() {{}}", i), ContextDomain::Code);
+            store.store(ctx).await.unwrap();
+        }
+        drop(store);
+
+        let store = ContextStore::new(config).unwrap();
+        store
+            .store(Context::new("fn fresh() {}", ContextDomain::Code))
+            .await
+            .unwrap();
+        let code = ContextQuery::new()
+            .with_domain(ContextDomain::Code)
+            .with_limit(10);
+        assert_eq!(store.query(&code).await.unwrap().len(), 6);
+        assert_eq!(store.count(&code).await.unwrap(), 6);
+
+        // Once loaded, the indexes alone answer the query
+        assert_eq!(store.load_indexes().await.unwrap(), 0);
+        let candidates = store.get_candidate_ids(&code).await.unwrap();
+        assert_eq!(candidates.ids.len(), 6);
+        assert!(!candidates.scan_disk);
+        assert_eq!(store.count(&code).await.unwrap(), 6);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_index_lookups_load_indexes_after_restart() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(100, temp_dir.path());
+        let store = ContextStore::new(config.clone()).unwrap();
+        for i in 0..3 {
+            let ctx = Context::new(format!("turn {}", i), ContextDomain::Conversation)
+                .with_thread("chat");
+            store.store(ctx).await.unwrap();
+        }
+        drop(store);
+
+        let store = ContextStore::new(config).unwrap();
+        assert_eq!(store.get_thread("chat").await.unwrap().len(), 3);
+        let conversation = store.list_all_ids(Some(&ContextDomain::Conversation));
+        assert_eq!(conversation.await.unwrap().len(), 3);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_open_skips_undecodable_records() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(100, temp_dir.path());
+        let store = ContextStore::new(config.clone()).unwrap();
+        for i in 0..3 {
+            let ctx = Context::new(format!("fn g{}() {{}}", i), ContextDomain::Code);
+            store.store(ctx).await.unwrap();
+        }
+        store
+            .disk_store
+            .as_ref()
+            .unwrap()
+            .insert("garbage", &b"not a record"[..])
+            .unwrap();
+        drop(store);
+
+        let store = ContextStore::open(config.clone()).await.unwrap();
+        let code = ContextQuery::new()
+            .with_domain(ContextDomain::Code)
+            .with_limit(10);
+        assert_eq!(store.query(&code).await.unwrap().len(), 3);
+        assert_eq!(store.load_indexes().await.unwrap(), 1);
+
+        // The record is still there for `verify` to report
+        let corrupt = store.iter_sled().filter(|ctx| ctx.is_err()).count();
+        assert_eq!(corrupt, 1);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_task_removes_expired_contexts() {
+        let config = StorageConfig {
+            cleanup_interval_secs: 1,
+            ..StorageConfig::memory_only(10)
+        };
+        let store = Arc::new(ContextStore::new(config).unwrap());
+        let mut ctx = Context::new("short lived", ContextDomain::General);
+        ctx.expires_at = Some(Utc::now() + Duration::milliseconds(100));
+        let id = store.store(ctx).await.unwrap().id;
+        let kept = store
+            .store(Context::new("kept", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+
+        let task = ContextStore::spawn_cleanup_task(&store).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+        assert!(!store.exists(&id).await.unwrap());
+        assert!(store.exists(&kept).await.unwrap());
+
+        task.abort();
+        let disabled = StorageConfig {
+            auto_cleanup: false,
+            ..StorageConfig::memory_only(10)
+        };
+        let disabled = Arc::new(ContextStore::new(disabled).unwrap());
+        assert!(ContextStore::spawn_cleanup_task(&disabled).is_none());
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_cleanup_removes_expired_contexts_from_disk() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = ContextStore::new(StorageConfig::with_persistence(1, temp_dir.path())).unwrap();
+        let mut ctx = Context::new("short lived", ContextDomain::General);
+        ctx.expires_at = Some(Utc::now() + Duration::milliseconds(50));
+        let expiring = store.store(ctx).await.unwrap().id;
+        // Pushes the expiring context out of the one-entry cache
+        store
+            .store(Context::new("evicts it", ContextDomain::General))
+            .await
+            .unwrap();
+        assert!(!store.memory_cache.read().await.contains(&expiring));
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        assert_eq!(store.stats().await.disk_count, 2);
+        assert_eq!(store.cleanup_expired().await.unwrap(), 1);
+        assert_eq!(store.stats().await.disk_count, 1);
+        assert!(store.get(&expiring).await.unwrap().is_none());
+        let general = ContextQuery::new().with_domain(ContextDomain::General);
+        assert_eq!(store.count(&general).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_visits_only_expiring_contexts() {
+        use std::sync::atomic::Ordering;
+
+        let store = ContextStore::new(StorageConfig::memory_only(5000)).unwrap();
+        let lasting = (0..2000)
+            .map(|i| Context::new(format!("lasting {}", i), ContextDomain::General))
+            .collect();
+        store.store_batch(lasting).await.unwrap();
+        for i in 0..5 {
+            let mut ctx = Context::new(format!("expiring {}", i), ContextDomain::General);
+            ctx.expires_at = Some(Utc::now() + Duration::milliseconds(50));
+            store.store(ctx).await.unwrap();
+        }
+        // Expires after the next cleanup, so it must not be visited yet
+        let mut later = Context::new("expires later", ContextDomain::General);
+        later.expires_at = Some(Utc::now() + Duration::hours(1));
+        store.store(later).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        assert_eq!(store.cleanup_expired().await.unwrap(), 5);
+        assert_eq!(store.cleanup_checked.load(Ordering::Relaxed), 5);
+        assert_eq!(store.stats().await.memory_count, 2001);
+
+        assert_eq!(store.cleanup_expired().await.unwrap(), 0);
+        assert_eq!(store.cleanup_checked.load(Ordering::Relaxed), 5);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_expiry_tree_drives_cleanup_after_restart() {
+        use std::sync::atomic::Ordering;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(100, temp_dir.path());
+        let store = ContextStore::new(config.clone()).unwrap();
+        for i in 0..50 {
+            store
+                .store(Context::new(
+                    format!("lasting {}", i),
+                    ContextDomain::General,
+                ))
+                .await
+                .unwrap();
+        }
+        let mut ctx = Context::new("short lived", ContextDomain::General);
+        ctx.expires_at = Some(Utc::now() + Duration::milliseconds(50));
+        let expiring = store.store(ctx).await.unwrap().id;
+        // Listed once, though it is in both the memory index and the tree
+        let soon = Utc::now() + Duration::hours(1);
+        assert_eq!(store.expired_entries(soon).await.unwrap().len(), 1);
+        drop(store);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let store = ContextStore::new(config).unwrap();
+        assert_eq!(store.cleanup_expired().await.unwrap(), 1);
+        assert_eq!(store.cleanup_checked.load(Ordering::Relaxed), 1);
+        assert!(store.get(&expiring).await.unwrap().is_none());
+        let tree = store
+            .disk_store
+            .as_ref()
+            .unwrap()
+            .open_tree(EXPIRY_TREE)
+            .unwrap();
+        assert!(tree.is_empty());
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_source_filter_finds_evicted_contexts() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(2, temp_dir.path());
+        let store = ContextStore::new(config.clone()).unwrap();
+        let mut tracked = Vec::new();
+        for i in 0..3 {
+            let ctx = Context::new(format!("issue {}", i), ContextDomain::General)
+                .with_source("github:issue-tracker");
+            tracked.push(store.store(ctx).await.unwrap().id);
+        }
+        for i in 0..10 {
+            let ctx =
+                Context::new(format!("note {}", i), ContextDomain::General).with_source("user");
+            store.store(ctx).await.unwrap();
+        }
+        {
+            let cache = store.memory_cache.read().await;
+            assert!(tracked.iter().all(|id| !cache.contains(id)));
+        }
+
+        let query = ContextQuery::new()
+            .with_source("github:issue-tracker")
+            .with_limit(10);
+        let candidates = store.get_candidate_ids(&query).await.unwrap();
+        assert_eq!(candidates.ids.len(), 3);
+        assert!(!candidates.scan_disk);
+        assert_eq!(store.query(&query).await.unwrap().len(), 3);
+
+        // Deleting and re-sourcing contexts keeps the index in step
+        store.delete(&tracked[0]).await.unwrap();
+        store
+            .update(&tracked[1], |ctx| ctx.metadata.source = "user".to_string())
+            .await
+            .unwrap();
+        let candidates = store.get_candidate_ids(&query).await.unwrap();
+        assert_eq!(candidates.ids, vec![tracked[2].clone()]);
+        assert_eq!(store.count(&query).await.unwrap(), 1);
+        assert_eq!(store.source_index.read().await["user"].len(), 11);
+        drop(store);
+
+        // After a reopen, with none of them read back into the cache yet
+        let store = ContextStore::new(config).unwrap();
+        store
+            .store(
+                Context::new("issue 3", ContextDomain::General).with_source("github:issue-tracker"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(store.count(&query).await.unwrap(), 2);
+        let found = store.query(&query).await.unwrap();
+        assert!(found.iter().any(|ctx| ctx.id == tracked[2]));
+    }
+
+    #[tokio::test]
+    async fn test_max_age_queries_use_created_index() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let now = Utc::now();
+        let aged = |content: &str, domain: ContextDomain, age: Duration| {
+            let mut ctx = Context::new(content, domain);
+            ctx.created_at = now - age;
+            ctx
+        };
+        for i in 0..50 {
+            store
+                .store(aged(
+                    &format!("old {}", i),
+                    ContextDomain::General,
+                    Duration::days(2),
+                ))
+                .await
+                .unwrap();
+        }
+        let inside = store
+            .store(aged("inside", ContextDomain::Code, Duration::seconds(3599)))
+            .await
+            .unwrap()
+            .id;
+        // An age of 3600 whole seconds is still within an hour
+        let boundary = store
+            .store(aged(
+                "boundary",
+                ContextDomain::General,
+                Duration::milliseconds(3_600_100),
+            ))
+            .await
+            .unwrap()
+            .id;
+        store
+            .store(aged(
+                "outside",
+                ContextDomain::Code,
+                Duration::milliseconds(3_601_500),
+            ))
+            .await
+            .unwrap();
+
+        let query = ContextQuery::new().with_max_age_hours(1).with_limit(100);
+        let candidates = store.get_candidate_ids(&query).await.unwrap();
+        assert_eq!(candidates.ids.len(), 2);
+        assert!(!candidates.scan_disk);
+        let mut found: Vec<ContextId> = store
+            .query(&query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|ctx| ctx.id)
+            .collect();
+        found.sort();
+        let mut expected = vec![inside.clone(), boundary];
+        expected.sort();
+        assert_eq!(found, expected);
+        assert_eq!(store.count(&query).await.unwrap(), 2);
+
+        // Intersected with the domain index
+        let code = query.with_domain(ContextDomain::Code);
+        assert_eq!(
+            store.get_candidate_ids(&code).await.unwrap().ids,
+            vec![inside]
+        );
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_created_tree_answers_max_age_queries_after_restart() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(100, temp_dir.path());
+        let store = ContextStore::new(config.clone()).unwrap();
+        for i in 0..20 {
+            let mut ctx = Context::new(format!("old {}", i), ContextDomain::General);
+            ctx.created_at = Utc::now() - Duration::days(2);
+            store.store(ctx).await.unwrap();
+        }
+        let recent = store
+            .store(Context::new("recent", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        drop(store);
+
+        let store = ContextStore::new(config).unwrap();
+        let query = ContextQuery::new().with_max_age_hours(1);
+        let candidates = store.get_candidate_ids(&query).await.unwrap();
+        assert_eq!(candidates.ids, vec![recent.clone()]);
+        assert!(!candidates.scan_disk);
+        let found = store.query(&query).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, recent);
+
+        // Combined with a domain filter after new contexts arrive in this run
+        store
+            .store(Context::new("newer", ContextDomain::General))
+            .await
+            .unwrap();
+        let general = query.with_domain(ContextDomain::General);
+        assert_eq!(store.count(&general).await.unwrap(), 2);
+    }
+
+    #[cfg(feature = "persistence")]
+    fn inject_faults(store: &ContextStore, faults: impl IntoIterator<Item = sled::Error>) {
+        store.injected_faults.lock().unwrap().extend(faults);
+    }
+
+    #[cfg(feature = "persistence")]
+    fn timed_out() -> sled::Error {
+        sled::Error::Io(std::io::Error::from(std::io::ErrorKind::TimedOut))
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_transient_disk_errors_are_retried() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(100, temp_dir.path());
+        let store = ContextStore::new(config).unwrap();
+
+        inject_faults(&store, [timed_out(), timed_out()]);
+        let id = store
+            .store(Context::new("retried", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        assert!(store
+            .disk_store
+            .as_ref()
+            .unwrap()
+            .contains_key(id.as_str())
+            .unwrap());
+
+        inject_faults(&store, [timed_out()]);
+        assert!(store.delete(&id).await.unwrap());
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_failed_disk_write_rolls_back_memory() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(100, temp_dir.path());
+        let store = ContextStore::new(config).unwrap();
+
+        // Fatal errors are not retried
+        inject_faults(&store, [sled::Error::Unsupported("read-only".into())]);
+        let ctx = Context::new("never persisted", ContextDomain::Code);
+        let id = ctx.id.clone();
+        let err = store.store(ctx).await.unwrap_err();
+        assert!(!err.is_transient());
+        assert!(store.get(&id).await.unwrap().is_none());
+        assert!(store
+            .query(&ContextQuery::new().with_domain(ContextDomain::Code))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Transient errors are returned once the attempts run out
+        let kept = store
+            .store(Context::new("kept", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        inject_faults(&store, (0..DISK_WRITE_ATTEMPTS).map(|_| timed_out()));
+        assert!(store.delete(&kept).await.unwrap_err().is_transient());
+        assert!(store.get(&kept).await.unwrap().is_some());
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_store_batch_is_atomic() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(100, temp_dir.path());
+        let store = ContextStore::new(config).unwrap();
+
+        let batch: Vec<Context> = (0..20)
+            .map(|i| Context::new(format!("batched {}", i), ContextDomain::Code))
+            .collect();
+        let ids = store.store_batch(batch.clone()).await.unwrap();
+        assert_eq!(ids, batch.iter().map(|c| c.id.clone()).collect::<Vec<_>>());
+        assert_eq!(store.disk_store.as_ref().unwrap().len(), 20);
+        let code = ContextQuery::new()
+            .with_domain(ContextDomain::Code)
+            .with_limit(100);
+        assert_eq!(store.query(&code).await.unwrap().len(), 20);
+
+        // A failed write leaves neither disk, cache nor indexes touched
+        inject_faults(&store, [sled::Error::Unsupported("read-only".into())]);
+        let failed: Vec<Context> = (0..5)
+            .map(|i| Context::new(format!("lost {}", i), ContextDomain::Documentation))
+            .collect();
+        assert!(store.store_batch(failed.clone()).await.is_err());
+        assert_eq!(store.disk_store.as_ref().unwrap().len(), 20);
+        for ctx in &failed {
+            assert!(store.get(&ctx.id).await.unwrap().is_none());
+        }
+        assert!(!store
+            .domain_index
+            .read()
+            .await
+            .contains_key(&ContextDomain::Documentation));
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_explicit_flush_survives_reopen() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::builder()
+            .persist_path(temp_dir.path())
+            .flush_policy(FlushPolicy::Never)
+            .build();
+        let store = ContextStore::new(config.clone()).unwrap();
+        let id = store
+            .store(Context::new("flushed on request", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        store.flush().await.unwrap();
+        drop(store);
+
+        let reopened = ContextStore::new(config).unwrap();
+        let ctx = reopened.get(&id).await.unwrap().unwrap();
+        assert_eq!(ctx.content, "flushed on request");
+
+        let zero = StorageConfig::builder()
+            .flush_policy(FlushPolicy::Interval(std::time::Duration::ZERO))
+            .build();
+        assert!(matches!(
+            ContextStore::new(zero),
+            Err(ContextError::Config(_))
+        ));
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_disk_usage_by_domain() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(100, temp_dir.path());
+        let store = ContextStore::new(config).unwrap();
+
+        store
+            .store(Context::new("fn main() {}", ContextDomain::Code))
+            .await
+            .unwrap();
+        store
+            .store(Context::new("fn helper() {}", ContextDomain::Code))
+            .await
+            .unwrap();
+        store
+            .store(Context::new("Some prose", ContextDomain::Documentation))
+            .await
+            .unwrap();
+
+        let usage = store.get_disk_usage_by_domain().unwrap();
+        assert_eq!(usage.len(), 2);
+        assert!(usage["Code"] > usage["Documentation"]);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_checkpoint_and_restore() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(100, temp_dir.path());
+        let store = ContextStore::new(config).unwrap();
+
+        let kept = Context::new("before checkpoint", ContextDomain::Code);
+        let kept_id = store.store(kept).await.unwrap().id;
+
+        let checkpoint = store.create_sled_checkpoint().unwrap();
+        assert!(checkpoint.starts_with(temp_dir.path().join("checkpoints")));
+
+        let later_id = store
+            .store(Context::new("after checkpoint", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        store.delete(&kept_id).await.unwrap();
+
+        store.restore_from_checkpoint(&checkpoint).await.unwrap();
+
+        assert!(store.get(&kept_id).await.unwrap().is_some());
+        assert!(store.get(&later_id).await.unwrap().is_none());
+        let code = store
+            .query(&ContextQuery::new().with_domain(ContextDomain::Code))
+            .await
+            .unwrap();
+        assert_eq!(code.len(), 1);
+
+        // The checkpoint itself is left intact for later restores
+        assert!(checkpoint.is_dir());
+        assert_ne!(store.create_sled_checkpoint().unwrap(), checkpoint);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_restore_drops_redirects_and_events_after_checkpoint() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = StorageConfig::with_persistence(100, temp_dir.path());
+        let store = ContextStore::new(config).unwrap();
+        let primary = store
+            .store(Context::new("primary", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        let secondary = store
+            .store(Context::new("secondary", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        let checkpoint = store.create_sled_checkpoint().unwrap();
+        let seq_at_checkpoint = store.audit_entries_since(0).last().unwrap().seq;
+
+        store
+            .merge(&primary, &secondary, MergeStrategy::PreferPrimary)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get(&secondary).await.unwrap().unwrap().id,
+            primary.clone()
+        );
+
+        store.restore_from_checkpoint(&checkpoint).await.unwrap();
+        assert_eq!(
+            store.get(&secondary).await.unwrap().unwrap().content,
+            "secondary"
+        );
+        assert_eq!(
+            store.audit_entries_since(0).last().unwrap().seq,
+            seq_at_checkpoint
+        );
+        let next = store
+            .store(Context::new("after restore", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        let events = store.audit_entries_since(seq_at_checkpoint + 1);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, next);
+
+        // Gone from disk too, so it stays gone after a reopen
+        let redirects = load_redirects(store.disk_store.as_ref().unwrap()).unwrap();
+        assert!(redirects.is_empty());
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_scheduled_snapshots() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().join("store");
+        let policy = SnapshotPolicy {
+            interval: std::time::Duration::from_millis(30),
+            // Inside the persist path, so snapshots must not copy each other
+            directory: root.join("snapshots"),
+            keep_last: 2,
+        };
+        let store = Arc::new(
+            ContextStore::new(
+                StorageConfig::builder()
+                    .persist_path(&root)
+                    .snapshot(policy)
+                    .build(),
+            )
+            .unwrap(),
+        );
+        let id = store
+            .store(Context::new("snapshotted", ContextDomain::Code))
+            .await
+            .unwrap()
+            .id;
+
+        let task = ContextStore::spawn_snapshot_task(&store).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        task.abort();
+        // Let a snapshot already running on the blocking pool finish
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let snapshots = store.list_snapshots().unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert!(store.counters().snapshot().snapshots > 2);
+        let status = store.snapshot_status();
+        assert!(status.is_healthy());
+        assert_eq!(status.last_snapshot.as_ref(), Some(&snapshots[1].name));
+        let newest = &snapshots[1];
+        assert!(newest.size_bytes > 0);
+        assert!(!newest.path.join("snapshots").exists());
+
+        store.delete(&id).await.unwrap();
+        store.restore_from_checkpoint(&newest.path).await.unwrap();
+        assert!(store.get(&id).await.unwrap().is_some());
+
+        let invalid = StorageConfig::builder()
+            .memory_only()
+            .snapshot(SnapshotPolicy {
+                interval: std::time::Duration::from_secs(60),
+                directory: temp_dir.path().join("unused"),
+                keep_last: 1,
+            })
+            .build();
+        assert!(matches!(
+            ContextStore::new(invalid),
+            Err(ContextError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_decay_importance_and_rebuild_indexes() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let mut idle = Context::new("idle", ContextDomain::Code);
+        idle.accessed_at = Utc::now() - Duration::days(30);
+        let idle_id = store.store(idle).await.unwrap().id;
+        store
+            .store(Context::new("fresh", ContextDomain::Code))
+            .await
+            .unwrap();
+
+        let changed = store
+            .decay_importance(0.5, Duration::days(7))
+            .await
+            .unwrap();
+        assert_eq!(changed, 1);
+
+        let top = store.get_top_n_by_importance(2, None).await.unwrap();
+        assert_eq!(top[1].id, idle_id);
+        assert_eq!(top[1].metadata.importance, 0.5);
+
+        assert_eq!(store.rebuild_indexes().await.unwrap(), 2);
+        let code = store
+            .query(&ContextQuery::new().with_domain(ContextDomain::Code))
+            .await
+            .unwrap();
+        assert_eq!(code.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_watch_importance_threshold() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let (high_tx, mut high_rx) = mpsc::channel(8);
+        let (low_tx, mut low_rx) = mpsc::channel(8);
+        store.watch_importance_threshold(0.6, high_tx);
+        store.watch_importance_threshold(0.2, low_tx);
+
+        let mut idle = Context::new("decaying", ContextDomain::Code);
+        idle.accessed_at = Utc::now() - Duration::days(30);
+        let id = store.store(idle).await.unwrap().id;
+
+        // 1.0 -> 0.5 crosses 0.6 only
+        store
+            .decay_importance(0.5, Duration::days(7))
+            .await
+            .unwrap();
+        assert_eq!(high_rx.try_recv().unwrap(), id);
+        assert!(low_rx.try_recv().is_err());
+
+        // 0.5 -> 0.25 crosses neither; 0.25 -> 0.125 crosses 0.2
+        store
+            .decay_importance(0.5, Duration::days(7))
+            .await
+            .unwrap();
+        store
+            .decay_importance(0.5, Duration::days(7))
+            .await
+            .unwrap();
+        assert!(high_rx.try_recv().is_err());
+        assert_eq!(low_rx.try_recv().unwrap(), id);
+
+        drop(high_rx);
+        let mut ctx = store.get(&id).await.unwrap().unwrap();
+        ctx.metadata.importance = 0.01;
+        store.store(ctx).await.unwrap();
+        assert_eq!(store.importance_watchers.lock().unwrap().len(), 1);
+    }
+
+    struct VerdictHook(ScreeningStatus);
+
+    #[async_trait::async_trait]
+    impl ScreeningHook for VerdictHook {
+        async fn screen(&self, context: &Context) -> ScreeningStatus {
+            assert_eq!(context.metadata.screening_status, ScreeningStatus::Pending);
+            self.0.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_screening_hook_applies_verdict_on_store() {
+        let store = ContextStore::new(StorageConfig::memory_only(100))
+            .unwrap()
+            .with_screening_hook(Arc::new(VerdictHook(ScreeningStatus::Flagged)));
+        let id = store
+            .store(Context::new("scanned", ContextDomain::Code))
+            .await
+            .unwrap()
+            .id;
+        let ctx = store.get(&id).await.unwrap().unwrap();
+        assert_eq!(ctx.metadata.screening_status, ScreeningStatus::Flagged);
+
+        // Unchanged content is not re-screened
+        store
+            .update_screening_status(&id, ScreeningStatus::Safe)
+            .await
+            .unwrap();
+        let ctx = store.get(&id).await.unwrap().unwrap();
+        assert_eq!(ctx.metadata.screening_status, ScreeningStatus::Safe);
+
+        let rejecting = ContextStore::new(StorageConfig::memory_only(100))
+            .unwrap()
+            .with_screening_hook(Arc::new(VerdictHook(ScreeningStatus::Unscreened)));
+        let result = rejecting
+            .store(Context::new("scanned", ContextDomain::Code))
+            .await;
+        assert!(matches!(result, Err(ContextError::ScreeningFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_update_screening_status_enforces_transitions() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let id = store
+            .store(Context::new("content", ContextDomain::Code))
+            .await
+            .unwrap()
+            .id;
+
+        store
+            .update_screening_status(&id, ScreeningStatus::Blocked)
+            .await
+            .unwrap();
+        let result = store
+            .update_screening_status(&id, ScreeningStatus::Safe)
+            .await;
+        assert!(matches!(result, Err(ContextError::ScreeningFailed(_))));
+
+        let previous = store
+            .update_screening_status(&id, ScreeningStatus::Pending)
+            .await
+            .unwrap();
+        assert_eq!(previous, ScreeningStatus::Blocked);
+        store
+            .update_screening_status(&id, ScreeningStatus::Safe)
+            .await
+            .unwrap();
+        // Goes through `update`: stamped, and not counted as a read
+        let peeked = store.peek(&id).await.unwrap().unwrap();
+        assert!(peeked.updated_at.is_some());
+        assert_eq!(peeked.access_count, 0);
+
+        // Each change is kept as a revision
+        let ctx = store.get(&id).await.unwrap().unwrap();
+        let statuses: Vec<_> = ctx
+            .history
+            .iter()
+            .map(|rev| rev.metadata.screening_status.clone())
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ScreeningStatus::Unscreened,
+                ScreeningStatus::Blocked,
+                ScreeningStatus::Pending
+            ]
+        );
+        assert!(store
+            .update_screening_status(&ContextId::new(), ScreeningStatus::Safe)
+            .await
+            .unwrap_err()
+            .is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_eviction_history() {
+        let store = ContextStore::new(StorageConfig {
+            eviction_history_size: 2,
+            ..StorageConfig::memory_only(2)
+        })
+        .unwrap();
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let ctx = Context::new(format!("entry {}", i), ContextDomain::General);
+            ids.push(store.store(ctx).await.unwrap().id);
+        }
+
+        // Three evictions, of which the last two are kept
+        let history = store.get_eviction_history(10);
+        let evicted: Vec<&ContextId> = history.iter().map(|(id, _)| id).collect();
+        assert_eq!(evicted, [&ids[1], &ids[2]]);
+        assert!(history[0].1 <= history[1].1);
+        assert_eq!(store.get_eviction_history(1)[0].0, ids[2]);
+        assert!(store.get(&ids[0]).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_language_detected_on_store() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let german = Context::new(
+            "Die Anfrage konnte nicht bearbeitet werden, weil der Speicher voll ist.",
+            ContextDomain::General,
+        );
+        let english = Context::new(
+            "The request could not be processed because the storage is full.",
+            ContextDomain::General,
+        );
+        // Prose-like, but code contexts are never classified
+        let code = Context::new(
+            "The request could not be processed because the disk is full.",
+            ContextDomain::Code,
+        );
+        for ctx in [german.clone(), english.clone(), code.clone()] {
+            store.store(ctx).await.unwrap();
+        }
+
+        let mut languages = Vec::new();
+        for id in [&german.id, &english.id, &code.id] {
+            languages.push(store.peek(id).await.unwrap().unwrap().metadata.language);
+        }
+        assert_eq!(
+            languages,
+            [Some("de".to_string()), Some("en".to_string()), None]
+        );
+
+        let german_only = store
+            .query(&ContextQuery::new().with_language("de"))
+            .await
+            .unwrap();
+        assert_eq!(german_only.len(), 1);
+        assert_eq!(german_only[0].id, german.id);
+
+        let store = ContextStore::new(
+            StorageConfig::builder()
+                .detect_language(false)
+                .memory_only()
+                .build(),
+        )
+        .unwrap();
+        let id = store.store(english).await.unwrap().id;
+        assert_eq!(
+            store.get(&id).await.unwrap().unwrap().metadata.language,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_text() {
+        let store = ContextStore::new(StorageConfig::builder().memory_only().build()).unwrap();
+        for (content, domain, importance) in [
+            ("Borrow checker rules", ContextDomain::Code, 0.3),
+            ("the borrow checker again", ContextDomain::Code, 0.8),
+            ("borrow checker notes", ContextDomain::General, 0.5),
+            ("unrelated", ContextDomain::Code, 1.0),
+        ] {
+            let ctx = Context::new(content, domain).with_importance(importance);
+            store.store(ctx).await.unwrap();
+        }
+
+        let all = store.query_text("BORROW", 10, None).await.unwrap();
+        let contents: Vec<&str> = all.iter().map(|ctx| ctx.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            [
+                "the borrow checker again",
+                "borrow checker notes",
+                "Borrow checker rules"
+            ]
+        );
+        let code = store
+            .query_text("borrow", 10, Some(&ContextDomain::Code))
+            .await
+            .unwrap();
+        assert_eq!(code.len(), 2);
+        assert_eq!(code[0].content, "the borrow checker again");
+    }
+
+    #[test]
+    fn test_storage_config_builder() {
+        let config = StorageConfig::builder()
+            .memory_cache_size(42)
+            .persist_path("/tmp/contexts")
+            .auto_cleanup(false)
+            .build();
+        assert_eq!(config.memory_cache_size, 42);
+        assert!(config.enable_persistence);
+        assert_eq!(config.persist_dir(), PathBuf::from("/tmp/contexts"));
+        assert!(!config.auto_cleanup);
+        assert_eq!(config.max_ttl_hours, StorageConfig::default().max_ttl_hours);
+
+        assert!(
+            !StorageConfig::builder()
+                .memory_only()
+                .build()
+                .enable_persistence
+        );
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_store_read_write() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let kept = store
+            .store(Context::new("kept", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+
+        let result = store.benchmark_store_read_write(20).await.unwrap();
+        assert!(result.store_ops_per_sec > 0.0);
+        assert!(result.get_ops_per_sec > 0.0);
+        assert!(result.query_ops_per_sec > 0.0);
+        assert_eq!(result.sled_write_latency_p99_us, 0);
+        // Only the pre-existing context survives the cleanup
+        assert_eq!(store.list_all_ids(None).await.unwrap(), vec![kept]);
+
+        assert!(matches!(
+            store.benchmark_store_read_write(0).await.unwrap_err(),
+            ContextError::InvalidQuery(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_compute_retrieval_recall() {
+        let store = Arc::new(ContextStore::new(StorageConfig::memory_only(100)).unwrap());
+        let rag = RagProcessor::with_defaults(store.clone());
+        // Text does not affect scores without embeddings, so importance fixes the ranking
+        let borrow = store
+            .store(Context::new("rust borrow checker", ContextDomain::Code).with_importance(0.9))
+            .await
+            .unwrap()
+            .id;
+        let gc = store
+            .store(
+                Context::new("python garbage collector", ContextDomain::Code).with_importance(0.2),
+            )
+            .await
+            .unwrap()
+            .id;
+
+        let report = store
+            .compute_retrieval_recall(
+                &[
+                    ("borrow checker".to_string(), vec![borrow]),
+                    (
+                        "garbage collector".to_string(),
+                        vec![gc.clone(), ContextId::new()],
+                    ),
+                ],
+                &rag,
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.queries, 2);
+        assert_eq!(report.avg_recall_at_1, 0.5);
+        assert_eq!(report.avg_recall_at_5, 0.75);
+        assert_eq!(report.mrr, 0.75);
+
+        let err = store
+            .compute_retrieval_recall(&[("anything".to_string(), Vec::new())], &rag)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ContextError::InvalidQuery(_)));
+    }
+
+    #[tokio::test]
+    async fn test_compute_domain_overlap() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let tagged = |content: &str, domain: ContextDomain, tags: &[&str]| {
+            Context::new(content, domain).with_tags(tags.iter().map(|t| t.to_string()).collect())
+        };
+        for ctx in [
+            tagged("a1", ContextDomain::Code, &["rust", "async"]),
+            tagged("a2", ContextDomain::Code, &["rust", "tokio"]),
+            tagged("b1", ContextDomain::Documentation, &["rust", "guide"]),
+        ] {
+            store.store(ctx).await.unwrap();
+        }
+
+        // {rust, async, tokio} vs {rust, guide}: 1 shared of 4
+        let overlap = store
+            .compute_domain_overlap(&ContextDomain::Code, &ContextDomain::Documentation)
+            .await
+            .unwrap();
+        assert!((overlap - 0.25).abs() < 1e-9);
+
+        let same = store
+            .compute_domain_overlap(&ContextDomain::Code, &ContextDomain::Code)
+            .await
+            .unwrap();
+        assert_eq!(same, 1.0);
+
+        let empty = store
+            .compute_domain_overlap(&ContextDomain::General, &ContextDomain::Research)
+            .await
+            .unwrap();
+        assert_eq!(empty, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_get_required() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let ctx = Context::new("Required context", ContextDomain::General);
+        let id = store.store(ctx).await.unwrap().id;
+
+        assert_eq!(store.get_required(&id).await.unwrap().id, id);
+
+        let missing = ContextId::new();
+        match store.get_required(&missing).await {
+            Err(ContextError::NotFound(detail)) => assert_eq!(detail, missing.to_string()),
+            other => panic!("expected NotFound, got {:?}", other.map(|c| c.id)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_domain_snapshot() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let ctx = Context::new(format!("code {}", i), ContextDomain::Code);
+            ids.push(store.store(ctx).await.unwrap().id);
+        }
+        store
+            .store(Context::new("general", ContextDomain::General))
+            .await
+            .unwrap();
+
+        let mut stream = store.stream_domain(&ContextDomain::Code).await;
+        // Neither a later insert nor a deletion changes what the stream visits
+        store
+            .store(Context::new("code late", ContextDomain::Code))
+            .await
+            .unwrap();
+        store.delete(&ids[2]).await.unwrap();
+
+        let mut streamed = Vec::new();
+        while let Some(ctx) = stream.next().await {
+            streamed.push(ctx.unwrap().id);
+        }
+        let mut expected = ids[..2].to_vec();
+        expected.sort();
+        assert_eq!(streamed, expected);
+    }
+
+    #[tokio::test]
+    async fn test_clone_to_new_store() {
+        let source = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let ctx = Context::new(format!("migrate {}", i), ContextDomain::Code);
+            ids.push(source.store(ctx).await.unwrap().id);
+        }
+        source
+            .merge(&ids[0], &ids[1], MergeStrategy::default())
+            .await
+            .unwrap();
+
+        let dest = source
+            .clone_to_new_store(StorageConfig::memory_only(100))
+            .await
+            .unwrap();
+        assert!(dest.get(&ids[0]).await.unwrap().is_some());
+        assert!(dest.get(&ids[2]).await.unwrap().is_some());
+        // The merged-away ID still resolves in the copy
+        assert_eq!(dest.get(&ids[1]).await.unwrap().unwrap().id, ids[0]);
+        assert_eq!(
+            dest.query(&ContextQuery::new().with_domain(ContextDomain::Code))
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_context_size_histogram() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        for len in [10, 50, 500, 5_000, 200_000] {
+            let content = format!("{}{}", len, "x".repeat(len - len.to_string().len()));
+            store
+                .store(Context::new(content, ContextDomain::General))
+                .await
+                .unwrap();
+        }
+
+        let histogram = store.get_context_size_histogram().await.unwrap();
+        let counts: Vec<usize> = histogram.buckets.iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![2, 1, 1, 0, 1]);
+        assert_eq!(histogram.buckets[1].lower_chars, 100);
+        assert_eq!(histogram.buckets[1].upper_chars, 1_000);
+        assert_eq!(histogram.median_chars, 500);
+        assert_eq!(histogram.p95_chars, 200_000);
+        assert_eq!(histogram.max_chars, 200_000);
+        assert_eq!(histogram.mean_chars, 205_560.0 / 5.0);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_embeddings() {
+        use crate::embeddings::MockEmbeddingGenerator;
+
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        for i in 0..3 {
+            store
+                .store(Context::new(format!("code {}", i), ContextDomain::Code))
+                .await
+                .unwrap();
+        }
+        store
+            .store(Context::new("embedded", ContextDomain::Code).with_embedding(vec![1.0; 8]))
+            .await
+            .unwrap();
+        store
+            .store(Context::new("general", ContextDomain::General))
+            .await
+            .unwrap();
+
+        let missing = store
+            .find_contexts_without_embeddings(Some(&ContextDomain::Code))
+            .await
+            .unwrap();
+        assert_eq!(missing.len(), 3);
+
+        let generator = Arc::new(MockEmbeddingGenerator::new(8));
+        let embedded = store
+            .backfill_embeddings(Some(&ContextDomain::Code), 2, generator.clone())
+            .await
+            .unwrap();
+        assert_eq!(embedded, 3);
+        assert!(store
+            .find_contexts_without_embeddings(Some(&ContextDomain::Code))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Everything else, here only the General context, is still missing
+        let rest = store.find_contexts_without_embeddings(None).await.unwrap();
+        assert_eq!(rest.len(), 1);
+        assert!(store.backfill_embeddings(None, 0, generator).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_context_lineage() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let root = store
+            .store(Context::new("root", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        let middle = store
+            .store(Context::new("middle", ContextDomain::General).with_parent(root.clone()))
+            .await
+            .unwrap()
+            .id;
+        let leaf = store
+            .store(Context::new("leaf", ContextDomain::General).with_parent(middle.clone()))
+            .await
+            .unwrap()
+            .id;
+
+        let lineage = store.get_context_lineage(&middle, 10).await.unwrap();
+        assert_eq!(lineage.context.id, middle);
+        assert_eq!(lineage.ancestors.len(), 1);
+        assert_eq!(lineage.ancestors[0].id, root);
+        assert_eq!(lineage.descendants.len(), 1);
+        assert_eq!(lineage.descendants[0].id, leaf);
+
+        let shallow = store.get_context_lineage(&leaf, 1).await.unwrap();
+        let ancestors: Vec<ContextId> = shallow.ancestors.into_iter().map(|c| c.id).collect();
+        assert_eq!(ancestors, vec![middle]);
+    }
+
+    #[tokio::test]
+    async fn test_get_by_content_hash() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let ctx = Context::new("hashed content", ContextDomain::General);
+        let hash = ctx.content_hash();
+        let id = store.store(ctx).await.unwrap().id;
+
+        let found = store.get_by_content_hash(&hash).await.unwrap().unwrap();
+        assert_eq!(found.id, id);
+        let upper = store
+            .get_by_content_hash(&hash.to_uppercase())
+            .await
+            .unwrap();
+        assert!(upper.is_some());
+
+        store.delete(&id).await.unwrap();
+        assert!(store.get_by_content_hash(&hash).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_with_remote() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let local = store
+            .store(Context::new("local", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        let shared = store
+            .store(Context::new("shared", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        let remote = ContextId::from_string("remote".into());
+
+        let stats = store
+            .reconcile_with_remote(&[shared.clone(), remote.clone(), remote.clone()])
+            .await
+            .unwrap();
+        assert_eq!(stats.local_only, vec![local]);
+        assert_eq!(stats.remote_only, vec![remote]);
+        assert_eq!(stats.shared, vec![shared]);
+    }
+
+    #[tokio::test]
+    async fn test_enforce_source_allowlist() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        for (content, source) in [
+            ("a", "https://docs.internal/a"),
+            ("b", "https://docs.internal"),
+            ("c", "https://elsewhere/c"),
+            ("d", ""),
+        ] {
+            store
+                .store(Context::new(content, ContextDomain::General).with_source(source))
+                .await
+                .unwrap();
+        }
+
+        let allowed = ["https://docs.internal"];
+        let exact = store
+            .enforce_source_allowlist(&allowed, SourceMatchMode::Exact, true)
+            .await
+            .unwrap();
+        assert_eq!(exact, 3);
+        let prefix = store
+            .enforce_source_allowlist(&allowed, SourceMatchMode::Prefix, true)
+            .await
+            .unwrap();
+        assert_eq!(prefix, 2);
+        assert_eq!(store.all_contexts().await.unwrap().len(), 4);
+
+        let removed = store
+            .enforce_source_allowlist(&["https://docs.internal/*"], SourceMatchMode::Exact, false)
+            .await
+            .unwrap();
+        assert_eq!(removed, 3);
+        assert_eq!(store.all_contexts().await.unwrap().len(), 1);
+
+        assert!(store
+            .enforce_source_allowlist(&[], SourceMatchMode::Prefix, true)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_migrate_to_versioned() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let mut ids = Vec::new();
+        for content in ["legacy one", "legacy two"] {
+            let mut legacy = Context::new(content, ContextDomain::Code);
+            legacy.version = 0;
+            ids.push(store.store(legacy).await.unwrap().id);
+        }
+        store
+            .store(Context::new("current", ContextDomain::Code))
+            .await
+            .unwrap();
+
+        store.convert_to_versioned(&ids[0]).await.unwrap();
+        let ctx = store.get(&ids[0]).await.unwrap().unwrap();
+        assert_eq!(ctx.version, 1);
+        assert!(ctx.history.is_empty());
+
+        assert_eq!(store.migrate_all_to_versioned().await.unwrap(), 1);
+        assert_eq!(store.migrate_all_to_versioned().await.unwrap(), 0);
+        store.convert_to_versioned(&ids[1]).await.unwrap();
+        assert_eq!(store.get(&ids[1]).await.unwrap().unwrap().version, 1);
+    }
+
+    #[tokio::test]
+    async fn test_content_similarity_clusters() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let mut ids = Vec::new();
+        for (i, embedding) in [vec![1.0, 0.0], vec![0.99, 0.1], vec![0.0, 1.0]]
+            .into_iter()
+            .enumerate()
+        {
+            let ctx = Context::new(format!("embedded {}", i), ContextDomain::Code)
+                .with_embedding(embedding);
+            ids.push(store.store(ctx).await.unwrap().id);
+        }
+        store
+            .store(Context::new("no embedding", ContextDomain::Code))
+            .await
+            .unwrap();
+        let other = Context::new("other", ContextDomain::General).with_embedding(vec![1.0, 0.0]);
+        let other_id = store.store(other).await.unwrap().id;
+
+        let clusters = store
+            .content_similarity_clusters(0.9, Some(&ContextDomain::Code))
+            .await
+            .unwrap();
+        let mut expected = vec![ids[0].clone(), ids[1].clone()];
+        expected.sort();
+        assert_eq!(clusters, vec![expected]);
+
+        let clusters = store.content_similarity_clusters(0.9, None).await.unwrap();
+        assert_eq!(clusters.len(), 1);
+        assert!(clusters[0].contains(&other_id));
+
+        let limited = ContextStore::new(StorageConfig {
+            max_cluster_contexts: 1,
+            ..StorageConfig::memory_only(100)
+        })
+        .unwrap();
+        for i in 0..2 {
+            let ctx = Context::new(format!("embedded {}", i), ContextDomain::Code)
+                .with_embedding(vec![1.0]);
+            limited.store(ctx).await.unwrap();
+        }
+        assert!(limited
+            .content_similarity_clusters(0.5, None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dump_index_statistics_counts_orphans() {
+        let config = StorageConfig {
+            index_stats_sample_fraction: 1.0,
+            ..StorageConfig::memory_only(100)
+        };
+        let store = ContextStore::new(config).unwrap();
+        let ctx = Context::new("indexed", ContextDomain::Code)
+            .with_tags(vec!["a".to_string(), "b".to_string()]);
+        store.store(ctx).await.unwrap();
+
+        let stats = store.dump_index_statistics().await.unwrap();
+        assert_eq!(stats.domain_index_total_entries, 1);
+        assert_eq!(stats.tag_index_total_entries, 2);
+        assert_eq!(stats.domain_index_live_ratio, 1.0);
+        assert_eq!(stats.estimated_wasted_bytes, 0);
+
+        store
+            .domain_index
+            .write()
+            .await
+            .entry(ContextDomain::Code)
+            .or_default()
+            .insert(ContextId::from_string("ghost".into()));
+
+        let stats = store.dump_index_statistics().await.unwrap();
+        assert_eq!(stats.domain_index_total_entries, 2);
+        assert_eq!(stats.domain_index_live_ratio, 0.5);
+        assert_eq!(stats.tag_index_live_ratio, 1.0);
+        assert!(stats.estimated_wasted_bytes >= std::mem::size_of::<ContextId>());
+    }
+
+    #[tokio::test]
+    async fn test_apply_decay_strategy() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let mut old = Context::new("old", ContextDomain::Code);
+        old.created_at = Utc::now() - Duration::hours(24);
+        let old_id = store.store(old).await.unwrap().id;
+        let mut other = Context::new("other domain", ContextDomain::Documentation);
+        other.created_at = Utc::now() - Duration::hours(24);
+        store.store(other).await.unwrap();
+
+        let strategy = DecayStrategy::ExponentialByAge {
+            half_life_hours: 24.0,
+        };
+        let code = ContextDomain::Code;
+        assert_eq!(
+            store
+                .apply_decay_strategy(strategy, Some(&code), true)
+                .await
+                .unwrap(),
+            1
+        );
+        let importance = store
+            .get(&old_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .metadata
+            .importance;
+        assert_eq!(importance, 1.0);
+
+        store
+            .apply_decay_strategy(strategy, Some(&code), false)
+            .await
+            .unwrap();
+        let importance = store
+            .get(&old_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .metadata
+            .importance;
+        assert!((importance - 0.5).abs() < 0.01);
+
+        let zero = DecayStrategy::SetToZeroIfOlderThan {
+            threshold_hours: 12.0,
+        };
+        assert_eq!(
+            store.apply_decay_strategy(zero, None, false).await.unwrap(),
+            2
+        );
+        assert!(store
+            .apply_decay_strategy(
+                DecayStrategy::ExponentialByAge {
+                    half_life_hours: 0.0
+                },
+                None,
+                true
+            )
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_content_vocabulary() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        for content in [
+            "Rust is fast, rust is safe",
+            "The Rust compiler",
+            "Go is fast",
+        ] {
+            store
+                .store(Context::new(content, ContextDomain::Code))
+                .await
+                .unwrap();
+        }
+
+        let vocab = store.get_content_vocabulary(2, 10, None).await.unwrap();
+        assert_eq!(vocab.len(), 3);
+        assert_eq!(vocab["rust"], 3);
+        assert_eq!(vocab["is"], 3);
+        assert_eq!(vocab["fast"], 2);
+
+        let stop: HashSet<String> = ["is".to_string()].into();
+        let vocab = store
+            .get_content_vocabulary(1, 2, Some(stop))
+            .await
+            .unwrap();
+        assert_eq!(vocab.len(), 2);
+        assert!(vocab.contains_key("rust") && vocab.contains_key("fast"));
+    }
+
+    #[tokio::test]
+    async fn test_store_records_revisions() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let ctx = Context::new("v1", ContextDomain::General);
+        let id = store.store(ctx.clone()).await.unwrap().id;
+
+        // An identical re-store is not a new revision
+        store.store(ctx.clone()).await.unwrap();
+        assert_eq!(store.get(&id).await.unwrap().unwrap().version, 1);
+
+        for n in 2..=MAX_REVISIONS as u32 + 3 {
+            let mut next = store.get(&id).await.unwrap().unwrap();
+            next.content = format!("v{}", n);
+            store.store(next).await.unwrap();
+        }
+
+        let current = store.get(&id).await.unwrap().unwrap();
+        assert_eq!(current.version, MAX_REVISIONS as u32 + 3);
+        assert_eq!(current.history.len(), MAX_REVISIONS);
+        assert_eq!(current.history[0].version, 3);
+        assert_eq!(current.at_revision(4).unwrap().content, "v4");
+        assert!(current.at_revision(2).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rollback_to_revision() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let id = store
+            .store(Context::new("v1", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        let mut next = store.get(&id).await.unwrap().unwrap();
+        next.content = "v2".to_string();
+        store.store(next).await.unwrap();
+
+        store.rollback_to_revision(&id, 1).await.unwrap();
+        let current = store.get(&id).await.unwrap().unwrap();
+        assert_eq!(current.content, "v1");
+        assert_eq!(current.version, 3);
+        assert_eq!(current.at_revision(2).unwrap().content, "v2");
+
+        let err = store.rollback_to_revision(&id, 9).await.unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_get_related_by_tags() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let tagged = |content: &str, tags: &[&str]| {
+            Context::new(content, ContextDomain::General)
+                .with_tags(tags.iter().map(|t| t.to_string()).collect())
+        };
+        let source = store
+            .store(tagged("source", &["rust", "async", "tokio"]))
+            .await
+            .unwrap()
+            .id;
+        for (content, tags) in [
+            ("two", vec!["rust", "async"]),
+            ("three", vec!["rust", "async", "tokio"]),
+            ("one", vec!["tokio", "io"]),
+            ("none", vec!["python"]),
+        ] {
+            store.store(tagged(content, &tags)).await.unwrap();
+        }
+
+        let related = store.get_related_by_tags(&source, 1, 10).await.unwrap();
+        let found: Vec<(&str, usize)> = related
+            .iter()
+            .map(|(ctx, n)| (ctx.content.as_str(), *n))
+            .collect();
+        assert_eq!(found, [("three", 3), ("two", 2), ("one", 1)]);
+
+        let related = store.get_related_by_tags(&source, 2, 1).await.unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].0.content, "three");
+    }
+
+    #[tokio::test]
+    async fn test_auto_tag() {
+        struct Fixed;
+        impl AutoTagger for Fixed {
+            fn suggest_tags(&self, _content: &str, _existing: &[String]) -> Vec<String> {
+                vec!["rust".into(), "async".into(), "async".into()]
+            }
+        }
+
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let ctx = Context::new("Async Rust", ContextDomain::Code).with_tags(vec!["rust".into()]);
+        let id = store.store(ctx).await.unwrap().id;
+
+        let added = store.auto_tag(&id, &Fixed).await.unwrap();
+        assert_eq!(added, vec!["async"]);
+        let tags = store.get(&id).await.unwrap().unwrap().metadata.tags;
+        assert_eq!(tags, vec!["rust", "async"]);
+
+        // The tag index picks up the new tag
+        let tagged = store
+            .query(&ContextQuery::new().with_tag("async".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(tagged.len(), 1);
+
+        assert!(store.auto_tag(&id, &Fixed).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_merge_contexts() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let primary = Context::new("Rust has ownership", ContextDomain::Code)
+            .with_tags(vec!["rust".to_string()])
+            .with_importance(0.4);
+        let mut secondary = Context::new("Ownership prevents leaks", ContextDomain::Code)
+            .with_tags(vec!["memory".to_string(), "rust".to_string()])
+            .with_importance(0.8)
+            .with_thread("chat-1");
+        secondary.created_at = primary.created_at - Duration::days(1);
+        let reply = Context::new("Reply", ContextDomain::Code).with_parent(secondary.id.clone());
+        let (p, s, r) = (primary.id.clone(), secondary.id.clone(), reply.id.clone());
+        let secondary_created = secondary.created_at;
+        for ctx in [primary, secondary, reply] {
+            store.store(ctx).await.unwrap();
+        }
+
+        let planned = store
+            .plan_merge(&p, &s, MergeStrategy::Concatenate)
+            .await
+            .unwrap();
+        // Planning writes nothing
+        assert_eq!(store.get(&s).await.unwrap().unwrap().id, s);
+
+        let merged = store
+            .merge(&p, &s, MergeStrategy::Concatenate)
+            .await
+            .unwrap();
+        assert_eq!(merged.content, planned.content);
+        assert_eq!(
+            merged.content,
+            "Rust has ownership\n\nOwnership prevents leaks"
+        );
+        assert_eq!(merged.metadata.tags, vec!["rust", "memory"]);
+        assert_eq!(merged.metadata.importance, 0.8);
+        assert_eq!(merged.created_at, secondary_created);
+        assert_eq!(merged.thread_id.as_deref(), Some("chat-1"));
+
+        // The old ID redirects, the reply is re-parented, the thread follows
+        assert_eq!(store.get(&s).await.unwrap().unwrap().id, p);
+        assert_eq!(
+            store.get(&r).await.unwrap().unwrap().parent_id,
+            Some(p.clone())
+        );
+        assert_eq!(store.get_thread("chat-1").await.unwrap()[0].id, p);
+        assert!(store.assert_domain_invariants().await.unwrap().is_empty());
+
+        assert!(store
+            .merge(&p, &p, MergeStrategy::PreferPrimary)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_paginate_by_created_at() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let epoch = DateTime::<Utc>::UNIX_EPOCH;
+        let mut times = Vec::new();
+        for i in 0..5 {
+            let mut ctx = Context::new(format!("event {}", i), ContextDomain::General);
+            ctx.created_at = epoch + Duration::minutes(i);
+            times.push(ctx.created_at);
+            store.store(ctx).await.unwrap();
+        }
+        let contents = |page: &Page<Context>| -> Vec<String> {
+            page.items.iter().map(|ctx| ctx.content.clone()).collect()
+        };
+
+        let first = store
+            .paginate_by_created_at(2, None, PaginationDir::Forward)
+            .await
+            .unwrap();
+        assert_eq!(contents(&first), ["event 0", "event 1"]);
+        assert_eq!(first.total_count, 5);
+        assert_eq!(first.next_cursor, Some(times[1]));
+        assert_eq!(first.prev_cursor, None);
+
+        let second = store
+            .paginate_by_created_at(2, first.next_cursor, PaginationDir::Forward)
+            .await
+            .unwrap();
+        assert_eq!(contents(&second), ["event 2", "event 3"]);
+        assert_eq!(second.prev_cursor, Some(times[2]));
+
+        let last = store
+            .paginate_by_created_at(2, second.next_cursor, PaginationDir::Forward)
+            .await
+            .unwrap();
+        assert_eq!(contents(&last), ["event 4"]);
+        assert_eq!(last.next_cursor, None);
+
+        // Going back from the second page returns the first
+        let back = store
+            .paginate_by_created_at(2, second.prev_cursor, PaginationDir::Backward)
+            .await
+            .unwrap();
+        assert_eq!(contents(&back), ["event 1", "event 0"]);
+        assert_eq!(back.next_cursor, None);
+
+        let newest = store
+            .paginate_by_created_at(2, None, PaginationDir::Backward)
+            .await
+            .unwrap();
+        assert_eq!(contents(&newest), ["event 4", "event 3"]);
+        assert_eq!(newest.prev_cursor, None);
+
+        // Cursors at the very start and end of time
+        let min = DateTime::<Utc>::MIN_UTC;
+        let max = DateTime::<Utc>::MAX_UTC;
+        let from_min = store
+            .paginate_by_created_at(2, Some(min), PaginationDir::Forward)
+            .await
+            .unwrap();
+        assert_eq!(contents(&from_min), ["event 0", "event 1"]);
+        let past_max = store
+            .paginate_by_created_at(2, Some(max), PaginationDir::Forward)
+            .await
+            .unwrap();
+        assert!(past_max.items.is_empty());
+        assert_eq!(past_max.next_cursor, None);
+        let before_min = store
+            .paginate_by_created_at(2, Some(min), PaginationDir::Backward)
+            .await
+            .unwrap();
+        assert!(before_min.items.is_empty());
+        let from_max = store
+            .paginate_by_created_at(2, Some(max), PaginationDir::Backward)
+            .await
+            .unwrap();
+        assert_eq!(contents(&from_max), ["event 4", "event 3"]);
+
+        // The oldest context sits exactly on the epoch and is excluded by it
+        let after_epoch = store
+            .paginate_by_created_at(10, Some(epoch), PaginationDir::Forward)
+            .await
+            .unwrap();
+        assert_eq!(after_epoch.items.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_search_by_creation_window() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let epoch = DateTime::<Utc>::UNIX_EPOCH;
+        let mut ids = Vec::new();
+        for i in 0..4 {
+            let mut ctx = Context::new(format!("event {}", i), ContextDomain::General);
+            ctx.created_at = epoch + Duration::hours(i.min(2));
+            ids.push(store.store(ctx).await.unwrap().id);
+        }
+
+        let window = store
+            .search_by_creation_window(epoch + Duration::hours(1), epoch + Duration::hours(2), 10)
+            .await
+            .unwrap();
+        let mut tied = ids[2..].to_vec();
+        tied.sort();
+        let expected: Vec<ContextId> = std::iter::once(ids[1].clone()).chain(tied).collect();
+        assert_eq!(
+            window.iter().map(|ctx| ctx.id.clone()).collect::<Vec<_>>(),
+            expected
+        );
+
+        let limited = store
+            .search_by_creation_window(epoch, epoch + Duration::hours(2), 2)
+            .await
+            .unwrap();
+        assert_eq!(limited.len(), 2);
+        assert!(store
+            .search_by_creation_window(epoch + Duration::hours(1), epoch, 10)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stalest_contexts() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let mut ids = Vec::new();
+        for days in [3, 10, 5] {
+            let mut ctx = Context::new(format!("idle {} days", days), ContextDomain::General);
+            ctx.accessed_at = Utc::now() - Duration::days(days);
+            ids.push(store.store(ctx).await.unwrap().id);
+        }
+
+        let stalest = store.get_stalest_contexts(2).await.unwrap();
+        assert_eq!(stalest.len(), 2);
+        assert_eq!(stalest[0].0.id, ids[1]);
+        assert_eq!(stalest[1].0.id, ids[2]);
+        assert!(stalest[0].1 > stalest[1].1);
+        assert!((stalest[0].1 - 240.0).abs() < 0.1);
+
+        // Accessing a context makes it the freshest
+        store.get(&ids[1]).await.unwrap();
+        let stalest = store.get_stalest_contexts(3).await.unwrap();
+        assert_eq!(stalest[0].0.id, ids[2]);
+        assert_eq!(stalest[2].0.id, ids[1]);
+    }
+
+    #[tokio::test]
+    async fn test_enforce_ttl_bounds() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let short = store
+            .store(
+                Context::new("short", ContextDomain::General)
+                    .with_expiration(Utc::now() + Duration::hours(1)),
+            )
+            .await
+            .unwrap()
+            .id;
+        let long = store
+            .store(
+                Context::new("long", ContextDomain::General)
+                    .with_expiration(Utc::now() + Duration::days(30)),
+            )
+            .await
+            .unwrap()
+            .id;
+        let forever = store
+            .store(Context::new("forever", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+
+        assert_eq!(
+            store
+                .enforce_ttl_minimum(Duration::hours(24))
+                .await
+                .unwrap(),
+            1
+        );
+        let extended = store.get(&short).await.unwrap().unwrap();
+        assert!(extended.expires_at.unwrap() > Utc::now() + Duration::hours(23));
+        assert!(store
+            .get(&forever)
+            .await
+            .unwrap()
+            .unwrap()
+            .expires_at
+            .is_none());
+
+        assert_eq!(
+            store.enforce_ttl_maximum(Duration::days(7)).await.unwrap(),
+            2
+        );
+        for id in [&long, &forever] {
+            let capped = store.get(id).await.unwrap().unwrap().expires_at.unwrap();
+            assert!(capped <= Utc::now() + Duration::days(7));
+        }
+        // Already within the limit
+        let short_exp = store
+            .get(&short)
+            .await
+            .unwrap()
+            .unwrap()
+            .expires_at
+            .unwrap();
+        assert!(short_exp < Utc::now() + Duration::days(2));
+    }
+
+    #[tokio::test]
+    async fn test_store_reports_previous_version() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+
+        let first = Context::new("same content", ContextDomain::Code).with_tags(vec!["a".into()]);
+        let outcome = store.store(first).await.unwrap();
+        assert!(!outcome.existed());
+
+        let second = Context::new("same content", ContextDomain::Code).with_tags(vec!["b".into()]);
+        let outcome = store.store_if_absent(second.clone()).await.unwrap();
+        assert_eq!(outcome.previous.unwrap().metadata.tags, vec!["a"]);
+        assert_eq!(
+            store.get(&outcome.id).await.unwrap().unwrap().metadata.tags,
+            vec!["a"]
+        );
+
+        let outcome = store.store(second).await.unwrap();
+        assert!(outcome.existed());
+        assert_eq!(outcome.previous.unwrap().metadata.tags, vec!["a"]);
+        assert_eq!(
+            store.get(&outcome.id).await.unwrap().unwrap().metadata.tags,
+            vec!["b"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_write_ratio() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let id = store
+            .store(Context::new("counted", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        for _ in 0..3 {
+            store.get(&id).await.unwrap();
+        }
+        store.delete(&id).await.unwrap();
+
+        let stats = store.get_read_write_ratio().unwrap();
+        assert_eq!((stats.reads, stats.writes, stats.deletes), (3, 1, 1));
+        assert_eq!(stats.ratio, 1.5);
+
+        store.reset_counters();
+        let stats = store.get_read_write_ratio().unwrap();
+        assert_eq!((stats.reads, stats.writes, stats.deletes), (0, 0, 0));
+        assert_eq!(stats.ratio, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_observer_sees_operations() {
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<String>>);
+
+        impl StoreObserver for Recorder {
+            fn on_store(&self, context: &Context) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("store {}", context.content));
+            }
+
+            fn on_get(&self, hit: bool) {
+                self.0.lock().unwrap().push(format!("get {}", hit));
+            }
+
+            fn on_delete(&self, _id: &ContextId, found: bool) {
+                self.0.lock().unwrap().push(format!("delete {}", found));
+            }
+        }
+
+        let recorder = Arc::new(Recorder::default());
+        let store = ContextStore::new(StorageConfig::memory_only(100))
+            .unwrap()
+            .with_observer(recorder.clone());
+        let id = store
+            .store(Context::new("seen", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        store.get(&id).await.unwrap();
+        store.delete(&id).await.unwrap();
+        store.get(&id).await.unwrap();
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            ["store seen", "get true", "delete true", "get false"]
+        );
+        assert_eq!(store.counters().snapshot().gets, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stats_with_optional_sections() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let id = store
+            .store(Context::new("fn main() {}", ContextDomain::Code))
+            .await
+            .unwrap()
+            .id;
+        store
+            .store(Context::new("notes", ContextDomain::General))
+            .await
+            .unwrap();
+        store.get(&id).await.unwrap();
+        store
+            .get(&ContextId::from_string("missing".into()))
+            .await
+            .unwrap();
+
+        let cheap = store.stats().await;
+        assert_eq!(cheap.cache_hit_rate, Some(0.5));
+        assert!(cheap.memory_bytes.is_none() && cheap.counts_by_domain.is_none());
+
+        let full = store
+            .stats_with(StatsInclude {
+                domains: true,
+                sizes: true,
+            })
+            .await
+            .unwrap();
+        assert!(full.memory_bytes.unwrap() > "fn main() {}notes".len());
+        let domains = full.counts_by_domain.unwrap();
+        assert_eq!(domains["Code"], 1);
+        assert_eq!(domains["General"], 1);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_stats_counters_track_cache_traffic() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = ContextStore::new(StorageConfig::with_persistence(1, temp_dir.path())).unwrap();
+        let mut ctx = Context::new("short lived", ContextDomain::General);
+        ctx.expires_at = Some(Utc::now() + Duration::milliseconds(50));
+        let evicted = store.store(ctx).await.unwrap().id;
+        let cached = store
+            .store(Context::new("evicts it", ContextDomain::General))
+            .await
+            .unwrap()
+            .id;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let before = store.stats().await;
+        assert_eq!((before.cache_hits, before.cache_misses), (0, 0));
+        assert_eq!(before.evictions, 1);
+        // Counted from the expiry index even though it is only on disk
+        assert_eq!(before.expired_pending_cleanup, 1);
+
+        store.get(&cached).await.unwrap();
+        store.get(&evicted).await.unwrap();
+        store
+            .get(&ContextId::from_string("missing".into()))
+            .await
+            .unwrap();
+        let after = store.stats().await;
+        assert_eq!((after.cache_hits, after.cache_misses), (1, 2));
+
+        store.cleanup_expired().await.unwrap();
+        assert_eq!(store.stats().await.expired_pending_cleanup, 0);
+    }
+
+    #[tokio::test]
+    async fn test_domain_invariants() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let id = store
+            .store(Context::new("consistent", ContextDomain::Code))
+            .await
+            .unwrap()
+            .id;
+        assert!(store.assert_domain_invariants().await.unwrap().is_empty());
+
+        // Corrupt the index: a dangling entry and a context missing from it
+        {
+            let mut domain_idx = store.domain_index.write().await;
+            domain_idx.clear();
+            domain_idx
+                .entry(ContextDomain::General)
+                .or_default()
+                .insert(ContextId::from_string("ghost".into()));
+        }
+
+        let violations = store.assert_domain_invariants().await.unwrap();
+        let kinds: Vec<ViolationKind> = violations.iter().map(|v| v.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ViolationKind::DanglingIndexEntry,
+                ViolationKind::UnindexedContext
+            ]
+        );
+        assert_eq!(violations[1].context_id, Some(id));
+
+        store.rebuild_indexes().await.unwrap();
+        assert!(store.assert_domain_invariants().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_cache_size_limit() {
+        let config = StorageConfig {
+            max_memory_cache_size: 50,
+            ..StorageConfig::memory_only(100)
+        };
+        assert!(matches!(
+            ContextStore::new(config),
+            Err(ContextError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_thread_survives_parent_delete() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let mut root = Context::new("Hello", ContextDomain::Conversation).with_thread("t1");
+        root.created_at = Utc::now() - Duration::minutes(2);
+        let root_id = store.store(root).await.unwrap().id;
+
+        let mut reply = Context::new("Hi there", ContextDomain::Conversation)
+            .with_thread("t1")
+            .with_parent(root_id.clone());
+        reply.created_at = Utc::now() - Duration::minutes(1);
+        let reply_id = store.store(reply).await.unwrap().id;
+
+        let follow_up = Context::new("How are you?", ContextDomain::Conversation)
+            .with_thread("t1")
+            .with_parent(reply_id.clone());
+        let follow_up_id = store.store(follow_up).await.unwrap().id;
+
+        let thread: Vec<ContextId> = store
+            .get_thread("t1")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(
+            thread,
+            vec![root_id.clone(), reply_id.clone(), follow_up_id.clone()]
+        );
+
+        store.delete(&root_id).await.unwrap();
+        let thread = store.get_thread("t1").await.unwrap();
+        assert_eq!(thread.len(), 2);
+        assert_eq!(thread[0].parent_id, Some(root_id));
+        assert!(store.get_thread("missing").await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("user", "user"));
+        assert!(!glob_match("user", "user2"));
+        assert!(glob_match(
+            "https://old-api.example.com/*",
+            "https://old-api.example.com/v1/x"
+        ));
+        assert!(!glob_match(
+            "https://old-api.example.com/*",
+            "https://new-api.example.com/v1"
+        ));
+        assert!(glob_match("file:*.rs", "file:src/main.rs"));
+        assert!(glob_match("web?", "web1"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_source() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+
+        for (content, source) in [
+            ("a", "https://old-api.example.com/a"),
+            ("b", "https://old-api.example.com/b/c"),
+            ("c", "https://new-api.example.com/a"),
+        ] {
+            store
+                .store(Context::new(content, ContextDomain::WebSearch).with_source(source))
+                .await
+                .unwrap();
+        }
+
+        let matches = store
+            .find_by_source("https://old-api.example.com/*")
+            .await
+            .unwrap();
+        assert_eq!(matches.len(), 2);
+
+        let removed = store
+            .cleanup_source("https://old-api.example.com/*")
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(store.all_contexts().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_top_n_by_importance() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let now = Utc::now();
+
+        let high = Context::new("high", ContextDomain::Code).with_importance(0.9);
+        let mut tie_old = Context::new("tie old", ContextDomain::Code).with_importance(0.5);
+        tie_old.accessed_at = now - chrono::Duration::hours(2);
+        let mut tie_new = Context::new("tie new", ContextDomain::Code).with_importance(0.5);
+        tie_new.accessed_at = now - chrono::Duration::hours(1);
+        let low = Context::new("low", ContextDomain::Code).with_importance(0.1);
+        let other = Context::new("other", ContextDomain::Research).with_importance(1.0);
+
+        for ctx in [&low, &tie_old, &high, &tie_new, &other] {
+            store.store(ctx.clone()).await.unwrap();
+        }
+
+        let top: Vec<String> = store
+            .get_top_n_by_importance(3, Some(&ContextDomain::Code))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.content)
+            .collect();
+        assert_eq!(top, vec!["high", "tie new", "tie old"]);
+
+        let top = store.get_top_n_by_importance(1, None).await.unwrap();
+        assert_eq!(top[0].content, "other");
+
+        // Re-storing with a new importance moves the entry instead of duplicating it
+        store.store(low.with_importance(0.95)).await.unwrap();
+        let top = store
+            .get_top_n_by_importance(10, Some(&ContextDomain::Code))
+            .await
+            .unwrap();
+        assert_eq!(top.len(), 4);
+        assert_eq!(top[0].content, "low");
+    }
+
+    #[tokio::test]
+    async fn test_sort_by_semantic_coherence() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let central = Context::new("central", ContextDomain::Code).with_embedding(vec![1.0, 1.0]);
+        let edge = Context::new("edge", ContextDomain::Code).with_embedding(vec![1.0, 0.0]);
+        let other = Context::new("other", ContextDomain::Code).with_embedding(vec![0.0, 1.0]);
+        let bare = Context::new("bare", ContextDomain::Code);
+        let central_id = central.id.clone();
+        let bare_id = bare.id.clone();
+        for ctx in [central, edge, other, bare] {
+            store.store(ctx).await.unwrap();
+        }
+        store
+            .store(Context::new("elsewhere", ContextDomain::General).with_embedding(vec![5.0, 0.0]))
+            .await
+            .unwrap();
+
+        let ranked = store
+            .sort_by_semantic_coherence(&ContextDomain::Code)
+            .await
+            .unwrap();
+        assert_eq!(ranked.len(), 4);
+        assert_eq!(ranked[0].0, central_id);
+        assert!((ranked[0].1 - 1.0).abs() < 1e-9);
+        assert_eq!(ranked[3], (bare_id, 0.0));
+    }
+
+    #[tokio::test]
+    async fn test_rebalance_importance() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let mut ids = Vec::new();
+        for i in 0..4 {
+            let ctx = Context::new(format!("Code {}", i), ContextDomain::Code);
+            ids.push(ctx.id.clone());
+            store.store(ctx).await.unwrap();
+        }
+        store
+            .store(Context::new("Other", ContextDomain::General))
+            .await
+            .unwrap();
+
+        let changed = store
+            .rebalance_importance(
+                &ContextDomain::Code,
+                RebalanceStrategy::Percentile { top_frac: 0.25 },
+            )
+            .await
+            .unwrap();
+        assert_eq!(changed, 3);
+
+        let top = store
+            .get_top_n_by_importance(10, Some(&ContextDomain::Code))
+            .await
+            .unwrap();
+        let scores: Vec<f32> = top.iter().map(|c| c.metadata.importance).collect();
+        assert_eq!(scores, vec![1.0, 0.75, 0.5, 0.25]);
+
+        // Other domains are untouched
+        let general = store
+            .get_top_n_by_importance(10, Some(&ContextDomain::General))
+            .await
+            .unwrap();
+        assert_eq!(general[0].metadata.importance, 1.0);
+
+        // Access-based: the only accessed context ranks first
+        store.get(&ids[2]).await.unwrap();
+        store
+            .rebalance_importance(&ContextDomain::Code, RebalanceStrategy::AccessBased)
+            .await
+            .unwrap();
+        let top = store
+            .get_top_n_by_importance(1, Some(&ContextDomain::Code))
+            .await
+            .unwrap();
+        assert_eq!(top[0].id, ids[2]);
+        assert_eq!(top[0].metadata.importance, 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_updates_since_replays_then_goes_live() {
+        let store = ContextStore::new(StorageConfig::memory_only(100)).unwrap();
+        let first = Context::new("First", ContextDomain::General);
+        let second = Context::new("Second", ContextDomain::General);
+        store.store(first.clone()).await.unwrap();
+        store.store(second.clone()).await.unwrap();
+
+        let stream = store.stream_updates_since(2);
+        tokio::pin!(stream);
+        store.delete(&first.id).await.unwrap();
+
+        let mut events = Vec::new();
+        for _ in 0..2 {
+            let event = tokio::time::timeout(std::time::Duration::from_secs(1), stream.next())
+                .await
+                .unwrap()
+                .unwrap();
+            events.push(event);
+        }
+        assert_eq!(events[0].seq, 2);
+        assert_eq!(events[0].id, second.id);
+        assert_eq!(events[1].seq, 3);
+        assert_eq!(events[1].kind, StoreEventKind::Deleted);
+        assert_eq!(events[1].id, first.id);
+
+        // Nothing replayed is repeated from the live channel
+        let next = tokio::time::timeout(std::time::Duration::from_millis(50), stream.next()).await;
+        assert!(next.is_err());
     }
 }
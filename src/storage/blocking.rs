@@ -0,0 +1,110 @@
+//! Synchronous facade over [`ContextStore`]
+//!
+//! For CLI tools and tests that are not async. [`ContextStoreBlocking`]
+//! owns a current-thread tokio runtime and blocks on it for each call, so
+//! the semantics match the async methods exactly.
+//!
+//! It must not be used from within an async context: calls made on a
+//! runtime thread fail with [`ContextError::Internal`] instead of
+//! blocking the runtime, and the facade must be dropped outside one too.
+
+use tokio::runtime::{Builder, Handle, Runtime};
+
+use super::{ContextStore, StorageConfig, StorageStats, StoreOutcome};
+use crate::context::{Context, ContextId, ContextQuery};
+use crate::error::{ContextError, Result};
+
+/// [`ContextStore`] with blocking methods
+pub struct ContextStoreBlocking {
+    runtime: Runtime,
+    store: ContextStore,
+}
+
+impl ContextStoreBlocking {
+    /// Create a store with `config` and a runtime to drive it
+    pub fn new(config: StorageConfig) -> Result<Self> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(Self {
+            runtime,
+            store: ContextStore::new(config)?,
+        })
+    }
+
+    /// The wrapped async store
+    pub fn inner(&self) -> &ContextStore {
+        &self.store
+    }
+
+    /// See [`ContextStore::store`]
+    pub fn store(&self, context: Context) -> Result<StoreOutcome> {
+        self.block_on(self.store.store(context))?
+    }
+
+    /// See [`ContextStore::get`]
+    pub fn get(&self, id: &ContextId) -> Result<Option<Context>> {
+        self.block_on(self.store.get(id))?
+    }
+
+    /// See [`ContextStore::delete`]
+    pub fn delete(&self, id: &ContextId) -> Result<bool> {
+        self.block_on(self.store.delete(id))?
+    }
+
+    /// See [`ContextStore::query`]
+    pub fn query(&self, query: &ContextQuery) -> Result<Vec<Context>> {
+        self.block_on(self.store.query(query))?
+    }
+
+    /// See [`ContextStore::stats`]
+    ///
+    /// Unlike the async method this can fail, when called from within an
+    /// async context.
+    pub fn stats(&self) -> Result<StorageStats> {
+        self.block_on(self.store.stats())
+    }
+
+    fn block_on<F: std::future::Future>(&self, future: F) -> Result<F::Output> {
+        if Handle::try_current().is_ok() {
+            return Err(ContextError::Internal(
+                "ContextStoreBlocking cannot be called from within an async context".into(),
+            ));
+        }
+        Ok(self.runtime.block_on(future))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ContextDomain;
+
+    #[test]
+    fn test_blocking_round_trip() {
+        let store = ContextStoreBlocking::new(StorageConfig::memory_only(10)).unwrap();
+        let id = store
+            .store(Context::new("no runtime needed", ContextDomain::General))
+            .unwrap()
+            .id;
+
+        assert_eq!(
+            store.get(&id).unwrap().unwrap().content,
+            "no runtime needed"
+        );
+        let found = store
+            .query(&ContextQuery::new().with_domain(ContextDomain::General))
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(store.stats().unwrap().memory_count, 1);
+        assert!(store.delete(&id).unwrap());
+        assert!(store.get(&id).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_blocking_refuses_async_context() {
+        let store = ContextStoreBlocking::new(StorageConfig::memory_only(10)).unwrap();
+        let err = store.stats().unwrap_err();
+        assert!(matches!(err, ContextError::Internal(_)));
+        // Dropping a runtime on a runtime thread panics, so hand it off
+        std::thread::spawn(move || drop(store)).join().unwrap();
+    }
+}
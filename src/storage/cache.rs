@@ -0,0 +1,172 @@
+//! Memory cache bounded by entry count and, optionally, by bytes
+//!
+//! Contexts with [`ContextMetadata::pinned`](crate::context::ContextMetadata::pinned)
+//! set live in a separate map that is looked up before the LRU and never
+//! evicted; they count towards the byte total but not towards the entry
+//! limit. Sizes are the estimate of [`estimated_size`], taken when a
+//! context is inserted and again when it leaves, so cached contexts must
+//! not change their content, metadata or embedding in place.
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+use lru::LruCache;
+
+use super::estimated_size;
+use crate::context::{Context, ContextId};
+
+/// LRU cache of contexts that also evicts to stay under a byte budget
+pub(super) struct MemoryCache {
+    entries: LruCache<ContextId, Context>,
+    pinned: HashMap<ContextId, Context>,
+    max_bytes: Option<usize>,
+    bytes: usize,
+    evictions: u64,
+}
+
+impl MemoryCache {
+    pub(super) fn new(capacity: NonZeroUsize, max_bytes: Option<usize>) -> Self {
+        Self {
+            entries: LruCache::new(capacity),
+            pinned: HashMap::new(),
+            max_bytes,
+            bytes: 0,
+            evictions: 0,
+        }
+    }
+
+    /// Insert a context, returning the entries evicted to make room
+    ///
+    /// Least recently used entries go first, until both the entry count
+    /// and the byte budget are met. The new entry itself is never evicted,
+    /// even when it alone exceeds the budget. Replacing the entry under the
+    /// same ID is not an eviction, and a context moves between the LRU and
+    /// the pinned map as its flag changes.
+    pub(super) fn push(&mut self, id: ContextId, context: Context) -> Vec<(ContextId, Context)> {
+        self.pop(&id);
+        self.bytes += estimated_size(&context);
+        let mut evicted = Vec::new();
+        if context.metadata.pinned {
+            self.pinned.insert(id, context);
+        } else if let Some((old_id, old)) = self.entries.push(id, context) {
+            self.bytes -= estimated_size(&old);
+            evicted.push((old_id, old));
+        }
+        while self.max_bytes.is_some_and(|max| self.bytes > max) && self.entries.len() > 1 {
+            let Some((old_id, old)) = self.entries.pop_lru() else {
+                break;
+            };
+            self.bytes -= estimated_size(&old);
+            evicted.push((old_id, old));
+        }
+        self.evictions += evicted.len() as u64;
+        evicted
+    }
+
+    /// Remove a context
+    pub(super) fn pop(&mut self, id: &ContextId) -> Option<Context> {
+        let context = self.pinned.remove(id).or_else(|| self.entries.pop(id))?;
+        self.bytes -= estimated_size(&context);
+        Some(context)
+    }
+
+    /// Look up a context without marking it recently used
+    pub(super) fn peek(&self, id: &ContextId) -> Option<&Context> {
+        self.pinned.get(id).or_else(|| self.entries.peek(id))
+    }
+
+    /// Mutable access for updating access statistics, marking it most recently used
+    pub(super) fn get_mut(&mut self, id: &ContextId) -> Option<&mut Context> {
+        match self.pinned.get_mut(id) {
+            Some(context) => Some(context),
+            None => self.entries.get_mut(id),
+        }
+    }
+
+    /// Whether a context is cached
+    pub(super) fn contains(&self, id: &ContextId) -> bool {
+        self.pinned.contains_key(id) || self.entries.contains(id)
+    }
+
+    /// Every cached context, pinned ones first
+    pub(super) fn iter(&self) -> impl Iterator<Item = (&ContextId, &Context)> {
+        self.pinned.iter().chain(self.entries.iter())
+    }
+
+    /// Number of cached contexts, pinned ones included
+    pub(super) fn len(&self) -> usize {
+        self.pinned.len() + self.entries.len()
+    }
+
+    /// Number of pinned contexts
+    pub(super) fn pinned_len(&self) -> usize {
+        self.pinned.len()
+    }
+
+    /// Remove every context
+    #[cfg_attr(not(feature = "persistence"), allow(dead_code))]
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+        self.pinned.clear();
+        self.bytes = 0;
+    }
+
+    /// Estimated bytes held by the cached contexts
+    pub(super) fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Entries evicted since the cache was created
+    pub(super) fn evictions(&self) -> u64 {
+        self.evictions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ContextDomain;
+
+    #[test]
+    fn test_evicts_to_byte_budget() {
+        let ctx = |content: &str| Context::new(content, ContextDomain::General);
+        let small = estimated_size(&ctx("small 0"));
+        let mut cache = MemoryCache::new(NonZeroUsize::new(10).unwrap(), Some(3 * small));
+
+        for i in 0..3 {
+            let c = ctx(&format!("small {}", i));
+            assert!(cache.push(c.id.clone(), c).is_empty());
+        }
+        assert_eq!(cache.bytes(), 3 * small);
+
+        let big = ctx(&"x".repeat(3 * small));
+        let evicted = cache.push(big.id.clone(), big.clone());
+        assert_eq!(evicted.len(), 3);
+        assert_eq!((cache.len(), cache.evictions()), (1, 3));
+        assert!(cache.contains(&big.id));
+
+        cache.pop(&big.id);
+        assert_eq!(cache.bytes(), 0);
+    }
+
+    #[test]
+    fn test_pinned_entries_are_not_evicted() {
+        let mut cache = MemoryCache::new(NonZeroUsize::new(2).unwrap(), None);
+        let mut pinned = Context::new("pinned", ContextDomain::General);
+        pinned.metadata.pinned = true;
+        cache.push(pinned.id.clone(), pinned.clone());
+
+        for i in 0..5 {
+            let c = Context::new(format!("filler {}", i), ContextDomain::General);
+            cache.push(c.id.clone(), c);
+        }
+        assert_eq!((cache.len(), cache.pinned_len()), (3, 1));
+        assert!(cache.peek(&pinned.id).is_some());
+
+        // Unpinning moves it back into the LRU
+        pinned.metadata.pinned = false;
+        let evicted = cache.push(pinned.id.clone(), pinned.clone());
+        assert_eq!((evicted.len(), cache.pinned_len()), (1, 0));
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -0,0 +1,375 @@
+//! Envelope around persisted values
+//!
+//! Every context [`ContextStore`](super::ContextStore) persists starts with
+//! a fixed header naming how the rest was written:
+//!
+//! | bytes | field                                |
+//! |-------|--------------------------------------|
+//! | 0..2  | magic, `0xFF 'C'`                    |
+//! | 2     | payload format, see [`RecordFormat`] |
+//! | 3..5  | schema version, big-endian `u16`     |
+//! | 5     | flags, see [`FLAG_ZSTD`]             |
+//!
+//! [`RecordFormat::Bincode`] payloads are the bincode encoding of a pair:
+//! the value as JSON with any top-level `embedding` removed, then that
+//! embedding as raw `f32`s. Contexts carry optional fields and free-form
+//! JSON metadata that bincode cannot describe, so only the embedding, which
+//! dominates their size and parse time, leaves the JSON.
+//!
+//! With [`FLAG_ZSTD`] set, everything after the header is the payload
+//! compressed with zstd.
+//!
+//! Values written before the envelope existed are bare JSON. JSON never
+//! starts with `0xFF`, so those are told apart by the first byte and read
+//! as [`RecordFormat::Json`] at schema version 0. Records with an unknown
+//! format, a newer schema or an unknown flag are rejected rather than
+//! misread, so a database touched by a newer build fails loudly here.
+
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ContextError, Result};
+
+/// First bytes of every enveloped record
+pub const MAGIC: [u8; 2] = [0xFF, b'C'];
+
+/// Length of the envelope header in bytes
+pub const HEADER_LEN: usize = 6;
+
+/// Schema version written by this build
+pub const SCHEMA_VERSION: u16 = 1;
+
+/// Flag bit: the payload is zstd-compressed
+pub const FLAG_ZSTD: u8 = 0x01;
+
+/// Every flag bit this build understands
+const KNOWN_FLAGS: u8 = FLAG_ZSTD;
+
+/// Encoding of the payload after the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordFormat {
+    /// serde_json
+    #[default]
+    Json,
+    /// bincode, with the embedding stored as raw floats
+    Bincode,
+}
+
+impl RecordFormat {
+    /// Every format this build can read and write
+    pub const ALL: [RecordFormat; 2] = [RecordFormat::Json, RecordFormat::Bincode];
+
+    /// Byte identifying the format in the header
+    pub fn id(self) -> u8 {
+        match self {
+            RecordFormat::Json => 1,
+            RecordFormat::Bincode => 2,
+        }
+    }
+
+    /// Format for a header byte, if this build knows it
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.id() == id)
+    }
+}
+
+impl fmt::Display for RecordFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordFormat::Json => write!(f, "json"),
+            RecordFormat::Bincode => write!(f, "bincode"),
+        }
+    }
+}
+
+/// Parsed envelope header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordHeader {
+    /// Payload encoding
+    pub format: RecordFormat,
+    /// Schema version of the payload; 0 for legacy bare JSON
+    pub schema_version: u16,
+    /// Flag bits
+    pub flags: u8,
+    /// Whether the value carried a header at all
+    pub enveloped: bool,
+}
+
+impl RecordHeader {
+    /// Header this build writes
+    pub fn current(format: RecordFormat) -> Self {
+        Self {
+            format,
+            schema_version: SCHEMA_VERSION,
+            flags: 0,
+            enveloped: true,
+        }
+    }
+
+    /// Parse the header of a persisted value, returning it and the payload
+    pub fn parse(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        if !bytes.starts_with(&MAGIC) {
+            let legacy = Self {
+                format: RecordFormat::Json,
+                schema_version: 0,
+                flags: 0,
+                enveloped: false,
+            };
+            return Ok((legacy, bytes));
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(ContextError::Storage(format!(
+                "record header truncated at {} bytes",
+                bytes.len()
+            )));
+        }
+
+        let format = RecordFormat::from_id(bytes[2])
+            .ok_or_else(|| ContextError::Storage(format!("unknown record format {}", bytes[2])))?;
+        let schema_version = u16::from_be_bytes([bytes[3], bytes[4]]);
+        if schema_version > SCHEMA_VERSION {
+            return Err(ContextError::Storage(format!(
+                "record schema version {} is newer than supported version {}",
+                schema_version, SCHEMA_VERSION
+            )));
+        }
+        let flags = bytes[5];
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(ContextError::Storage(format!(
+                "unsupported record flags {:#04x}",
+                flags
+            )));
+        }
+
+        let header = Self {
+            format,
+            schema_version,
+            flags,
+            enveloped: true,
+        };
+        Ok((header, &bytes[HEADER_LEN..]))
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&MAGIC);
+        out.push(self.format.id());
+        out.extend_from_slice(&self.schema_version.to_be_bytes());
+        out.push(self.flags);
+    }
+}
+
+/// Serialize `value` in `format` behind a current header
+pub fn encode_as<T: Serialize>(value: &T, format: RecordFormat) -> Result<Vec<u8>> {
+    encode_with(value, format, None)
+}
+
+/// Serialize `value` in `format`, compressed at `compression_level` if given
+///
+/// The payload is only kept compressed when that makes it smaller, so
+/// short values are not inflated by the zstd frame.
+pub fn encode_with<T: Serialize>(
+    value: &T,
+    format: RecordFormat,
+    compression_level: Option<i32>,
+) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(HEADER_LEN + 256);
+    RecordHeader::current(format).write(&mut out);
+    write_payload(value, format, &mut out)?;
+
+    if let Some(level) = compression_level {
+        let compressed = zstd::bulk::compress(&out[HEADER_LEN..], level)
+            .map_err(|e| ContextError::Storage(format!("compressing record: {}", e)))?;
+        if compressed.len() < out.len() - HEADER_LEN {
+            out.truncate(HEADER_LEN);
+            out[5] |= FLAG_ZSTD;
+            out.extend_from_slice(&compressed);
+        }
+    }
+    Ok(out)
+}
+
+fn write_payload<T: Serialize>(value: &T, format: RecordFormat, out: &mut Vec<u8>) -> Result<()> {
+    match format {
+        RecordFormat::Json => serde_json::to_writer(&mut *out, value)?,
+        RecordFormat::Bincode => {
+            let mut body = serde_json::to_value(value)?;
+            let embedding: Option<Vec<f32>> = match body.as_object_mut() {
+                Some(fields) => fields
+                    .remove("embedding")
+                    .filter(|embedding| !embedding.is_null())
+                    .map(serde_json::from_value)
+                    .transpose()?,
+                None => None,
+            };
+            let body = serde_json::to_vec(&body)?;
+            let payload = (body.as_slice(), embedding);
+            bincode::serialize_into(out, &payload).map_err(bincode_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Serialize `value` in the default format
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    encode_as(value, RecordFormat::Json)
+}
+
+/// Deserialize a persisted value, enveloped or legacy
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let (header, payload) = RecordHeader::parse(bytes)?;
+    if header.flags & FLAG_ZSTD != 0 {
+        let payload = zstd::stream::decode_all(payload)
+            .map_err(|e| ContextError::Storage(format!("decompressing record: {}", e)))?;
+        return read_payload(header.format, &payload);
+    }
+    read_payload(header.format, payload)
+}
+
+/// Deserialize a persisted value, leaving out a bincode record's embedding
+///
+/// For reading a few fields cheaply: the raw floats of a
+/// [`RecordFormat::Bincode`] record are never decoded, nor decompressed when
+/// the record is compressed, so `T` sees no `embedding`. JSON records are
+/// still parsed in full, and decompressed first when compressed.
+pub fn decode_without_embedding<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let (header, payload) = RecordHeader::parse(bytes)?;
+    let compressed = header.flags & FLAG_ZSTD != 0;
+    match header.format {
+        RecordFormat::Json => decode(bytes),
+        // The JSON body comes first, so reading stops before the embedding
+        RecordFormat::Bincode if compressed => {
+            let decoder = zstd::stream::read::Decoder::with_buffer(payload)
+                .map_err(|e| ContextError::Storage(format!("decompressing record: {}", e)))?;
+            let body: Vec<u8> = bincode::deserialize_from(decoder).map_err(bincode_error)?;
+            Ok(serde_json::from_slice(&body)?)
+        }
+        RecordFormat::Bincode => {
+            let body: &[u8] = bincode::deserialize(payload).map_err(bincode_error)?;
+            Ok(serde_json::from_slice(body)?)
+        }
+    }
+}
+
+fn read_payload<T: DeserializeOwned>(format: RecordFormat, payload: &[u8]) -> Result<T> {
+    match format {
+        RecordFormat::Json => Ok(serde_json::from_slice(payload)?),
+        RecordFormat::Bincode => {
+            let (body, embedding): (&[u8], Option<Vec<f32>>) =
+                bincode::deserialize(payload).map_err(bincode_error)?;
+            match embedding {
+                None => Ok(serde_json::from_slice(body)?),
+                Some(embedding) => {
+                    let mut value: serde_json::Value = serde_json::from_slice(body)?;
+                    if let Some(fields) = value.as_object_mut() {
+                        fields.insert("embedding".to_string(), embedding.into());
+                    }
+                    Ok(T::deserialize(value)?)
+                }
+            }
+        }
+    }
+}
+
+fn bincode_error(e: bincode::Error) -> ContextError {
+    ContextError::Storage(format!("bincode record: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{Context, ContextDomain};
+
+    #[test]
+    fn test_round_trip_every_format() {
+        let ctx = Context::new("enveloped", ContextDomain::Code)
+            .with_tags(vec!["a".into()])
+            .with_embedding((0..384).map(|i| i as f32 / 7.0).collect());
+        let legacy = serde_json::to_vec(&ctx).unwrap();
+
+        for format in RecordFormat::ALL {
+            let bytes = encode_as(&ctx, format).unwrap();
+            let (header, _) = RecordHeader::parse(&bytes).unwrap();
+            assert_eq!(header, RecordHeader::current(format));
+            let decoded: Context = decode(&bytes).unwrap();
+            assert_eq!(
+                (decoded.id, decoded.content, decoded.embedding),
+                (ctx.id.clone(), ctx.content.clone(), ctx.embedding.clone())
+            );
+        }
+        let json = encode_as(&ctx, RecordFormat::Json).unwrap();
+        let bincode = encode_as(&ctx, RecordFormat::Bincode).unwrap();
+        assert!(bincode.len() < json.len() / 2);
+
+        let (header, payload) = RecordHeader::parse(&legacy).unwrap();
+        assert!(!header.enveloped);
+        assert_eq!((header.schema_version, payload.len()), (0, legacy.len()));
+        let decoded: Context = decode(&legacy).unwrap();
+        assert_eq!(decoded.id, ctx.id);
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let long = "compressible ".repeat(1000);
+        for format in RecordFormat::ALL {
+            let plain = encode_as(&long, format).unwrap();
+            let compressed = encode_with(&long, format, Some(3)).unwrap();
+            let (header, _) = RecordHeader::parse(&compressed).unwrap();
+            assert_eq!(header.flags, FLAG_ZSTD);
+            assert!(compressed.len() < plain.len() / 10);
+            assert_eq!(decode::<String>(&compressed).unwrap(), long);
+        }
+
+        // Too short to gain anything, so written uncompressed
+        let short = encode_with(&"tiny", RecordFormat::Json, Some(3)).unwrap();
+        assert_eq!(RecordHeader::parse(&short).unwrap().0.flags, 0);
+    }
+
+    #[test]
+    fn test_decode_without_embedding() {
+        #[derive(Deserialize)]
+        struct Partial {
+            domain: ContextDomain,
+            embedding: Option<Vec<f32>>,
+        }
+
+        let ctx = Context::new("partial ".repeat(100), ContextDomain::Research)
+            .with_embedding(vec![0.5; 384]);
+        for format in RecordFormat::ALL {
+            for level in [None, Some(3)] {
+                let bytes = encode_with(&ctx, format, level).unwrap();
+                let partial: Partial = decode_without_embedding(&bytes).unwrap();
+                assert_eq!(partial.domain, ContextDomain::Research);
+                assert_eq!(
+                    partial.embedding.is_some(),
+                    format == RecordFormat::Json,
+                    "{} {:?}",
+                    format,
+                    level
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rejects_unreadable_headers() {
+        let mut bytes = encode(&"payload").unwrap();
+        assert!(RecordHeader::parse(&bytes[..3]).is_err());
+
+        bytes[2] = 0xEE;
+        assert!(decode::<String>(&bytes).is_err());
+
+        let mut newer = encode(&"payload").unwrap();
+        newer[3..5].copy_from_slice(&(SCHEMA_VERSION + 1).to_be_bytes());
+        assert!(decode::<String>(&newer).is_err());
+
+        let mut flagged = encode(&"payload").unwrap();
+        flagged[5] = 0x80;
+        assert!(matches!(
+            decode::<String>(&flagged).unwrap_err(),
+            ContextError::Storage(_)
+        ));
+    }
+}
@@ -0,0 +1,142 @@
+//! Automatic tag suggestion for stored contexts
+//!
+//! Provides the [`AutoTagger`] extension point used by
+//! [`ContextStore::auto_tag`] and a TF-IDF keyword tagger built from the
+//! store's own content.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::error::Result;
+use crate::rag::words;
+use crate::storage::ContextStore;
+
+/// Suggests tags for a piece of content
+pub trait AutoTagger: Send + Sync {
+    /// Suggest tags for `content`, leaving out any in `existing_tags`
+    fn suggest_tags(&self, content: &str, existing_tags: &[String]) -> Vec<String>;
+}
+
+/// Common English words never suggested as tags
+const STOP_WORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been",
+    "but", "by", "can", "for", "from", "has", "have", "how", "if", "in", "into", "is", "it", "its",
+    "not", "of", "on", "or", "so", "than", "that", "the", "their", "them", "then", "there",
+    "these", "they", "this", "to", "was", "we", "were", "what", "when", "which", "who", "will",
+    "with", "you", "your",
+];
+
+/// Words shorter than this are never suggested
+const MIN_KEYWORD_LEN: usize = 3;
+
+/// Tags content with its highest TF-IDF keywords
+///
+/// Document frequencies come from the corpus the tagger was built from, so
+/// words common to every context score low and distinctive ones high.
+#[derive(Debug, Clone)]
+pub struct KeywordAutoTagger {
+    /// Number of contexts each word appears in
+    document_frequency: HashMap<String, u32>,
+    /// Number of contexts in the corpus
+    document_count: usize,
+    /// Maximum number of tags suggested per call
+    max_tags: usize,
+}
+
+impl KeywordAutoTagger {
+    /// Default number of tags suggested per call
+    pub const DEFAULT_MAX_TAGS: usize = 5;
+
+    /// Build a tagger from a corpus of documents
+    pub fn from_documents<'a>(documents: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut document_frequency: HashMap<String, u32> = HashMap::new();
+        let mut document_count = 0;
+        for document in documents {
+            document_count += 1;
+            let unique: HashSet<String> = words(document).collect();
+            for word in unique {
+                *document_frequency.entry(word).or_default() += 1;
+            }
+        }
+
+        Self {
+            document_frequency,
+            document_count,
+            max_tags: Self::DEFAULT_MAX_TAGS,
+        }
+    }
+
+    /// Build a tagger from every live context in a store
+    pub async fn from_store(store: &ContextStore) -> Result<Self> {
+        let contexts = store.all_contexts().await?;
+        Ok(Self::from_documents(
+            contexts
+                .iter()
+                .filter(|ctx| !ctx.is_expired())
+                .map(|ctx| ctx.content.as_str()),
+        ))
+    }
+
+    /// Set the maximum number of tags suggested per call
+    pub fn with_max_tags(mut self, max_tags: usize) -> Self {
+        self.max_tags = max_tags;
+        self
+    }
+
+    fn idf(&self, word: &str) -> f64 {
+        let df = self.document_frequency.get(word).copied().unwrap_or(0) as f64;
+        ((1.0 + self.document_count as f64) / (1.0 + df)).ln() + 1.0
+    }
+}
+
+impl AutoTagger for KeywordAutoTagger {
+    fn suggest_tags(&self, content: &str, existing_tags: &[String]) -> Vec<String> {
+        let existing: HashSet<String> = existing_tags.iter().map(|t| t.to_lowercase()).collect();
+
+        let mut term_frequency: HashMap<String, u32> = HashMap::new();
+        for word in words(content) {
+            let keyword = word.chars().count() >= MIN_KEYWORD_LEN
+                && !word.chars().all(|c| c.is_numeric())
+                && !STOP_WORDS.contains(&word.as_str())
+                && !existing.contains(&word);
+            if keyword {
+                *term_frequency.entry(word).or_default() += 1;
+            }
+        }
+
+        let mut scored: Vec<(String, f64)> = term_frequency
+            .into_iter()
+            .map(|(word, tf)| {
+                let score = tf as f64 * self.idf(&word);
+                (word, score)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scored.truncate(self.max_tags);
+        scored.into_iter().map(|(word, _)| word).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyword_tagger_prefers_distinctive_words() {
+        let tagger = KeywordAutoTagger::from_documents([
+            "The server handles requests",
+            "The server stores contexts",
+            "Borrow checker rules for the server",
+        ])
+        .with_max_tags(2);
+
+        let tags = tagger.suggest_tags(
+            "The server borrow checker rejects the borrow",
+            &["checker".to_string()],
+        );
+        // "borrow" is frequent here and rare in the corpus; "server" is everywhere
+        assert_eq!(tags[0], "borrow");
+        assert!(!tags.contains(&"checker".to_string()));
+        assert!(!tags.contains(&"the".to_string()));
+        assert_eq!(tags.len(), 2);
+    }
+}
@@ -0,0 +1,17 @@
+//! Helpers for the optional `tracing` instrumentation
+//!
+//! Call sites stay free of `#[cfg]` noise: without the `tracing` feature
+//! these compile to nothing.
+
+/// Record `value` as `field` on the current span
+///
+/// The field must have been declared, usually as `tracing::field::Empty`,
+/// in the span's `#[tracing::instrument]` attribute.
+#[cfg(feature = "tracing")]
+pub(crate) fn record<V: tracing::Value>(field: &'static str, value: V) {
+    tracing::Span::current().record(field, value);
+}
+
+/// Record `value` as `field` on the current span (no-op without `tracing`)
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record<V>(_field: &'static str, _value: V) {}
@@ -156,6 +156,15 @@ impl SparseQuantizer {
     }
 
     /// Quantize a dense embedding to sparse ternary
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "sparse_quantize",
+            level = "trace",
+            skip_all,
+            fields(dimension = embedding.len())
+        )
+    )]
     pub fn quantize(&self, embedding: &[f32]) -> Result<SparseTernaryEmbedding> {
         let dimension = embedding.len();
 
@@ -311,6 +320,15 @@ impl RvqQuantizer {
     }
 
     /// Quantize a dense embedding with RVQ
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "rvq_quantize",
+            level = "trace",
+            skip_all,
+            fields(dimension = embedding.len())
+        )
+    )]
     pub fn quantize(&self, embedding: &[f32]) -> Result<RvqCodebook> {
         let dimension = embedding.len();
         let mut residual = embedding.to_vec();
@@ -454,6 +472,15 @@ impl TernaryEmbeddingGenerator {
     }
 
     /// Quantize a dense embedding
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "quantize",
+            level = "debug",
+            skip_all,
+            fields(strategy = %self.strategy, dimension = dense.len())
+        )
+    )]
     pub fn quantize(&self, dense: &[f32]) -> Result<TernaryQuantizedEmbedding> {
         let sparse = if let Some(ref sq) = self.sparse_quantizer {
             Some(sq.quantize(dense)?)
@@ -3,62 +3,263 @@
 //! Provides tools for storing, retrieving, and querying contexts
 //! with temporal reasoning and RAG support.
 
-use serde_json::{json, Value};
-use std::collections::HashMap;
-use std::sync::Arc;
+use async_trait::async_trait;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tokio::sync::broadcast;
 
-use crate::context::{Context, ContextDomain, ContextQuery, ScreeningStatus};
-use crate::protocol::{CallToolResult, InputSchema, PropertySchema, Tool};
+use crate::context::{
+    compile_content_regex, hash_content, Context, ContextDomain, ContextId, ContextMetadata,
+    ContextQuery, DataPathFilter, QueryCursor, ScreeningStatus, SortOrder, IMPORTANCE_SIGNALS_KEY,
+};
+use crate::embeddings::EmbeddingGenerator;
+use crate::error::{ContextError, Result};
+use crate::protocol::{CallToolResult, InputSchema, Notification, PropertySchema, Tool};
 use crate::rag::{RagProcessor, RetrievalQuery};
-use crate::storage::ContextStore;
+use crate::storage::{
+    ContextStore, DecayStrategy, MergeStrategy, SourceMatchMode, StatsInclude, StorageConfig,
+};
+use crate::tagging::KeywordAutoTagger;
+use crate::telemetry;
 use crate::temporal::TemporalQuery;
 
+/// Shared handles passed to every tool invocation
+#[derive(Clone)]
+pub struct ToolContext {
+    /// Context storage
+    pub store: Arc<ContextStore>,
+    /// RAG processor
+    pub rag: Arc<RagProcessor>,
+    notifications: broadcast::Sender<Notification>,
+}
+
+impl ToolContext {
+    /// Send a notification to every subscriber of the registry
+    pub fn notify(&self, notification: Notification) {
+        // No subscribers is not an error
+        let _ = self.notifications.send(notification);
+    }
+}
+
+/// A tool exposed through the MCP `tools/list` and `tools/call` methods
+///
+/// Implement this to add domain-specific tools without forking the crate,
+/// then add them with [`ToolRegistry::register`]. Arguments are validated
+/// against [`ContextTool::definition`]'s input schema, then by
+/// [`ContextTool::validate`], before `execute` runs.
+#[async_trait]
+pub trait ContextTool: Send + Sync {
+    /// Tool name, description and input schema
+    fn definition(&self) -> Tool;
+
+    /// Tool-specific argument checks beyond the input schema
+    ///
+    /// Only called once the arguments satisfy the schema.
+    fn validate(&self, _args: &HashMap<String, Value>) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Run the tool
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult;
+}
+
 /// Tool registry managing all available tools
 pub struct ToolRegistry {
-    store: Arc<ContextStore>,
-    rag: Arc<RagProcessor>,
+    context: ToolContext,
+    tools: RwLock<Vec<Arc<dyn ContextTool>>>,
 }
 
 impl ToolRegistry {
-    /// Create a new tool registry
+    /// Create a new tool registry with the built-in tools
     pub fn new(store: Arc<ContextStore>, rag: Arc<RagProcessor>) -> Self {
-        Self { store, rag }
+        let tools: Vec<Arc<dyn ContextTool>> = vec![
+            Arc::new(StoreContextTool),
+            Arc::new(GetContextTool),
+            Arc::new(DeleteContextTool),
+            Arc::new(QueryContextsTool),
+            Arc::new(RetrieveContextsTool),
+            Arc::new(SummarizeContextsTool),
+            Arc::new(SearchByRegexTool),
+            Arc::new(UpdateScreeningTool),
+            Arc::new(GetTemporalStatsTool),
+            Arc::new(GetStorageStatsTool),
+            Arc::new(GetOperationStatsTool),
+            Arc::new(CheckStoreInvariantsTool),
+            Arc::new(CleanupExpiredTool),
+            Arc::new(GetDiskUsageTool),
+            Arc::new(CleanupSourceTool),
+            Arc::new(CreateCheckpointTool),
+            Arc::new(ListSnapshotsTool),
+            Arc::new(RunMaintenanceTool::default()),
+            Arc::new(GetStaleContextsTool),
+            Arc::new(GetVocabularyTool),
+            Arc::new(MergeContextsTool),
+            Arc::new(AutoTagContextTool),
+            Arc::new(DiffRevisionsTool),
+            Arc::new(ApplyDecayStrategyTool),
+            Arc::new(GetIndexStatsTool),
+            Arc::new(GetSizeDistributionTool),
+            Arc::new(EnforceSourcePolicyTool),
+            Arc::new(LookupByHashTool),
+            Arc::new(GetContextLineageTool),
+            Arc::new(MigrateStoreTool),
+            Arc::new(ComputeDomainOverlapTool),
+            Arc::new(RollbackContextTool),
+            Arc::new(EvaluateRetrievalTool),
+            Arc::new(ListContextIdsTool),
+            Arc::new(BenchmarkStorageTool),
+            #[cfg(feature = "client")]
+            Arc::new(ReconcileWithRemoteTool),
+        ];
+        let (notifications, _) = broadcast::channel(16);
+
+        Self {
+            context: ToolContext {
+                store,
+                rag,
+                notifications,
+            },
+            tools: RwLock::new(tools),
+        }
+    }
+
+    /// Register an additional tool
+    ///
+    /// Fails if a tool with the same name already exists. Subscribers
+    /// receive a `notifications/tools/list_changed` notification.
+    pub fn register(&self, tool: Box<dyn ContextTool>) -> Result<()> {
+        let name = tool.definition().name;
+        {
+            let mut tools = self.tools.write().expect("tool registry lock poisoned");
+            if tools.iter().any(|t| t.definition().name == name) {
+                return Err(ContextError::Config(format!(
+                    "Tool already registered: {}",
+                    name
+                )));
+            }
+            tools.push(Arc::from(tool));
+        }
+
+        self.context.notify(Notification::tools_list_changed());
+        Ok(())
+    }
+
+    /// Subscribe to registry notifications such as tools list changes and tool progress
+    pub fn subscribe(&self) -> broadcast::Receiver<Notification> {
+        self.context.notifications.subscribe()
     }
 
     /// Get all available tools
     pub fn list_tools(&self) -> Vec<Tool> {
-        vec![
-            self.store_context_tool(),
-            self.get_context_tool(),
-            self.delete_context_tool(),
-            self.query_contexts_tool(),
-            self.retrieve_contexts_tool(),
-            self.update_screening_tool(),
-            self.get_temporal_stats_tool(),
-            self.get_storage_stats_tool(),
-            self.cleanup_expired_tool(),
-        ]
+        self.tools
+            .read()
+            .expect("tool registry lock poisoned")
+            .iter()
+            .map(|t| t.definition())
+            .collect()
+    }
+
+    /// Look up a tool by name
+    fn find(&self, name: &str) -> Option<Arc<dyn ContextTool>> {
+        self.tools
+            .read()
+            .expect("tool registry lock poisoned")
+            .iter()
+            .find(|t| t.definition().name == name)
+            .cloned()
+    }
+
+    /// Validate arguments against a tool's declared input schema
+    ///
+    /// Unknown tools pass validation; dispatch reports them separately.
+    pub fn validate(
+        &self,
+        name: &str,
+        args: &HashMap<String, Value>,
+    ) -> std::result::Result<(), Vec<String>> {
+        let tool = match self.find(name) {
+            Some(tool) => tool,
+            None => return Ok(()),
+        };
+
+        let mut violations = validate_arguments(&tool.definition().input_schema, args);
+        if violations.is_empty() {
+            violations = tool.validate(args);
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
     }
 
     /// Execute a tool by name
-    pub async fn execute(&self, name: &str, args: HashMap<String, Value>) -> CallToolResult {
-        match name {
-            "store_context" => self.store_context(args).await,
-            "get_context" => self.get_context(args).await,
-            "delete_context" => self.delete_context(args).await,
-            "query_contexts" => self.query_contexts(args).await,
-            "retrieve_contexts" => self.retrieve_contexts(args).await,
-            "update_screening" => self.update_screening(args).await,
-            "get_temporal_stats" => self.get_temporal_stats(args).await,
-            "get_storage_stats" => self.get_storage_stats(args).await,
-            "cleanup_expired" => self.cleanup_expired(args).await,
-            _ => CallToolResult::error(format!("Unknown tool: {}", name)),
+    ///
+    /// Unknown tools and invalid arguments fail with
+    /// [`ContextError::InvalidQuery`], which the server reports as a
+    /// protocol error. Failures while the tool runs come back as a
+    /// [`CallToolResult`] with `is_error` set, per the MCP specification.
+    pub async fn execute(
+        &self,
+        name: &str,
+        args: HashMap<String, Value>,
+    ) -> Result<CallToolResult> {
+        if let Err(violations) = self.validate(name, &args) {
+            return Err(ContextError::InvalidQuery(format!(
+                "Invalid arguments for tool '{}': {}",
+                name,
+                violations.join("; ")
+            )));
+        }
+
+        self.dispatch(name, args).await
+    }
+
+    /// Execute a tool by name without validating its arguments
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "tool",
+            level = "debug",
+            skip_all,
+            fields(
+                tool = name,
+                is_error = tracing::field::Empty,
+                duration_ms = tracing::field::Empty
+            )
+        )
+    )]
+    pub(crate) async fn dispatch(
+        &self,
+        name: &str,
+        args: HashMap<String, Value>,
+    ) -> Result<CallToolResult> {
+        match self.find(name) {
+            Some(tool) => {
+                let start = Instant::now();
+                let result = tool.execute(args, &self.context).await;
+                telemetry::record("is_error", result.is_error);
+                telemetry::record("duration_ms", start.elapsed().as_millis() as u64);
+                Ok(result)
+            }
+            None => Err(ContextError::InvalidQuery(format!(
+                "Unknown tool: {}",
+                name
+            ))),
         }
     }
+}
+
+// Built-in tools
 
-    // Tool definitions
+struct StoreContextTool;
 
-    fn store_context_tool(&self) -> Tool {
+#[async_trait]
+impl ContextTool for StoreContextTool {
+    fn definition(&self) -> Tool {
         Tool {
             name: "store_context".to_string(),
             description: Some("Store a new context with metadata and optional TTL".to_string()),
@@ -66,137 +267,86 @@ impl ToolRegistry {
                 .with_required("content", PropertySchema::string("The context content"))
                 .with_property(
                     "domain",
-                    PropertySchema::string("Context domain").with_enum(vec![
-                        "General",
-                        "Code",
-                        "Documentation",
-                        "Conversation",
-                        "Filesystem",
-                        "WebSearch",
-                        "Dataset",
-                        "Research",
-                    ]),
+                    PropertySchema::string(
+                        "Context domain: General, Code, Documentation, Conversation, \
+                         Filesystem, WebSearch, Dataset, Research, or any other name \
+                         (optionally written custom:<name>) for a custom domain",
+                    ),
                 )
                 .with_property("source", PropertySchema::string("Source of the context"))
                 .with_property("tags", PropertySchema::array("Tags for categorization"))
+                .with_property(
+                    "language",
+                    PropertySchema::string("Language of the content (default: detected)"),
+                )
                 .with_property(
                     "importance",
-                    PropertySchema::number("Importance 0.0-1.0").with_default(json!(0.5)),
+                    PropertySchema::number("Importance 0.0-1.0")
+                        .with_default(json!(0.5))
+                        .with_range(0.0, 1.0),
                 )
-                .with_property("ttl_hours", PropertySchema::number("Time to live in hours")),
-        }
-    }
-
-    fn get_context_tool(&self) -> Tool {
-        Tool {
-            name: "get_context".to_string(),
-            description: Some("Retrieve a context by ID".to_string()),
-            input_schema: InputSchema::object()
-                .with_required("id", PropertySchema::string("Context ID")),
-        }
-    }
-
-    fn delete_context_tool(&self) -> Tool {
-        Tool {
-            name: "delete_context".to_string(),
-            description: Some("Delete a context by ID".to_string()),
-            input_schema: InputSchema::object()
-                .with_required("id", PropertySchema::string("Context ID")),
-        }
-    }
-
-    fn query_contexts_tool(&self) -> Tool {
-        Tool {
-            name: "query_contexts".to_string(),
-            description: Some("Query contexts with filters".to_string()),
-            input_schema: InputSchema::object()
-                .with_property("domain", PropertySchema::string("Filter by domain"))
-                .with_property("tags", PropertySchema::array("Filter by tags"))
                 .with_property(
-                    "min_importance",
-                    PropertySchema::number("Minimum importance threshold"),
+                    "auto_importance",
+                    PropertySchema::boolean(
+                        "Suggest an importance from the content when none is given \
+                         (default: server setting)",
+                    ),
                 )
                 .with_property(
-                    "max_age_hours",
-                    PropertySchema::number("Maximum age in hours"),
+                    "ttl_hours",
+                    PropertySchema::number("Time to live in hours").with_minimum(0.0),
                 )
                 .with_property(
-                    "verified_only",
-                    PropertySchema::boolean("Only return verified contexts"),
+                    "if_absent",
+                    PropertySchema::boolean("Keep an existing context with the same ID")
+                        .with_default(json!(false)),
                 )
                 .with_property(
-                    "limit",
-                    PropertySchema::number("Maximum results").with_default(json!(10)),
-                ),
-        }
-    }
-
-    fn retrieve_contexts_tool(&self) -> Tool {
-        Tool {
-            name: "retrieve_contexts".to_string(),
-            description: Some("Retrieve contexts using RAG with scoring".to_string()),
-            input_schema: InputSchema::object()
-                .with_property("text", PropertySchema::string("Text query"))
-                .with_property("domain", PropertySchema::string("Domain filter"))
-                .with_property("tags", PropertySchema::array("Tag filters"))
+                    "parent_id",
+                    PropertySchema::string("ID of the context this one follows from"),
+                )
                 .with_property(
-                    "min_importance",
-                    PropertySchema::number("Minimum importance"),
+                    "thread_id",
+                    PropertySchema::string("Conversation thread (defaults to the parent's)"),
                 )
                 .with_property(
-                    "max_age_hours",
-                    PropertySchema::number("Maximum age for temporal filtering"),
+                    "data",
+                    PropertySchema::object("Structured payload stored alongside the content"),
                 )
                 .with_property(
-                    "max_results",
-                    PropertySchema::number("Maximum results").with_default(json!(10)),
-                ),
-        }
-    }
-
-    fn update_screening_tool(&self) -> Tool {
-        Tool {
-            name: "update_screening".to_string(),
-            description: Some("Update screening status of a context".to_string()),
-            input_schema: InputSchema::object()
-                .with_required("id", PropertySchema::string("Context ID"))
-                .with_required(
-                    "status",
-                    PropertySchema::string("New screening status")
-                        .with_enum(vec!["Safe", "Flagged", "Blocked"]),
+                    "auto_chunk",
+                    PropertySchema::boolean("Split long content into overlapping chunks")
+                        .with_default(json!(false)),
                 )
-                .with_property("reason", PropertySchema::string("Reason for status change")),
-        }
-    }
-
-    fn get_temporal_stats_tool(&self) -> Tool {
-        Tool {
-            name: "get_temporal_stats".to_string(),
-            description: Some("Get temporal statistics for stored contexts".to_string()),
-            input_schema: InputSchema::object()
-                .with_property("domain", PropertySchema::string("Filter by domain")),
-        }
-    }
-
-    fn get_storage_stats_tool(&self) -> Tool {
-        Tool {
-            name: "get_storage_stats".to_string(),
-            description: Some("Get storage statistics".to_string()),
-            input_schema: InputSchema::object(),
+                .with_property(
+                    "chunk_size",
+                    PropertySchema::number("Maximum characters per chunk")
+                        .with_default(json!(DEFAULT_CHUNK_SIZE))
+                        .with_minimum(1.0),
+                )
+                .with_property(
+                    "chunk_overlap",
+                    PropertySchema::number("Characters repeated between adjacent chunks")
+                        .with_default(json!(DEFAULT_CHUNK_OVERLAP))
+                        .with_minimum(0.0),
+                ),
         }
     }
 
-    fn cleanup_expired_tool(&self) -> Tool {
-        Tool {
-            name: "cleanup_expired".to_string(),
-            description: Some("Remove expired contexts".to_string()),
-            input_schema: InputSchema::object(),
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        let mut violations = id_violations(args, &["parent_id"]);
+        if let Some(hours) = args.get("ttl_hours").and_then(|v| v.as_f64()) {
+            if hours <= 0.0 {
+                violations.push(format!(
+                    "parameter 'ttl_hours' must be a positive number of hours, got {}",
+                    hours
+                ));
+            }
         }
+        violations
     }
 
-    // Tool implementations
-
-    async fn store_context(&self, args: HashMap<String, Value>) -> CallToolResult {
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
         let content = match args.get("content").and_then(|v| v.as_str()) {
             Some(c) => c.to_string(),
             None => return CallToolResult::error("Missing required parameter: content"),
@@ -222,299 +372,4057 @@ impl ToolRegistry {
                 .collect();
         }
 
-        if let Some(importance) = args.get("importance").and_then(|v| v.as_f64()) {
-            ctx.metadata.importance = importance.clamp(0.0, 1.0) as f32;
-        }
-
-        if let Some(ttl) = args.get("ttl_hours").and_then(|v| v.as_i64()) {
-            ctx = ctx.with_ttl(std::time::Duration::from_secs(ttl as u64 * 3600));
-        }
-
-        let id = ctx.id.clone();
-        match self.store.store(ctx).await {
-            Ok(_stored_id) => CallToolResult::json(json!({
-                "success": true,
-                "id": id.to_string(),
-                "message": "Context stored successfully"
-            })),
-            Err(e) => CallToolResult::error(format!("Failed to store context: {}", e)),
+        if let Some(language) = args.get("language").and_then(|v| v.as_str()) {
+            ctx.metadata.language = Some(language.to_lowercase());
         }
-    }
 
-    async fn get_context(&self, args: HashMap<String, Value>) -> CallToolResult {
-        let id_str = match args.get("id").and_then(|v| v.as_str()) {
-            Some(id) => id,
-            None => return CallToolResult::error("Missing required parameter: id"),
-        };
-
-        let id = crate::context::ContextId::from_string(id_str.to_string());
-
-        match self.store.get(&id).await {
-            Ok(Some(ctx)) => CallToolResult::json(json!({
-                "id": ctx.id.to_string(),
-                "content": ctx.content,
-                "domain": format!("{:?}", ctx.domain),
-                "created_at": ctx.created_at.to_rfc3339(),
-                "accessed_at": ctx.accessed_at.to_rfc3339(),
-                "metadata": {
-                    "source": ctx.metadata.source,
-                    "tags": ctx.metadata.tags,
-                    "importance": ctx.metadata.importance,
-                    "verified": ctx.metadata.verified,
-                    "screening_status": format!("{:?}", ctx.metadata.screening_status)
-                },
-                "age_hours": ctx.age_hours()
-            })),
-            Ok(None) => CallToolResult::error(format!("Context not found: {}", id_str)),
-            Err(e) => CallToolResult::error(format!("Error retrieving context: {}", e)),
+        match args.get("importance").and_then(|v| v.as_f64()) {
+            Some(importance) => ctx.metadata.importance = importance.clamp(0.0, 1.0) as f32,
+            None => {
+                let auto = args
+                    .get("auto_importance")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(cx.store.config().auto_importance);
+                if auto {
+                    let signals = ContextMetadata::suggest_importance_with(
+                        &ctx.content,
+                        &ctx.domain,
+                        &ctx.metadata.tags,
+                        &cx.rag.config().tag_importance_priors,
+                    );
+                    ctx.metadata.importance = signals.importance;
+                    ctx.metadata
+                        .custom
+                        .insert(IMPORTANCE_SIGNALS_KEY.to_string(), json!(signals));
+                }
+            }
         }
-    }
-
-    async fn delete_context(&self, args: HashMap<String, Value>) -> CallToolResult {
-        let id_str = match args.get("id").and_then(|v| v.as_str()) {
-            Some(id) => id,
-            None => return CallToolResult::error("Missing required parameter: id"),
-        };
-
-        let id = crate::context::ContextId::from_string(id_str.to_string());
 
-        match self.store.delete(&id).await {
-            Ok(true) => CallToolResult::json(json!({
-                "success": true,
-                "message": "Context deleted"
-            })),
-            Ok(false) => CallToolResult::error(format!("Context not found: {}", id_str)),
-            Err(e) => CallToolResult::error(format!("Error deleting context: {}", e)),
+        if let Some(hours) = args.get("ttl_hours").and_then(|v| v.as_f64()) {
+            let max_hours = cx.store.config().max_ttl_hours;
+            if hours <= 0.0 {
+                return CallToolResult::error(format!(
+                    "ttl_hours must be a positive number of hours, got {}",
+                    hours
+                ));
+            }
+            if hours > max_hours as f64 {
+                return CallToolResult::error(format!(
+                    "ttl_hours {} exceeds the maximum of {} hours",
+                    hours, max_hours
+                ));
+            }
+            let ttl = match std::time::Duration::try_from_secs_f64(hours * 3600.0) {
+                Ok(ttl) => ttl,
+                Err(e) => return CallToolResult::error(format!("Invalid ttl_hours: {}", e)),
+            };
+            ctx = match ctx.try_with_ttl(ttl) {
+                Ok(ctx) => ctx,
+                Err(e) => return CallToolResult::error(e.to_string()),
+            };
         }
-    }
 
-    async fn query_contexts(&self, args: HashMap<String, Value>) -> CallToolResult {
-        let mut query = ContextQuery::new();
-
-        if let Some(domain) = args.get("domain").and_then(|v| v.as_str()) {
-            query = query.with_domain(parse_domain(domain));
+        if let Some(data) = args.get("data") {
+            ctx.data = Some(data.clone());
         }
 
-        if let Some(tags) = args.get("tags").and_then(|v| v.as_array()) {
-            for tag in tags.iter().filter_map(|v| v.as_str()) {
-                query = query.with_tag(tag.to_string());
+        if let Some(parent_id) = args.get("parent_id").and_then(|v| v.as_str()) {
+            match ContextId::parse(parent_id) {
+                Ok(parent_id) => ctx.parent_id = Some(parent_id),
+                Err(e) => return CallToolResult::error(e.to_string()),
             }
         }
 
-        if let Some(min_importance) = args.get("min_importance").and_then(|v| v.as_f64()) {
-            query = query.with_min_importance(min_importance as f32);
+        match args.get("thread_id").and_then(|v| v.as_str()) {
+            Some(thread_id) => ctx.thread_id = Some(thread_id.to_string()),
+            None => {
+                if let Some(ref parent_id) = ctx.parent_id {
+                    if let Ok(Some(parent)) = cx.store.get(parent_id).await {
+                        ctx.thread_id = parent.thread_id;
+                    }
+                }
+            }
         }
 
-        if let Some(max_age) = args.get("max_age_hours").and_then(|v| v.as_i64()) {
-            query = query.with_max_age_hours(max_age);
-        }
+        let if_absent = args
+            .get("if_absent")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
 
-        if let Some(verified) = args.get("verified_only").and_then(|v| v.as_bool()) {
-            if verified {
-                query = query.verified_only();
+        if args
+            .get("auto_chunk")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+        {
+            let chunk_size = args
+                .get("chunk_size")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(DEFAULT_CHUNK_SIZE as u64) as usize;
+            let overlap = args
+                .get("chunk_overlap")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(DEFAULT_CHUNK_OVERLAP as u64) as usize;
+            let chunks = ctx.split_into_chunks(chunk_size, overlap);
+            if chunks.len() > 1 {
+                return store_chunks(chunks, if_absent, cx).await;
             }
         }
 
-        if let Some(limit) = args.get("limit").and_then(|v| v.as_u64()) {
-            query = query.with_limit(limit as usize);
-        }
+        let outcome = if if_absent {
+            cx.store.store_if_absent(ctx).await
+        } else {
+            cx.store.store(ctx).await
+        };
 
-        match self.store.query(&query).await {
-            Ok(contexts) => {
-                let results: Vec<Value> = contexts
-                    .iter()
-                    .map(|ctx| {
-                        json!({
-                            "id": ctx.id.to_string(),
-                            "content_preview": ctx.content.chars().take(100).collect::<String>(),
-                            "domain": format!("{:?}", ctx.domain),
-                            "importance": ctx.metadata.importance,
-                            "age_hours": ctx.age_hours(),
-                            "tags": ctx.metadata.tags
-                        })
+        match outcome {
+            Ok(outcome) => {
+                let stored = !(if_absent && outcome.existed());
+                let message = match (stored, outcome.existed()) {
+                    (false, _) => "Context already exists; not overwritten",
+                    (true, true) => "Existing context overwritten",
+                    (true, false) => "Context stored successfully",
+                };
+                let previous = outcome.previous.as_ref().map(|prev| {
+                    json!({
+                        "domain": prev.domain.to_string(),
+                        "source": prev.metadata.source,
+                        "tags": prev.metadata.tags,
+                        "importance": prev.metadata.importance,
+                        "created_at": prev.created_at.to_rfc3339()
                     })
-                    .collect();
+                });
 
                 CallToolResult::json(json!({
-                    "count": results.len(),
-                    "contexts": results
+                    "success": true,
+                    "id": outcome.id.to_string(),
+                    "stored": stored,
+                    "existed": outcome.existed(),
+                    "previous": previous,
+                    "message": message
                 }))
             }
-            Err(e) => CallToolResult::error(format!("Query failed: {}", e)),
+            Err(e) => CallToolResult::error(format!("Failed to store context: {}", e)),
         }
     }
+}
 
-    async fn retrieve_contexts(&self, args: HashMap<String, Value>) -> CallToolResult {
-        let mut query = RetrievalQuery::new();
+/// Default `chunk_size` for `store_context` with `auto_chunk`
+const DEFAULT_CHUNK_SIZE: usize = 1000;
 
-        if let Some(text) = args.get("text").and_then(|v| v.as_str()) {
-            query.text = Some(text.to_string());
-        }
+/// Default `chunk_overlap` for `store_context` with `auto_chunk`
+const DEFAULT_CHUNK_OVERLAP: usize = 100;
 
-        if let Some(domain) = args.get("domain").and_then(|v| v.as_str()) {
-            query = query.with_domain(parse_domain(domain));
+/// Store the chunks of a split context and report their IDs
+async fn store_chunks(chunks: Vec<Context>, if_absent: bool, cx: &ToolContext) -> CallToolResult {
+    let group = chunks[0].metadata.custom.get("chunk_group").cloned();
+    let mut ids = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        let outcome = if if_absent {
+            cx.store.store_if_absent(chunk).await
+        } else {
+            cx.store.store(chunk).await
+        };
+        match outcome {
+            Ok(outcome) => ids.push(outcome.id.to_string()),
+            Err(e) => return CallToolResult::error(format!("Failed to store chunk: {}", e)),
         }
+    }
 
-        if let Some(tags) = args.get("tags").and_then(|v| v.as_array()) {
-            for tag in tags.iter().filter_map(|v| v.as_str()) {
-                query = query.with_tag(tag.to_string());
-            }
-        }
+    CallToolResult::json(json!({
+        "success": true,
+        "id": ids[0],
+        "chunk_group": group,
+        "chunk_ids": ids,
+        "message": format!("Context stored as {} chunks", ids.len())
+    }))
+}
 
-        if let Some(min_importance) = args.get("min_importance").and_then(|v| v.as_f64()) {
-            query = query.with_min_importance(min_importance as f32);
-        }
+struct GetContextTool;
 
-        if let Some(max_age) = args.get("max_age_hours").and_then(|v| v.as_i64()) {
-            query = query.with_temporal(TemporalQuery::recent(max_age));
+#[async_trait]
+impl ContextTool for GetContextTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "get_context".to_string(),
+            description: Some("Retrieve a context by ID".to_string()),
+            input_schema: Projection::schema(
+                InputSchema::object()
+                    .with_required("id", PropertySchema::string("Context ID"))
+                    .with_property(CLIENT_PARAM, client_schema()),
+            ),
         }
+    }
 
-        match self.rag.retrieve(&query).await {
-            Ok(result) => {
-                let contexts: Vec<Value> = result
-                    .contexts
-                    .iter()
-                    .map(|sc| {
-                        json!({
-                            "id": sc.context.id.to_string(),
-                            "content": sc.context.content,
-                            "domain": format!("{:?}", sc.context.domain),
-                            "score": sc.score,
-                            "score_breakdown": {
-                                "temporal": sc.score_breakdown.temporal,
-                                "importance": sc.score_breakdown.importance,
-                                "domain_match": sc.score_breakdown.domain_match,
-                                "tag_match": sc.score_breakdown.tag_match
-                            },
-                            "age_hours": sc.context.age_hours(),
-                            "tags": sc.context.metadata.tags
-                        })
-                    })
-                    .collect();
-
-                CallToolResult::json(json!({
-                    "count": contexts.len(),
-                    "candidates_considered": result.candidates_considered,
-                    "processing_time_ms": result.processing_time_ms,
-                    "temporal_stats": {
-                        "count": result.temporal_stats.count,
-                        "avg_age_hours": result.temporal_stats.avg_age_hours,
-                        "distribution": result.temporal_stats.distribution
-                    },
-                    "contexts": contexts
-                }))
-            }
-            Err(e) => CallToolResult::error(format!("Retrieval failed: {}", e)),
-        }
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        id_violations(args, &["id"])
     }
 
-    async fn update_screening(&self, args: HashMap<String, Value>) -> CallToolResult {
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
         let id_str = match args.get("id").and_then(|v| v.as_str()) {
             Some(id) => id,
             None => return CallToolResult::error("Missing required parameter: id"),
         };
 
-        let status_str = match args.get("status").and_then(|v| v.as_str()) {
-            Some(s) => s,
-            None => return CallToolResult::error("Missing required parameter: status"),
-        };
-
-        let status = match status_str.to_lowercase().as_str() {
-            "safe" => ScreeningStatus::Safe,
-            "flagged" => ScreeningStatus::Flagged,
-            "blocked" => ScreeningStatus::Blocked,
-            _ => return CallToolResult::error(format!("Invalid status: {}", status_str)),
+        let id = match ContextId::parse(id_str) {
+            Ok(id) => id,
+            Err(e) => return CallToolResult::error(e.to_string()),
         };
+        let projection = Projection::from_args(&args);
 
-        let id = crate::context::ContextId::from_string(id_str.to_string());
+        let client = args.get(CLIENT_PARAM).and_then(|v| v.as_str());
 
-        match self.store.get(&id).await {
-            Ok(Some(mut ctx)) => {
-                ctx.metadata.screening_status = status.clone();
-                match self.store.store(ctx).await {
-                    Ok(_) => CallToolResult::json(json!({
-                        "success": true,
-                        "id": id_str,
-                        "new_status": format!("{:?}", status)
-                    })),
-                    Err(e) => CallToolResult::error(format!("Failed to update: {}", e)),
+        match cx.store.get_required_as(&id, client).await {
+            Ok(ctx) => {
+                let mut result = json!({
+                    "id": ctx.id.to_string(),
+                    "content": ctx.content,
+                    "domain": ctx.domain.to_string(),
+                    "created_at": ctx.created_at.to_rfc3339(),
+                    "accessed_at": ctx.accessed_at.to_rfc3339(),
+                    "metadata": {
+                        "source": ctx.metadata.source,
+                        "tags": ctx.metadata.tags,
+                        "importance": ctx.metadata.importance,
+                        "language": ctx.metadata.language,
+                        "verified": ctx.metadata.verified,
+                        "screening_status": format!("{:?}", ctx.metadata.screening_status)
+                    },
+                    "age_hours": ctx.age_hours(),
+                    "access": {
+                        "count": ctx.access_count,
+                        "last_accessed_by": ctx.last_accessed_by,
+                        "recent": ctx
+                            .recent_accesses
+                            .iter()
+                            .map(|at| at.to_rfc3339())
+                            .collect::<Vec<_>>()
+                    }
+                });
+                if let Some(ref data) = ctx.data {
+                    result["data"] = data.clone();
                 }
+                projection.apply(&ctx, cx, &mut result).await;
+                CallToolResult::json(result)
             }
-            Ok(None) => CallToolResult::error(format!("Context not found: {}", id_str)),
-            Err(e) => CallToolResult::error(format!("Error: {}", e)),
+            Err(e) if e.is_not_found() => CallToolResult::from_error(&e),
+            Err(e) => CallToolResult::error(format!("Error retrieving context: {}", e)),
         }
     }
+}
 
-    async fn get_temporal_stats(&self, args: HashMap<String, Value>) -> CallToolResult {
-        let mut query = ContextQuery::new();
+struct DeleteContextTool;
 
-        if let Some(domain) = args.get("domain").and_then(|v| v.as_str()) {
-            query = query.with_domain(parse_domain(domain));
+#[async_trait]
+impl ContextTool for DeleteContextTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "delete_context".to_string(),
+            description: Some("Delete a context by ID".to_string()),
+            input_schema: InputSchema::object()
+                .with_required("id", PropertySchema::string("Context ID")),
         }
+    }
 
-        match self.store.query(&query).await {
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        id_violations(args, &["id"])
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let id_str = match args.get("id").and_then(|v| v.as_str()) {
+            Some(id) => id,
+            None => return CallToolResult::error("Missing required parameter: id"),
+        };
+
+        let id = match ContextId::parse(id_str) {
+            Ok(id) => id,
+            Err(e) => return CallToolResult::error(e.to_string()),
+        };
+
+        match cx.store.delete(&id).await {
+            Ok(true) => CallToolResult::json(json!({
+                "success": true,
+                "message": "Context deleted"
+            })),
+            Ok(false) => CallToolResult::from_error(&ContextError::NotFound(id.to_string())),
+            Err(e) => CallToolResult::error(format!("Error deleting context: {}", e)),
+        }
+    }
+}
+
+struct MergeContextsTool;
+
+#[async_trait]
+impl ContextTool for MergeContextsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "merge_contexts".to_string(),
+            description: Some(
+                "Merge a duplicate context into another, leaving a redirect from its ID"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_required(
+                    "primary_id",
+                    PropertySchema::string("Context that survives the merge"),
+                )
+                .with_required(
+                    "secondary_id",
+                    PropertySchema::string("Context merged in and deleted"),
+                )
+                .with_property(
+                    "strategy",
+                    PropertySchema::string("How to combine content")
+                        .with_enum(vec!["concatenate", "prefer_primary", "prefer_secondary"])
+                        .with_default(json!("concatenate")),
+                )
+                .with_property(
+                    "dry_run",
+                    PropertySchema::boolean("Show the merged context without saving it")
+                        .with_default(json!(false)),
+                ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        id_violations(args, &["primary_id", "secondary_id"])
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let (primary, secondary) = match (
+            args.get("primary_id").and_then(|v| v.as_str()),
+            args.get("secondary_id").and_then(|v| v.as_str()),
+        ) {
+            (Some(p), Some(s)) => match (ContextId::parse(p), ContextId::parse(s)) {
+                (Ok(p), Ok(s)) => (p, s),
+                (Err(e), _) | (_, Err(e)) => return CallToolResult::error(e.to_string()),
+            },
+            _ => {
+                return CallToolResult::error(
+                    "Missing required parameter: primary_id or secondary_id",
+                )
+            }
+        };
+        let strategy = match args.get("strategy").and_then(|v| v.as_str()) {
+            Some("prefer_primary") => MergeStrategy::PreferPrimary,
+            Some("prefer_secondary") => MergeStrategy::PreferSecondary,
+            _ => MergeStrategy::Concatenate,
+        };
+        let dry_run = args
+            .get("dry_run")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let outcome = if dry_run {
+            cx.store.plan_merge(&primary, &secondary, strategy).await
+        } else {
+            cx.store.merge(&primary, &secondary, strategy).await
+        };
+
+        match outcome {
+            Ok(merged) => CallToolResult::json(json!({
+                "success": true,
+                "dry_run": dry_run,
+                "merged": {
+                    "id": merged.id.to_string(),
+                    "content": merged.content,
+                    "domain": merged.domain.to_string(),
+                    "tags": merged.metadata.tags,
+                    "importance": merged.metadata.importance,
+                    "created_at": merged.created_at.to_rfc3339(),
+                    "thread_id": merged.thread_id,
+                    "parent_id": merged.parent_id.as_ref().map(|id| id.to_string())
+                },
+                "message": if dry_run {
+                    "Dry run; nothing was changed"
+                } else {
+                    "Contexts merged"
+                }
+            })),
+            Err(e) => CallToolResult::error(format!("Failed to merge contexts: {}", e)),
+        }
+    }
+}
+
+struct AutoTagContextTool;
+
+#[async_trait]
+impl ContextTool for AutoTagContextTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "auto_tag_context".to_string(),
+            description: Some(
+                "Add keyword tags to a context, picked by TF-IDF against stored content"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_required("id", PropertySchema::string("Context ID"))
+                .with_property(
+                    "max_tags",
+                    PropertySchema::number("Maximum tags to suggest")
+                        .with_default(json!(KeywordAutoTagger::DEFAULT_MAX_TAGS))
+                        .with_minimum(1.0),
+                ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        id_violations(args, &["id"])
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let id = match args.get("id").and_then(|v| v.as_str()) {
+            Some(id) => match ContextId::parse(id) {
+                Ok(id) => id,
+                Err(e) => return CallToolResult::error(e.to_string()),
+            },
+            None => return CallToolResult::error("Missing required parameter: id"),
+        };
+        let max_tags = args
+            .get("max_tags")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(KeywordAutoTagger::DEFAULT_MAX_TAGS as u64) as usize;
+
+        let tagger = match KeywordAutoTagger::from_store(&cx.store).await {
+            Ok(tagger) => tagger.with_max_tags(max_tags),
+            Err(e) => return CallToolResult::error(format!("Failed to build tagger: {}", e)),
+        };
+
+        match cx.store.auto_tag(&id, &tagger).await {
+            Ok(added) => CallToolResult::json(json!({
+                "success": true,
+                "id": id.to_string(),
+                "added_tags": added
+            })),
+            Err(e) => CallToolResult::error(format!("Failed to tag context: {}", e)),
+        }
+    }
+}
+
+struct DiffRevisionsTool;
+
+#[async_trait]
+impl ContextTool for DiffRevisionsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "diff_revisions".to_string(),
+            description: Some("Show what changed between two revisions of a context".to_string()),
+            input_schema: InputSchema::object()
+                .with_required("id", PropertySchema::string("Context ID"))
+                .with_property(
+                    "from_rev",
+                    PropertySchema::number("Old revision (default: the one before to_rev)")
+                        .with_minimum(1.0),
+                )
+                .with_property(
+                    "to_rev",
+                    PropertySchema::number("New revision (default: current)").with_minimum(1.0),
+                ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        id_violations(args, &["id"])
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let id_str = match args.get("id").and_then(|v| v.as_str()) {
+            Some(id) => id,
+            None => return CallToolResult::error("Missing required parameter: id"),
+        };
+
+        let id = match ContextId::parse(id_str) {
+            Ok(id) => id,
+            Err(e) => return CallToolResult::error(e.to_string()),
+        };
+        let ctx = match cx.store.get_required(&id).await {
+            Ok(ctx) => ctx,
+            Err(e) if e.is_not_found() => return CallToolResult::from_error(&e),
+            Err(e) => return CallToolResult::error(format!("Error retrieving context: {}", e)),
+        };
+
+        let to_rev = args
+            .get("to_rev")
+            .and_then(|v| v.as_u64())
+            .map_or(ctx.version, |v| v as u32);
+        let from_rev = args
+            .get("from_rev")
+            .and_then(|v| v.as_u64())
+            .map_or(to_rev.saturating_sub(1), |v| v as u32);
+
+        let (old, new) = match (ctx.at_revision(from_rev), ctx.at_revision(to_rev)) {
+            (Some(old), Some(new)) => (old, new),
+            (None, _) => return CallToolResult::error(format!("Revision {} not found", from_rev)),
+            (_, None) => return CallToolResult::error(format!("Revision {} not found", to_rev)),
+        };
+
+        let diff = crate::context::diff(&old, &new);
+        CallToolResult::json(json!({
+            "id": id_str,
+            "rendered": diff.render(),
+            "diff": diff
+        }))
+    }
+}
+
+struct RollbackContextTool;
+
+#[async_trait]
+impl ContextTool for RollbackContextTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "rollback_context".to_string(),
+            description: Some(
+                "Restore a context's content from an earlier revision, recording a new revision"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_required("id", PropertySchema::string("Context ID"))
+                .with_required(
+                    "version",
+                    PropertySchema::number("Revision to restore").with_minimum(1.0),
+                ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        id_violations(args, &["id"])
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let id_str = match args.get("id").and_then(|v| v.as_str()) {
+            Some(id) => id,
+            None => return CallToolResult::error("Missing required parameter: id"),
+        };
+        let version = match args.get("version").and_then(|v| v.as_u64()) {
+            Some(version) => version as u32,
+            None => return CallToolResult::error("Missing required parameter: version"),
+        };
+
+        let id = match ContextId::parse(id_str) {
+            Ok(id) => id,
+            Err(e) => return CallToolResult::error(e.to_string()),
+        };
+        if let Err(e) = cx.store.rollback_to_revision(&id, version).await {
+            if e.is_not_found() {
+                return CallToolResult::from_error(&e);
+            }
+            return CallToolResult::error(format!("Error rolling back context: {}", e));
+        }
+
+        let ctx = match cx.store.get_required(&id).await {
+            Ok(ctx) => ctx,
+            Err(e) => return CallToolResult::error(format!("Error retrieving context: {}", e)),
+        };
+        CallToolResult::json(json!({
+            "id": id_str,
+            "rolled_back_to": version,
+            "version": ctx.version
+        }))
+    }
+}
+
+struct GetContextLineageTool;
+
+impl GetContextLineageTool {
+    fn summary(ctx: &Context) -> Value {
+        json!({
+            "id": ctx.id.to_string(),
+            "content": ctx.content,
+            "domain": ctx.domain.to_string(),
+            "version": ctx.version,
+            "created_at": ctx.created_at.to_rfc3339()
+        })
+    }
+}
+
+#[async_trait]
+impl ContextTool for GetContextLineageTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "get_context_lineage".to_string(),
+            description: Some(
+                "Show a context's provenance: revisions, ancestors and descendants".to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_required("id", PropertySchema::string("Context ID"))
+                .with_property(
+                    "max_depth",
+                    PropertySchema::number("Generations to follow up and down")
+                        .with_default(json!(10))
+                        .with_minimum(0.0),
+                ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        id_violations(args, &["id"])
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let id_str = match args.get("id").and_then(|v| v.as_str()) {
+            Some(id) => id,
+            None => return CallToolResult::error("Missing required parameter: id"),
+        };
+        let id = match ContextId::parse(id_str) {
+            Ok(id) => id,
+            Err(e) => return CallToolResult::error(e.to_string()),
+        };
+        let max_depth = args.get("max_depth").and_then(|v| v.as_u64()).unwrap_or(10) as usize;
+
+        match cx.store.get_context_lineage(&id, max_depth).await {
+            Ok(lineage) => CallToolResult::json(json!({
+                "context": Self::summary(&lineage.context),
+                "ancestors": lineage.ancestors.iter().map(Self::summary).collect::<Vec<_>>(),
+                "descendants": lineage.descendants.iter().map(Self::summary).collect::<Vec<_>>(),
+                "revisions": lineage.revisions.iter().map(|rev| json!({
+                    "version": rev.version,
+                    "replaced_at": rev.replaced_at.to_rfc3339(),
+                    "content": rev.content
+                })).collect::<Vec<_>>()
+            })),
+            Err(e) if e.is_not_found() => CallToolResult::from_error(&e),
+            Err(e) => CallToolResult::error(format!("Lineage lookup failed: {}", e)),
+        }
+    }
+}
+
+struct MigrateStoreTool;
+
+#[async_trait]
+impl ContextTool for MigrateStoreTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "migrate_store".to_string(),
+            description: Some(
+                "Copy every context into a new persistent store at another path; restart \
+                 with that --storage-path to switch over"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_required(
+                    "persist_path",
+                    PropertySchema::string("Directory for the new store; must not be in use"),
+                )
+                .with_property(
+                    "cache_size",
+                    PropertySchema::number("Memory cache size of the new store")
+                        .with_default(json!(1000))
+                        .with_minimum(1.0),
+                ),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let path = match args.get("persist_path").and_then(|v| v.as_str()) {
+            Some(path) => path,
+            None => return CallToolResult::error("Missing required parameter: persist_path"),
+        };
+        let cache_size = args
+            .get("cache_size")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(1000) as usize;
+
+        let dest_config = StorageConfig {
+            auto_cleanup: false,
+            ..StorageConfig::with_persistence(cache_size, path)
+        };
+        let dest = match cx.store.clone_to_new_store(dest_config).await {
+            Ok(dest) => dest,
+            Err(e) => return CallToolResult::error(format!("Migration failed: {}", e)),
+        };
+
+        match dest.all_contexts().await {
+            Ok(contexts) => CallToolResult::json(json!({
+                "success": true,
+                "persist_path": path,
+                "copied_count": contexts.len()
+            })),
+            Err(e) => CallToolResult::error(format!("Migrated store is unreadable: {}", e)),
+        }
+    }
+}
+
+struct ApplyDecayStrategyTool;
+
+impl ApplyDecayStrategyTool {
+    fn strategy(args: &HashMap<String, Value>) -> std::result::Result<DecayStrategy, String> {
+        let name = args.get("strategy").and_then(|v| v.as_str()).unwrap_or("");
+        let number = |param: &str| {
+            args.get(param)
+                .and_then(|v| v.as_f64())
+                .ok_or_else(|| format!("parameter '{}' is required for strategy '{}'", param, name))
+        };
+
+        let strategy = match name {
+            "exponential_by_age" => DecayStrategy::ExponentialByAge {
+                half_life_hours: number("half_life_hours")?,
+            },
+            "multiply_by_access_rate" => DecayStrategy::MultiplyByAccessRate {
+                window_hours: number("window_hours")? as u64,
+            },
+            "set_to_zero_if_older_than" => DecayStrategy::SetToZeroIfOlderThan {
+                threshold_hours: number("threshold_hours")?,
+            },
+            other => return Err(format!("Unknown decay strategy: {}", other)),
+        };
+        strategy.validate().map_err(|e| e.to_string())?;
+        Ok(strategy)
+    }
+}
+
+#[async_trait]
+impl ContextTool for ApplyDecayStrategyTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "apply_decay_strategy".to_string(),
+            description: Some(
+                "Recompute importance scores with a named decay strategy".to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_required(
+                    "strategy",
+                    PropertySchema::string("Decay strategy").with_enum(vec![
+                        "exponential_by_age",
+                        "multiply_by_access_rate",
+                        "set_to_zero_if_older_than",
+                    ]),
+                )
+                .with_property(
+                    "half_life_hours",
+                    PropertySchema::number("Half-life for exponential_by_age").with_minimum(0.0),
+                )
+                .with_property(
+                    "window_hours",
+                    PropertySchema::number("Access window for multiply_by_access_rate")
+                        .with_minimum(0.0),
+                )
+                .with_property(
+                    "threshold_hours",
+                    PropertySchema::number("Age limit for set_to_zero_if_older_than")
+                        .with_minimum(0.0),
+                )
+                .with_property(
+                    "domain",
+                    PropertySchema::string("Only adjust contexts in this domain"),
+                )
+                .with_property(
+                    "dry_run",
+                    PropertySchema::boolean("Count affected contexts without changing them")
+                        .with_default(json!(false)),
+                ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        Self::strategy(args).err().into_iter().collect()
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let strategy = match Self::strategy(&args) {
+            Ok(strategy) => strategy,
+            Err(e) => return CallToolResult::error(e),
+        };
+        let domain = args
+            .get("domain")
+            .and_then(|v| v.as_str())
+            .map(parse_domain);
+        let dry_run = args
+            .get("dry_run")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        match cx
+            .store
+            .apply_decay_strategy(strategy, domain.as_ref(), dry_run)
+            .await
+        {
+            Ok(affected) => CallToolResult::json(json!({
+                "strategy": strategy,
+                "domain": domain.map(|d| d.to_string()),
+                "dry_run": dry_run,
+                "affected": affected
+            })),
+            Err(e) => CallToolResult::error(format!("Decay failed: {}", e)),
+        }
+    }
+}
+
+struct QueryContextsTool;
+
+#[async_trait]
+impl ContextTool for QueryContextsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "query_contexts".to_string(),
+            description: Some("Query contexts with filters".to_string()),
+            input_schema: Projection::schema(InputSchema::object())
+                .with_property("domain", PropertySchema::string("Filter by domain"))
+                .with_property("tags", PropertySchema::array("Filter by tags"))
+                .with_property(
+                    "source",
+                    PropertySchema::string("Filter by exact source, e.g. github:issue-tracker"),
+                )
+                .with_property(
+                    "language",
+                    PropertySchema::string("Filter by detected language, e.g. en or de"),
+                )
+                .with_property(
+                    "min_importance",
+                    PropertySchema::number("Minimum importance threshold").with_range(0.0, 1.0),
+                )
+                .with_property(
+                    "max_age_hours",
+                    PropertySchema::number("Maximum age in hours").with_minimum(0.0),
+                )
+                .with_property(
+                    "verified_only",
+                    PropertySchema::boolean("Only return verified contexts"),
+                )
+                .with_property(
+                    "sort",
+                    PropertySchema::string("Result order")
+                        .with_enum(
+                            SortOrder::ALIASES
+                                .iter()
+                                .map(|(alias, _)| *alias)
+                                .chain(SortOrder::ALL.iter().map(|order| order.name()))
+                                .collect(),
+                        )
+                        .with_default(json!("importance")),
+                )
+                .with_property(CLIENT_PARAM, client_schema())
+                .with_property(
+                    "data_filter",
+                    PropertySchema::string(
+                        "Structured data equality, e.g. data.cluster == \"prod\"",
+                    ),
+                )
+                .with_property(
+                    "limit",
+                    PropertySchema::number("Maximum results")
+                        .with_default(json!(10))
+                        .with_minimum(1.0),
+                )
+                .with_property(
+                    "offset",
+                    PropertySchema::number("Sorted matches to skip")
+                        .with_default(json!(0))
+                        .with_minimum(0.0),
+                )
+                .with_property(
+                    "cursor",
+                    PropertySchema::string(
+                        "next_cursor of the previous page, to continue after it",
+                    ),
+                ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        let mut errors = Vec::new();
+        if let Some(expr) = args.get("data_filter").and_then(|v| v.as_str()) {
+            if let Err(e) = DataPathFilter::parse(expr) {
+                errors.push(format!("parameter 'data_filter' is invalid: {}", e));
+            }
+        }
+        if let Some(cursor) = args.get("cursor").and_then(|v| v.as_str()) {
+            if let Err(e) = QueryCursor::decode(cursor) {
+                errors.push(format!("parameter 'cursor' is invalid: {}", e));
+            }
+        }
+        errors
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let mut query = ContextQuery::new();
+
+        if let Some(expr) = args.get("data_filter").and_then(|v| v.as_str()) {
+            match DataPathFilter::parse(expr) {
+                Ok(filter) => query = query.with_data_path_filter(filter),
+                Err(e) => return CallToolResult::error(format!("Invalid data filter: {}", e)),
+            }
+        }
+
+        if let Some(domain) = args.get("domain").and_then(|v| v.as_str()) {
+            query = query.with_domain(parse_domain(domain));
+        }
+
+        if let Some(tags) = args.get("tags").and_then(|v| v.as_array()) {
+            for tag in tags.iter().filter_map(|v| v.as_str()) {
+                query = query.with_tag(tag.to_string());
+            }
+        }
+
+        if let Some(source) = args.get("source").and_then(|v| v.as_str()) {
+            query = query.with_source(source);
+        }
+
+        if let Some(min_importance) = args.get("min_importance").and_then(|v| v.as_f64()) {
+            query = query.with_min_importance(min_importance as f32);
+        }
+
+        if let Some(max_age) = args.get("max_age_hours").and_then(|v| v.as_i64()) {
+            query = query.with_max_age_hours(max_age);
+        }
+
+        if let Some(verified) = args.get("verified_only").and_then(|v| v.as_bool()) {
+            if verified {
+                query = query.verified_only();
+            }
+        }
+
+        if let Some(language) = args.get("language").and_then(|v| v.as_str()) {
+            query = query.with_language(language.to_lowercase());
+        }
+
+        if let Some(limit) = args.get("limit").and_then(|v| v.as_u64()) {
+            query = query.with_limit(limit as usize);
+        }
+
+        if let Some(sort) = args.get("sort").and_then(|v| v.as_str()) {
+            match SortOrder::parse(sort) {
+                Some(sort) => query = query.with_sort(sort),
+                None => return CallToolResult::error(format!("Unknown sort order: {}", sort)),
+            }
+        }
+
+        if let Some(offset) = args.get("offset").and_then(|v| v.as_u64()) {
+            query = query.with_offset(offset as usize);
+        }
+
+        if let Some(cursor) = args.get("cursor").and_then(|v| v.as_str()) {
+            match QueryCursor::decode(cursor) {
+                Ok(cursor) => query = query.with_cursor(cursor),
+                Err(e) => return CallToolResult::error(e.to_string()),
+            }
+        }
+
+        if let Some(client) = args.get(CLIENT_PARAM).and_then(|v| v.as_str()) {
+            query = query.with_accessor(client);
+        }
+
+        let projection = Projection::from_args(&args);
+
+        match cx.store.query_page(&query).await {
+            Ok(page) => {
+                let contexts = page.contexts;
+                let mut results: Vec<Value> = Vec::with_capacity(contexts.len());
+                for ctx in &contexts {
+                    let mut result = json!({
+                        "id": ctx.id.to_string(),
+                        "content_preview": ctx.content.chars().take(100).collect::<String>(),
+                        "domain": ctx.domain.to_string(),
+                        "importance": ctx.metadata.importance,
+                        "age_hours": ctx.age_hours(),
+                        "tags": ctx.metadata.tags
+                    });
+                    projection.apply(ctx, cx, &mut result).await;
+                    results.push(result);
+                }
+
+                CallToolResult::json(json!({
+                    "count": results.len(),
+                    "total_matches": page.total_matches,
+                    "next_cursor": page.next_cursor.map(|cursor| cursor.encode()),
+                    "contexts": results
+                }))
+            }
+            Err(e) => CallToolResult::error(format!("Query failed: {}", e)),
+        }
+    }
+}
+
+struct RetrieveContextsTool;
+
+#[async_trait]
+impl ContextTool for RetrieveContextsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "retrieve_contexts".to_string(),
+            description: Some("Retrieve contexts using RAG with scoring".to_string()),
+            input_schema: InputSchema::object()
+                .with_property("text", PropertySchema::string("Text query"))
+                .with_property("domain", PropertySchema::string("Domain filter"))
+                .with_property("tags", PropertySchema::array("Tag filters"))
+                .with_property(
+                    "language",
+                    PropertySchema::string("Language filter, e.g. en or de"),
+                )
+                .with_property(CLIENT_PARAM, client_schema())
+                .with_property(
+                    "min_importance",
+                    PropertySchema::number("Minimum importance").with_range(0.0, 1.0),
+                )
+                .with_property(
+                    "max_age_hours",
+                    PropertySchema::number("Maximum age for temporal filtering").with_minimum(0.0),
+                )
+                .with_property(
+                    "max_results",
+                    PropertySchema::number("Maximum results")
+                        .with_default(json!(10))
+                        .with_minimum(1.0),
+                )
+                .with_property(
+                    "expand_thread",
+                    PropertySchema::boolean("Include neighbouring turns from each hit's thread")
+                        .with_default(json!(false)),
+                )
+                .with_property(
+                    "thread_window",
+                    PropertySchema::number("Turns before and after a hit to include")
+                        .with_default(json!(2))
+                        .with_minimum(0.0),
+                )
+                .with_property(
+                    "coalesce_chunks",
+                    PropertySchema::boolean("Merge adjacent chunks of the same document")
+                        .with_default(json!(false)),
+                ),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let query = retrieval_query_from_args(&args);
+        let expand_thread = args
+            .get("expand_thread")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let thread_window = args
+            .get("thread_window")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(2) as usize;
+
+        let coalesce = args
+            .get("coalesce_chunks")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        match cx.rag.retrieve(&query).await {
+            Ok(result) => {
+                let result = if coalesce {
+                    result.coalesce_chunks()
+                } else {
+                    result
+                };
+                let mut contexts: Vec<Value> = Vec::with_capacity(result.contexts.len());
+                for sc in &result.contexts {
+                    let mut entry = json!({
+                        "id": sc.context.id.to_string(),
+                        "content": sc.context.content,
+                        "domain": sc.context.domain.to_string(),
+                        "score": sc.score,
+                        "score_breakdown": {
+                            "temporal": sc.score_breakdown.temporal,
+                            "importance": sc.score_breakdown.importance,
+                            "domain_match": sc.score_breakdown.domain_match,
+                            "tag_match": sc.score_breakdown.tag_match
+                        },
+                        "age_hours": sc.context.age_hours(),
+                        "tags": sc.context.metadata.tags
+                    });
+                    if let Some(chunk_ids) = sc.context.metadata.custom.get("chunk_ids") {
+                        entry["chunk_ids"] = chunk_ids.clone();
+                    }
+
+                    if let (true, Some(thread_id)) = (expand_thread, &sc.context.thread_id) {
+                        let turns = match cx.store.get_thread(thread_id).await {
+                            Ok(turns) => turns,
+                            Err(e) => {
+                                return CallToolResult::error(format!(
+                                    "Failed to expand thread: {}",
+                                    e
+                                ))
+                            }
+                        };
+                        let siblings = thread_neighbours(&turns, &sc.context.id, thread_window);
+                        entry["thread_id"] = json!(thread_id);
+                        entry["thread"] = json!(siblings
+                            .iter()
+                            .map(|turn| json!({
+                                "id": turn.id.to_string(),
+                                "content": turn.content,
+                                "created_at": turn.created_at.to_rfc3339()
+                            }))
+                            .collect::<Vec<_>>());
+                    }
+                    contexts.push(entry);
+                }
+
+                CallToolResult::json(json!({
+                    "count": contexts.len(),
+                    "candidates_considered": result.candidates_considered,
+                    "processing_time_ms": result.processing_time_ms,
+                    "temporal_stats": {
+                        "count": result.temporal_stats.count,
+                        "avg_age_hours": result.temporal_stats.avg_age_hours,
+                        "distribution": result.temporal_stats.distribution
+                    },
+                    "contexts": contexts
+                }))
+            }
+            Err(e) => CallToolResult::error(format!("Retrieval failed: {}", e)),
+        }
+    }
+}
+
+struct SummarizeContextsTool;
+
+#[async_trait]
+impl ContextTool for SummarizeContextsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "summarize_contexts".to_string(),
+            description: Some(
+                "Retrieve contexts and compress them into a single cited digest".to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_required("text", PropertySchema::string("Text query"))
+                .with_property("domain", PropertySchema::string("Domain filter"))
+                .with_property("tags", PropertySchema::array("Tag filters"))
+                .with_property(
+                    "min_importance",
+                    PropertySchema::number("Minimum importance").with_range(0.0, 1.0),
+                )
+                .with_property(
+                    "max_age_hours",
+                    PropertySchema::number("Maximum age for temporal filtering").with_minimum(0.0),
+                )
+                .with_property(
+                    "max_chars",
+                    PropertySchema::number("Maximum length of the digest in characters")
+                        .with_default(json!(2000))
+                        .with_minimum(1.0),
+                ),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let query = retrieval_query_from_args(&args);
+        let text = query.text.clone().unwrap_or_default();
+        let max_chars = args
+            .get("max_chars")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(2000) as usize;
+
+        match cx.rag.retrieve(&query).await {
+            Ok(result) => {
+                let summary = result.extractive_summary(&text, max_chars);
+                let sections: Vec<Value> = summary
+                    .sections
+                    .iter()
+                    .map(|section| {
+                        json!({
+                            "domain": section.domain.to_string(),
+                            "lines": section.lines
+                        })
+                    })
+                    .collect();
+
+                CallToolResult::json(json!({
+                    "summary": summary.text,
+                    "sections": sections,
+                    "truncated": summary.truncated,
+                    "contexts_considered": result.contexts.len()
+                }))
+            }
+            Err(e) => CallToolResult::error(format!("Summarization failed: {}", e)),
+        }
+    }
+}
+
+struct SearchByRegexTool;
+
+impl SearchByRegexTool {
+    fn compile(args: &HashMap<String, Value>) -> Result<regex::Regex> {
+        let pattern = args.get("pattern").and_then(|v| v.as_str()).unwrap_or("");
+        let case_insensitive = args
+            .get("case_insensitive")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        compile_content_regex(pattern, case_insensitive)
+    }
+}
+
+#[async_trait]
+impl ContextTool for SearchByRegexTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "search_by_regex".to_string(),
+            description: Some(
+                "Find contexts whose content matches a regular expression".to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_required("pattern", PropertySchema::string("Regular expression"))
+                .with_property("domain", PropertySchema::string("Domain filter"))
+                .with_property("tags", PropertySchema::array("Tag filters"))
+                .with_property(
+                    "max_results",
+                    PropertySchema::number("Maximum results")
+                        .with_default(json!(10))
+                        .with_minimum(1.0),
+                )
+                .with_property(
+                    "case_insensitive",
+                    PropertySchema::boolean("Match case-insensitively").with_default(json!(false)),
+                ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        match Self::compile(args) {
+            Ok(_) => Vec::new(),
+            Err(e) => vec![format!("parameter 'pattern' is not a valid regex: {}", e)],
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let regex = match Self::compile(&args) {
+            Ok(regex) => regex,
+            Err(e) => return CallToolResult::error(format!("Invalid pattern: {}", e)),
+        };
+
+        let mut query = ContextQuery::new().with_content_regex(regex.clone());
+
+        if let Some(domain) = args.get("domain").and_then(|v| v.as_str()) {
+            query = query.with_domain(parse_domain(domain));
+        }
+
+        if let Some(tags) = args.get("tags").and_then(|v| v.as_array()) {
+            let tags: Vec<String> = tags
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect();
+            query = query.with_tags(tags);
+        }
+
+        if let Some(limit) = args.get("max_results").and_then(|v| v.as_u64()) {
+            query = query.with_limit(limit as usize);
+        }
+
+        match cx.store.query(&query).await {
+            Ok(results) => {
+                let matches: Vec<Value> = results
+                    .iter()
+                    .filter_map(|ctx| {
+                        let m = regex.find(&ctx.content)?;
+                        Some(json!({
+                            "id": ctx.id.to_string(),
+                            "domain": ctx.domain.to_string(),
+                            "match_offset": m.start(),
+                            "match": m.as_str(),
+                            "snippet": snippet(&ctx.content, m.start(), m.end(), SNIPPET_RADIUS),
+                            "tags": ctx.metadata.tags
+                        }))
+                    })
+                    .collect();
+
+                CallToolResult::json(json!({
+                    "count": matches.len(),
+                    "matches": matches
+                }))
+            }
+            Err(e) => CallToolResult::error(format!("Search failed: {}", e)),
+        }
+    }
+}
+
+/// Bytes of surrounding content shown on each side of a regex match
+const SNIPPET_RADIUS: usize = 40;
+
+/// Excerpt of `content` around the byte range `start..end`, widened to char boundaries
+fn snippet(content: &str, start: usize, end: usize, radius: usize) -> &str {
+    let mut from = start.saturating_sub(radius);
+    while !content.is_char_boundary(from) {
+        from -= 1;
+    }
+    let mut to = (end + radius).min(content.len());
+    while !content.is_char_boundary(to) {
+        to += 1;
+    }
+    &content[from..to]
+}
+
+struct UpdateScreeningTool;
+
+#[async_trait]
+impl ContextTool for UpdateScreeningTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "update_screening".to_string(),
+            description: Some("Update screening status of a context".to_string()),
+            input_schema: InputSchema::object()
+                .with_required("id", PropertySchema::string("Context ID"))
+                .with_required(
+                    "status",
+                    PropertySchema::string("New screening status")
+                        .with_enum(vec!["Safe", "Flagged", "Blocked", "Pending"]),
+                )
+                .with_property("reason", PropertySchema::string("Reason for status change")),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        id_violations(args, &["id"])
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let id_str = match args.get("id").and_then(|v| v.as_str()) {
+            Some(id) => id,
+            None => return CallToolResult::error("Missing required parameter: id"),
+        };
+
+        let status_str = match args.get("status").and_then(|v| v.as_str()) {
+            Some(s) => s,
+            None => return CallToolResult::error("Missing required parameter: status"),
+        };
+
+        let status = match status_str.to_lowercase().as_str() {
+            "safe" => ScreeningStatus::Safe,
+            "flagged" => ScreeningStatus::Flagged,
+            "blocked" => ScreeningStatus::Blocked,
+            "pending" => ScreeningStatus::Pending,
+            _ => return CallToolResult::error(format!("Invalid status: {}", status_str)),
+        };
+
+        let id = match ContextId::parse(id_str) {
+            Ok(id) => id,
+            Err(e) => return CallToolResult::error(e.to_string()),
+        };
+
+        match cx.store.update_screening_status(&id, status.clone()).await {
+            Ok(previous) => CallToolResult::json(json!({
+                "success": true,
+                "id": id_str,
+                "previous_status": format!("{:?}", previous),
+                "new_status": format!("{:?}", status)
+            })),
+            Err(e) if e.is_not_found() => CallToolResult::from_error(&e),
+            Err(e) => CallToolResult::error(format!("Failed to update: {}", e)),
+        }
+    }
+}
+
+struct GetTemporalStatsTool;
+
+#[async_trait]
+impl ContextTool for GetTemporalStatsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "get_temporal_stats".to_string(),
+            description: Some("Get temporal statistics for stored contexts".to_string()),
+            input_schema: InputSchema::object()
+                .with_property("domain", PropertySchema::string("Filter by domain")),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let mut query = ContextQuery::new();
+
+        if let Some(domain) = args.get("domain").and_then(|v| v.as_str()) {
+            query = query.with_domain(parse_domain(domain));
+        }
+
+        match cx.store.query(&query).await {
             Ok(contexts) => {
                 let stats = crate::temporal::TemporalStats::from_contexts(&contexts);
                 CallToolResult::json(json!({
-                    "count": stats.count,
-                    "oldest": stats.oldest.map(|t| t.to_rfc3339()),
-                    "newest": stats.newest.map(|t| t.to_rfc3339()),
-                    "avg_age_hours": stats.avg_age_hours,
-                    "distribution": {
-                        "last_hour": stats.distribution.last_hour,
-                        "last_day": stats.distribution.last_day,
-                        "last_week": stats.distribution.last_week,
-                        "last_month": stats.distribution.last_month,
-                        "older": stats.distribution.older
-                    }
+                    "count": stats.count,
+                    "oldest": stats.oldest.map(|t| t.to_rfc3339()),
+                    "newest": stats.newest.map(|t| t.to_rfc3339()),
+                    "avg_age_hours": stats.avg_age_hours,
+                    "distribution": {
+                        "last_hour": stats.distribution.last_hour,
+                        "last_day": stats.distribution.last_day,
+                        "last_week": stats.distribution.last_week,
+                        "last_month": stats.distribution.last_month,
+                        "older": stats.distribution.older
+                    }
+                }))
+            }
+            Err(e) => CallToolResult::error(format!("Failed to get stats: {}", e)),
+        }
+    }
+}
+
+struct GetStorageStatsTool;
+
+#[async_trait]
+impl ContextTool for GetStorageStatsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "get_storage_stats".to_string(),
+            description: Some(
+                "Get storage statistics. cache_bytes and memory_bytes are estimates; \
+                 cache_hit_rate, cache_hits and cache_misses cover the window since operation \
+                 counters were last reset"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object().with_property(
+                "include",
+                PropertySchema::array("Optional sections to compute: \"domains\", \"sizes\""),
+            ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        args.get("include")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter(|section| !matches!(section.as_str(), Some("domains" | "sizes")))
+            .map(|section| {
+                format!(
+                    "parameter 'include' must contain only \"domains\" or \"sizes\", got {}",
+                    section
+                )
+            })
+            .collect()
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let sections: Vec<&str> = args
+            .get("include")
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default();
+        let include = StatsInclude {
+            domains: sections.contains(&"domains"),
+            sizes: sections.contains(&"sizes"),
+        };
+
+        let stats = match cx.store.stats_with(include).await {
+            Ok(stats) => stats,
+            Err(e) => return CallToolResult::error(format!("Failed to compute stats: {}", e)),
+        };
+
+        let mut result = json!({
+            "memory_count": stats.memory_count,
+            "pinned_count": stats.pinned_count,
+            "disk_count": stats.disk_count,
+            "cache_capacity": stats.cache_capacity,
+            "cache_hit_rate": stats.cache_hit_rate,
+            "cache_hits": stats.cache_hits,
+            "cache_misses": stats.cache_misses,
+            "cache_bytes": stats.cache_bytes,
+            "max_cache_bytes": stats.max_cache_bytes,
+            "evictions": stats.evictions,
+            "expired_pending_cleanup": stats.expired_pending_cleanup
+        });
+        if include.sizes {
+            result["memory_bytes"] = json!(stats.memory_bytes);
+            result["disk_bytes"] = json!(stats.disk_bytes);
+        }
+        if include.domains {
+            result["counts_by_domain"] = json!(stats.counts_by_domain);
+        }
+        CallToolResult::json(result)
+    }
+}
+
+struct GetIndexStatsTool;
+
+#[async_trait]
+impl ContextTool for GetIndexStatsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "get_index_stats".to_string(),
+            description: Some(
+                "Get domain and tag index sizes and the estimated share of orphan entries"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object(),
+        }
+    }
+
+    async fn execute(&self, _args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        match cx.store.dump_index_statistics().await {
+            Ok(stats) => CallToolResult::json(json!(stats)),
+            Err(e) => CallToolResult::error(format!("Failed to compute index stats: {}", e)),
+        }
+    }
+}
+
+struct GetSizeDistributionTool;
+
+#[async_trait]
+impl ContextTool for GetSizeDistributionTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "get_size_distribution".to_string(),
+            description: Some(
+                "Get a histogram and percentiles of context content length in characters"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object(),
+        }
+    }
+
+    async fn execute(&self, _args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        match cx.store.get_context_size_histogram().await {
+            Ok(histogram) => CallToolResult::json(json!(histogram)),
+            Err(e) => CallToolResult::error(format!("Failed to compute size distribution: {}", e)),
+        }
+    }
+}
+
+struct ListContextIdsTool;
+
+#[async_trait]
+impl ContextTool for ListContextIdsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "list_context_ids".to_string(),
+            description: Some("List stored context IDs without loading their content".to_string()),
+            input_schema: InputSchema::object()
+                .with_property("domain", PropertySchema::string("Only IDs in this domain")),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let domain = args
+            .get("domain")
+            .and_then(|v| v.as_str())
+            .map(parse_domain);
+
+        match cx.store.list_all_ids(domain.as_ref()).await {
+            Ok(ids) => CallToolResult::json(json!({
+                "count": ids.len(),
+                "ids": ids.iter().map(|id| id.to_string()).collect::<Vec<_>>()
+            })),
+            Err(e) => CallToolResult::error(format!("Failed to list context IDs: {}", e)),
+        }
+    }
+}
+
+/// Largest operation count a `benchmark_storage` call may request
+const MAX_BENCHMARK_OPERATIONS: usize = 10_000;
+
+struct BenchmarkStorageTool;
+
+#[async_trait]
+impl ContextTool for BenchmarkStorageTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "benchmark_storage".to_string(),
+            description: Some(
+                "Time store, get and query throughput on temporary synthetic contexts".to_string(),
+            ),
+            input_schema: InputSchema::object().with_property(
+                "operations",
+                PropertySchema::number("Operations of each kind to time")
+                    .with_default(json!(100))
+                    .with_range(1.0, MAX_BENCHMARK_OPERATIONS as f64),
+            ),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let n = args
+            .get("operations")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(100) as usize;
+
+        match cx.store.benchmark_store_read_write(n).await {
+            Ok(result) => CallToolResult::json(json!({
+                "operations": n,
+                "result": result
+            })),
+            Err(e) => CallToolResult::error(format!("Benchmark failed: {}", e)),
+        }
+    }
+}
+
+struct ComputeDomainOverlapTool;
+
+#[async_trait]
+impl ContextTool for ComputeDomainOverlapTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "compute_domain_overlap".to_string(),
+            description: Some(
+                "Jaccard similarity (0.0-1.0) of the tag vocabularies of two domains".to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_required("domain_a", PropertySchema::string("First domain"))
+                .with_required("domain_b", PropertySchema::string("Second domain")),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let (a, b) = match (
+            args.get("domain_a").and_then(|v| v.as_str()),
+            args.get("domain_b").and_then(|v| v.as_str()),
+        ) {
+            (Some(a), Some(b)) => (parse_domain(a), parse_domain(b)),
+            _ => return CallToolResult::error("Missing required parameters: domain_a, domain_b"),
+        };
+
+        match cx.store.compute_domain_overlap(&a, &b).await {
+            Ok(overlap) => CallToolResult::json(json!({
+                "domain_a": a.to_string(),
+                "domain_b": b.to_string(),
+                "overlap": overlap
+            })),
+            Err(e) => CallToolResult::error(format!("Failed to compute overlap: {}", e)),
+        }
+    }
+}
+
+struct EvaluateRetrievalTool;
+
+impl EvaluateRetrievalTool {
+    /// Parse `[{"query": ..., "relevant_ids": [...]}, ...]` into ground-truth pairs
+    fn ground_truth(value: &Value) -> std::result::Result<Vec<(String, Vec<ContextId>)>, String> {
+        let entries = value.as_array().ok_or("ground_truth must be an array")?;
+        entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let query = entry["query"]
+                    .as_str()
+                    .ok_or_else(|| format!("ground_truth[{}] has no query", i))?;
+                let ids = entry["relevant_ids"]
+                    .as_array()
+                    .ok_or_else(|| format!("ground_truth[{}] has no relevant_ids", i))?
+                    .iter()
+                    .map(|id| {
+                        id.as_str()
+                            .ok_or_else(|| format!("ground_truth[{}] has a non-string ID", i))
+                            .and_then(|id| {
+                                ContextId::parse(id)
+                                    .map_err(|e| format!("ground_truth[{}]: {}", i, e))
+                            })
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                Ok((query.to_string(), ids))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ContextTool for EvaluateRetrievalTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "evaluate_retrieval".to_string(),
+            description: Some(
+                "Measure recall@1/5/10 and MRR of retrieval against known-relevant contexts"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object().with_required(
+                "ground_truth",
+                PropertySchema::array(
+                    "Objects of the form {\"query\": text, \"relevant_ids\": [context IDs]}",
+                ),
+            ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        match args.get("ground_truth").map(Self::ground_truth) {
+            Some(Err(e)) => vec![e],
+            _ => Vec::new(),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let ground_truth = match args.get("ground_truth").map(Self::ground_truth) {
+            Some(Ok(ground_truth)) => ground_truth,
+            Some(Err(e)) => return CallToolResult::error(e),
+            None => return CallToolResult::error("Missing required parameter: ground_truth"),
+        };
+
+        match cx
+            .store
+            .compute_retrieval_recall(&ground_truth, &cx.rag)
+            .await
+        {
+            Ok(report) => CallToolResult::json(json!(report)),
+            Err(e) => CallToolResult::error(format!("Failed to evaluate retrieval: {}", e)),
+        }
+    }
+}
+
+struct GetOperationStatsTool;
+
+#[async_trait]
+impl ContextTool for GetOperationStatsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "get_operation_stats".to_string(),
+            description: Some(
+                "Get read, write, delete, query and retrieval counts since the counters were last reset"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object().with_property(
+                "reset",
+                PropertySchema::boolean("Reset the counters after reading them")
+                    .with_default(json!(false)),
+            ),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let stats = match cx.store.get_read_write_ratio() {
+            Ok(stats) => stats,
+            Err(e) => return CallToolResult::error(format!("Failed to read counters: {}", e)),
+        };
+        let counts = cx.store.counters().snapshot();
+
+        let reset = args.get("reset").and_then(|v| v.as_bool()).unwrap_or(false);
+        if reset {
+            cx.store.reset_counters();
+        }
+
+        CallToolResult::json(json!({
+            "reads": stats.reads,
+            "writes": stats.writes,
+            "deletes": stats.deletes,
+            "queries": counts.queries,
+            "retrievals": counts.retrievals,
+            "snapshots": counts.snapshots,
+            "snapshot_failures": counts.snapshot_failures,
+            "window_secs": stats.window_secs,
+            "ratio": stats.ratio,
+            "reset": reset
+        }))
+    }
+}
+
+struct CheckStoreInvariantsTool;
+
+#[async_trait]
+impl ContextTool for CheckStoreInvariantsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "check_store_invariants".to_string(),
+            description: Some(
+                "Report inconsistencies between the domain index and stored contexts".to_string(),
+            ),
+            input_schema: InputSchema::object(),
+        }
+    }
+
+    async fn execute(&self, _args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        match cx.store.assert_domain_invariants().await {
+            Ok(violations) => CallToolResult::json(json!({
+                "ok": violations.is_empty(),
+                "violation_count": violations.len(),
+                "violations": violations
+            })),
+            Err(e) => CallToolResult::error(format!("Invariant check failed: {}", e)),
+        }
+    }
+}
+
+struct CleanupExpiredTool;
+
+#[async_trait]
+impl ContextTool for CleanupExpiredTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "cleanup_expired".to_string(),
+            description: Some("Remove expired contexts".to_string()),
+            input_schema: InputSchema::object(),
+        }
+    }
+
+    async fn execute(&self, _args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        match cx.store.cleanup_expired().await {
+            Ok(count) => CallToolResult::json(json!({
+                "success": true,
+                "removed_count": count
+            })),
+            Err(e) => CallToolResult::error(format!("Cleanup failed: {}", e)),
+        }
+    }
+}
+
+struct GetDiskUsageTool;
+
+#[async_trait]
+impl ContextTool for GetDiskUsageTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "get_disk_usage".to_string(),
+            description: Some("Get persisted bytes per domain".to_string()),
+            input_schema: InputSchema::object(),
+        }
+    }
+
+    async fn execute(&self, _args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        match cx.store.get_disk_usage_by_domain() {
+            Ok(by_domain) => CallToolResult::json(json!({
+                "total_bytes": by_domain.values().sum::<usize>(),
+                "by_domain": by_domain
+            })),
+            Err(e) => CallToolResult::error(format!("Failed to compute disk usage: {}", e)),
+        }
+    }
+}
+
+struct CreateCheckpointTool;
+
+#[async_trait]
+impl ContextTool for CreateCheckpointTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "create_checkpoint".to_string(),
+            description: Some("Copy the persisted store to a timestamped checkpoint".to_string()),
+            input_schema: InputSchema::object(),
+        }
+    }
+
+    async fn execute(&self, _args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let store = cx.store.clone();
+        match tokio::task::spawn_blocking(move || store.create_sled_checkpoint()).await {
+            Ok(Ok(path)) => CallToolResult::json(json!({
+                "path": path.display().to_string()
+            })),
+            Ok(Err(e)) => CallToolResult::error(format!("Checkpoint failed: {}", e)),
+            Err(e) => CallToolResult::error(format!("Checkpoint task failed: {}", e)),
+        }
+    }
+}
+
+struct ListSnapshotsTool;
+
+#[async_trait]
+impl ContextTool for ListSnapshotsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "list_snapshots".to_string(),
+            description: Some(
+                "List scheduled snapshots, oldest first, with the snapshot policy and the outcome of the last attempt"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object(),
+        }
+    }
+
+    async fn execute(&self, _args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let store = cx.store.clone();
+        match tokio::task::spawn_blocking(move || store.list_snapshots()).await {
+            Ok(Ok(snapshots)) => CallToolResult::json(json!({
+                "policy": cx.store.config().snapshot,
+                "snapshots": snapshots,
+                "status": cx.store.snapshot_status()
+            })),
+            Ok(Err(e)) => CallToolResult::error(format!("Failed to list snapshots: {}", e)),
+            Err(e) => CallToolResult::error(format!("Snapshot listing failed: {}", e)),
+        }
+    }
+}
+
+/// Operations accepted by `run_maintenance`
+const MAINTENANCE_OPERATIONS: &[&str] = &[
+    "importance_decay",
+    "rebuild_indexes",
+    "reindex_embeddings",
+    "archive_old",
+];
+
+#[derive(Default)]
+struct RunMaintenanceTool {
+    /// Held for the duration of a run so runs never overlap
+    running: tokio::sync::Mutex<()>,
+    /// Report of the most recent run of each operation
+    last_runs: RwLock<BTreeMap<String, Value>>,
+}
+
+impl RunMaintenanceTool {
+    async fn run_operation(
+        &self,
+        operation: &str,
+        args: &HashMap<String, Value>,
+        cx: &ToolContext,
+    ) -> Result<usize> {
+        let days = |name: &str, default: f64| {
+            let days = args.get(name).and_then(|v| v.as_f64()).unwrap_or(default);
+            chrono::Duration::seconds((days * 86_400.0) as i64)
+        };
+
+        match operation {
+            "importance_decay" => {
+                let factor = args
+                    .get("decay_factor")
+                    .and_then(|v| v.as_f64())
+                    .unwrap_or(0.9) as f32;
+                cx.store
+                    .decay_importance(factor, days("idle_days", 7.0))
+                    .await
+            }
+            "rebuild_indexes" => cx.store.rebuild_indexes().await,
+            "reindex_embeddings" => cx.rag.reindex_embeddings().await,
+            "archive_old" => cx
+                .store
+                .archive_older_than(days("archive_after_days", 90.0))
+                .await
+                .map(|(archived, _)| archived),
+            other => Err(ContextError::InvalidQuery(format!(
+                "Unknown maintenance operation: {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl ContextTool for RunMaintenanceTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "run_maintenance".to_string(),
+            description: Some(
+                "Run maintenance operations (one run at a time) or report the last run of each"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_property(
+                    "action",
+                    PropertySchema::string("Run operations or report their status")
+                        .with_enum(vec!["run", "status"])
+                        .with_default(json!("run")),
+                )
+                .with_property(
+                    "operations",
+                    PropertySchema::array(
+                        "Operations to run: importance_decay, rebuild_indexes, \
+                         reindex_embeddings, archive_old",
+                    ),
+                )
+                .with_property(
+                    "decay_factor",
+                    PropertySchema::number("Importance multiplier for importance_decay")
+                        .with_default(json!(0.9))
+                        .with_range(0.0, 1.0),
+                )
+                .with_property(
+                    "idle_days",
+                    PropertySchema::number("Days without access before importance decays")
+                        .with_default(json!(7))
+                        .with_minimum(0.0),
+                )
+                .with_property(
+                    "archive_after_days",
+                    PropertySchema::number("Age in days after which archive_old moves contexts")
+                        .with_default(json!(90))
+                        .with_minimum(0.0),
+                ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        let action = args.get("action").and_then(|v| v.as_str()).unwrap_or("run");
+        if action.eq_ignore_ascii_case("status") {
+            return Vec::new();
+        }
+
+        let operations = args.get("operations").and_then(|v| v.as_array());
+        match operations {
+            Some(operations) if !operations.is_empty() => operations
+                .iter()
+                .filter(|op| {
+                    !op.as_str()
+                        .map(|op| MAINTENANCE_OPERATIONS.contains(&op))
+                        .unwrap_or(false)
+                })
+                .map(|op| {
+                    format!(
+                        "parameter 'operations' must contain only {:?}, got {}",
+                        MAINTENANCE_OPERATIONS, op
+                    )
+                })
+                .collect(),
+            _ => vec!["parameter 'operations' must list at least one operation".to_string()],
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let action = args.get("action").and_then(|v| v.as_str()).unwrap_or("run");
+        if action.eq_ignore_ascii_case("status") {
+            let last_runs = self
+                .last_runs
+                .read()
+                .expect("maintenance status lock poisoned")
+                .clone();
+            return CallToolResult::json(json!({
+                "running": self.running.try_lock().is_err(),
+                "last_runs": last_runs
+            }));
+        }
+
+        let _running = match self.running.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => return CallToolResult::error("Maintenance is already running"),
+        };
+
+        let operations: Vec<String> = args
+            .get("operations")
+            .and_then(|v| v.as_array())
+            .map(|ops| {
+                ops.iter()
+                    .filter_map(|op| op.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let total = operations.len();
+        let token = format!("maintenance-{}", chrono::Utc::now().timestamp_millis());
+
+        let mut reports = Vec::with_capacity(total);
+        for (i, operation) in operations.iter().enumerate() {
+            cx.notify(Notification::progress(
+                &token,
+                i,
+                total,
+                format!("Running {}", operation),
+            ));
+
+            let started = Instant::now();
+            let (items_touched, errors) = match self.run_operation(operation, &args, cx).await {
+                Ok(touched) => (touched, Vec::new()),
+                Err(e) => (0, vec![e.to_string()]),
+            };
+            let report = json!({
+                "operation": operation,
+                "items_touched": items_touched,
+                "duration_ms": started.elapsed().as_millis() as u64,
+                "errors": errors,
+                "finished_at": chrono::Utc::now().to_rfc3339()
+            });
+
+            self.last_runs
+                .write()
+                .expect("maintenance status lock poisoned")
+                .insert(operation.clone(), report.clone());
+            reports.push(report);
+        }
+
+        cx.notify(Notification::progress(
+            &token,
+            total,
+            total,
+            "Maintenance complete",
+        ));
+        CallToolResult::json(json!({ "operations": reports }))
+    }
+}
+
+struct GetStaleContextsTool;
+
+#[async_trait]
+impl ContextTool for GetStaleContextsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "get_stale_contexts".to_string(),
+            description: Some(
+                "List the contexts that have gone longest without access".to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_property(
+                    "threshold_days",
+                    PropertySchema::number(
+                        "Only include contexts idle for at least this many days",
+                    )
+                    .with_default(json!(0))
+                    .with_minimum(0.0),
+                )
+                .with_property(
+                    "limit",
+                    PropertySchema::number("Maximum results")
+                        .with_default(json!(10))
+                        .with_minimum(1.0),
+                ),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let threshold_hours = args
+            .get("threshold_days")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0)
+            * 24.0;
+        let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(10) as usize;
+
+        match cx.store.get_stalest_contexts(limit).await {
+            Ok(stalest) => {
+                let results: Vec<Value> = stalest
+                    .iter()
+                    .filter(|(_, hours)| *hours >= threshold_hours)
+                    .map(|(ctx, hours)| {
+                        json!({
+                            "id": ctx.id.to_string(),
+                            "content_preview": ctx.content.chars().take(100).collect::<String>(),
+                            "domain": ctx.domain.to_string(),
+                            "importance": ctx.metadata.importance,
+                            "accessed_at": ctx.accessed_at.to_rfc3339(),
+                            "hours_since_access": hours
+                        })
+                    })
+                    .collect();
+
+                CallToolResult::json(json!({
+                    "count": results.len(),
+                    "contexts": results
+                }))
+            }
+            Err(e) => CallToolResult::error(format!("Failed to find stale contexts: {}", e)),
+        }
+    }
+}
+
+struct GetVocabularyTool;
+
+#[async_trait]
+impl ContextTool for GetVocabularyTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "get_vocabulary".to_string(),
+            description: Some(
+                "List the most frequent words across stored context content".to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_property(
+                    "min_count",
+                    PropertySchema::number("Only include words seen at least this often")
+                        .with_default(json!(1))
+                        .with_minimum(1.0),
+                )
+                .with_property(
+                    "max_words",
+                    PropertySchema::number("Maximum words to return")
+                        .with_default(json!(100))
+                        .with_minimum(1.0),
+                )
+                .with_property(
+                    "stop_words",
+                    PropertySchema::array("Words to leave out (matched lowercase)"),
+                ),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let min_count = args.get("min_count").and_then(|v| v.as_u64()).unwrap_or(1) as u32;
+        let max_words = args
+            .get("max_words")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(100) as usize;
+        let stop_words = args
+            .get("stop_words")
+            .and_then(|v| v.as_array())
+            .map(|words| {
+                words
+                    .iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_lowercase()))
+                    .collect::<HashSet<String>>()
+            });
+
+        match cx
+            .store
+            .get_content_vocabulary(min_count, max_words, stop_words)
+            .await
+        {
+            Ok(vocabulary) => {
+                let mut words: Vec<(String, u32)> = vocabulary.into_iter().collect();
+                words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                let words: Vec<Value> = words
+                    .into_iter()
+                    .map(|(word, count)| json!({"word": word, "count": count}))
+                    .collect();
+
+                CallToolResult::json(json!({
+                    "count": words.len(),
+                    "words": words
                 }))
             }
-            Err(e) => CallToolResult::error(format!("Failed to get stats: {}", e)),
+            Err(e) => CallToolResult::error(format!("Failed to build vocabulary: {}", e)),
+        }
+    }
+}
+
+struct CleanupSourceTool;
+
+#[async_trait]
+impl ContextTool for CleanupSourceTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "cleanup_source".to_string(),
+            description: Some(
+                "Delete all contexts from a retired source (exact match or glob with * and ?)"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_required(
+                    "source_pattern",
+                    PropertySchema::string("Source or glob pattern, e.g. https://old-api/*"),
+                )
+                .with_property(
+                    "dry_run",
+                    PropertySchema::boolean("Only report matching sources without deleting")
+                        .with_default(json!(false)),
+                ),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let pattern = match args.get("source_pattern").and_then(|v| v.as_str()) {
+            Some(p) => p,
+            None => return CallToolResult::error("Missing required parameter: source_pattern"),
+        };
+        let dry_run = args
+            .get("dry_run")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let matches = match cx.store.find_by_source(pattern).await {
+            Ok(m) => m,
+            Err(e) => return CallToolResult::error(format!("Failed to match sources: {}", e)),
+        };
+
+        let mut sources: HashMap<String, usize> = HashMap::new();
+        for (_, source) in &matches {
+            *sources.entry(source.clone()).or_default() += 1;
+        }
+
+        if dry_run {
+            return CallToolResult::json(json!({
+                "dry_run": true,
+                "match_count": matches.len(),
+                "sources": sources
+            }));
+        }
+
+        let ids: Vec<_> = matches.into_iter().map(|(id, _)| id).collect();
+        match cx.store.delete_batch(&ids).await {
+            Ok(count) => CallToolResult::json(json!({
+                "success": true,
+                "removed_count": count,
+                "sources": sources
+            })),
+            Err(e) => CallToolResult::error(format!("Source cleanup failed: {}", e)),
+        }
+    }
+}
+
+struct EnforceSourcePolicyTool;
+
+#[async_trait]
+impl ContextTool for EnforceSourcePolicyTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "enforce_source_policy".to_string(),
+            description: Some(
+                "Delete every context whose source is not on an allowlist".to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_required(
+                    "allowed",
+                    PropertySchema::array("Allowed sources; a trailing * matches as a prefix"),
+                )
+                .with_property(
+                    "mode",
+                    PropertySchema::string("Treat every entry as a prefix, or match exactly")
+                        .with_enum(vec!["prefix", "exact"])
+                        .with_default(json!("prefix")),
+                )
+                .with_property(
+                    "dry_run",
+                    PropertySchema::boolean("Only count contexts that would be deleted")
+                        .with_default(json!(false)),
+                ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        match args.get("allowed").and_then(|v| v.as_array()) {
+            Some(allowed) if allowed.is_empty() => {
+                vec!["parameter 'allowed' must list at least one source".to_string()]
+            }
+            Some(allowed) if allowed.iter().any(|v| !v.is_string()) => {
+                vec!["parameter 'allowed' must contain only strings".to_string()]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let allowed: Vec<&str> = match args.get("allowed").and_then(|v| v.as_array()) {
+            Some(allowed) => allowed.iter().filter_map(|v| v.as_str()).collect(),
+            None => return CallToolResult::error("Missing required parameter: allowed"),
+        };
+        let mode = match args.get("mode").and_then(|v| v.as_str()) {
+            Some(mode) if mode.eq_ignore_ascii_case("exact") => SourceMatchMode::Exact,
+            _ => SourceMatchMode::Prefix,
+        };
+        let dry_run = args
+            .get("dry_run")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        match cx
+            .store
+            .enforce_source_allowlist(&allowed, mode, dry_run)
+            .await
+        {
+            Ok(count) if dry_run => CallToolResult::json(json!({
+                "dry_run": true,
+                "mode": mode,
+                "match_count": count
+            })),
+            Ok(count) => CallToolResult::json(json!({
+                "success": true,
+                "mode": mode,
+                "removed_count": count
+            })),
+            Err(e) => CallToolResult::error(format!("Source policy failed: {}", e)),
+        }
+    }
+}
+
+struct LookupByHashTool;
+
+#[async_trait]
+impl ContextTool for LookupByHashTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "lookup_by_hash".to_string(),
+            description: Some(
+                "Find a context by the SHA-256 of its content, or by the content itself"
+                    .to_string(),
+            ),
+            input_schema: InputSchema::object()
+                .with_property(
+                    "hash",
+                    PropertySchema::string("SHA-256 of the content as 64 hex characters"),
+                )
+                .with_property(
+                    "content",
+                    PropertySchema::string("Content to hash and look up, instead of hash"),
+                ),
+        }
+    }
+
+    fn validate(&self, args: &HashMap<String, Value>) -> Vec<String> {
+        let hash = args.get("hash").and_then(|v| v.as_str());
+        let content = args.get("content").and_then(|v| v.as_str());
+        match (hash, content) {
+            (Some(_), Some(_)) => vec!["give either 'hash' or 'content', not both".to_string()],
+            (None, None) => vec!["one of 'hash' or 'content' is required".to_string()],
+            (Some(hash), None)
+                if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                vec!["parameter 'hash' must be 64 hex characters".to_string()]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let hash = match (
+            args.get("hash").and_then(|v| v.as_str()),
+            args.get("content").and_then(|v| v.as_str()),
+        ) {
+            (Some(hash), None) => hash.to_ascii_lowercase(),
+            (None, Some(content)) => hash_content(content),
+            _ => return CallToolResult::error("Give exactly one of: hash, content"),
+        };
+
+        match cx.store.get_by_content_hash(&hash).await {
+            Ok(Some(ctx)) => CallToolResult::json(json!({
+                "found": true,
+                "hash": hash,
+                "id": ctx.id.to_string(),
+                "domain": ctx.domain.to_string(),
+                "content": ctx.content
+            })),
+            Ok(None) => CallToolResult::json(json!({
+                "found": false,
+                "hash": hash
+            })),
+            Err(e) => CallToolResult::error(format!("Lookup failed: {}", e)),
+        }
+    }
+}
+
+/// Generates embeddings for stored contexts that lack one
+///
+/// Not registered by default since it needs an embedding backend; add it
+/// with [`ToolRegistry::register`] once one is available.
+pub struct BackfillEmbeddingsTool {
+    generator: Arc<dyn EmbeddingGenerator>,
+}
+
+impl BackfillEmbeddingsTool {
+    /// Create the tool around an embedding generator
+    pub fn new(generator: Arc<dyn EmbeddingGenerator>) -> Self {
+        Self { generator }
+    }
+}
+
+#[async_trait]
+impl ContextTool for BackfillEmbeddingsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "backfill_embeddings".to_string(),
+            description: Some("Generate embeddings for contexts stored without one".to_string()),
+            input_schema: InputSchema::object()
+                .with_property(
+                    "domain",
+                    PropertySchema::string("Only embed contexts in this domain"),
+                )
+                .with_property(
+                    "batch_size",
+                    PropertySchema::number("Embeddings generated concurrently per batch")
+                        .with_default(json!(32))
+                        .with_minimum(1.0),
+                )
+                .with_property(
+                    "dry_run",
+                    PropertySchema::boolean("Only count the contexts lacking an embedding")
+                        .with_default(json!(false)),
+                ),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let domain = args
+            .get("domain")
+            .and_then(|v| v.as_str())
+            .map(parse_domain);
+        let batch_size = args
+            .get("batch_size")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(32) as usize;
+        let dry_run = args
+            .get("dry_run")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        if dry_run {
+            return match cx
+                .store
+                .find_contexts_without_embeddings(domain.as_ref())
+                .await
+            {
+                Ok(ids) => CallToolResult::json(json!({
+                    "dry_run": true,
+                    "missing_count": ids.len()
+                })),
+                Err(e) => CallToolResult::error(format!("Scan failed: {}", e)),
+            };
+        }
+
+        match cx
+            .store
+            .backfill_embeddings(domain.as_ref(), batch_size, self.generator.clone())
+            .await
+        {
+            Ok(count) => CallToolResult::json(json!({
+                "success": true,
+                "embedded_count": count
+            })),
+            Err(e) => CallToolResult::error(format!("Backfill failed: {}", e)),
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+struct ReconcileWithRemoteTool;
+
+#[cfg(feature = "client")]
+impl ReconcileWithRemoteTool {
+    /// Fetch context IDs from a remote endpoint
+    ///
+    /// The response must be a JSON array of IDs or an object with an `ids` array.
+    async fn fetch_remote_ids(url: &str) -> std::result::Result<Vec<ContextId>, String> {
+        let body: Value = reqwest::get(url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid response from {}: {}", url, e))?;
+
+        let ids = body
+            .as_array()
+            .or_else(|| body.get("ids").and_then(|v| v.as_array()))
+            .ok_or_else(|| {
+                format!(
+                    "Response from {} is not an ID array or an object with an \"ids\" array",
+                    url
+                )
+            })?;
+        ids.iter()
+            .map(|id| {
+                let id = id
+                    .as_str()
+                    .ok_or_else(|| format!("Remote ID {} is not a string", id))?;
+                ContextId::parse(id).map_err(|e| e.to_string())
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+impl ContextTool for ReconcileWithRemoteTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "reconcile_with_remote".to_string(),
+            description: Some(
+                "Compare stored context IDs with those listed by a remote server".to_string(),
+            ),
+            input_schema: InputSchema::object().with_required(
+                "url",
+                PropertySchema::string(
+                    "URL returning a JSON array of IDs, or an object with an \"ids\" array",
+                ),
+            ),
+        }
+    }
+
+    async fn execute(&self, args: HashMap<String, Value>, cx: &ToolContext) -> CallToolResult {
+        let url = match args.get("url").and_then(|v| v.as_str()) {
+            Some(url) => url,
+            None => return CallToolResult::error("Missing required parameter: url"),
+        };
+
+        let remote_ids = match Self::fetch_remote_ids(url).await {
+            Ok(ids) => ids,
+            Err(e) => return CallToolResult::error(e),
+        };
+
+        match cx.store.reconcile_with_remote(&remote_ids).await {
+            Ok(stats) => CallToolResult::json(json!({
+                "local_only_count": stats.local_only.len(),
+                "remote_only_count": stats.remote_only.len(),
+                "shared_count": stats.shared.len(),
+                "local_only": stats.local_only,
+                "remote_only": stats.remote_only,
+                "shared": stats.shared
+            })),
+            Err(e) => CallToolResult::error(format!("Reconciliation failed: {}", e)),
+        }
+    }
+}
+
+/// Check tool arguments against an input schema
+///
+/// Returns every violation found: missing required fields, unknown keys,
+/// type mismatches, enum membership (case-insensitive, matching how the
+/// tools parse these values) and numeric range bounds.
+pub fn validate_arguments(schema: &InputSchema, args: &HashMap<String, Value>) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    for name in &schema.required {
+        if args.get(name).map(|v| v.is_null()).unwrap_or(true) {
+            violations.push(format!("missing required parameter '{}'", name));
+        }
+    }
+
+    let mut names: Vec<&String> = args.keys().collect();
+    names.sort();
+
+    for name in names {
+        let value = &args[name];
+        let property = match schema.properties.get(name) {
+            Some(p) => p,
+            None => {
+                violations.push(format!("unknown parameter '{}'", name));
+                continue;
+            }
+        };
+
+        if value.is_null() {
+            continue;
+        }
+
+        let type_ok = match property.schema_type.as_str() {
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "boolean" => value.is_boolean(),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            _ => true,
+        };
+        if !type_ok {
+            violations.push(format!(
+                "parameter '{}' must be of type {}",
+                name, property.schema_type
+            ));
+            continue;
+        }
+
+        if let (Some(allowed), Some(s)) = (&property.enum_values, value.as_str()) {
+            if !allowed.iter().any(|a| a.eq_ignore_ascii_case(s)) {
+                violations.push(format!(
+                    "parameter '{}' must be one of [{}], got '{}'",
+                    name,
+                    allowed.join(", "),
+                    s
+                ));
+            }
+        }
+
+        if let Some(n) = value.as_f64() {
+            if let Some(min) = property.minimum {
+                if n < min {
+                    violations.push(format!(
+                        "parameter '{}' must be >= {}, got {}",
+                        name, min, n
+                    ));
+                }
+            }
+            if let Some(max) = property.maximum {
+                if n > max {
+                    violations.push(format!(
+                        "parameter '{}' must be <= {}, got {}",
+                        name, max, n
+                    ));
+                }
+            }
+        }
+    }
+
+    violations
+}
+
+/// Optional sections of a context response, shared by `get_context` and `query_contexts`
+#[derive(Debug, Clone, Copy, Default)]
+struct Projection {
+    embedding: bool,
+    quantized_embedding: bool,
+    relations: bool,
+    custom: bool,
+    screening_history: bool,
+    base64: bool,
+}
+
+impl Projection {
+    /// Add the projection arguments to a tool's input schema
+    fn schema(schema: InputSchema) -> InputSchema {
+        schema
+            .with_property(
+                "include_embedding",
+                PropertySchema::boolean("Include the embedding vector").with_default(json!(false)),
+            )
+            .with_property(
+                "include_quantized_embedding",
+                PropertySchema::boolean("Include the quantized embedding")
+                    .with_default(json!(false)),
+            )
+            .with_property(
+                "include_relations",
+                PropertySchema::boolean("Include related context IDs").with_default(json!(false)),
+            )
+            .with_property(
+                "include_custom",
+                PropertySchema::boolean("Include custom metadata").with_default(json!(false)),
+            )
+            .with_property(
+                "include_screening_history",
+                PropertySchema::boolean("Include screening history").with_default(json!(false)),
+            )
+            .with_property(
+                "embedding_format",
+                PropertySchema::string("Encoding of the embedding vector")
+                    .with_enum(vec!["array", "base64"])
+                    .with_default(json!("array")),
+            )
+    }
+
+    fn from_args(args: &HashMap<String, Value>) -> Self {
+        let flag = |name: &str| args.get(name).and_then(|v| v.as_bool()).unwrap_or(false);
+        Self {
+            embedding: flag("include_embedding"),
+            quantized_embedding: flag("include_quantized_embedding"),
+            relations: flag("include_relations"),
+            custom: flag("include_custom"),
+            screening_history: flag("include_screening_history"),
+            base64: args
+                .get("embedding_format")
+                .and_then(|v| v.as_str())
+                .map(|f| f.eq_ignore_ascii_case("base64"))
+                .unwrap_or(false),
+        }
+    }
+
+    /// Add the requested sections to a context's JSON representation
+    async fn apply(&self, ctx: &Context, cx: &ToolContext, result: &mut Value) {
+        let mut extra = Map::new();
+
+        if self.embedding {
+            let embedding = match &ctx.embedding {
+                Some(embedding) if self.base64 => {
+                    let bytes: Vec<u8> = embedding.iter().flat_map(|f| f.to_le_bytes()).collect();
+                    json!(base64::engine::general_purpose::STANDARD.encode(bytes))
+                }
+                Some(embedding) => json!(embedding),
+                None => Value::Null,
+            };
+            extra.insert("embedding".into(), embedding);
+        }
+
+        if self.quantized_embedding {
+            let quantized = match cx.rag.embedding_generator() {
+                Some(generator) => generator
+                    .generate_quantized(&ctx.content)
+                    .await
+                    .ok()
+                    .and_then(|q| serde_json::to_value(q).ok())
+                    .unwrap_or(Value::Null),
+                None => Value::Null,
+            };
+            extra.insert("quantized_embedding".into(), quantized);
+        }
+
+        if self.relations {
+            extra.insert(
+                "relations".into(),
+                json!({
+                    "parent_id": ctx.parent_id.as_ref().map(|id| id.to_string()),
+                    "thread_id": ctx.thread_id
+                }),
+            );
+        }
+
+        if self.custom {
+            extra.insert("custom".into(), json!(ctx.metadata.custom));
+        }
+
+        if self.screening_history {
+            // Status changes as recorded in the revision history, oldest first
+            let mut history: Vec<Value> = Vec::new();
+            let mut last: Option<&ScreeningStatus> = None;
+            let revisions = ctx
+                .history
+                .iter()
+                .map(|rev| (rev.version, &rev.metadata.screening_status))
+                .chain(std::iter::once((
+                    ctx.version,
+                    &ctx.metadata.screening_status,
+                )));
+            for (version, status) in revisions {
+                if last != Some(status) {
+                    history.push(json!({
+                        "status": format!("{:?}", status),
+                        "version": version
+                    }));
+                    last = Some(status);
+                }
+            }
+            extra.insert("screening_history".into(), json!(history));
+        }
+
+        if let Value::Object(map) = result {
+            map.extend(extra);
         }
     }
+}
 
-    async fn get_storage_stats(&self, _args: HashMap<String, Value>) -> CallToolResult {
-        let stats = self.store.stats().await;
-        CallToolResult::json(json!({
-            "memory_count": stats.memory_count,
-            "disk_count": stats.disk_count,
-            "cache_capacity": stats.cache_capacity
-        }))
+/// Turns within `window` positions of `id` in a thread, excluding `id` itself
+fn thread_neighbours<'a>(turns: &'a [Context], id: &ContextId, window: usize) -> Vec<&'a Context> {
+    match turns.iter().position(|turn| &turn.id == id) {
+        Some(pos) => {
+            let start = pos.saturating_sub(window);
+            let end = (pos + window + 1).min(turns.len());
+            turns[start..end]
+                .iter()
+                .filter(|turn| &turn.id != id)
+                .collect()
+        }
+        None => Vec::new(),
     }
+}
 
-    async fn cleanup_expired(&self, _args: HashMap<String, Value>) -> CallToolResult {
-        match self.store.cleanup_expired().await {
-            Ok(count) => CallToolResult::json(json!({
-                "success": true,
-                "removed_count": count
-            })),
-            Err(e) => CallToolResult::error(format!("Cleanup failed: {}", e)),
+/// Build a retrieval query from the arguments shared by the retrieval tools
+fn retrieval_query_from_args(args: &HashMap<String, Value>) -> RetrievalQuery {
+    let mut query = RetrievalQuery::new();
+
+    if let Some(text) = args.get("text").and_then(|v| v.as_str()) {
+        query.text = Some(text.to_string());
+    }
+
+    if let Some(domain) = args.get("domain").and_then(|v| v.as_str()) {
+        query = query.with_domain(parse_domain(domain));
+    }
+
+    if let Some(tags) = args.get("tags").and_then(|v| v.as_array()) {
+        for tag in tags.iter().filter_map(|v| v.as_str()) {
+            query = query.with_tag(tag.to_string());
         }
     }
+
+    if let Some(min_importance) = args.get("min_importance").and_then(|v| v.as_f64()) {
+        query = query.with_min_importance(min_importance as f32);
+    }
+
+    if let Some(max_age) = args.get("max_age_hours").and_then(|v| v.as_i64()) {
+        query = query.with_temporal(TemporalQuery::recent(max_age));
+    }
+
+    if let Some(client) = args.get(CLIENT_PARAM).and_then(|v| v.as_str()) {
+        query = query.with_accessor(client);
+    }
+
+    if let Some(language) = args.get("language").and_then(|v| v.as_str()) {
+        query = query.with_language(language.to_lowercase());
+    }
+
+    query
+}
+
+/// Violations for context ID parameters that fail [`ContextId::parse`]
+fn id_violations(args: &HashMap<String, Value>, params: &[&str]) -> Vec<String> {
+    params
+        .iter()
+        .filter_map(|&param| {
+            let value = args.get(param)?.as_str()?;
+            ContextId::parse(value)
+                .err()
+                .map(|e| format!("parameter '{}' is invalid: {}", param, e))
+        })
+        .collect()
+}
+
+/// Parameter naming the calling client, recorded as a context's last accessor
+const CLIENT_PARAM: &str = "client";
+
+fn client_schema() -> PropertySchema {
+    PropertySchema::string("Name of the calling client, recorded as the last accessor")
 }
 
-/// Parse domain string to enum
+/// Parse domain string to enum; see [`ContextDomain::parse`]
 fn parse_domain(s: &str) -> ContextDomain {
-    match s.to_lowercase().as_str() {
-        "code" => ContextDomain::Code,
-        "documentation" | "docs" => ContextDomain::Documentation,
-        "conversation" | "chat" => ContextDomain::Conversation,
-        "filesystem" | "files" => ContextDomain::Filesystem,
-        "websearch" | "web" => ContextDomain::WebSearch,
-        "dataset" | "data" => ContextDomain::Dataset,
-        "research" => ContextDomain::Research,
-        _ => ContextDomain::General,
-    }
+    ContextDomain::parse(s)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocol::Content;
+
+    fn test_registry() -> ToolRegistry {
+        let store = Arc::new(ContextStore::new(StorageConfig::memory_only(100)).unwrap());
+        let rag = Arc::new(RagProcessor::with_defaults(store.clone()));
+        ToolRegistry::new(store, rag)
+    }
+
+    fn args(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn body(result: &CallToolResult) -> Value {
+        match &result.content[0] {
+            Content::Text { text } => serde_json::from_str(text).unwrap(),
+            other => panic!("expected text content, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_missing_required() {
+        let registry = test_registry();
+        let violations = registry
+            .validate("get_context", &args(json!({})))
+            .unwrap_err();
+        assert_eq!(violations, vec!["missing required parameter 'id'"]);
+    }
+
+    #[test]
+    fn test_validate_wrong_type_and_range() {
+        let registry = test_registry();
+        let violations = registry
+            .validate(
+                "store_context",
+                &args(json!({"content": "x", "importance": "high", "ttl_hours": -1})),
+            )
+            .unwrap_err();
+        assert_eq!(violations.len(), 2);
+        assert!(violations[0].contains("'importance' must be of type number"));
+        assert!(violations[1].contains("'ttl_hours' must be >= 0"));
+
+        let violations = registry
+            .validate("query_contexts", &args(json!({"min_importance": 1.5})))
+            .unwrap_err();
+        assert!(violations[0].contains("'min_importance' must be <= 1"));
+    }
+
+    #[test]
+    fn test_validate_unknown_key_and_enum() {
+        let registry = test_registry();
+        let violations = registry
+            .validate(
+                "update_screening",
+                &args(json!({"id": "abc", "status": "maybe", "extra": true})),
+            )
+            .unwrap_err();
+        assert_eq!(violations.len(), 2);
+        assert!(violations[0].contains("unknown parameter 'extra'"));
+        assert!(violations[1].contains("'status' must be one of"));
+
+        // Enum membership is case-insensitive, like the tool's own parsing
+        assert!(registry
+            .validate(
+                "update_screening",
+                &args(json!({"id": "abc", "status": "safe"}))
+            )
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_store_context_ttl_limits() {
+        let registry = test_registry();
+        let violations = registry
+            .validate(
+                "store_context",
+                &args(json!({"content": "x", "ttl_hours": 0})),
+            )
+            .unwrap_err();
+        assert!(violations[0].contains("'ttl_hours' must be a positive number of hours"));
+
+        let result = registry
+            .execute(
+                "store_context",
+                args(json!({"content": "x", "ttl_hours": 1e12})),
+            )
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(matches!(
+            &result.content[0],
+            Content::Text { text } if text.contains("exceeds the maximum")
+        ));
+
+        let result = registry
+            .execute(
+                "store_context",
+                args(json!({"content": "x", "ttl_hours": 2})),
+            )
+            .await
+            .unwrap();
+        assert!(!result.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_embeddings_tool() {
+        use crate::embeddings::MockEmbeddingGenerator;
+
+        let registry = test_registry();
+        registry
+            .register(Box::new(BackfillEmbeddingsTool::new(Arc::new(
+                MockEmbeddingGenerator::new(8),
+            ))))
+            .unwrap();
+        registry
+            .execute("store_context", args(json!({"content": "needs embedding"})))
+            .await
+            .unwrap();
+
+        let result = registry
+            .execute("backfill_embeddings", args(json!({"dry_run": true})))
+            .await
+            .unwrap();
+        assert_eq!(body(&result)["missing_count"], 1);
+
+        let result = registry
+            .execute("backfill_embeddings", args(json!({"batch_size": 4})))
+            .await
+            .unwrap();
+        assert_eq!(body(&result)["embedded_count"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_by_hash() {
+        let registry = test_registry();
+        let stored = registry
+            .execute("store_context", args(json!({"content": "known content"})))
+            .await
+            .unwrap();
+        let id = body(&stored)["id"].clone();
+
+        let result = registry
+            .execute("lookup_by_hash", args(json!({"content": "known content"})))
+            .await
+            .unwrap();
+        assert_eq!(body(&result)["id"], id);
+
+        let hash = hash_content("known content").to_uppercase();
+        let result = registry
+            .execute("lookup_by_hash", args(json!({"hash": hash})))
+            .await
+            .unwrap();
+        assert_eq!(body(&result)["found"], true);
+
+        let violations = registry
+            .validate("lookup_by_hash", &args(json!({"hash": "abc"})))
+            .unwrap_err();
+        assert!(violations[0].contains("64 hex characters"));
+    }
+
+    #[tokio::test]
+    async fn test_compute_domain_overlap() {
+        let registry = test_registry();
+        for (content, domain, tags) in [
+            ("overlap code", "code", json!(["rust", "async"])),
+            ("overlap docs", "docs", json!(["rust"])),
+        ] {
+            registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": content, "domain": domain, "tags": tags})),
+                )
+                .await
+                .unwrap();
+        }
+
+        let result = registry
+            .execute(
+                "compute_domain_overlap",
+                args(json!({"domain_a": "code", "domain_b": "documentation"})),
+            )
+            .await
+            .unwrap();
+        assert_eq!(body(&result)["overlap"], 0.5);
+    }
+
+    #[test]
+    fn test_validate_decay_strategy_parameters() {
+        let registry = test_registry();
+        let violations = registry
+            .validate(
+                "apply_decay_strategy",
+                &args(json!({"strategy": "exponential_by_age"})),
+            )
+            .unwrap_err();
+        assert!(violations[0].contains("'half_life_hours' is required"));
+
+        let violations = registry
+            .validate(
+                "apply_decay_strategy",
+                &args(json!({"strategy": "exponential_by_age", "half_life_hours": 0})),
+            )
+            .unwrap_err();
+        assert!(violations[0].contains("must be positive"));
+
+        assert!(registry
+            .validate(
+                "apply_decay_strategy",
+                &args(json!({"strategy": "multiply_by_access_rate", "window_hours": 24})),
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_validate_context_ids() {
+        let registry = test_registry();
+        let violations = registry
+            .validate("get_context", &args(json!({"id": ""})))
+            .unwrap_err();
+        assert!(violations[0].contains("parameter 'id' is invalid"));
+
+        let violations = registry
+            .validate(
+                "merge_contexts",
+                &args(json!({"primary_id": "a", "secondary_id": "b\u{7}"})),
+            )
+            .unwrap_err();
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("'secondary_id'"));
+
+        let long = "x".repeat(crate::context::MAX_ID_LEN + 1);
+        assert!(registry
+            .validate("delete_context", &args(json!({"id": long})))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_execute_rejects_invalid_arguments() {
+        let registry = test_registry();
+        let err = registry
+            .execute("retrieve_contexts", args(json!({"max_results": 0})))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ContextError::InvalidQuery(_)));
+
+        let err = registry
+            .execute("no_such_tool", HashMap::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown tool: no_such_tool"));
+    }
+
+    struct EchoTool;
+
+    #[async_trait]
+    impl ContextTool for EchoTool {
+        fn definition(&self) -> Tool {
+            Tool {
+                name: "echo".to_string(),
+                description: Some("Echo the input".to_string()),
+                input_schema: InputSchema::object()
+                    .with_required("text", PropertySchema::string("Text to echo")),
+            }
+        }
+
+        async fn execute(&self, args: HashMap<String, Value>, _cx: &ToolContext) -> CallToolResult {
+            CallToolResult::text(args["text"].as_str().unwrap_or_default())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_custom_tool() {
+        let registry = test_registry();
+        let mut notifications = registry.subscribe();
+
+        registry.register(Box::new(EchoTool)).unwrap();
+        assert!(registry.list_tools().iter().any(|t| t.name == "echo"));
+        assert_eq!(
+            notifications.recv().await.unwrap().method,
+            "notifications/tools/list_changed"
+        );
+
+        let result = registry
+            .execute("echo", args(json!({"text": "hi"})))
+            .await
+            .unwrap();
+        assert!(!result.is_error);
+
+        // Custom tools get the same schema validation as built-ins
+        assert!(registry.execute("echo", args(json!({}))).await.is_err());
+    }
+
+    #[test]
+    fn test_register_rejects_name_collision() {
+        let registry = test_registry();
+        registry.register(Box::new(EchoTool)).unwrap();
+        assert!(registry.register(Box::new(EchoTool)).is_err());
+
+        struct Shadow;
+        #[async_trait]
+        impl ContextTool for Shadow {
+            fn definition(&self) -> Tool {
+                Tool {
+                    name: "store_context".to_string(),
+                    description: None,
+                    input_schema: InputSchema::object(),
+                }
+            }
+
+            async fn execute(
+                &self,
+                _args: HashMap<String, Value>,
+                _cx: &ToolContext,
+            ) -> CallToolResult {
+                CallToolResult::text("shadowed")
+            }
+        }
+        assert!(registry.register(Box::new(Shadow)).is_err());
+    }
 
     #[test]
     fn test_parse_domain() {
         assert_eq!(parse_domain("Code"), ContextDomain::Code);
         assert_eq!(parse_domain("docs"), ContextDomain::Documentation);
-        assert_eq!(parse_domain("unknown"), ContextDomain::General);
+        assert_eq!(parse_domain("general"), ContextDomain::General);
+        assert_eq!(
+            parse_domain("legal"),
+            ContextDomain::Custom("legal".to_string())
+        );
+        assert_eq!(
+            parse_domain("custom:code"),
+            ContextDomain::Custom("code".to_string())
+        );
+
+        let custom = ContextDomain::Custom("Legal".to_string());
+        assert_eq!(parse_domain(&custom.to_string()), custom);
+    }
+
+    #[tokio::test]
+    async fn test_store_context_auto_chunk() {
+        let registry = test_registry();
+        let content = "Ownership moves values. Borrowing lends them out. Lifetimes bound borrows.";
+        let stored = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({
+                        "content": content,
+                        "auto_chunk": true,
+                        "chunk_size": 30,
+                        "chunk_overlap": 0
+                    })),
+                )
+                .await
+                .unwrap(),
+        );
+        let ids = stored["chunk_ids"].as_array().unwrap();
+        assert_eq!(ids.len(), 3);
+
+        let first = body(
+            &registry
+                .execute("get_context", args(json!({"id": ids[0]})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(first["content"], "Ownership moves values.");
+    }
+
+    #[tokio::test]
+    async fn test_structured_data_round_trip() {
+        let registry = test_registry();
+        let prod = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "Cluster A", "data": {"cluster": "prod", "nodes": 3}})),
+                )
+                .await
+                .unwrap(),
+        );
+        registry
+            .execute(
+                "store_context",
+                args(json!({"content": "Cluster B", "data": {"cluster": "staging"}})),
+            )
+            .await
+            .unwrap();
+
+        let fetched = body(
+            &registry
+                .execute("get_context", args(json!({"id": prod["id"]})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(fetched["data"]["nodes"], 3);
+
+        let result = body(
+            &registry
+                .execute(
+                    "query_contexts",
+                    args(json!({"data_filter": "data.cluster == \"prod\""})),
+                )
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["count"], 1);
+        assert_eq!(result["contexts"][0]["id"], prod["id"]);
+
+        let violations = registry
+            .validate("query_contexts", &args(json!({"data_filter": "cluster"})))
+            .unwrap_err();
+        assert!(violations[0].contains("'data_filter'"));
+    }
+
+    #[tokio::test]
+    async fn test_access_stats_and_most_accessed_sort() {
+        let registry = test_registry();
+        let cold = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "Cold", "importance": 0.9})),
+                )
+                .await
+                .unwrap(),
+        );
+        let hot = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "Hot", "importance": 0.1})),
+                )
+                .await
+                .unwrap(),
+        );
+        for _ in 0..3 {
+            registry
+                .execute(
+                    "get_context",
+                    args(json!({"id": hot["id"], "client": "vscode"})),
+                )
+                .await
+                .unwrap();
+        }
+
+        let fetched = body(
+            &registry
+                .execute("get_context", args(json!({"id": hot["id"]})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(fetched["access"]["count"], 4);
+        assert_eq!(fetched["access"]["last_accessed_by"], "vscode");
+        assert_eq!(fetched["access"]["recent"].as_array().unwrap().len(), 4);
+
+        let result = body(
+            &registry
+                .execute("query_contexts", args(json!({"sort": "most_accessed"})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["contexts"][0]["id"], hot["id"]);
+        assert_eq!(result["contexts"][1]["id"], cold["id"]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_contexts_dry_run() {
+        let registry = test_registry();
+        let a = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "A", "tags": ["x"]})),
+                )
+                .await
+                .unwrap(),
+        );
+        let b = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "B", "tags": ["y"]})),
+                )
+                .await
+                .unwrap(),
+        );
+        let merge_args = json!({"primary_id": a["id"], "secondary_id": b["id"]});
+
+        let mut dry = merge_args.clone();
+        dry["dry_run"] = json!(true);
+        let preview = body(&registry.execute("merge_contexts", args(dry)).await.unwrap());
+        assert_eq!(preview["merged"]["content"], "A\n\nB");
+        assert_eq!(preview["merged"]["tags"], json!(["x", "y"]));
+        let untouched = body(
+            &registry
+                .execute("get_context", args(json!({"id": b["id"]})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(untouched["content"], "B");
+
+        registry
+            .execute("merge_contexts", args(merge_args))
+            .await
+            .unwrap();
+        let redirected = body(
+            &registry
+                .execute("get_context", args(json!({"id": b["id"]})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(redirected["id"], a["id"]);
+    }
+
+    #[tokio::test]
+    async fn test_diff_revisions() {
+        let registry = test_registry();
+        let stored = body(
+            &registry
+                .execute("store_context", args(json!({"content": "alpha\nbeta"})))
+                .await
+                .unwrap(),
+        );
+        let id = ContextId::from_string(stored["id"].as_str().unwrap().to_string());
+        let mut ctx = registry.context.store.get(&id).await.unwrap().unwrap();
+        ctx.content = "alpha\ngamma".to_string();
+        registry.context.store.store(ctx).await.unwrap();
+
+        let result = body(
+            &registry
+                .execute("diff_revisions", args(json!({"id": stored["id"]})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["diff"]["from_version"], 1);
+        assert_eq!(result["diff"]["to_version"], 2);
+        assert!(result["rendered"]
+            .as_str()
+            .unwrap()
+            .contains("-beta\n+gamma\n"));
+
+        let missing = registry
+            .execute(
+                "diff_revisions",
+                args(json!({"id": stored["id"], "from_rev": 7})),
+            )
+            .await
+            .unwrap();
+        assert!(missing.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_rollback_context() {
+        let registry = test_registry();
+        let stored = body(
+            &registry
+                .execute("store_context", args(json!({"content": "first"})))
+                .await
+                .unwrap(),
+        );
+        let id = ContextId::from_string(stored["id"].as_str().unwrap().to_string());
+        let mut ctx = registry.context.store.get(&id).await.unwrap().unwrap();
+        ctx.content = "second".to_string();
+        registry.context.store.store(ctx).await.unwrap();
+
+        let result = body(
+            &registry
+                .execute(
+                    "rollback_context",
+                    args(json!({"id": stored["id"], "version": 1})),
+                )
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["rolled_back_to"], 1);
+        assert_eq!(result["version"], 3);
+        let current = registry.context.store.get(&id).await.unwrap().unwrap();
+        assert_eq!(current.content, "first");
+
+        let missing = registry
+            .execute(
+                "rollback_context",
+                args(json!({"id": stored["id"], "version": 7})),
+            )
+            .await
+            .unwrap();
+        assert!(missing.is_error);
+        assert_eq!(
+            body(&missing)["code"],
+            crate::protocol::error_codes::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_retrieval() {
+        let registry = test_registry();
+        let stored = body(
+            &registry
+                .execute("store_context", args(json!({"content": "only context"})))
+                .await
+                .unwrap(),
+        );
+
+        let report = body(
+            &registry
+                .execute(
+                    "evaluate_retrieval",
+                    args(json!({"ground_truth": [
+                        {"query": "only", "relevant_ids": [stored["id"]]}
+                    ]})),
+                )
+                .await
+                .unwrap(),
+        );
+        assert_eq!(report["queries"], 1);
+        assert_eq!(report["avg_recall_at_1"], 1.0);
+        assert_eq!(report["mrr"], 1.0);
+
+        let violations = registry.validate(
+            "evaluate_retrieval",
+            &args(json!({"ground_truth": [{"query": "no ids"}]})),
+        );
+        assert_eq!(
+            violations.unwrap_err(),
+            vec!["ground_truth[0] has no relevant_ids"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_language_filters() {
+        let registry = test_registry();
+        for content in [
+            "Wir haben uns entschieden, die alte Schnittstelle nicht mehr zu unterstützen.",
+            "We have decided to stop supporting the old interface.",
+        ] {
+            registry
+                .execute("store_context", args(json!({"content": content})))
+                .await
+                .unwrap();
+        }
+        registry
+            .execute(
+                "store_context",
+                args(json!({"content": "Kurz notiert", "language": "DE"})),
+            )
+            .await
+            .unwrap();
+
+        let german = body(
+            &registry
+                .execute("query_contexts", args(json!({"language": "de"})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(german["count"], 2);
+        let retrieved = body(
+            &registry
+                .execute("retrieve_contexts", args(json!({"language": "en"})))
+                .await
+                .unwrap(),
+        );
+        let contexts = retrieved["contexts"].as_array().unwrap();
+        assert_eq!(contexts.len(), 1);
+        assert!(contexts[0]["content"]
+            .as_str()
+            .unwrap()
+            .starts_with("We have decided"));
+    }
+
+    #[tokio::test]
+    async fn test_store_context_auto_importance() {
+        let registry = test_registry();
+        let store = |extra: Value| {
+            let mut call = json!({
+                "content": "URGENT: never commit credentials, rotate them asap",
+                "tags": ["security"]
+            });
+            call.as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            registry.execute("store_context", args(call))
+        };
+
+        let id = body(&store(json!({"auto_importance": true})).await.unwrap())["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let ctx = registry
+            .context
+            .store
+            .get(&ContextId::parse(&id).unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        let signals = &ctx.metadata.custom[IMPORTANCE_SIGNALS_KEY];
+        assert_eq!(
+            signals["importance"].as_f64().unwrap() as f32,
+            ctx.metadata.importance
+        );
+        assert!(ctx.metadata.importance > 0.8);
+        assert_eq!(
+            signals["urgency_markers"],
+            json!(["asap", "never", "urgent"])
+        );
+
+        // An explicit importance wins, and nothing is recorded
+        let id = body(
+            &store(json!({"auto_importance": true, "importance": 0.2}))
+                .await
+                .unwrap(),
+        )["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let ctx = registry
+            .context
+            .store
+            .get(&ContextId::parse(&id).unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ctx.metadata.importance, 0.2);
+        assert!(!ctx.metadata.custom.contains_key(IMPORTANCE_SIGNALS_KEY));
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_storage() {
+        let registry = test_registry();
+        let report = body(
+            &registry
+                .execute("benchmark_storage", args(json!({"operations": 5})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(report["operations"], 5);
+        assert!(report["result"]["store_ops_per_sec"].as_f64().unwrap() > 0.0);
+
+        let listed = body(
+            &registry
+                .execute("list_context_ids", args(json!({})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(listed["count"], 0);
+        assert!(registry
+            .validate("benchmark_storage", &args(json!({"operations": 0})))
+            .is_err());
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_list_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = StorageConfig::builder()
+            .persist_path(dir.path().join("store"))
+            .snapshot(crate::storage::SnapshotPolicy {
+                interval: std::time::Duration::from_secs(3600),
+                directory: dir.path().join("snapshots"),
+                keep_last: 3,
+            })
+            .build();
+        let store = Arc::new(ContextStore::new(config).unwrap());
+        let rag = Arc::new(RagProcessor::with_defaults(store.clone()));
+        let registry = ToolRegistry::new(store.clone(), rag);
+
+        let empty = body(
+            &registry
+                .execute("list_snapshots", args(json!({})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(empty["snapshots"], json!([]));
+        assert_eq!(empty["policy"]["keep_last"], 3);
+
+        let taken = store.take_snapshot().unwrap();
+        let listed = body(
+            &registry
+                .execute("list_snapshots", args(json!({})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(listed["snapshots"][0]["name"], taken.name);
+        assert_eq!(listed["status"]["last_snapshot"], taken.name);
+        assert_eq!(listed["status"]["consecutive_failures"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_query_contexts_pages_with_cursor() {
+        let registry = test_registry();
+        for i in 0..5 {
+            registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": format!("page item {}", i), "domain": "general"})),
+                )
+                .await
+                .unwrap();
+        }
+
+        let mut ids = HashSet::new();
+        let mut request = json!({"limit": 2});
+        loop {
+            let page = body(
+                &registry
+                    .execute("query_contexts", args(request.clone()))
+                    .await
+                    .unwrap(),
+            );
+            assert_eq!(page["total_matches"], 5);
+            for ctx in page["contexts"].as_array().unwrap() {
+                assert!(ids.insert(ctx["id"].as_str().unwrap().to_string()));
+            }
+            match page["next_cursor"].as_str() {
+                Some(cursor) => request["cursor"] = json!(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(ids.len(), 5);
+
+        assert!(registry
+            .validate("query_contexts", &args(json!({"cursor": "not a cursor"})))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_query_contexts_sort() {
+        let registry = test_registry();
+        for (content, importance) in [("low", 0.2), ("high", 0.9), ("mid", 0.5)] {
+            registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": content, "importance": importance})),
+                )
+                .await
+                .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+
+        let contents = |result: &CallToolResult| -> Vec<String> {
+            body(result)["contexts"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|ctx| ctx["content_preview"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let by_importance = registry
+            .execute("query_contexts", args(json!({"sort": "importance"})))
+            .await
+            .unwrap();
+        assert_eq!(contents(&by_importance), ["high", "mid", "low"]);
+        let oldest_first = registry
+            .execute("query_contexts", args(json!({"sort": "created_asc"})))
+            .await
+            .unwrap();
+        assert_eq!(contents(&oldest_first), ["low", "high", "mid"]);
+        let youngest_first = registry
+            .execute("query_contexts", args(json!({"sort": "age_asc"})))
+            .await
+            .unwrap();
+        assert_eq!(contents(&youngest_first), ["mid", "high", "low"]);
+
+        assert!(registry
+            .validate("query_contexts", &args(json!({"sort": "random"})))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_query_contexts_by_source() {
+        let registry = test_registry();
+        for (content, source) in [("from issues", "github:issue-tracker"), ("typed", "user")] {
+            registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": content, "source": source})),
+                )
+                .await
+                .unwrap();
+        }
+
+        let result = registry
+            .execute(
+                "query_contexts",
+                args(json!({"source": "github:issue-tracker"})),
+            )
+            .await
+            .unwrap();
+        let contexts = body(&result)["contexts"].as_array().unwrap().clone();
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0]["content_preview"], "from issues");
+    }
+
+    #[tokio::test]
+    async fn test_list_context_ids() {
+        let registry = test_registry();
+        let code = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "fn main() {}", "domain": "code"})),
+                )
+                .await
+                .unwrap(),
+        );
+        registry
+            .execute("store_context", args(json!({"content": "notes"})))
+            .await
+            .unwrap();
+
+        let all = body(
+            &registry
+                .execute("list_context_ids", args(json!({})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(all["count"], 2);
+        let only_code = body(
+            &registry
+                .execute("list_context_ids", args(json!({"domain": "code"})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(only_code["ids"], json!([code["id"]]));
+    }
+
+    #[tokio::test]
+    async fn test_custom_domain_round_trip() {
+        let registry = test_registry();
+        let stored = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "Contract clause", "domain": "legal"})),
+                )
+                .await
+                .unwrap(),
+        );
+        registry
+            .execute(
+                "store_context",
+                args(json!({"content": "fn contract_clause() {}", "domain": "Code"})),
+            )
+            .await
+            .unwrap();
+
+        let fetched = body(
+            &registry
+                .execute("get_context", args(json!({"id": stored["id"]})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(fetched["domain"], "custom:legal");
+
+        for domain in ["legal", "custom:legal"] {
+            let result = body(
+                &registry
+                    .execute("query_contexts", args(json!({"domain": domain})))
+                    .await
+                    .unwrap(),
+            );
+            let contexts = result["contexts"].as_array().unwrap();
+            assert_eq!(contexts.len(), 1);
+            assert_eq!(contexts[0]["id"], stored["id"]);
+        }
+    }
+
+    #[test]
+    fn test_search_by_regex_rejects_bad_pattern() {
+        let registry = test_registry();
+        let violations = registry
+            .validate("search_by_regex", &args(json!({"pattern": "fn (unclosed"})))
+            .unwrap_err();
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].starts_with("parameter 'pattern' is not a valid regex"));
+        assert!(violations[0].contains("unclosed"));
+    }
+
+    #[tokio::test]
+    async fn test_search_by_regex() {
+        let registry = test_registry();
+        let code = "// helpers\nfn get_user_handler(req: Request) -> Response { todo!() }";
+        registry
+            .execute(
+                "store_context",
+                args(json!({"content": code, "domain": "code"})),
+            )
+            .await
+            .unwrap();
+        registry
+            .execute("store_context", args(json!({"content": "fn main() {}"})))
+            .await
+            .unwrap();
+
+        let result = registry
+            .execute(
+                "search_by_regex",
+                args(json!({"pattern": r"FN\s+\w+_handler", "case_insensitive": true})),
+            )
+            .await
+            .unwrap();
+        let body = body(&result);
+        assert_eq!(body["count"], 1);
+        assert_eq!(body["matches"][0]["match_offset"], 11);
+        assert_eq!(body["matches"][0]["match"], "fn get_user_handler");
+        assert!(body["matches"][0]["snippet"]
+            .as_str()
+            .unwrap()
+            .starts_with("// helpers"));
+    }
+
+    #[test]
+    fn test_snippet_respects_char_boundaries() {
+        let content = "héllo wörld";
+        let start = content.find('w').unwrap();
+        assert_eq!(snippet(content, start, start + 1, 2), "o wö");
+    }
+
+    #[tokio::test]
+    async fn test_get_context_projection() {
+        let registry = test_registry();
+        let ctx = Context::new("projected", ContextDomain::General).with_embedding(vec![1.0, 0.5]);
+        let id = registry.context.store.store(ctx).await.unwrap().id;
+
+        let plain = body(
+            &registry
+                .execute("get_context", args(json!({"id": id.to_string()})))
+                .await
+                .unwrap(),
+        );
+        assert!(plain.get("embedding").is_none());
+        assert!(plain.get("custom").is_none());
+
+        let full = body(
+            &registry
+                .execute(
+                    "get_context",
+                    args(json!({"id": id.to_string(), "include_embedding": true, "include_custom": true})),
+                )
+                .await.unwrap(),
+        );
+        assert_eq!(full["embedding"], json!([1.0, 0.5]));
+        assert_eq!(full["custom"], json!({}));
+
+        let encoded = body(
+            &registry
+                .execute(
+                    "query_contexts",
+                    args(json!({"include_embedding": true, "embedding_format": "base64"})),
+                )
+                .await
+                .unwrap(),
+        );
+        let bytes: Vec<u8> = [1.0f32, 0.5].iter().flat_map(|f| f.to_le_bytes()).collect();
+        assert_eq!(
+            encoded["contexts"][0]["embedding"],
+            json!(base64::engine::general_purpose::STANDARD.encode(bytes))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_context_reports_not_found() {
+        let registry = test_registry();
+        let missing = ContextId::new().to_string();
+
+        for tool in ["get_context", "delete_context", "diff_revisions"] {
+            let result = registry
+                .execute(tool, args(json!({"id": missing})))
+                .await
+                .unwrap();
+            assert!(result.is_error, "{}", tool);
+            let error = body(&result);
+            assert_eq!(error["code"], crate::protocol::error_codes::NOT_FOUND);
+            assert_eq!(error["data"]["id"], missing);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_maintenance() {
+        let registry = test_registry();
+        registry
+            .execute("store_context", args(json!({"content": "indexed"})))
+            .await
+            .unwrap();
+        let mut notifications = registry.subscribe();
+
+        let result = registry
+            .execute(
+                "run_maintenance",
+                args(json!({"operations": ["rebuild_indexes", "archive_old"]})),
+            )
+            .await
+            .unwrap();
+        let report = body(&result);
+        assert_eq!(report["operations"][0]["items_touched"], 1);
+        assert!(report["operations"][0]["errors"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+        // Memory-only stores have nowhere to archive to
+        assert_eq!(
+            report["operations"][1]["errors"].as_array().unwrap().len(),
+            1
+        );
+        assert_eq!(
+            notifications.recv().await.unwrap().method,
+            "notifications/progress"
+        );
+
+        let status = body(
+            &registry
+                .execute("run_maintenance", args(json!({"action": "status"})))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(status["running"], false);
+        assert_eq!(status["last_runs"]["rebuild_indexes"]["items_touched"], 1);
+
+        let violations = registry
+            .validate("run_maintenance", &args(json!({"operations": ["defrag"]})))
+            .unwrap_err();
+        assert!(violations[0].contains("got \"defrag\""));
+    }
+
+    #[tokio::test]
+    async fn test_run_maintenance_is_serialized() {
+        let registry = test_registry();
+        let tool = RunMaintenanceTool::default();
+        let _running = tool.running.lock().await;
+
+        let result = tool
+            .execute(
+                args(json!({"operations": ["rebuild_indexes"]})),
+                &registry.context,
+            )
+            .await;
+        assert!(result.is_error);
+        assert!(matches!(
+            &result.content[0],
+            Content::Text { text } if text.contains("already running")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_store_context_reports_overwrite() {
+        let registry = test_registry();
+        let first = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "duplicate", "tags": ["first"]})),
+                )
+                .await
+                .unwrap(),
+        );
+        assert_eq!(first["existed"], false);
+        assert!(first["previous"].is_null());
+
+        let refused = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "duplicate", "tags": ["second"], "if_absent": true})),
+                )
+                .await
+                .unwrap(),
+        );
+        assert_eq!(refused["existed"], true);
+        assert_eq!(refused["stored"], false);
+
+        let replaced = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "duplicate", "tags": ["second"]})),
+                )
+                .await
+                .unwrap(),
+        );
+        assert_eq!(replaced["existed"], true);
+        assert_eq!(replaced["stored"], true);
+        assert_eq!(replaced["previous"]["tags"], json!(["first"]));
+        assert_eq!(replaced["id"], first["id"]);
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_expands_thread() {
+        let registry = test_registry();
+        let root = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "What is ownership?", "thread_id": "chat-1"})),
+                )
+                .await
+                .unwrap(),
+        );
+        let reply = body(
+            &registry
+                .execute(
+                    "store_context",
+                    args(json!({"content": "Rust memory model answer", "parent_id": root["id"]})),
+                )
+                .await
+                .unwrap(),
+        );
+
+        let result = body(
+            &registry
+                .execute(
+                    "retrieve_contexts",
+                    args(json!({"text": "memory", "expand_thread": true})),
+                )
+                .await
+                .unwrap(),
+        );
+        let hit = result["contexts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["id"] == reply["id"])
+            .unwrap();
+        // The reply inherited its parent's thread
+        assert_eq!(hit["thread_id"], "chat-1");
+        assert_eq!(hit["thread"][0]["id"], root["id"]);
     }
 }
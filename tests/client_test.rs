@@ -0,0 +1,77 @@
+//! Integration tests for the HTTP client against an in-process server
+
+#![cfg(all(feature = "client", feature = "server"))]
+
+use context_mcp::client::{ContextMcpClient, QueryOptions, StoreOptions};
+use context_mcp::context::ContextDomain;
+use context_mcp::{ContextError, ContextId, McpServer, ServerConfig, StorageConfig};
+
+/// Start a memory-only server on an ephemeral port and return a client for it
+async fn start_server() -> ContextMcpClient {
+    let config = ServerConfig {
+        storage: StorageConfig::memory_only(100),
+        ..Default::default()
+    };
+    let server = McpServer::new(config).unwrap();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { server.serve(listener).await });
+    ContextMcpClient::new(&format!("http://{}", addr))
+}
+
+#[tokio::test]
+async fn test_client_round_trip() {
+    let client = start_server().await;
+
+    let id = client
+        .store(
+            "Rust ownership rules",
+            StoreOptions {
+                domain: Some(ContextDomain::Code),
+                tags: vec!["rust".into()],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    let ctx = client.get(&id).await.unwrap().unwrap();
+    assert_eq!(ctx["content"], "Rust ownership rules");
+
+    let found = client
+        .query(QueryOptions {
+            domain: Some(ContextDomain::Code),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 1);
+
+    let retrieved = client
+        .retrieve("ownership", QueryOptions::default())
+        .await
+        .unwrap();
+    assert!(retrieved.len() <= 1);
+
+    let stats = client.stats().await.unwrap();
+    assert!(stats.is_object());
+
+    assert!(client.delete(&id).await.unwrap());
+    assert!(!client.delete(&id).await.unwrap());
+    assert!(client.get(&id).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_client_maps_errors() {
+    let client = start_server().await;
+
+    let err = client
+        .call_tool("no_such_tool", serde_json::json!({}))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ContextError::InvalidQuery(_)));
+
+    let unreachable = ContextMcpClient::new("http://127.0.0.1:1");
+    let err = unreachable.get(&ContextId::new()).await.unwrap_err();
+    assert!(matches!(err, ContextError::Io(_)));
+}
@@ -0,0 +1,47 @@
+//! Runs the self-contained examples and checks they exit cleanly
+//!
+//! `cargo test` builds the examples alongside the tests; each one is run
+//! from a fresh temp dir so anything it persists is thrown away. The
+//! server examples wait for clients and are only compile-checked.
+
+#![cfg(feature = "server")]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Examples that run to completion without external input
+const EXAMPLES: &[&str] = &["basic_usage", "custom_tool", "ternary_rag_demo"];
+
+/// Path of a built example, next to this test's own binary
+fn example_path(name: &str) -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    // target/<profile>/deps/examples_test-<hash> -> target/<profile>/examples/<name>
+    let profile_dir = exe.parent().and_then(|deps| deps.parent()).unwrap();
+    profile_dir
+        .join("examples")
+        .join(format!("{}{}", name, std::env::consts::EXE_SUFFIX))
+}
+
+#[test]
+fn test_examples_run() {
+    for name in EXAMPLES {
+        let path = example_path(name);
+        assert!(
+            path.exists(),
+            "{} is not built; run `cargo build --examples` or a plain `cargo test`",
+            path.display()
+        );
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let output = Command::new(&path)
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "example {} failed:\n{}",
+            name,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
@@ -1,5 +1,7 @@
 //! Integration tests for storage index cleanup and consistency
 
+#![cfg(feature = "storage")]
+
 use context_mcp::context::{ContextDomain, ContextQuery};
 use context_mcp::{Context, ContextId, ContextStore, StorageConfig};
 
@@ -120,3 +122,29 @@ async fn test_storage_consistency_after_multiple_deletes() {
     let results = store.query(&query).await.unwrap();
     assert_eq!(results.len(), 5, "Should have 5 remaining contexts");
 }
+
+#[cfg(feature = "persistence")]
+#[tokio::test]
+async fn test_query_finds_contexts_evicted_from_cache() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let store = ContextStore::new(StorageConfig::with_persistence(10, temp_dir.path())).unwrap();
+
+    for i in 0..100 {
+        let ctx = Context::new(format!("persisted context {}", i), ContextDomain::General);
+        store.store(ctx).await.unwrap();
+    }
+
+    let query = ContextQuery {
+        limit: 1000,
+        ..Default::default()
+    };
+    assert_eq!(store.query(&query).await.unwrap().len(), 100);
+
+    let text = ContextQuery {
+        limit: 1000,
+        ..Default::default()
+    }
+    .with_text("context 4");
+    // "context 4" and "context 40" through "context 49"
+    assert_eq!(store.query(&text).await.unwrap().len(), 11);
+}
@@ -0,0 +1,34 @@
+//! Smoke test for the wasm32 core build
+//!
+//! Runs under `wasm-bindgen-test-runner`:
+//!
+//! ```text
+//! CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER=wasm-bindgen-test-runner \
+//!     cargo test --target wasm32-unknown-unknown --no-default-features --features core --test wasm_smoke
+//! ```
+
+#![cfg(target_arch = "wasm32")]
+
+use context_mcp::context::ContextDomain;
+use context_mcp::ternary::{SparseQuantizer, SparsityConfig};
+use context_mcp::Context;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn sparse_quantize_runs_on_wasm() {
+    let quantizer = SparseQuantizer::new(SparsityConfig {
+        top_k: Some(2),
+        ..Default::default()
+    });
+    let quantized = quantizer.quantize(&[0.5, -0.9, 0.0, 0.8]).unwrap();
+
+    assert_eq!(quantized.non_zero_count(), 2);
+    assert_eq!(quantizer.dequantize(&quantized), vec![0.0, -1.0, 0.0, 1.0]);
+}
+
+#[wasm_bindgen_test]
+fn context_uses_the_js_clock_and_rng() {
+    let ctx = Context::new("in the browser", ContextDomain::General);
+    assert!(!ctx.is_expired());
+    assert_ne!(ctx.id, Context::new("again", ContextDomain::General).id);
+}